@@ -0,0 +1,89 @@
+use blart::{visitor::TreeStatsCollector, ShrinkPolicy, TreeMap};
+use criterion::{measurement::Measurement, BenchmarkId, Criterion, Throughput};
+
+#[macro_use]
+mod common;
+
+/// The number of children in each second level node, which are full nodes of
+/// each type so that one more child grows them.
+const NODE_SIZES: [u8; 4] = [4, 16, 32, 48];
+
+/// The number of rounds of churn used to check the resize counts.
+const CHURN_ROUNDS: usize = 100;
+
+/// Build a tree of `[u8; 2]` keys where every second level node is full, and
+/// return it along with the keys which grow those nodes by one child.
+fn setup(shrink_policy: ShrinkPolicy) -> (TreeMap<[u8; 2], u8>, Vec<[u8; 2]>) {
+    let mut tree = TreeMap::new();
+    tree.set_shrink_policy(shrink_policy);
+    let mut churn_keys = Vec::new();
+    for high in 0..=u8::MAX {
+        let num_children = NODE_SIZES[usize::from(high) % NODE_SIZES.len()];
+        for low in 0..num_children {
+            tree.insert([high, low], low);
+        }
+        churn_keys.push([high, num_children]);
+    }
+
+    (tree, churn_keys)
+}
+
+/// Insert and then remove a key from every second level node of the tree.
+fn churn(tree: &mut TreeMap<[u8; 2], u8>, churn_keys: &[[u8; 2]]) {
+    for key in churn_keys {
+        tree.insert(*key, 0);
+    }
+    for key in churn_keys {
+        tree.remove(key);
+    }
+}
+
+fn bench<M: Measurement>(c: &mut Criterion<M>, prefix: &str) {
+    let policies = [
+        ("eager", ShrinkPolicy::Eager),
+        ("hysteresis", ShrinkPolicy::Hysteresis),
+        ("lazy", ShrinkPolicy::Lazy),
+    ];
+
+    // Check how often each policy resizes the nodes, which is what the timings
+    // below depend on
+    for (name, policy) in policies {
+        let (mut tree, churn_keys) = setup(policy);
+        let before = TreeStatsCollector::collect(&tree).unwrap().resizes;
+        for _ in 0..CHURN_ROUNDS {
+            churn(&mut tree, &churn_keys);
+        }
+        let after = TreeStatsCollector::collect(&tree).unwrap().resizes;
+        let grows = after.grow_count - before.grow_count;
+        let shrinks = after.shrink_count - before.shrink_count;
+        let expected = match policy {
+            // Every round grows every node and shrinks it back
+            ShrinkPolicy::Eager => (
+                CHURN_ROUNDS * churn_keys.len(),
+                CHURN_ROUNDS * churn_keys.len(),
+            ),
+            // The nodes are grown once, and then stay large enough
+            ShrinkPolicy::Hysteresis | ShrinkPolicy::Lazy => (churn_keys.len(), 0),
+        };
+        assert_eq!((grows, shrinks), expected, "{name}: (grows, shrinks)");
+    }
+
+    let mut group = c.benchmark_group(format!("{prefix}/churn"));
+    group.throughput(Throughput::Elements(2 * 256));
+    for (name, policy) in policies {
+        let (mut tree, churn_keys) = setup(policy);
+        group.bench_function(BenchmarkId::new("insert_remove", name), |b| {
+            b.iter(|| churn(&mut tree, &churn_keys))
+        });
+    }
+    group.finish();
+}
+
+gen_benches!(
+    bench,
+    (cycles, perfcnt::linux::HardwareEventType::CPUCycles),
+    (
+        instructions,
+        perfcnt::linux::HardwareEventType::Instructions
+    )
+);
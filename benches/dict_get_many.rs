@@ -0,0 +1,58 @@
+use std::{ffi::CString, time::Duration};
+
+use blart::TreeMap;
+use criterion::{measurement::Measurement, BenchmarkId, Criterion};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+#[macro_use]
+mod common;
+
+fn bench<M: Measurement>(c: &mut Criterion<M>, prefix: &str) {
+    let mut rng = StdRng::seed_from_u64(69420);
+    let words = include_str!("dict.txt");
+    let mut words: Vec<_> = words.lines().map(|s| CString::new(s).unwrap()).collect();
+    words.dedup();
+    words.sort();
+
+    // Unlike `dict_get`, the searches are not sorted, so that consecutive
+    // searches do not share the nodes that are already in the cache.
+    let searches: Vec<_> = words.choose_multiple(&mut rng, 4_096).cloned().collect();
+    let search_refs: Vec<_> = searches.iter().map(CString::as_c_str).collect();
+
+    let tree: TreeMap<_, _> = words.into_iter().map(|s| (s, 0usize)).collect();
+
+    let mut group = c.benchmark_group(prefix.to_string());
+    group.throughput(criterion::Throughput::Elements(searches.len() as u64));
+    group.warm_up_time(Duration::from_secs(5));
+    group.measurement_time(Duration::from_secs(15));
+    group.bench_function("get", |b| {
+        b.iter(|| {
+            for search in &search_refs {
+                std::hint::black_box(tree.get(*search));
+            }
+        });
+    });
+    for batch_len in [8, 16, 32, 64] {
+        group.bench_with_input(
+            BenchmarkId::new("get_many", batch_len),
+            &batch_len,
+            |b, &batch_len| {
+                b.iter(|| {
+                    for batch in search_refs.chunks(batch_len) {
+                        std::hint::black_box(tree.get_many(batch));
+                    }
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+gen_benches!(
+    bench,
+    (cycles, perfcnt::linux::HardwareEventType::CPUCycles),
+    (
+        instructions,
+        perfcnt::linux::HardwareEventType::Instructions
+    )
+);
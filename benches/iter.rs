@@ -1,6 +1,8 @@
 use std::{ffi::CString, ptr::NonNull};
 
-use blart::{InnerNode, InnerNode16, InnerNode256, InnerNode4, InnerNode48, NodePtr, TreeMap};
+use blart::{
+    InnerNode, InnerNode16, InnerNode256, InnerNode32, InnerNode4, InnerNode48, NodePtr, TreeMap,
+};
 use criterion::{measurement::Measurement, Criterion};
 use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 
@@ -40,7 +42,8 @@ fn iter_node<const PREFIX_LEN: usize, M: Measurement, N: InnerNode<PREFIX_LEN>>(
 fn bench<M: Measurement>(c: &mut Criterion<M>, prefix: &str) {
     iter_node::<16, _, InnerNode4<CString, usize, 16>>(c, prefix, "n4", &[1, 4]);
     iter_node::<16, _, InnerNode16<CString, usize, 16>>(c, prefix, "n16", &[5, 12, 16]);
-    iter_node::<16, _, InnerNode48<CString, usize, 16>>(c, prefix, "n48", &[17, 32, 48]);
+    iter_node::<16, _, InnerNode32<CString, usize, 16>>(c, prefix, "n32", &[17, 24, 32]);
+    iter_node::<16, _, InnerNode48<CString, usize, 16>>(c, prefix, "n48", &[33, 40, 48]);
     iter_node::<16, _, InnerNode256<CString, usize, 16>>(
         c,
         prefix,
@@ -0,0 +1,543 @@
+//! Reading the entries of a tree from text, CSV and hex files, and converting
+//! the key fields to the bytes stored in the tree.
+
+use blart::{AsBytes, BytesMapping, HeapSize, ToIBE, ToUBE, TreeMap};
+use std::{
+    borrow::Borrow,
+    fmt::{self, Display, Formatter},
+    io::BufRead,
+    str::FromStr,
+};
+
+/// The tree built from an input file.
+pub type Tree = TreeMap<Bytes, Bytes>;
+
+/// The encoded key and the value of a line of the input.
+type RawEntry = (Vec<u8>, Vec<u8>);
+
+/// A byte string stored in the tree, either as a key or a value.
+///
+/// It is displayed as text if it is valid UTF-8 (ignoring a single trailing
+/// nul byte), and as hexadecimal otherwise.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bytes(pub Box<[u8]>);
+
+impl AsBytes for Bytes {
+    fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Borrow<[u8]> for Bytes {
+    fn borrow(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl HeapSize for Bytes {
+    fn heap_size(&self) -> usize {
+        self.0.heap_size()
+    }
+}
+
+impl From<Vec<u8>> for Bytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Bytes(bytes.into_boxed_slice())
+    }
+}
+
+impl Display for Bytes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let bytes = self.0.strip_suffix(&[0]).unwrap_or(&self.0);
+        match std::str::from_utf8(bytes) {
+            Ok(text) => f.write_str(text),
+            Err(_) => write_hex(&self.0, f),
+        }
+    }
+}
+
+/// Write bytes as `0x` followed by two lowercase hexadecimal digits per byte.
+pub fn write_hex(bytes: &[u8], f: &mut Formatter<'_>) -> fmt::Result {
+    f.write_str("0x")?;
+    bytes.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+}
+
+/// Decode a string of hexadecimal digits, with an optional `0x` prefix.
+pub fn decode_hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits = text.strip_prefix("0x").unwrap_or(text);
+    if digits.len() % 2 != 0 {
+        return Err(format!("odd number of hex digits in '{text}'"));
+    }
+
+    digits
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| format!("invalid hex digits in '{text}'"))
+        })
+        .collect()
+}
+
+/// The layout of the input file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    /// One entry per line, with the key and value separated by the first
+    /// delimiter, or only a key without a delimiter
+    Text,
+    /// Comma separated values, with optionally quoted fields
+    Csv,
+    /// One entry per line, with a hex encoded key and an optional hex encoded
+    /// value separated by whitespace
+    Hex,
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(InputFormat::Text),
+            "csv" => Ok(InputFormat::Csv),
+            "hex" => Ok(InputFormat::Hex),
+            _ => Err(format!("unknown input format '{s}'")),
+        }
+    }
+}
+
+/// How the key fields of the input, and the keys given to the queries, are
+/// converted to the bytes stored in the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEncoding {
+    /// The bytes of the field, unchanged. Inserting a key which is a prefix
+    /// of another key fails with this encoding.
+    Bytes,
+    /// The bytes of the field followed by a nul byte, like a C string, so
+    /// that no key is a prefix of another
+    CStr,
+    /// The field is decoded from hexadecimal
+    Hex,
+    /// The field is parsed as an unsigned 64-bit integer, stored big endian so
+    /// that the keys are sorted by value
+    U64,
+    /// The field is parsed as a signed 64-bit integer, stored so that the
+    /// keys are sorted by value
+    I64,
+}
+
+impl FromStr for KeyEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bytes" => Ok(KeyEncoding::Bytes),
+            "cstr" => Ok(KeyEncoding::CStr),
+            "hex" => Ok(KeyEncoding::Hex),
+            "u64" => Ok(KeyEncoding::U64),
+            "i64" => Ok(KeyEncoding::I64),
+            _ => Err(format!("unknown key encoding '{s}'")),
+        }
+    }
+}
+
+impl KeyEncoding {
+    /// Convert a field to a key.
+    pub fn encode(self, field: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            KeyEncoding::CStr => {
+                if field.contains(&0) {
+                    return Err("key contains a nul byte".into());
+                }
+                let mut key = field.to_vec();
+                key.push(0);
+                Ok(key)
+            },
+            _ => self.encode_prefix(field),
+        }
+    }
+
+    /// Convert a field to the start of a key, for prefix queries and range
+    /// bounds.
+    ///
+    /// This is the same as [`KeyEncoding::encode`], except that the nul byte
+    /// of [`KeyEncoding::CStr`] is not added.
+    pub fn encode_prefix(self, field: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            KeyEncoding::Bytes | KeyEncoding::CStr => Ok(field.to_vec()),
+            KeyEncoding::Hex => decode_hex(field_text(field)?.trim()),
+            KeyEncoding::U64 => {
+                parse_number(field).map(|value: u64| ToUBE::to_bytes(value).to_vec())
+            },
+            KeyEncoding::I64 => {
+                parse_number(field).map(|value: i64| ToIBE::to_bytes(value).to_vec())
+            },
+        }
+    }
+
+    /// Write a key in the form it had in the input.
+    ///
+    /// Keys which cannot have been produced by this encoding are written as
+    /// hexadecimal.
+    pub fn fmt_key(self, key: &[u8], f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyEncoding::Bytes | KeyEncoding::CStr => {
+                let text = match self {
+                    KeyEncoding::CStr => key.strip_suffix(&[0]),
+                    _ => Some(key),
+                }
+                .and_then(|text| std::str::from_utf8(text).ok());
+                match text {
+                    Some(text) => f.write_str(text),
+                    None => write_hex(key, f),
+                }
+            },
+            KeyEncoding::Hex => write_hex(key, f),
+            KeyEncoding::U64 => match <[u8; 8]>::try_from(key) {
+                Ok(bytes) => write!(f, "{}", <ToUBE as BytesMapping<u64>>::from_bytes(bytes)),
+                Err(_) => write_hex(key, f),
+            },
+            KeyEncoding::I64 => match <[u8; 8]>::try_from(key) {
+                Ok(bytes) => write!(f, "{}", <ToIBE as BytesMapping<i64>>::from_bytes(bytes)),
+                Err(_) => write_hex(key, f),
+            },
+        }
+    }
+}
+
+/// Adapter to write a key with [`KeyEncoding::fmt_key`] as a [`Display`]
+/// impl.
+pub struct DisplayKey<'a>(pub KeyEncoding, pub &'a [u8]);
+
+impl Display for DisplayKey<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.fmt_key(self.1, f)
+    }
+}
+
+fn field_text(field: &[u8]) -> Result<&str, String> {
+    std::str::from_utf8(field).map_err(|_| "key is not valid UTF-8".into())
+}
+
+fn parse_number<T: FromStr>(field: &[u8]) -> Result<T, String> {
+    let text = field_text(field)?.trim();
+    text.parse()
+        .map_err(|_| format!("'{text}' is not a valid integer key"))
+}
+
+/// How to split the input into entries.
+#[derive(Debug, Clone)]
+pub struct InputOptions {
+    /// The layout of the input
+    pub format: InputFormat,
+    /// How the key fields are converted to keys
+    pub key_encoding: KeyEncoding,
+    /// The delimiter between the key and the value of the text format, or
+    /// between the fields of the CSV format
+    pub delimiter: Option<char>,
+    /// The index of the key field of the CSV format
+    pub key_column: usize,
+    /// The index of the value field of the CSV format, if any
+    pub value_column: Option<usize>,
+    /// Whether the first line of a CSV file is a header
+    pub header: bool,
+    /// Whether to skip invalid entries instead of stopping at the first one
+    pub skip_invalid: bool,
+}
+
+impl InputOptions {
+    /// Split a line of the input into the key field and the value, or return
+    /// `None` if the line has no entry.
+    fn parse_line(&self, line: &str) -> Result<Option<RawEntry>, String> {
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+
+        let (key, value) = match self.format {
+            InputFormat::Text => match self.delimiter {
+                Some(delimiter) => match line.split_once(delimiter) {
+                    Some((key, value)) => (key.into(), value.into()),
+                    None => (line.into(), Vec::new()),
+                },
+                None => (line.into(), Vec::new()),
+            },
+            InputFormat::Csv => {
+                let mut fields = split_csv_line(line, self.delimiter.unwrap_or(','))?;
+                let num_fields = fields.len();
+                let mut take_field = |column: usize| {
+                    fields
+                        .get_mut(column)
+                        .map(std::mem::take)
+                        .ok_or_else(|| format!("no column {column}, the line has {num_fields}"))
+                };
+
+                let key = take_field(self.key_column)?;
+                let value = match self.value_column {
+                    Some(column) => take_field(column)?,
+                    None => String::new(),
+                };
+                (key.into(), value.into())
+            },
+            InputFormat::Hex => {
+                let mut fields = line.split_whitespace();
+                let key = decode_hex(fields.next().unwrap_or_default())?;
+                let value = fields.next().map(decode_hex).transpose()?;
+                if fields.next().is_some() {
+                    return Err("expected a hex key and an optional hex value".into());
+                }
+                (key, value.unwrap_or_default())
+            },
+        };
+
+        let key = self.key_encoding.encode(&key)?;
+        Ok(Some((key, value)))
+    }
+}
+
+/// Split a line of CSV into its fields.
+///
+/// Fields may be quoted with `"`, in which case they can contain the
+/// delimiter, and `""` stands for a single quote. Quoted fields cannot span
+/// several lines.
+pub fn split_csv_line(line: &str, delimiter: char) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        let mut field = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    },
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                    None => return Err("unterminated quoted field".into()),
+                }
+            }
+            match chars.next() {
+                None => {
+                    fields.push(field);
+                    return Ok(fields);
+                },
+                Some(c) if c == delimiter => fields.push(field),
+                Some(c) => return Err(format!("unexpected '{c}' after a quoted field")),
+            }
+        } else {
+            loop {
+                match chars.next() {
+                    None => {
+                        fields.push(field);
+                        return Ok(fields);
+                    },
+                    Some(c) if c == delimiter => break,
+                    Some(c) => field.push(c),
+                }
+            }
+            fields.push(field);
+        }
+    }
+}
+
+/// An entry of the input which could not be read or inserted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadError {
+    /// The line of the entry, starting from 1
+    pub line: usize,
+    /// What was wrong with the entry
+    pub message: String,
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+/// The outcome of loading an input file.
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    /// The number of entries read from the input
+    pub entries: usize,
+    /// The number of entries which replaced an entry with the same key
+    pub replaced: usize,
+    /// The invalid entries which were skipped
+    pub skipped: Vec<LoadError>,
+}
+
+impl Display for LoadReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "read {} entries, {} replaced an existing key, {} skipped",
+            self.entries,
+            self.replaced,
+            self.skipped.len()
+        )
+    }
+}
+
+/// Read all the entries of the input into a new tree.
+///
+/// Lines which fail to parse, or whose key is a prefix of another key (or the
+/// other way around), stop the loading unless
+/// [`skip_invalid`](InputOptions::skip_invalid) is set. Later entries
+/// replace earlier entries with the same key.
+pub fn load(input: impl BufRead, options: &InputOptions) -> Result<(Tree, LoadReport), LoadError> {
+    let mut tree = Tree::new();
+    let mut report = LoadReport::default();
+
+    for (idx, line) in input.lines().enumerate() {
+        let line_number = idx + 1;
+        let error = |message: String| LoadError {
+            line: line_number,
+            message,
+        };
+
+        let line = line.map_err(|err| error(err.to_string()))?;
+        if options.format == InputFormat::Csv && options.header && line_number == 1 {
+            continue;
+        }
+
+        let result = options.parse_line(&line).and_then(|entry| {
+            let Some((key, value)) = entry else {
+                return Ok(false);
+            };
+            match tree.try_insert(key.into(), value.into()) {
+                Ok(existing) => {
+                    report.entries += 1;
+                    report.replaced += usize::from(existing.is_some());
+                    Ok(true)
+                },
+                Err(err) => Err(err.to_string()),
+            }
+        });
+
+        match result {
+            Ok(_) => {},
+            Err(message) if options.skip_invalid => report.skipped.push(error(message)),
+            Err(message) => return Err(error(message)),
+        }
+    }
+
+    Ok((tree, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(format: InputFormat, key_encoding: KeyEncoding) -> InputOptions {
+        InputOptions {
+            format,
+            key_encoding,
+            delimiter: None,
+            key_column: 0,
+            value_column: None,
+            header: false,
+            skip_invalid: false,
+        }
+    }
+
+    fn display_key(encoding: KeyEncoding, key: &[u8]) -> String {
+        DisplayKey(encoding, key).to_string()
+    }
+
+    #[test]
+    fn key_encodings_round_trip() {
+        for (encoding, field, key, display) in [
+            (KeyEncoding::Bytes, "abc", &b"abc"[..], "abc"),
+            (KeyEncoding::CStr, "abc", &b"abc\0"[..], "abc"),
+            (KeyEncoding::Hex, "00ff10", &[0, 255, 16][..], "0x00ff10"),
+            (
+                KeyEncoding::U64,
+                " 258",
+                &[0, 0, 0, 0, 0, 0, 1, 2][..],
+                "258",
+            ),
+            (
+                KeyEncoding::I64,
+                "-1",
+                &[127, 255, 255, 255, 255, 255, 255, 255][..],
+                "-1",
+            ),
+        ] {
+            assert_eq!(encoding.encode(field.as_bytes()).unwrap(), key);
+            assert_eq!(display_key(encoding, key), display);
+        }
+
+        assert_eq!(KeyEncoding::CStr.encode_prefix(b"ab").unwrap(), b"ab");
+        assert!(KeyEncoding::CStr.encode(b"a\0b").is_err());
+        assert!(KeyEncoding::U64.encode(b"-1").is_err());
+        assert!(KeyEncoding::Hex.encode(b"abc").is_err());
+        assert_eq!(display_key(KeyEncoding::U64, &[1, 2]), "0x0102");
+        assert_eq!(display_key(KeyEncoding::CStr, &[255, 0]), "0xff00");
+    }
+
+    #[test]
+    fn csv_fields() {
+        assert_eq!(split_csv_line("a,b,,c", ',').unwrap(), ["a", "b", "", "c"]);
+        assert_eq!(
+            split_csv_line(r#""a,b","say ""hi""",c"#, ',').unwrap(),
+            ["a,b", r#"say "hi""#, "c"]
+        );
+        assert_eq!(split_csv_line("a;b", ';').unwrap(), ["a", "b"]);
+        assert!(split_csv_line(r#""a"#, ',').is_err());
+        assert!(split_csv_line(r#""a"b,c"#, ',').is_err());
+    }
+
+    #[test]
+    fn load_formats() {
+        let text = "apple=1\nbanana=2=3\n\ncherry\n";
+        let mut text_options = options(InputFormat::Text, KeyEncoding::CStr);
+        text_options.delimiter = Some('=');
+        let (tree, report) = load(text.as_bytes(), &text_options).unwrap();
+        assert_eq!(report.entries, 3);
+        assert_eq!(tree.get(&b"banana\0"[..]).unwrap().0.as_ref(), b"2=3");
+        assert_eq!(tree.get(&b"cherry\0"[..]).unwrap().0.as_ref(), b"");
+
+        let csv = "id,name\n2,\"b, c\"\n1,a\n2,d\n";
+        let mut csv_options = options(InputFormat::Csv, KeyEncoding::U64);
+        csv_options.header = true;
+        csv_options.value_column = Some(1);
+        let (tree, report) = load(csv.as_bytes(), &csv_options).unwrap();
+        assert_eq!((report.entries, report.replaced), (3, 1));
+        let values: Vec<_> = tree.values().map(ToString::to_string).collect();
+        assert_eq!(values, ["a", "d"]);
+
+        let hex = "0102 6869\n0103\n";
+        let (tree, _) = load(
+            hex.as_bytes(),
+            &options(InputFormat::Hex, KeyEncoding::Bytes),
+        )
+        .unwrap();
+        assert_eq!(tree.get(&[1, 2][..]).unwrap().to_string(), "hi");
+        assert_eq!(tree.len(), 2);
+    }
+
+    #[test]
+    fn load_invalid_entries() {
+        let input = "ab\nabc\nb\n";
+        let mut text_options = options(InputFormat::Text, KeyEncoding::Bytes);
+        assert_eq!(load(input.as_bytes(), &text_options).unwrap_err().line, 2);
+
+        text_options.skip_invalid = true;
+        let (tree, report) = load(input.as_bytes(), &text_options).unwrap();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].line, 2);
+
+        let csv_options = InputOptions {
+            value_column: Some(3),
+            ..options(InputFormat::Csv, KeyEncoding::CStr)
+        };
+        let err = load("a,b\n".as_bytes(), &csv_options).unwrap_err();
+        assert_eq!(err.to_string(), "line 1: no column 3, the line has 2");
+    }
+}
@@ -0,0 +1,236 @@
+//! Command line tool which loads keys and values from a file into a
+//! [`TreeMap`](blart::TreeMap), and then prints statistics about the tree,
+//! exports its structure, runs queries, or starts a REPL to explore it.
+
+use argh::FromArgs;
+use input::{InputFormat, InputOptions, KeyEncoding};
+use session::{ExportFormat, Session, ValueFormat};
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, IsTerminal, Write},
+    path::PathBuf,
+};
+
+mod input;
+mod repl;
+mod session;
+
+#[derive(FromArgs)]
+/// Load keys and values from a file into a blart TreeMap and inspect it.
+struct Args {
+    /// layout of the input, one of 'text', 'csv' or 'hex' (default: 'text')
+    #[argh(option, default = "InputFormat::Text")]
+    format: InputFormat,
+
+    /// how the key fields are converted to bytes, one of 'bytes', 'cstr',
+    /// 'hex', 'u64' or 'i64' (default: 'bytes' for the hex format, 'cstr'
+    /// otherwise)
+    #[argh(option)]
+    key_encoding: Option<KeyEncoding>,
+
+    /// how the values are printed, one of 'text' or 'hex' (default: 'text')
+    #[argh(option, default = "ValueFormat::Text")]
+    value_format: ValueFormat,
+
+    /// delimiter between the key and the value of the text format, or
+    /// between the fields of the csv format (default: none for text, ','
+    /// for csv)
+    #[argh(option)]
+    delimiter: Option<char>,
+
+    /// index of the key column of the csv format (default: 0)
+    #[argh(option, default = "0")]
+    key_column: usize,
+
+    /// index of the value column of the csv format, if any
+    #[argh(option)]
+    value_column: Option<usize>,
+
+    /// skip the first line of the csv format
+    #[argh(switch)]
+    header: bool,
+
+    /// skip invalid lines, and keys which are a prefix of another key,
+    /// instead of failing
+    #[argh(switch)]
+    skip_invalid: bool,
+
+    /// file to read the entries from, or '-' for the standard input
+    #[argh(positional)]
+    input: PathBuf,
+
+    #[argh(subcommand)]
+    command: Command,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Stats(StatsArgs),
+    Export(ExportArgs),
+    Get(GetArgs),
+    Prefix(PrefixArgs),
+    Fuzzy(FuzzyArgs),
+    Range(RangeArgs),
+    Repl(ReplArgs),
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "stats")]
+/// Print the statistics of the tree structure and its memory usage.
+struct StatsArgs {}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "export")]
+/// Write the structure of the tree.
+struct ExportArgs {
+    /// output format, one of 'dot', 'json', 'mermaid' or 'ascii' (default:
+    /// 'dot')
+    #[argh(option, default = "ExportFormat::Dot")]
+    to: ExportFormat,
+
+    /// add the node addresses to the output
+    #[argh(switch)]
+    display_node_address: bool,
+
+    /// file to write to (default: the standard output)
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "get")]
+/// Look up a key.
+struct GetArgs {
+    /// key to look up
+    #[argh(positional)]
+    key: String,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "prefix")]
+/// List the entries whose key starts with a prefix.
+struct PrefixArgs {
+    /// prefix of the keys
+    #[argh(positional)]
+    prefix: String,
+
+    /// maximum number of entries to list
+    #[argh(option)]
+    limit: Option<usize>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "fuzzy")]
+/// List the entries whose key is within an edit distance of a key.
+struct FuzzyArgs {
+    /// key to search for
+    #[argh(positional)]
+    key: String,
+
+    /// maximum Levenshtein distance to the key (default: 1)
+    #[argh(option, default = "1")]
+    max_edit_dist: usize,
+
+    /// maximum number of entries to list
+    #[argh(option)]
+    limit: Option<usize>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "range")]
+/// List the entries whose key is in a range, in order.
+struct RangeArgs {
+    /// inclusive start of the range (default: unbounded)
+    #[argh(option)]
+    from: Option<String>,
+
+    /// exclusive end of the range (default: unbounded)
+    #[argh(option)]
+    to: Option<String>,
+
+    /// maximum number of entries to list
+    #[argh(option)]
+    limit: Option<usize>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand, name = "repl")]
+/// Start an interactive prompt to run queries against the tree.
+struct ReplArgs {}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Args = argh::from_env();
+
+    let options = InputOptions {
+        format: args.format,
+        key_encoding: args.key_encoding.unwrap_or(match args.format {
+            InputFormat::Hex => KeyEncoding::Bytes,
+            InputFormat::Text | InputFormat::Csv => KeyEncoding::CStr,
+        }),
+        delimiter: args.delimiter,
+        key_column: args.key_column,
+        value_column: args.value_column,
+        header: args.header,
+        skip_invalid: args.skip_invalid,
+    };
+
+    let reading_stdin = args.input.as_os_str() == "-";
+    let input: Box<dyn BufRead> = if reading_stdin {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(&args.input)?))
+    };
+    let (tree, report) = input::load(input, &options)?;
+    for skipped in &report.skipped {
+        eprintln!("skipped {skipped}");
+    }
+    eprintln!("{report}");
+
+    let mut session = Session {
+        tree,
+        key_encoding: options.key_encoding,
+        value_format: args.value_format,
+    };
+
+    let stdout = io::stdout();
+    let mut output = BufWriter::new(stdout.lock());
+    match args.command {
+        Command::Stats(StatsArgs {}) => session.stats(&mut output)?,
+        Command::Export(export) => match export.output {
+            Some(path) => {
+                let mut file = BufWriter::new(File::create(path)?);
+                session.export(&mut file, export.to, export.display_node_address)?;
+                file.flush()?;
+            },
+            None => session.export(&mut output, export.to, export.display_node_address)?,
+        },
+        Command::Get(get) => session.get(&mut output, &get.key)?,
+        Command::Prefix(prefix) => session.prefix(&mut output, &prefix.prefix, prefix.limit)?,
+        Command::Fuzzy(fuzzy) => {
+            session.fuzzy(&mut output, &fuzzy.key, fuzzy.max_edit_dist, fuzzy.limit)?
+        },
+        Command::Range(range) => session.range(
+            &mut output,
+            range.from.as_deref(),
+            range.to.as_deref(),
+            range.limit,
+        )?,
+        Command::Repl(ReplArgs {}) => {
+            if reading_stdin {
+                return Err(
+                    "the repl reads commands from the standard input, so the entries must be \
+                     loaded from a file"
+                        .into(),
+                );
+            }
+            let stdin = io::stdin();
+            let interactive = stdin.is_terminal();
+            repl::run(&mut session, stdin.lock(), &mut output, interactive)?;
+        },
+    }
+    output.flush()?;
+
+    Ok(())
+}
@@ -0,0 +1,279 @@
+//! An interactive prompt for running queries against a loaded tree.
+
+use crate::session::{ExportFormat, Session};
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufRead, BufWriter, Write},
+};
+
+/// The number of entries listed by a query when no limit is given.
+const DEFAULT_LIMIT: usize = 20;
+
+const HELP: &str = "\
+commands:
+  get KEY                        look up a key
+  prefix PREFIX [LIMIT]          list the entries whose key starts with PREFIX
+  fuzzy KEY DIST [LIMIT]         list the entries within DIST edits of KEY
+  range START END [LIMIT]        list the entries in START..END, '..' for no bound
+  insert KEY VALUE               insert or replace an entry
+  remove KEY                     remove an entry
+  len                            print the number of entries
+  stats                          print the tree statistics
+  export FORMAT [PATH]           write the tree as dot, json, mermaid or ascii
+  help                           print this message
+  quit                           exit
+
+Arguments containing spaces can be quoted with '\"', and '\\' escapes the
+next character.";
+
+/// A parsed line of the REPL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    Get(String),
+    Prefix(String, usize),
+    Fuzzy(String, usize, usize),
+    Range(Option<String>, Option<String>, usize),
+    Insert(String, String),
+    Remove(String),
+    Len,
+    Stats,
+    Export(ExportFormat, Option<String>),
+    Help,
+    Quit,
+}
+
+impl Command {
+    /// Parse a line, returning `None` for an empty line.
+    fn parse(line: &str) -> Result<Option<Command>, String> {
+        let words = split_words(line)?;
+        let Some((name, args)) = words.split_first() else {
+            return Ok(None);
+        };
+
+        let limit = |idx: usize| {
+            args.get(idx)
+                .map_or(Ok(DEFAULT_LIMIT), |limit| parse_number(limit, "limit"))
+        };
+        let bound = |bound: &String| (bound != "..").then(|| bound.clone());
+
+        let command = match (name.as_str(), args) {
+            ("get", [key]) => Command::Get(key.clone()),
+            ("prefix", [prefix, ..]) if args.len() <= 2 => {
+                Command::Prefix(prefix.clone(), limit(1)?)
+            },
+            ("fuzzy", [key, dist, ..]) if args.len() <= 3 => {
+                Command::Fuzzy(key.clone(), parse_number(dist, "distance")?, limit(2)?)
+            },
+            ("range", [start, end, ..]) if args.len() <= 3 => {
+                Command::Range(bound(start), bound(end), limit(2)?)
+            },
+            ("insert", [key, value]) => Command::Insert(key.clone(), value.clone()),
+            ("remove", [key]) => Command::Remove(key.clone()),
+            ("len", []) => Command::Len,
+            ("stats", []) => Command::Stats,
+            ("export", [format, path @ ..]) if path.len() <= 1 => {
+                Command::Export(format.parse()?, path.first().cloned())
+            },
+            ("help", []) => Command::Help,
+            ("quit" | "exit", []) => Command::Quit,
+            (
+                "get" | "prefix" | "fuzzy" | "range" | "insert" | "remove" | "len" | "stats"
+                | "export" | "help" | "quit" | "exit",
+                _,
+            ) => return Err(format!("wrong arguments for '{name}', see 'help'")),
+            _ => return Err(format!("unknown command '{name}', see 'help'")),
+        };
+        Ok(Some(command))
+    }
+}
+
+fn parse_number(text: &str, what: &str) -> Result<usize, String> {
+    text.parse()
+        .map_err(|_| format!("'{text}' is not a valid {what}"))
+}
+
+/// Split a line into words separated by whitespace, where `"` quotes a
+/// sequence of characters including whitespace, and `\` escapes the next
+/// character.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut in_quotes = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let escaped = chars.next().ok_or("trailing '\\'")?;
+                word.get_or_insert_with(String::new).push(escaped);
+            },
+            '"' => {
+                in_quotes = !in_quotes;
+                word.get_or_insert_with(String::new);
+            },
+            c if c.is_whitespace() && !in_quotes => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err("unterminated quote".into());
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Run commands read from `input` until it ends or `quit` is entered.
+///
+/// If `interactive` is set, a greeting and a prompt before each command are
+/// printed. Errors in a command are printed and do not stop the REPL.
+pub fn run(
+    session: &mut Session,
+    input: impl BufRead,
+    output: &mut dyn Write,
+    interactive: bool,
+) -> io::Result<()> {
+    if interactive {
+        writeln!(
+            output,
+            "loaded {} entries, type 'help' for the list of commands",
+            session.tree.len()
+        )?;
+    }
+
+    let mut lines = input.lines();
+    loop {
+        if interactive {
+            write!(output, "> ")?;
+        }
+        output.flush()?;
+
+        let Some(line) = lines.next().transpose()? else {
+            return Ok(());
+        };
+
+        let result = Command::parse(&line)
+            .map_err(Box::<dyn Error>::from)
+            .and_then(|command| match command {
+                Some(Command::Quit) => Ok(false),
+                Some(command) => run_command(session, command, output).map(|()| true),
+                None => Ok(true),
+            });
+
+        match result {
+            Ok(true) => {},
+            Ok(false) => return Ok(()),
+            Err(err) => writeln!(output, "error: {err}")?,
+        }
+    }
+}
+
+fn run_command(
+    session: &mut Session,
+    command: Command,
+    output: &mut dyn Write,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Get(key) => session.get(output, &key),
+        Command::Prefix(prefix, limit) => session.prefix(output, &prefix, Some(limit)),
+        Command::Fuzzy(key, dist, limit) => session.fuzzy(output, &key, dist, Some(limit)),
+        Command::Range(start, end, limit) => {
+            session.range(output, start.as_deref(), end.as_deref(), Some(limit))
+        },
+        Command::Insert(key, value) => {
+            let replaced = session.insert(&key, &value)?;
+            writeln!(output, "{}", if replaced { "replaced" } else { "inserted" })?;
+            Ok(())
+        },
+        Command::Remove(key) => {
+            let removed = session.remove(&key)?;
+            writeln!(output, "{}", if removed { "removed" } else { "not found" })?;
+            Ok(())
+        },
+        Command::Len => {
+            writeln!(output, "{}", session.tree.len())?;
+            Ok(())
+        },
+        Command::Stats => session.stats(output),
+        Command::Export(format, None) => session.export(output, format, false),
+        Command::Export(format, Some(path)) => {
+            let mut file = BufWriter::new(File::create(&path)?);
+            session.export(&mut file, format, false)?;
+            file.flush()?;
+            writeln!(output, "wrote {path}")?;
+            Ok(())
+        },
+        Command::Help => {
+            writeln!(output, "{HELP}")?;
+            Ok(())
+        },
+        Command::Quit => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        input::{KeyEncoding, Tree},
+        session::ValueFormat,
+    };
+
+    #[test]
+    fn split_words_quotes_and_escapes() {
+        assert_eq!(split_words("  get  key ").unwrap(), ["get", "key"]);
+        assert_eq!(
+            split_words(r#"insert "a key" two\ words"#).unwrap(),
+            ["insert", "a key", "two words"]
+        );
+        assert_eq!(split_words(r#"get """#).unwrap(), ["get", ""]);
+        assert_eq!(split_words(r#"get \"a"#).unwrap(), ["get", "\"a"]);
+        assert!(split_words(r#"get "a"#).is_err());
+        assert!(split_words("get a\\").is_err());
+    }
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(Command::parse("   ").unwrap(), None);
+        assert_eq!(
+            Command::parse("prefix ab").unwrap(),
+            Some(Command::Prefix("ab".into(), DEFAULT_LIMIT))
+        );
+        assert_eq!(
+            Command::parse("fuzzy abc 2 5").unwrap(),
+            Some(Command::Fuzzy("abc".into(), 2, 5))
+        );
+        assert_eq!(
+            Command::parse("range .. b").unwrap(),
+            Some(Command::Range(None, Some("b".into()), DEFAULT_LIMIT))
+        );
+        assert_eq!(
+            Command::parse("export json out.json").unwrap(),
+            Some(Command::Export(ExportFormat::Json, Some("out.json".into())))
+        );
+        assert!(Command::parse("get").is_err());
+        assert!(Command::parse("fuzzy abc x").is_err());
+        assert!(Command::parse("export svg").is_err());
+        assert!(Command::parse("frobnicate").is_err());
+    }
+
+    #[test]
+    fn run_script() {
+        let mut session = Session {
+            tree: Tree::new(),
+            key_encoding: KeyEncoding::CStr,
+            value_format: ValueFormat::Text,
+        };
+        let script = "insert \"a b\" 1\ninsert c 2\nget \"a b\"\nlen\nbogus\nremove c\nquit\nlen\n";
+
+        let mut output = Vec::new();
+        run(&mut session, script.as_bytes(), &mut output, false).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "inserted\ninserted\na b => 1\n2\nerror: unknown command 'bogus', see \
+             'help'\nremoved\n"
+        );
+    }
+}
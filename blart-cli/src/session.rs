@@ -0,0 +1,349 @@
+//! The queries and reports which can be run on a loaded tree, shared by the
+//! subcommands and the REPL.
+
+use crate::input::{write_hex, Bytes, DisplayKey, KeyEncoding, Tree};
+use blart::{
+    visitor::{
+        AsciiPrinter, DotPrinter, DotPrinterSettings, ExportSettings, JsonPrinter, MermaidPrinter,
+        TreeStatsCollector,
+    },
+    AsBytes,
+};
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::{self, Write},
+    str::FromStr,
+};
+
+/// How the values are written by the queries and exporters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueFormat {
+    /// As text if the value is valid UTF-8, otherwise as hexadecimal
+    Text,
+    /// Always as hexadecimal
+    Hex,
+}
+
+impl FromStr for ValueFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(ValueFormat::Text),
+            "hex" => Ok(ValueFormat::Hex),
+            _ => Err(format!("unknown value format '{s}'")),
+        }
+    }
+}
+
+/// The formats which a tree can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// Graphviz, see [`DotPrinter`]
+    Dot,
+    /// See [`JsonPrinter`]
+    Json,
+    /// See [`MermaidPrinter`]
+    Mermaid,
+    /// See [`AsciiPrinter`]
+    Ascii,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(ExportFormat::Dot),
+            "json" => Ok(ExportFormat::Json),
+            "mermaid" => Ok(ExportFormat::Mermaid),
+            "ascii" => Ok(ExportFormat::Ascii),
+            _ => Err(format!("unknown export format '{s}'")),
+        }
+    }
+}
+
+/// A loaded tree, along with the settings needed to convert between the keys
+/// and values typed by the user and the bytes stored in the tree.
+pub struct Session {
+    /// The loaded tree
+    pub tree: Tree,
+    /// How the keys of the input were encoded
+    pub key_encoding: KeyEncoding,
+    /// How the values are written
+    pub value_format: ValueFormat,
+}
+
+impl Session {
+    /// Look up a single key.
+    pub fn get(&self, output: &mut dyn Write, key: &str) -> Result<(), Box<dyn Error>> {
+        let key = self.key_encoding.encode(key.as_bytes())?;
+        match self.tree.get_key_value(key.as_slice()) {
+            Some((key, value)) => self.write_entry(output, key, value)?,
+            None => writeln!(output, "not found")?,
+        }
+        Ok(())
+    }
+
+    /// List the entries whose key starts with the given prefix.
+    pub fn prefix(
+        &self,
+        output: &mut dyn Write,
+        prefix: &str,
+        limit: Option<usize>,
+    ) -> Result<(), Box<dyn Error>> {
+        let prefix = self.key_encoding.encode_prefix(prefix.as_bytes())?;
+        self.write_entries(output, self.tree.prefix(&prefix), limit)?;
+        Ok(())
+    }
+
+    /// List the entries whose key is within `max_edit_dist` of the given key,
+    /// sorted by key.
+    pub fn fuzzy(
+        &self,
+        output: &mut dyn Write,
+        key: &str,
+        max_edit_dist: usize,
+        limit: Option<usize>,
+    ) -> Result<(), Box<dyn Error>> {
+        let key = self.key_encoding.encode(key.as_bytes())?;
+        let mut entries: Vec<_> = self.tree.fuzzy(key.as_slice(), max_edit_dist).collect();
+        entries.sort_unstable_by_key(|(key, _)| *key);
+        self.write_entries(output, entries, limit)?;
+        Ok(())
+    }
+
+    /// List the entries whose key is in `start..end`, where a missing bound
+    /// is unbounded.
+    pub fn range(
+        &self,
+        output: &mut dyn Write,
+        start: Option<&str>,
+        end: Option<&str>,
+        limit: Option<usize>,
+    ) -> Result<(), Box<dyn Error>> {
+        let encode = |bound: Option<&str>| {
+            bound
+                .map(|bound| self.key_encoding.encode_prefix(bound.as_bytes()))
+                .transpose()
+        };
+        let start = encode(start)?;
+        let end = encode(end)?;
+
+        let first = match &start {
+            Some(start) => self.tree.ceiling(start.as_slice()),
+            None => self.tree.first_key_value(),
+        };
+        let entries = std::iter::successors(first, |(key, _)| self.tree.successor(key.as_bytes()))
+            .take_while(|(key, _)| end.as_ref().map_or(true, |end| key.as_bytes() < end));
+
+        self.write_entries(output, entries, limit)?;
+        Ok(())
+    }
+
+    /// Print the statistics of the tree structure and its memory usage.
+    pub fn stats(&self, output: &mut dyn Write) -> Result<(), Box<dyn Error>> {
+        let Some(stats) = TreeStatsCollector::collect(&self.tree) else {
+            writeln!(output, "the tree is empty")?;
+            return Ok(());
+        };
+
+        writeln!(output, "{stats}")?;
+
+        let usage = self.tree.memory_usage();
+        writeln!(output, "{usage:#?}")?;
+        writeln!(
+            output,
+            "{} bytes in total, {:.2} bytes of node overhead per key byte",
+            usage.total(),
+            (usage.node_overhead() as f64) / (stats.leaf.sum_key_bytes as f64)
+        )?;
+
+        Ok(())
+    }
+
+    /// Write the structure of the tree in the given format.
+    pub fn export(
+        &self,
+        output: &mut dyn Write,
+        format: ExportFormat,
+        display_node_address: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let key_encoding = self.key_encoding;
+        let value_format = self.value_format;
+        let settings = ExportSettings {
+            display_node_address,
+            format_key: move |key: &Bytes, f: &mut Formatter<'_>| {
+                key_encoding.fmt_key(key.as_bytes(), f)
+            },
+            format_value: move |value: &Bytes, f: &mut Formatter<'_>| {
+                fmt_value(value_format, value, f)
+            },
+        };
+
+        let result = match format {
+            ExportFormat::Dot => DotPrinter::print(
+                output,
+                &self.tree,
+                DotPrinterSettings {
+                    display_node_address,
+                },
+            ),
+            ExportFormat::Json => JsonPrinter::print(output, &self.tree, settings),
+            ExportFormat::Mermaid => MermaidPrinter::print(output, &self.tree, settings),
+            ExportFormat::Ascii => AsciiPrinter::print(output, &self.tree, settings),
+        };
+
+        match result {
+            Some(result) => result?,
+            None => eprintln!("the tree is empty, nothing was exported"),
+        }
+        Ok(())
+    }
+
+    /// Insert an entry, returning whether it replaced an existing entry.
+    pub fn insert(&mut self, key: &str, value: &str) -> Result<bool, Box<dyn Error>> {
+        let key = self.key_encoding.encode(key.as_bytes())?;
+        let existing = self.tree.try_insert(key.into(), Vec::from(value).into())?;
+        Ok(existing.is_some())
+    }
+
+    /// Remove an entry, returning whether it was present.
+    pub fn remove(&mut self, key: &str) -> Result<bool, Box<dyn Error>> {
+        let key = self.key_encoding.encode(key.as_bytes())?;
+        Ok(self.tree.remove(key.as_slice()).is_some())
+    }
+
+    fn write_entry(&self, output: &mut dyn Write, key: &Bytes, value: &Bytes) -> io::Result<()> {
+        writeln!(
+            output,
+            "{} => {}",
+            DisplayKey(self.key_encoding, key.as_bytes()),
+            DisplayValue(self.value_format, value)
+        )
+    }
+
+    /// Write the entries, up to `limit`, followed by the number of entries
+    /// which were not written.
+    fn write_entries<'a>(
+        &self,
+        output: &mut dyn Write,
+        entries: impl IntoIterator<Item = (&'a Bytes, &'a Bytes)>,
+        limit: Option<usize>,
+    ) -> io::Result<()> {
+        let mut entries = entries.into_iter();
+        let mut written = 0;
+        for (key, value) in entries.by_ref().take(limit.unwrap_or(usize::MAX)) {
+            self.write_entry(output, key, value)?;
+            written += 1;
+        }
+
+        let remaining = entries.count();
+        if remaining > 0 {
+            writeln!(output, "... {remaining} more")?;
+        } else if written == 0 {
+            writeln!(output, "no entries")?;
+        }
+        Ok(())
+    }
+}
+
+fn fmt_value(format: ValueFormat, value: &Bytes, f: &mut Formatter<'_>) -> fmt::Result {
+    match format {
+        ValueFormat::Text => Display::fmt(value, f),
+        ValueFormat::Hex => write_hex(value.as_bytes(), f),
+    }
+}
+
+/// Adapter to write a value with [`fmt_value`] as a [`Display`] impl.
+struct DisplayValue<'a>(ValueFormat, &'a Bytes);
+
+impl Display for DisplayValue<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        fmt_value(self.0, self.1, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(keys: &[&str]) -> Session {
+        let mut session = Session {
+            tree: Tree::new(),
+            key_encoding: KeyEncoding::CStr,
+            value_format: ValueFormat::Text,
+        };
+        for (idx, key) in keys.iter().enumerate() {
+            session.insert(key, &idx.to_string()).unwrap();
+        }
+        session
+    }
+
+    fn run(f: impl FnOnce(&mut dyn Write) -> Result<(), Box<dyn Error>>) -> String {
+        let mut output = Vec::new();
+        f(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn queries() {
+        let session = session(&["apple", "apricot", "banana", "band", "cherry"]);
+
+        assert_eq!(run(|out| session.get(out, "band")), "band => 3\n");
+        assert_eq!(run(|out| session.get(out, "ban")), "not found\n");
+        assert_eq!(
+            run(|out| session.prefix(out, "ap", None)),
+            "apple => 0\napricot => 1\n"
+        );
+        assert_eq!(
+            run(|out| session.prefix(out, "", Some(2))),
+            "apple => 0\napricot => 1\n... 3 more\n"
+        );
+        assert_eq!(
+            run(|out| session.fuzzy(out, "bands", 1, None)),
+            "band => 3\n"
+        );
+        assert_eq!(
+            run(|out| session.range(out, Some("apricot"), Some("band"), None)),
+            "apricot => 1\nbanana => 2\n"
+        );
+        assert_eq!(
+            run(|out| session.range(out, Some("c"), None, None)),
+            "cherry => 4\n"
+        );
+        assert_eq!(
+            run(|out| session.range(out, Some("d"), None, None)),
+            "no entries\n"
+        );
+    }
+
+    #[test]
+    fn insert_and_remove() {
+        let mut session = session(&["a"]);
+        session.value_format = ValueFormat::Hex;
+
+        assert!(!session.insert("b", "hi").unwrap());
+        assert!(session.insert("b", "ho").unwrap());
+        assert_eq!(run(|out| session.get(out, "b")), "b => 0x686f\n");
+        assert!(session.remove("a").unwrap());
+        assert!(!session.remove("a").unwrap());
+        assert_eq!(session.tree.len(), 1);
+    }
+
+    #[test]
+    fn exports() {
+        let session = session(&["ab", "ac"]);
+
+        let ascii = run(|out| session.export(out, ExportFormat::Ascii, false));
+        assert!(ascii.contains("Leaf ab => 0"));
+        let json = run(|out| session.export(out, ExportFormat::Json, false));
+        assert!(json.contains(r#""key":"ac","value":"1""#));
+        let dot = run(|out| session.export(out, ExportFormat::Dot, false));
+        assert!(dot.starts_with("strict digraph G {"));
+
+        let stats = run(|out| session.stats(out));
+        assert!(stats.contains("bytes in total"));
+    }
+}
@@ -0,0 +1,141 @@
+//! Compare the time and memory used by [`TreeMap`](blart::TreeMap),
+//! [`BTreeMap`](std::collections::BTreeMap) and
+//! [`HashMap`](std::collections::HashMap) on the same workloads.
+//!
+//! Each workload is run against every map for each of the key sets generated
+//! by [`blart::tests_common`], and the median time, peak heap usage and number
+//! of allocations are reported. Build in release mode to get meaningful times:
+//!
+//! ```text
+//! cargo run --release --example compare_maps -- --size 100000
+//! ```
+//!
+//! The memory is measured with [`dhat`], which is installed as the global
+//! allocator, so all the times include its small bookkeeping cost on every
+//! allocation.
+
+use argh::FromArgs;
+use blart::TreeMap;
+use maps::MapKind;
+use rand::{rngs::StdRng, SeedableRng};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+};
+use workload::{KeySet, Measurement, Workload, WorkloadInput};
+
+mod maps;
+mod workload;
+
+#[global_allocator]
+static ALLOC: dhat::Alloc = dhat::Alloc;
+
+/// Compare TreeMap, BTreeMap and HashMap on generated workloads
+#[derive(FromArgs)]
+struct CompareMaps {
+    /// key set to generate, one of 'skewed', 'fixed-length' or 'prefixed',
+    /// can be repeated (default: all)
+    #[argh(option)]
+    keys: Vec<KeySet>,
+
+    /// map to measure, one of 'treemap', 'btreemap' or 'hashmap', can be
+    /// repeated (default: all)
+    #[argh(option)]
+    map: Vec<MapKind>,
+
+    /// workload to run, one of 'insert', 'get', 'remove', 'iter', 'prefix' or
+    /// 'range', can be repeated (default: all)
+    #[argh(option)]
+    workload: Vec<Workload>,
+
+    /// number of keys in each key set (default: 10000)
+    #[argh(option, default = "10_000")]
+    size: usize,
+
+    /// number of queries of the prefix and range workloads (default: 1000)
+    #[argh(option, default = "1000")]
+    queries: usize,
+
+    /// number of keys spanned by each range query (default: 100)
+    #[argh(option, default = "100")]
+    range_len: usize,
+
+    /// number of timed runs of each workload (default: 10)
+    #[argh(option, default = "10")]
+    iterations: usize,
+
+    /// seed of the random key sampling and ordering (default: 0)
+    #[argh(option, default = "0")]
+    seed: u64,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: CompareMaps = argh::from_env();
+    if args.size == 0 {
+        return Err("the number of keys must be greater than 0".into());
+    }
+
+    let key_sets = or_all(args.keys, &KeySet::ALL);
+    let maps = or_all(args.map, &MapKind::ALL);
+    let workloads = or_all(args.workload, &Workload::ALL);
+
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    for key_set in key_sets {
+        let keys = key_set.generate(args.size, &mut rng);
+        let input = WorkloadInput::new(keys, args.queries, args.range_len, &mut rng);
+
+        println!(
+            "{key_set}: {} keys, {} key bytes",
+            input.keys.len(),
+            input.key_bytes()
+        );
+        println!(
+            "{:<8} {:<8} {:>12} {:>12} {:>12}",
+            "workload", "map", "median time", "peak bytes", "allocations"
+        );
+
+        for &workload in &workloads {
+            let mut expected_checksum = None;
+            for &map in &maps {
+                let measurement = measure(map, workload, &input, args.iterations);
+                println!(
+                    "{workload:<8} {map:<8} {:>12} {:>12} {:>12}",
+                    format!("{:.3?}", measurement.time),
+                    measurement.peak_bytes,
+                    measurement.allocations
+                );
+
+                let expected = *expected_checksum.get_or_insert(measurement.checksum);
+                assert_eq!(
+                    measurement.checksum, expected,
+                    "the {workload} workload visited different entries in the {map}"
+                );
+            }
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+/// Return the selected items, or all of them if none were selected.
+fn or_all<T: Clone>(selected: Vec<T>, all: &[T]) -> Vec<T> {
+    if selected.is_empty() {
+        all.to_vec()
+    } else {
+        selected
+    }
+}
+
+fn measure(
+    map: MapKind,
+    workload: Workload,
+    input: &WorkloadInput,
+    iterations: usize,
+) -> Measurement {
+    match map {
+        MapKind::TreeMap => workload.measure::<TreeMap<&[u8], usize>>(input, iterations),
+        MapKind::BTreeMap => workload.measure::<BTreeMap<&[u8], usize>>(input, iterations),
+        MapKind::HashMap => workload.measure::<HashMap<&[u8], usize>>(input, iterations),
+    }
+}
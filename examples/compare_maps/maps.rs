@@ -0,0 +1,198 @@
+//! The [`MapUnderTest`] trait, and its implementations for the maps which are
+//! compared.
+
+use blart::TreeMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    ops::Bound,
+    str::FromStr,
+};
+
+/// The operations used by the workloads, so that each workload can be run
+/// against any of the maps.
+///
+/// The keys are borrowed from the generated input, so the memory measured for
+/// a map only covers its own structure and not copies of the keys.
+pub trait MapUnderTest<'k>: Default {
+    /// Insert an entry, the key is never a prefix of another key.
+    fn insert(&mut self, key: &'k [u8], value: usize);
+
+    /// Look up the value of a key.
+    fn get(&self, key: &[u8]) -> Option<&usize>;
+
+    /// Remove an entry, returning its value.
+    fn remove(&mut self, key: &[u8]) -> Option<usize>;
+
+    /// Return the number of entries.
+    fn len(&self) -> usize;
+
+    /// Call `f` with every entry of the map.
+    fn for_each(&self, f: impl FnMut(&[u8], &usize));
+
+    /// Call `f` with every entry whose key starts with `prefix`.
+    fn for_each_prefix(&self, prefix: &[u8], f: impl FnMut(&[u8], &usize));
+
+    /// Call `f` with every entry whose key is in `start..end`.
+    fn for_each_in_range(&self, start: &[u8], end: &[u8], f: impl FnMut(&[u8], &usize));
+}
+
+impl<'k> MapUnderTest<'k> for TreeMap<&'k [u8], usize> {
+    fn insert(&mut self, key: &'k [u8], value: usize) {
+        self.try_insert(key, value)
+            .expect("the generated keys should not be prefixes of each other");
+    }
+
+    fn get(&self, key: &[u8]) -> Option<&usize> {
+        TreeMap::get(self, key)
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Option<usize> {
+        TreeMap::remove(self, key)
+    }
+
+    fn len(&self) -> usize {
+        TreeMap::len(self)
+    }
+
+    fn for_each(&self, mut f: impl FnMut(&[u8], &usize)) {
+        for (key, value) in self.iter() {
+            f(key, value);
+        }
+    }
+
+    fn for_each_prefix(&self, prefix: &[u8], mut f: impl FnMut(&[u8], &usize)) {
+        for (key, value) in self.prefix(prefix) {
+            f(key, value);
+        }
+    }
+
+    fn for_each_in_range(&self, start: &[u8], end: &[u8], mut f: impl FnMut(&[u8], &usize)) {
+        // There is no public range iterator yet, so step through the range
+        // one successor at a time
+        let mut entry = self.ceiling(start);
+        while let Some((key, value)) = entry.filter(|(key, _)| **key < end) {
+            f(key, value);
+            entry = self.successor(*key);
+        }
+    }
+}
+
+impl<'k> MapUnderTest<'k> for BTreeMap<&'k [u8], usize> {
+    fn insert(&mut self, key: &'k [u8], value: usize) {
+        BTreeMap::insert(self, key, value);
+    }
+
+    fn get(&self, key: &[u8]) -> Option<&usize> {
+        BTreeMap::get(self, key)
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Option<usize> {
+        BTreeMap::remove(self, key)
+    }
+
+    fn len(&self) -> usize {
+        BTreeMap::len(self)
+    }
+
+    fn for_each(&self, mut f: impl FnMut(&[u8], &usize)) {
+        for (key, value) in self.iter() {
+            f(key, value);
+        }
+    }
+
+    fn for_each_prefix(&self, prefix: &[u8], mut f: impl FnMut(&[u8], &usize)) {
+        let entries = self
+            .range::<[u8], _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(|(key, _)| key.starts_with(prefix));
+        for (key, value) in entries {
+            f(key, value);
+        }
+    }
+
+    fn for_each_in_range(&self, start: &[u8], end: &[u8], mut f: impl FnMut(&[u8], &usize)) {
+        for (key, value) in self.range::<[u8], _>((Bound::Included(start), Bound::Excluded(end))) {
+            f(key, value);
+        }
+    }
+}
+
+/// The [`HashMap`] has no order, so the prefix and range workloads scan all
+/// of its entries.
+impl<'k> MapUnderTest<'k> for HashMap<&'k [u8], usize> {
+    fn insert(&mut self, key: &'k [u8], value: usize) {
+        HashMap::insert(self, key, value);
+    }
+
+    fn get(&self, key: &[u8]) -> Option<&usize> {
+        HashMap::get(self, key)
+    }
+
+    fn remove(&mut self, key: &[u8]) -> Option<usize> {
+        HashMap::remove(self, key)
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+
+    fn for_each(&self, mut f: impl FnMut(&[u8], &usize)) {
+        for (key, value) in self.iter() {
+            f(key, value);
+        }
+    }
+
+    fn for_each_prefix(&self, prefix: &[u8], mut f: impl FnMut(&[u8], &usize)) {
+        for (key, value) in self.iter().filter(|(key, _)| key.starts_with(prefix)) {
+            f(key, value);
+        }
+    }
+
+    fn for_each_in_range(&self, start: &[u8], end: &[u8], mut f: impl FnMut(&[u8], &usize)) {
+        for (key, value) in self.iter().filter(|(key, _)| (start..end).contains(*key)) {
+            f(key, value);
+        }
+    }
+}
+
+/// The maps which can be compared, used to pick a [`MapUnderTest`]
+/// implementation from the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::enum_variant_names)] // the variants are named after the maps
+pub enum MapKind {
+    /// [`TreeMap`]
+    TreeMap,
+    /// [`BTreeMap`]
+    BTreeMap,
+    /// [`HashMap`], with the default hasher
+    HashMap,
+}
+
+impl MapKind {
+    /// All the maps, in the order they are reported.
+    pub const ALL: [MapKind; 3] = [MapKind::TreeMap, MapKind::BTreeMap, MapKind::HashMap];
+}
+
+impl FromStr for MapKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "treemap" => Ok(MapKind::TreeMap),
+            "btreemap" => Ok(MapKind::BTreeMap),
+            "hashmap" => Ok(MapKind::HashMap),
+            _ => Err(format!("unknown map '{s}'")),
+        }
+    }
+}
+
+impl fmt::Display for MapKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MapKind::TreeMap => "TreeMap",
+            MapKind::BTreeMap => "BTreeMap",
+            MapKind::HashMap => "HashMap",
+        };
+        f.pad(name)
+    }
+}
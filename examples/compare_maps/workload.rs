@@ -0,0 +1,339 @@
+//! The generated key sets, and the workloads which are measured against each
+//! map.
+
+use crate::maps::MapUnderTest;
+use blart::tests_common::{
+    generate_key_fixed_length, generate_key_with_prefix, generate_keys_skewed, PrefixExpansion,
+};
+use rand::{seq::SliceRandom, Rng};
+use std::{
+    fmt,
+    hint::black_box,
+    ops::Range,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+/// The number of bytes in the keys of the fixed length and prefixed key sets,
+/// before the prefix expansions.
+const KEY_LEVELS: usize = 4;
+
+/// The maximum number of keys in the skewed key set.
+///
+/// The length of the skewed keys grows with their number, so the size of the
+/// key set is quadratic in the number of keys.
+const MAX_SKEWED_KEYS: usize = 1024;
+
+/// The generators from [`blart::tests_common`] which the keys are built
+/// with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeySet {
+    /// Keys of increasing length, see [`generate_keys_skewed`]
+    Skewed,
+    /// Dense keys of the same length, see [`generate_key_fixed_length`]
+    FixedLength,
+    /// Dense keys with long shared runs of bytes, see
+    /// [`generate_key_with_prefix`]
+    Prefixed,
+}
+
+impl KeySet {
+    /// All the key sets, in the order they are reported.
+    pub const ALL: [KeySet; 3] = [KeySet::Skewed, KeySet::FixedLength, KeySet::Prefixed];
+
+    /// Generate `size` keys, none of which is a prefix of another.
+    ///
+    /// The skewed key set is capped at [`MAX_SKEWED_KEYS`]. The other key
+    /// sets are generated with the smallest level widths which give at least
+    /// `size` keys, and then sampled down to `size` keys.
+    ///
+    /// # Panics
+    ///  - Panics if `size` is 0.
+    pub fn generate(self, size: usize, rng: &mut impl Rng) -> Vec<Box<[u8]>> {
+        let level_widths = [level_width(size); KEY_LEVELS];
+        let keys: Vec<_> = match self {
+            KeySet::Skewed => return generate_keys_skewed(size.min(MAX_SKEWED_KEYS)).collect(),
+            KeySet::FixedLength => generate_key_fixed_length(level_widths).collect(),
+            KeySet::Prefixed => generate_key_with_prefix(
+                level_widths,
+                [
+                    PrefixExpansion {
+                        base_index: 1,
+                        expanded_length: 4,
+                    },
+                    PrefixExpansion {
+                        base_index: 3,
+                        expanded_length: 8,
+                    },
+                ],
+            )
+            .collect(),
+        };
+
+        keys.choose_multiple(rng, size).cloned().collect()
+    }
+}
+
+/// Return the smallest level width which makes the key generators produce at
+/// least `size` keys of [`KEY_LEVELS`] bytes.
+fn level_width(size: usize) -> u8 {
+    // A level width of `w` gives `w + 1` distinct bytes at each level
+    (1..u8::MAX)
+        .find(|width| (usize::from(*width) + 1).pow(KEY_LEVELS as u32) >= size)
+        .unwrap_or(u8::MAX)
+}
+
+impl FromStr for KeySet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skewed" => Ok(KeySet::Skewed),
+            "fixed-length" => Ok(KeySet::FixedLength),
+            "prefixed" => Ok(KeySet::Prefixed),
+            _ => Err(format!("unknown key set '{s}'")),
+        }
+    }
+}
+
+impl fmt::Display for KeySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            KeySet::Skewed => "skewed",
+            KeySet::FixedLength => "fixed-length",
+            KeySet::Prefixed => "prefixed",
+        };
+        f.pad(name)
+    }
+}
+
+/// The keys and queries shared by all the workloads of a key set.
+pub struct WorkloadInput {
+    /// The keys, in the random order in which they are inserted, looked up
+    /// and removed
+    pub keys: Vec<Box<[u8]>>,
+    /// The prefixes of the prefix workload, each one is the first half of a
+    /// random key
+    pub prefixes: Vec<Box<[u8]>>,
+    /// The ranges of the range workload, each one spanning up to
+    /// `range_len` keys
+    pub ranges: Vec<Range<Box<[u8]>>>,
+}
+
+impl WorkloadInput {
+    /// Shuffle the keys and pick `queries` random prefixes and ranges.
+    ///
+    /// # Panics
+    ///  - Panics if `keys` is empty.
+    pub fn new(
+        mut keys: Vec<Box<[u8]>>,
+        queries: usize,
+        range_len: usize,
+        rng: &mut impl Rng,
+    ) -> Self {
+        assert!(!keys.is_empty(), "there must be at least one key");
+
+        let mut sorted_keys = keys.clone();
+        sorted_keys.sort_unstable();
+        let last = sorted_keys.len() - 1;
+
+        let prefixes = (0..queries)
+            .map(|_| {
+                let key = &sorted_keys[rng.gen_range(0..=last)];
+                key[..key.len() / 2].into()
+            })
+            .collect();
+        let ranges = (0..queries)
+            .map(|_| {
+                let start = rng.gen_range(0..=last);
+                let end = start.saturating_add(range_len).min(last);
+                sorted_keys[start].clone()..sorted_keys[end].clone()
+            })
+            .collect();
+
+        keys.shuffle(rng);
+
+        WorkloadInput {
+            keys,
+            prefixes,
+            ranges,
+        }
+    }
+
+    /// Return the total number of bytes in the keys.
+    pub fn key_bytes(&self) -> usize {
+        self.keys.iter().map(|key| key.len()).sum()
+    }
+}
+
+/// An operation which is measured against each map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workload {
+    /// Insert all the keys into an empty map
+    Insert,
+    /// Look up all the keys
+    Get,
+    /// Remove all the keys
+    Remove,
+    /// Iterate over all the entries
+    Iter,
+    /// Iterate over the entries of each prefix query
+    Prefix,
+    /// Iterate over the entries of each range query
+    Range,
+}
+
+impl Workload {
+    /// All the workloads, in the order they are reported.
+    pub const ALL: [Workload; 6] = [
+        Workload::Insert,
+        Workload::Get,
+        Workload::Remove,
+        Workload::Iter,
+        Workload::Prefix,
+        Workload::Range,
+    ];
+
+    /// Create the map which the workload starts from, which is empty for the
+    /// insert workload and holds all the keys otherwise.
+    fn setup<'k, M: MapUnderTest<'k>>(self, input: &'k WorkloadInput) -> M {
+        let mut map = M::default();
+        if self != Workload::Insert {
+            Workload::Insert.run(&mut map, input);
+        }
+        map
+    }
+
+    /// Run the workload, returning a checksum of the entries it visited.
+    ///
+    /// The checksum only depends on which entries were visited, so it is the
+    /// same for every map.
+    fn run<'k, M: MapUnderTest<'k>>(self, map: &mut M, input: &'k WorkloadInput) -> usize {
+        let mut checksum = 0usize;
+        let mut visit = |_: &[u8], value: &usize| checksum = checksum.wrapping_add(*value);
+
+        match self {
+            Workload::Insert => {
+                for (value, key) in input.keys.iter().enumerate() {
+                    map.insert(black_box(key), value);
+                }
+                return map.len();
+            },
+            Workload::Get => {
+                for key in &input.keys {
+                    if let Some(value) = map.get(black_box(key)) {
+                        visit(key, value);
+                    }
+                }
+            },
+            Workload::Remove => {
+                for key in &input.keys {
+                    if let Some(value) = map.remove(black_box(key)) {
+                        visit(key, &value);
+                    }
+                }
+            },
+            Workload::Iter => map.for_each(visit),
+            Workload::Prefix => {
+                for prefix in &input.prefixes {
+                    map.for_each_prefix(black_box(prefix), &mut visit);
+                }
+            },
+            Workload::Range => {
+                for range in &input.ranges {
+                    map.for_each_in_range(
+                        black_box(&range.start),
+                        black_box(&range.end),
+                        &mut visit,
+                    );
+                }
+            },
+        }
+
+        checksum
+    }
+
+    /// Measure the workload against the map `M`.
+    ///
+    /// The workload is timed `iterations` times, each time starting from a
+    /// new map, and the median time is returned. The memory is measured in one
+    /// more run under a [`dhat::Profiler`], which is not timed since the
+    /// profiler records a backtrace for every allocation.
+    pub fn measure<'k, M: MapUnderTest<'k>>(
+        self,
+        input: &'k WorkloadInput,
+        iterations: usize,
+    ) -> Measurement {
+        let mut times: Vec<_> = (0..iterations.max(1))
+            .map(|_| {
+                let mut map = self.setup::<M>(input);
+                let start = Instant::now();
+                black_box(self.run(&mut map, input));
+                let time = start.elapsed();
+                drop(map);
+                time
+            })
+            .collect();
+        times.sort_unstable();
+
+        let profiler = dhat::Profiler::builder().testing().build();
+        let mut map = self.setup::<M>(input);
+        let before = dhat::HeapStats::get();
+        let checksum = self.run(&mut map, input);
+        let after = dhat::HeapStats::get();
+        drop(map);
+        drop(profiler);
+
+        Measurement {
+            time: times[times.len() / 2],
+            peak_bytes: after.max_bytes,
+            allocations: after.total_blocks - before.total_blocks,
+            checksum,
+        }
+    }
+}
+
+impl FromStr for Workload {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "insert" => Ok(Workload::Insert),
+            "get" => Ok(Workload::Get),
+            "remove" => Ok(Workload::Remove),
+            "iter" => Ok(Workload::Iter),
+            "prefix" => Ok(Workload::Prefix),
+            "range" => Ok(Workload::Range),
+            _ => Err(format!("unknown workload '{s}'")),
+        }
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Workload::Insert => "insert",
+            Workload::Get => "get",
+            Workload::Remove => "remove",
+            Workload::Iter => "iter",
+            Workload::Prefix => "prefix",
+            Workload::Range => "range",
+        };
+        f.pad(name)
+    }
+}
+
+/// The result of measuring a workload against a map.
+#[derive(Debug, Clone, Copy)]
+pub struct Measurement {
+    /// The median time of the workload
+    pub time: Duration,
+    /// The maximum number of bytes on the heap while the map was created and
+    /// the workload ran, which does not include the keys
+    pub peak_bytes: usize,
+    /// The number of heap allocations made by the workload itself
+    pub allocations: u64,
+    /// The checksum of the entries visited by the workload, see
+    /// [`Workload::run`]
+    pub checksum: usize,
+}
@@ -58,7 +58,7 @@ struct WordStats<'b> {
     last_word_count: u64,
 }
 
-fn count_words_blart(contents: &[u8]) -> WordStats {
+fn count_words_blart(contents: &[u8]) -> WordStats<'_> {
     let mut map = TreeMap::<&[u8], u64>::new();
 
     for word in contents.split_inclusive(|b| *b == SPLIT_BYTE) {
@@ -88,7 +88,7 @@ fn count_words_blart(contents: &[u8]) -> WordStats {
 
 const SPLIT_BYTE: u8 = b' ';
 
-fn count_words_std(contents: &[u8]) -> WordStats {
+fn count_words_std(contents: &[u8]) -> WordStats<'_> {
     let mut map = BTreeMap::<&[u8], u64>::new();
 
     for word in contents.split_inclusive(|b| *b == SPLIT_BYTE) {
@@ -50,6 +50,27 @@ fn collect_and_output_stats(tree: TreeMap<Box<[u8]>, ()>) -> Result<(), Box<dyn
 
     println!("{overhead_bytes_per_key_byte} bytes of overhead, per byte of key stored in tree");
 
+    let usage = tree.memory_usage();
+    println!("{usage:#?}");
+    println!(
+        "{} bytes in total, including keys, values and allocator slack",
+        usage.total()
+    );
+
+    for (name, histogram) in [
+        ("leaf depth", &stats.leaf_depth),
+        ("key length", &stats.key_len),
+    ] {
+        println!(
+            "{name}: mean {:.2}, p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+            histogram.mean(),
+            histogram.percentile(0.5).unwrap_or_default(),
+            histogram.percentile(0.9).unwrap_or_default(),
+            histogram.percentile(0.99).unwrap_or_default(),
+            histogram.max().unwrap_or_default(),
+        );
+    }
+
     Ok(())
 }
 
@@ -1,15 +1,19 @@
 use argh::FromArgs;
 use blart::{
-    visitor::{DotPrinter, DotPrinterSettings},
+    visitor::{
+        format_debug, format_display, format_hex, format_utf8_lossy, AsciiPrinter, DotPrinter,
+        DotPrinterSettings, ExportSettings, JsonPrinter, MermaidPrinter,
+    },
     AsBytes, NoPrefixesBytes, TreeMap,
 };
 use std::{
     error::Error,
     ffi::CString,
-    fmt::Display,
+    fmt::{self, Display, Formatter},
     fs::OpenOptions,
     io::{self, BufRead, BufReader, BufWriter, Write},
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
 #[derive(FromArgs)]
@@ -19,6 +23,20 @@ struct TreeToDotArgs {
     #[argh(option)]
     delimiter: Option<char>,
 
+    /// output format, one of 'dot', 'json', 'mermaid' or 'ascii' (default:
+    /// 'dot')
+    #[argh(option, default = "OutputFormat::Dot")]
+    format: OutputFormat,
+
+    /// how to write the keys with the 'json', 'mermaid' and 'ascii' formats,
+    /// one of 'utf8', 'hex' or 'debug' (default: 'utf8')
+    #[argh(option, default = "KeyFormat::Utf8")]
+    key_format: KeyFormat,
+
+    /// add the node addresses to the output
+    #[argh(switch)]
+    display_node_address: bool,
+
     /// input to read keys from an external file
     #[argh(positional)]
     input_file: PathBuf,
@@ -49,23 +67,66 @@ fn main() {
 
         let mut buffer = BufWriter::new(handle);
 
-        write_tree(&mut buffer, tree)
+        write_tree(&mut buffer, tree, &args)
     } else {
         let file = OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
             .truncate(true)
-            .open(args.output_location)
+            .open(&args.output_location)
             .expect("Failed to open file for output");
 
         let mut buffer = BufWriter::new(file);
 
-        write_tree(&mut buffer, tree)
+        write_tree(&mut buffer, tree, &args)
     }
     .expect("Failed to write tree to output")
 }
 
+#[derive(Debug, Clone, Copy)]
+enum OutputFormat {
+    Dot,
+    Json,
+    Mermaid,
+    Ascii,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(OutputFormat::Dot),
+            "json" => Ok(OutputFormat::Json),
+            "mermaid" => Ok(OutputFormat::Mermaid),
+            "ascii" => Ok(OutputFormat::Ascii),
+            _ => Err(format!("unknown output format '{s}'")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum KeyFormat {
+    Utf8,
+    Hex,
+    Debug,
+}
+
+impl FromStr for KeyFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "utf8" => Ok(KeyFormat::Utf8),
+            "hex" => Ok(KeyFormat::Hex),
+            "debug" => Ok(KeyFormat::Debug),
+            _ => Err(format!("unknown key format '{s}'")),
+        }
+    }
+}
+
+#[derive(Debug)]
 pub struct DisplayWrapper(CString);
 
 impl Display for DisplayWrapper {
@@ -86,14 +147,33 @@ unsafe impl NoPrefixesBytes for DisplayWrapper where CString: NoPrefixesBytes {}
 fn write_tree(
     output: &mut dyn Write,
     tree: TreeMap<DisplayWrapper, String>,
+    args: &TreeToDotArgs,
 ) -> Result<(), Box<dyn Error>> {
-    DotPrinter::print(
-        output,
-        &tree,
-        DotPrinterSettings {
-            display_node_address: false,
-        },
-    )
+    type KeyFormatter = fn(&DisplayWrapper, &mut Formatter<'_>) -> fmt::Result;
+
+    let format_key: KeyFormatter = match args.key_format {
+        KeyFormat::Utf8 => format_utf8_lossy,
+        KeyFormat::Hex => format_hex,
+        KeyFormat::Debug => format_debug,
+    };
+    let settings = ExportSettings {
+        display_node_address: args.display_node_address,
+        format_key,
+        format_value: format_display::<String>,
+    };
+
+    match args.format {
+        OutputFormat::Dot => DotPrinter::print(
+            output,
+            &tree,
+            DotPrinterSettings {
+                display_node_address: args.display_node_address,
+            },
+        ),
+        OutputFormat::Json => JsonPrinter::print(output, &tree, settings),
+        OutputFormat::Mermaid => MermaidPrinter::print(output, &tree, settings),
+        OutputFormat::Ascii => AsciiPrinter::print(output, &tree, settings),
+    }
     .unwrap()?;
 
     Ok(())
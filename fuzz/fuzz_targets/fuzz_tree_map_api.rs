@@ -2,6 +2,7 @@
 #![no_main]
 
 use blart::TreeMap;
+use blart::visitor::WellFormedChecker;
 use blart::map::Entry;
 use blart::map::EntryRef;
 use libfuzzer_sys::arbitrary::{self, Arbitrary};
@@ -186,6 +187,10 @@ libfuzzer_sys::fuzz_target!(|actions: Vec<Action>| {
             },
         }
     }
+
+    if let Err(errors) = WellFormedChecker::check_all(&tree) {
+        panic!("tree is not well-formed: {errors:#?}");
+    }
 });
 
 fn hash_one(hasher_builder: &impl BuildHasher, value: impl Hash) -> u64 {
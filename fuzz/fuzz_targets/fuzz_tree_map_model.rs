@@ -0,0 +1,12 @@
+#![no_main]
+
+use blart::tests_model::{check_operations, decode_input};
+
+// The panic hook of the fuzzer aborts the process, so failing inputs are not
+// shrunk by `assert_operations`. Use `cargo fuzz tmin` to minimize them.
+libfuzzer_sys::fuzz_target!(|data: &[u8]| {
+    let (config, operations) = decode_input(data);
+    if let Err(err) = check_operations(&config, &operations) {
+        panic!("{}", err);
+    }
+});
@@ -0,0 +1,62 @@
+//! This crate checks that `blart` builds and works without the standard
+//! library, using only `core` and `alloc`.
+//!
+//! To make sure nothing from `std` sneaks in, build it for a target that has no
+//! `std` available, like:
+//!
+//! ```bash
+//! cargo build -p blart-no-std-test --target thumbv7em-none-eabihf
+//! ```
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::{boxed::Box, vec::Vec};
+use blart::{
+    visitor::{TreeStatsCollector, WellFormedChecker},
+    TreeMap,
+};
+
+/// Exercise the main `TreeMap` APIs and visitors, returning the keys of the
+/// final tree in order.
+pub fn exercise_tree_map() -> Vec<Box<[u8]>> {
+    let mut tree: TreeMap<Box<[u8]>, u32> = TreeMap::new();
+
+    for idx in 0..=u8::MAX {
+        tree.try_insert(Box::new([idx, idx / 2, 0xFF]), u32::from(idx))
+            .unwrap();
+    }
+
+    tree.try_entry(Box::new([0xFF, 0xFF, 0xFF, 0xFF]))
+        .unwrap()
+        .or_insert(1000);
+
+    for idx in (0..=u8::MAX).step_by(2) {
+        assert_eq!(
+            tree.remove([idx, idx / 2, 0xFF].as_slice()),
+            Some(u32::from(idx))
+        );
+    }
+
+    assert_eq!(tree.prefix(&[1u8]).count(), 1);
+    assert_eq!(tree.fuzzy([1u8, 0, 0xFF].as_slice(), 1).count(), 1);
+    assert!(TreeStatsCollector::collect(&tree).is_some());
+    WellFormedChecker::check(&tree).unwrap();
+
+    tree.into_keys().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_map_without_std() {
+        let keys = exercise_tree_map();
+
+        assert_eq!(keys.len(), 129);
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(keys.last().unwrap().as_ref(), &[0xFF, 0xFF, 0xFF, 0xFF]);
+    }
+}
@@ -31,6 +31,20 @@ cargo "${TOOLCHAIN_ARG}" test   $TOOLCHAIN_EXTRA_ARGS --doc
 cargo "${TOOLCHAIN_ARG}" clippy $TOOLCHAIN_EXTRA_ARGS --all-targets 
 cargo "${TOOLCHAIN_ARG}" doc    $TOOLCHAIN_EXTRA_ARGS --no-deps --document-private-items
 
+# The metrics feature adds an allocation to every map which records an event,
+# which the heap usage tests do not expect, so only run the unit tests and
+# doctests with it
+cargo "${TOOLCHAIN_ARG}" clippy $TOOLCHAIN_EXTRA_ARGS --features metrics --all-targets
+cargo "${TOOLCHAIN_ARG}" test   $TOOLCHAIN_EXTRA_ARGS --features metrics --lib
+cargo "${TOOLCHAIN_ARG}" test   $TOOLCHAIN_EXTRA_ARGS --features metrics --doc
+
+# Build and test without the standard library. The crate is `no_std` in the
+# unit tests as well, and CI also builds it for a target which does not have
+# `std` at all
+cargo "${TOOLCHAIN_ARG}" build  $TOOLCHAIN_EXTRA_ARGS --no-default-features
+cargo "${TOOLCHAIN_ARG}" clippy $TOOLCHAIN_EXTRA_ARGS --no-default-features --all-targets
+cargo "${TOOLCHAIN_ARG}" test   $TOOLCHAIN_EXTRA_ARGS --no-default-features --lib
+
 if [ "${TOOLCHAIN}" = "nightly" ]; then
     # Test with and without the toolchain-specific features
     # Also don't test benchmarks, since those load from disk
@@ -1,18 +1,30 @@
-use std::{
-    borrow::Cow,
-    ffi::{CStr, CString, OsStr, OsString},
-    io::{IoSlice, IoSliceMut},
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    ffi::CString,
+    rc::Rc,
+    string::String,
+    sync::Arc,
+    vec::Vec,
+};
+use core::{
+    ffi::CStr,
     mem::ManuallyDrop,
     num::{
         NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
         NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
     },
+};
+#[cfg(feature = "std")]
+use std::{
+    ffi::{OsStr, OsString},
+    io::{IoSlice, IoSliceMut},
     path::{Path, PathBuf},
-    rc::Rc,
-    sync::Arc,
 };
 
+mod heap_size;
 mod mapped;
+pub use heap_size::*;
 pub use mapped::*;
 
 /// Any type implementing `AsBytes` can be decomposed into bytes.
@@ -39,6 +51,28 @@ pub unsafe trait NoPrefixesBytes: AsBytes {}
 ///  - This trait can only be implemented if the above condition holds.
 pub unsafe trait OrderedBytes: AsBytes + Ord {}
 
+/// Any type implementing `FromBytes` can be rebuilt from the bytes produced by
+/// its [`AsBytes`] implementation.
+///
+/// This is the counterpart of [`AsBytes`], in the same way that
+/// [`BytesMapping::from_bytes`] is the counterpart of
+/// [`BytesMapping::to_bytes`]. It allows a key to be reconstructed from the
+/// bytes of the path leading to it in a trie, instead of reading a stored copy.
+///
+/// The following property should hold true:
+///
+/// ```plaintext
+/// T::from_bytes(value.as_bytes()) == value
+/// ```
+pub trait FromBytes: AsBytes + Sized {
+    /// Rebuild a value from its byte representation.
+    ///
+    /// # Panics
+    ///  - Panics if `bytes` was not produced by [`AsBytes::as_bytes`] for a
+    ///    value of this type.
+    fn from_bytes(bytes: &[u8]) -> Self;
+}
+
 macro_rules! as_bytes_for_integer_like_types {
     ($($type:ty),*) => {
         $(
@@ -181,7 +215,7 @@ unsafe impl NoPrefixesBytes for CString {}
 // ordering of bytes and `Ord` implementation are the same
 unsafe impl OrderedBytes for CString {}
 
-#[cfg(unix)]
+#[cfg(all(feature = "std", unix))]
 impl AsBytes for OsStr {
     fn as_bytes(&self) -> &[u8] {
         use std::os::unix::prelude::OsStrExt;
@@ -190,7 +224,7 @@ impl AsBytes for OsStr {
     }
 }
 
-#[cfg(unix)]
+#[cfg(all(feature = "std", unix))]
 impl AsBytes for OsString {
     fn as_bytes(&self) -> &[u8] {
         use std::os::unix::prelude::OsStrExt;
@@ -199,7 +233,7 @@ impl AsBytes for OsString {
     }
 }
 
-#[cfg(target_os = "wasi")]
+#[cfg(all(feature = "std", target_os = "wasi"))]
 impl AsBytes for OsStr {
     fn as_bytes(&self) -> &[u8] {
         use std::os::wasi::prelude::OsStrExt;
@@ -208,7 +242,7 @@ impl AsBytes for OsStr {
     }
 }
 
-#[cfg(target_os = "wasi")]
+#[cfg(all(feature = "std", target_os = "wasi"))]
 impl AsBytes for OsString {
     fn as_bytes(&self) -> &[u8] {
         use std::os::wasi::prelude::OsStrExt;
@@ -219,15 +253,15 @@ impl AsBytes for OsString {
 
 // SAFETY: This trait is safe to implement because the lexicographic
 // ordering of bytes and `Ord` implementation are the same
-#[cfg(any(unix, target_os = "wasi"))]
+#[cfg(all(feature = "std", any(unix, target_os = "wasi")))]
 unsafe impl OrderedBytes for OsStr {}
 
 // SAFETY: This trait is safe to implement because the lexicographic
 // ordering of bytes and `Ord` implementation are the same
-#[cfg(any(unix, target_os = "wasi"))]
+#[cfg(all(feature = "std", any(unix, target_os = "wasi")))]
 unsafe impl OrderedBytes for OsString {}
 
-#[cfg(any(unix, target_os = "wasi"))]
+#[cfg(all(feature = "std", any(unix, target_os = "wasi")))]
 impl AsBytes for Path {
     fn as_bytes(&self) -> &[u8] {
         <OsStr as AsBytes>::as_bytes(self.as_os_str())
@@ -236,10 +270,10 @@ impl AsBytes for Path {
 
 // SAFETY: This trait is safe to implement because the lexicographic
 // ordering of bytes and `Ord` implementation are the same
-#[cfg(any(unix, target_os = "wasi"))]
+#[cfg(all(feature = "std", any(unix, target_os = "wasi")))]
 unsafe impl OrderedBytes for Path {}
 
-#[cfg(any(unix, target_os = "wasi"))]
+#[cfg(all(feature = "std", any(unix, target_os = "wasi")))]
 impl AsBytes for PathBuf {
     fn as_bytes(&self) -> &[u8] {
         <OsStr as AsBytes>::as_bytes(self.as_os_str())
@@ -248,10 +282,10 @@ impl AsBytes for PathBuf {
 
 // SAFETY: This trait is safe to implement because the lexicographic
 // ordering of bytes and `Ord` implementation are the same
-#[cfg(any(unix, target_os = "wasi"))]
+#[cfg(all(feature = "std", any(unix, target_os = "wasi")))]
 unsafe impl OrderedBytes for PathBuf {}
 
-impl<'a, B> AsBytes for Cow<'a, B>
+impl<B> AsBytes for Cow<'_, B>
 where
     B: ToOwned + AsBytes + ?Sized,
 {
@@ -280,7 +314,7 @@ where
 {
 }
 
-impl<'a, T> AsBytes for &'a T
+impl<T> AsBytes for &T
 where
     T: AsBytes + ?Sized,
 {
@@ -292,14 +326,14 @@ where
 // SAFETY: This trait is safe to implement because the underlying
 // type is already implements `OrderedBytes`, and the `Ord` impl works the same
 // way
-unsafe impl<'a, T> OrderedBytes for &'a T where T: OrderedBytes + ?Sized {}
+unsafe impl<T> OrderedBytes for &T where T: OrderedBytes + ?Sized {}
 
 // SAFETY: This trait is safe to implement because the underlying
 // type is already implements `NoPrefixesBytes`, and the wrapper type would not
 // change that property
-unsafe impl<'a, T> NoPrefixesBytes for &'a T where T: NoPrefixesBytes + ?Sized {}
+unsafe impl<T> NoPrefixesBytes for &T where T: NoPrefixesBytes + ?Sized {}
 
-impl<'a, T> AsBytes for &'a mut T
+impl<T> AsBytes for &mut T
 where
     T: AsBytes + ?Sized,
 {
@@ -311,12 +345,12 @@ where
 // SAFETY: This trait is safe to implement because the underlying
 // type is already implements `OrderedBytes`, and the `Ord` impl works the same
 // way
-unsafe impl<'a, T> OrderedBytes for &'a mut T where T: OrderedBytes + ?Sized {}
+unsafe impl<T> OrderedBytes for &mut T where T: OrderedBytes + ?Sized {}
 
 // SAFETY: This trait is safe to implement because the underlying
 // type is already implements `NoPrefixesBytes`, and the wrapper type would not
 // change that property
-unsafe impl<'a, T> NoPrefixesBytes for &'a mut T where T: NoPrefixesBytes + ?Sized {}
+unsafe impl<T> NoPrefixesBytes for &mut T where T: NoPrefixesBytes + ?Sized {}
 
 impl<T> AsBytes for Rc<T>
 where
@@ -394,22 +428,176 @@ unsafe impl<T> OrderedBytes for ManuallyDrop<T> where T: OrderedBytes + ?Sized {
 // change that property
 unsafe impl<T> NoPrefixesBytes for ManuallyDrop<T> where T: NoPrefixesBytes + ?Sized {}
 
-impl<'a> AsBytes for IoSlice<'a> {
+#[cfg(feature = "std")]
+impl AsBytes for IoSlice<'_> {
     fn as_bytes(&self) -> &[u8] {
         self
     }
 }
 
-impl<'a> AsBytes for IoSliceMut<'a> {
+#[cfg(feature = "std")]
+impl AsBytes for IoSliceMut<'_> {
     fn as_bytes(&self) -> &[u8] {
         self
     }
 }
 
+macro_rules! from_bytes_for_integer_like_types {
+    ($($type:ty),*) => {
+        $(
+            impl FromBytes for $type {
+                fn from_bytes(bytes: &[u8]) -> Self {
+                    bytemuck::checked::pod_read_unaligned(bytes)
+                }
+            }
+        )*
+    };
+}
+
+from_bytes_for_integer_like_types!(
+    u8,
+    i8,
+    u16,
+    i16,
+    u32,
+    i32,
+    u64,
+    i64,
+    u128,
+    i128,
+    usize,
+    isize,
+    char,
+    bool,
+    NonZeroU8,
+    NonZeroI8,
+    NonZeroU16,
+    NonZeroI16,
+    NonZeroU32,
+    NonZeroI32,
+    NonZeroU64,
+    NonZeroI64,
+    NonZeroU128,
+    NonZeroI128,
+    NonZeroUsize,
+    NonZeroIsize
+);
+
+macro_rules! from_bytes_for_integer_arrays {
+    ($($type:ty),*) => {
+        $(
+            impl<const N: usize> FromBytes for [$type; N] {
+                fn from_bytes(bytes: &[u8]) -> Self {
+                    bytemuck::pod_read_unaligned(bytes)
+                }
+            }
+        )*
+    };
+}
+
+from_bytes_for_integer_arrays!(u8, i8, u16, i16, u32, i32, u64, i64, u128, i128);
+
+impl FromBytes for Vec<u8> {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        bytes.to_vec()
+    }
+}
+
+impl FromBytes for Box<[u8]> {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Box::from(bytes)
+    }
+}
+
+impl FromBytes for String {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        String::from_utf8(bytes.to_vec()).expect("bytes should be valid UTF-8")
+    }
+}
+
+impl FromBytes for CString {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        CStr::from_bytes_with_nul(bytes)
+            .expect("bytes should be nul-terminated without interior nul bytes")
+            .to_owned()
+    }
+}
+
+impl<T> FromBytes for Box<T>
+where
+    T: FromBytes,
+{
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Box::new(T::from_bytes(bytes))
+    }
+}
+
+impl<T> FromBytes for Rc<T>
+where
+    T: FromBytes,
+{
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Rc::new(T::from_bytes(bytes))
+    }
+}
+
+impl<T> FromBytes for Arc<T>
+where
+    T: FromBytes,
+{
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Arc::new(T::from_bytes(bytes))
+    }
+}
+
+impl<T> FromBytes for ManuallyDrop<T>
+where
+    T: FromBytes,
+{
+    fn from_bytes(bytes: &[u8]) -> Self {
+        ManuallyDrop::new(T::from_bytes(bytes))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn from_bytes_round_trip() {
+        fn check<T: FromBytes + PartialEq + core::fmt::Debug>(value: T) {
+            assert_eq!(T::from_bytes(value.as_bytes()), value);
+        }
+
+        check(u8::MAX);
+        check(-12345i16);
+        check(0xDEAD_BEEFu32);
+        check(i128::MIN);
+        check(usize::MAX / 3);
+        check('\u{1F980}');
+        check(true);
+        check(NonZeroU64::new(42).unwrap());
+        check([1u8, 2, 3, 4, 5]);
+        check([u16::MAX, 0, 1234]);
+        check([-1i64; 3]);
+        check(Vec::from([0u8, 255, 7]));
+        check(Box::<[u8]>::from([9u8, 8, 7]));
+        check(String::from("hello world"));
+        check(CString::new("hello").unwrap());
+        check(Box::new(77u32));
+        check(Rc::new(String::from("rc")));
+        check(Arc::new([3u8; 4]));
+        check(Mapped::<ToUBE, u64>::new(0x0102_0304_0506_0708));
+        check(Mapped::<ToIBE, i32>::new(-5));
+        check(Mapped::<ToUBE, [u16; 3]>::new([1, 2, 3]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn from_bytes_wrong_length_panics() {
+        let _ = u32::from_bytes(&[1, 2, 3]);
+    }
+
     #[test]
     fn various_numeric_types_as_bytes() {
         assert_eq!(u8::MAX.as_bytes(), &[u8::MAX]);
@@ -471,22 +659,18 @@ mod tests {
             b"hello world"
         );
         assert_eq!(
-            <CStr as AsBytes>::as_bytes(CStr::from_bytes_with_nul(b"hello world\0").unwrap()),
+            <CStr as AsBytes>::as_bytes(c"hello world"),
             b"hello world\0"
         );
         assert_eq!(
-            <CString as AsBytes>::as_bytes(
-                &CStr::from_bytes_with_nul(b"hello world\0").unwrap().into()
-            ),
+            <CString as AsBytes>::as_bytes(&c"hello world".into()),
             b"hello world\0"
         );
         assert_eq!(
-            <CString as AsBytes>::as_bytes(
-                &CStr::from_bytes_with_nul(b"hello world\0").unwrap().into()
-            ),
+            <CString as AsBytes>::as_bytes(&c"hello world".into()),
             b"hello world\0"
         );
-        #[cfg(any(unix, target_os = "wasi"))]
+        #[cfg(all(feature = "std", any(unix, target_os = "wasi")))]
         {
             assert_eq!(
                 <OsStr as AsBytes>::as_bytes(OsStr::new("hello world")),
@@ -497,7 +681,7 @@ mod tests {
                 b"hello world"
             );
         }
-        #[cfg(any(unix, target_os = "wasi"))]
+        #[cfg(all(feature = "std", any(unix, target_os = "wasi")))]
         {
             assert_eq!(
                 <Path as AsBytes>::as_bytes(Path::new("hello/world")),
@@ -540,14 +724,17 @@ mod tests {
             <ManuallyDrop<&[u8]> as AsBytes>::as_bytes(&ManuallyDrop::new(b"hello world")),
             b"hello world"
         );
-        assert_eq!(
-            <IoSlice as AsBytes>::as_bytes(&IoSlice::new(b"hello world")),
-            b"hello world"
-        );
-        let mut buffer = [104u8, 101, 108, 108, 111, 32, 119, 111, 114, 108, 100];
-        assert_eq!(
-            <IoSliceMut as AsBytes>::as_bytes(&IoSliceMut::new(&mut buffer)),
-            b"hello world"
-        )
+        #[cfg(feature = "std")]
+        {
+            assert_eq!(
+                <IoSlice as AsBytes>::as_bytes(&IoSlice::new(b"hello world")),
+                b"hello world"
+            );
+            let mut buffer = [104u8, 101, 108, 108, 111, 32, 119, 111, 114, 108, 100];
+            assert_eq!(
+                <IoSliceMut as AsBytes>::as_bytes(&IoSliceMut::new(&mut buffer)),
+                b"hello world"
+            )
+        }
     }
 }
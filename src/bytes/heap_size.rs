@@ -0,0 +1,312 @@
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    ffi::CString,
+    rc::Rc,
+    string::String,
+    sync::Arc,
+    vec::Vec,
+};
+use core::{
+    ffi::CStr,
+    mem::{size_of, size_of_val, ManuallyDrop},
+    num::{
+        NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroIsize, NonZeroU128,
+        NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize,
+    },
+};
+#[cfg(feature = "std")]
+use std::{
+    ffi::{OsStr, OsString},
+    io::{IoSlice, IoSliceMut},
+    path::{Path, PathBuf},
+};
+
+/// Any type implementing `HeapSize` can report the number of bytes it owns on
+/// the heap.
+///
+/// The reported size is deep, so it includes the heap memory owned by any
+/// nested values, but it does not include the `size_of::<Self>()` bytes of the
+/// value itself. Those are already accounted for by whatever contains the
+/// value, for example the leaf nodes of a [`TreeMap`](crate::TreeMap), see
+/// [`TreeMap::memory_usage`](crate::TreeMap::memory_usage).
+///
+/// Sizes are based on the capacity of the allocations, not their length, and
+/// do not include any overhead of the allocator.
+///
+/// # Examples
+///
+/// ```rust
+/// use blart::HeapSize;
+///
+/// assert_eq!(42u64.heap_size(), 0);
+/// assert_eq!(Box::<[u8]>::from(&b"hello"[..]).heap_size(), 5);
+///
+/// let mut names = Vec::with_capacity(4);
+/// names.push(String::from("abc"));
+/// assert_eq!(names.heap_size(), 4 * std::mem::size_of::<String>() + 3);
+/// ```
+pub trait HeapSize {
+    /// Return the number of bytes owned by this value on the heap.
+    fn heap_size(&self) -> usize;
+}
+
+macro_rules! heap_size_for_inline_types {
+    ($($type:ty),*) => {
+        $(
+            impl HeapSize for $type {
+                #[inline(always)]
+                fn heap_size(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+heap_size_for_inline_types!(
+    (),
+    u8,
+    i8,
+    u16,
+    i16,
+    u32,
+    i32,
+    u64,
+    i64,
+    u128,
+    i128,
+    usize,
+    isize,
+    f32,
+    f64,
+    char,
+    bool,
+    NonZeroU8,
+    NonZeroI8,
+    NonZeroU16,
+    NonZeroI16,
+    NonZeroU32,
+    NonZeroI32,
+    NonZeroU64,
+    NonZeroI64,
+    NonZeroU128,
+    NonZeroI128,
+    NonZeroUsize,
+    NonZeroIsize,
+    str,
+    CStr
+);
+
+#[cfg(feature = "std")]
+heap_size_for_inline_types!(OsStr, Path, IoSlice<'_>, IoSliceMut<'_>);
+
+impl<T: HeapSize> HeapSize for [T] {
+    fn heap_size(&self) -> usize {
+        self.iter().map(HeapSize::heap_size).sum()
+    }
+}
+
+impl<T: HeapSize, const N: usize> HeapSize for [T; N] {
+    fn heap_size(&self) -> usize {
+        self.as_slice().heap_size()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.as_slice().heap_size()
+    }
+}
+
+impl HeapSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl HeapSize for CString {
+    fn heap_size(&self) -> usize {
+        // A `CString` is stored as a `Box<[u8]>`, which has no spare capacity
+        self.as_bytes_with_nul().len()
+    }
+}
+
+#[cfg(feature = "std")]
+impl HeapSize for OsString {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+#[cfg(feature = "std")]
+impl HeapSize for PathBuf {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: HeapSize> HeapSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, HeapSize::heap_size)
+    }
+}
+
+impl<T> HeapSize for Box<T>
+where
+    T: HeapSize + ?Sized,
+{
+    fn heap_size(&self) -> usize {
+        size_of_val::<T>(self) + T::heap_size(self)
+    }
+}
+
+/// The allocation of an `Rc` or `Arc` holds the strong and weak counts next
+/// to the value.
+const REF_COUNTS_SIZE: usize = 2 * size_of::<usize>();
+
+/// The allocation behind an `Rc` is shared by all of its clones, so each one
+/// reports an equal share of it. Summing the heap size of every clone gives
+/// the size of the allocation, up to rounding.
+impl<T> HeapSize for Rc<T>
+where
+    T: HeapSize + ?Sized,
+{
+    fn heap_size(&self) -> usize {
+        (REF_COUNTS_SIZE + size_of_val::<T>(self) + T::heap_size(self)) / Rc::strong_count(self)
+    }
+}
+
+/// The allocation behind an `Arc` is shared by all of its clones, so each one
+/// reports an equal share of it. Summing the heap size of every clone gives
+/// the size of the allocation, up to rounding.
+impl<T> HeapSize for Arc<T>
+where
+    T: HeapSize + ?Sized,
+{
+    fn heap_size(&self) -> usize {
+        (REF_COUNTS_SIZE + size_of_val::<T>(self) + T::heap_size(self)) / Arc::strong_count(self)
+    }
+}
+
+impl<B> HeapSize for Cow<'_, B>
+where
+    B: ToOwned + ?Sized,
+    B::Owned: HeapSize,
+{
+    fn heap_size(&self) -> usize {
+        match self {
+            Cow::Borrowed(_) => 0,
+            Cow::Owned(owned) => owned.heap_size(),
+        }
+    }
+}
+
+/// References do not own the memory they point to.
+impl<T: ?Sized> HeapSize for &T {
+    #[inline(always)]
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+/// References do not own the memory they point to.
+impl<T: ?Sized> HeapSize for &mut T {
+    #[inline(always)]
+    fn heap_size(&self) -> usize {
+        0
+    }
+}
+
+impl<T> HeapSize for ManuallyDrop<T>
+where
+    T: HeapSize + ?Sized,
+{
+    fn heap_size(&self) -> usize {
+        T::heap_size(self)
+    }
+}
+
+macro_rules! heap_size_for_tuples {
+    ($(($($ty:ident)+))+) => {
+        $(
+            impl<$($ty: HeapSize,)+> HeapSize for ($($ty,)+) {
+                #[allow(non_snake_case)]
+                fn heap_size(&self) -> usize {
+                    let ($($ty,)+) = self;
+                    0 $(+ $ty.heap_size())+
+                }
+            }
+        )+
+    };
+}
+
+heap_size_for_tuples! {
+    (A)
+    (A B)
+    (A B C)
+    (A B C D)
+    (A B C D E)
+    (A B C D E F)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Identity, Mapped, ToIBE};
+    use alloc::vec;
+
+    #[test]
+    fn inline_types_have_no_heap() {
+        assert_eq!(0u32.heap_size(), 0);
+        assert_eq!([1u64; 16].heap_size(), 0);
+        assert_eq!((1u8, 'a', ()).heap_size(), 0);
+        assert_eq!("hello".heap_size(), 0);
+        let owned = String::from("hello");
+        assert_eq!(<&String as HeapSize>::heap_size(&&owned), 0);
+        assert_eq!(Mapped::<ToIBE, i32>::new(-1).heap_size(), 0);
+    }
+
+    #[test]
+    fn owned_buffers_count_capacity() {
+        assert_eq!(String::with_capacity(10).heap_size(), 10);
+        assert_eq!(Vec::<u32>::with_capacity(10).heap_size(), 40);
+        assert_eq!(Box::<[u16]>::from(&[1, 2, 3][..]).heap_size(), 6);
+        assert_eq!(CString::new("abc").unwrap().heap_size(), 4);
+        assert_eq!(Box::new(5u64).heap_size(), 8);
+        assert_eq!(Some(String::from("ab")).heap_size(), 2);
+        assert_eq!(Cow::<str>::Owned(String::from("abc")).heap_size(), 3);
+        assert_eq!(Cow::Borrowed("abc").heap_size(), 0);
+        assert_eq!(
+            Mapped::<Identity, Box<[u8]>>::new(Box::from(&b"abcd"[..])).heap_size(),
+            4
+        );
+    }
+
+    #[test]
+    fn nested_heap_is_counted() {
+        let strings = vec![String::from("a"), String::from("bcd")];
+        assert_eq!(strings.heap_size(), 2 * size_of::<String>() + 4);
+
+        let boxed: Box<Box<[u8]>> = Box::new(Box::from(&b"xyz"[..]));
+        assert_eq!(boxed.heap_size(), size_of::<Box<[u8]>>() + 3);
+
+        assert_eq!((String::from("ab"), vec![0u8; 3]).heap_size(), 5);
+    }
+
+    #[test]
+    fn shared_allocations_are_split_between_clones() {
+        let first: Rc<[u8]> = Rc::from(&[0u8; 16][..]);
+        assert_eq!(first.heap_size(), REF_COUNTS_SIZE + 16);
+
+        let second = Rc::clone(&first);
+        assert_eq!(first.heap_size() + second.heap_size(), REF_COUNTS_SIZE + 16);
+
+        let shared = Arc::new(String::from("abcdef"));
+        let clones = [Arc::clone(&shared), Arc::clone(&shared)];
+        assert_eq!(
+            (REF_COUNTS_SIZE + size_of::<String>() + 6) / 3,
+            clones[0].heap_size()
+        );
+    }
+}
@@ -1,2 +1,4 @@
+pub mod keyless_map;
 pub mod map;
+pub use keyless_map::KeylessTreeMap;
 pub use map::TreeMap;
@@ -0,0 +1,1642 @@
+//! Module containing implementations of the `KeylessTreeMap` and associated
+//! iterators/etc.
+
+use crate::{
+    visitor::TreeStatsCollector, AsBytes, ConcreteNodePtr, FromBytes, Header, InnerNode,
+    InnerNode4, LeafNode, NoPrefixesBytes, NodePtr, OpaqueNodePtr, PrefixStrategy, FREE_NODE_TAGS,
+};
+use alloc::{boxed::Box, vec::Vec};
+use bytemuck::NoUninit;
+use core::{
+    borrow::Borrow,
+    fmt,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ptr::{self, NonNull},
+};
+
+mod entry;
+mod iterators;
+pub use entry::*;
+pub use iterators::*;
+
+/// The node pointers of a [`KeylessTreeMap`], none of the leaves store a key.
+type KeylessNodePtr<V, const PREFIX_LEN: usize> = OpaqueNodePtr<(), V, PREFIX_LEN>;
+
+/// The tag of pointers to a [`SuffixLeaf`].
+const SUFFIX_LEAF_TAG: usize = FREE_NODE_TAGS[0];
+
+/// The tag of child slots which hold a value instead of a pointer, see
+/// [`KeylessTreeMap::with_inline_values`].
+const INLINE_VALUE_TAG: usize = FREE_NODE_TAGS[1];
+
+/// Return the offset of a value stored in a child slot, which keeps the value
+/// clear of the byte holding the tag.
+const fn inline_value_offset<V>() -> usize {
+    if cfg!(target_endian = "little") {
+        mem::align_of::<V>()
+    } else {
+        0
+    }
+}
+
+/// Return a child slot which holds the value, tagged with
+/// [`INLINE_VALUE_TAG`].
+///
+/// # Safety
+///  - `V` must be smaller than a pointer, and must not contain any
+///    uninitialized bytes.
+unsafe fn inline_value_slot<V, const PREFIX_LEN: usize>(value: V) -> KeylessNodePtr<V, PREFIX_LEN> {
+    let mut slot = MaybeUninit::<KeylessNodePtr<V, PREFIX_LEN>>::uninit();
+    // SAFETY: The tag initializes the whole slot. Since `V` is smaller than a
+    // pointer, it is at most as aligned as the slot, and the offset keeps it
+    // clear of the byte holding the tag. The tag is not zero, so the slot is not a
+    // null pointer, and the bytes of the value are initialized.
+    unsafe {
+        slot.as_mut_ptr().cast::<usize>().write(INLINE_VALUE_TAG);
+        slot.as_mut_ptr()
+            .cast::<u8>()
+            .add(inline_value_offset::<V>())
+            .cast::<V>()
+            .write(value);
+        slot.assume_init()
+    }
+}
+
+/// Return a pointer to the value held by a child slot tagged with
+/// [`INLINE_VALUE_TAG`].
+fn inline_value_ptr<V, const PREFIX_LEN: usize>(
+    slot: NonNull<KeylessNodePtr<V, PREFIX_LEN>>,
+) -> *mut V {
+    slot.as_ptr()
+        .cast::<u8>()
+        .wrapping_add(inline_value_offset::<V>())
+        .cast()
+}
+
+/// A leaf whose key is not completely spelled out by the path leading to it,
+/// so it stores the remaining bytes of the key.
+///
+/// The path stops at the first key byte which tells the leaf apart from its
+/// siblings, so this happens to every leaf whose key is longer than that.
+#[repr(align(8))]
+struct SuffixLeaf<V> {
+    /// The leaf value.
+    value: V,
+    /// The bytes of the key after the path leading to the leaf.
+    suffix: Box<[u8]>,
+}
+
+/// A pointer to one of the leaves of a [`KeylessTreeMap`].
+enum LeafPtr<V, const PREFIX_LEN: usize> {
+    /// A leaf whose key is completely spelled out by the path leading to it,
+    /// so it only stores the value.
+    Full(NodePtr<PREFIX_LEN, LeafNode<(), V>>),
+    /// A leaf which stores the end of its key.
+    Suffix(NonNull<SuffixLeaf<V>>),
+    /// A value whose key is completely spelled out by the path leading to it,
+    /// which is stored in the child slot of its parent.
+    Inline(NonNull<KeylessNodePtr<V, PREFIX_LEN>>),
+}
+
+impl<V, const PREFIX_LEN: usize> Clone for LeafPtr<V, PREFIX_LEN> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V, const PREFIX_LEN: usize> Copy for LeafPtr<V, PREFIX_LEN> {}
+
+impl<V, const PREFIX_LEN: usize> LeafPtr<V, PREFIX_LEN> {
+    /// Return the bytes of the key after the path leading to the leaf.
+    ///
+    /// # Safety
+    ///  - The leaf must not be mutated for the duration of the lifetime `'a`.
+    unsafe fn suffix<'a>(self) -> &'a [u8]
+    where
+        V: 'a,
+    {
+        match self {
+            LeafPtr::Full(_) | LeafPtr::Inline(_) => &[],
+            // SAFETY: Covered by the safety requirements of the containing function
+            LeafPtr::Suffix(leaf_ptr) => unsafe { &leaf_ptr.as_ref().suffix },
+        }
+    }
+
+    /// Return a shared reference to the value of the leaf.
+    ///
+    /// # Safety
+    ///  - The value must not be mutated for the duration of the lifetime `'a`.
+    unsafe fn value_ref<'a>(self) -> &'a V {
+        match self {
+            // SAFETY: Covered by the safety requirements of the containing function
+            LeafPtr::Full(leaf_ptr) => unsafe { leaf_ptr.as_value_ref() },
+            // SAFETY: Covered by the safety requirements of the containing function
+            LeafPtr::Suffix(leaf_ptr) => unsafe { &leaf_ptr.as_ref().value },
+            // SAFETY: Covered by the safety requirements of the containing function
+            LeafPtr::Inline(slot) => unsafe { &*inline_value_ptr(slot) },
+        }
+    }
+
+    /// Return a mutable reference to the value of the leaf.
+    ///
+    /// # Safety
+    ///  - The leaf must not be accessed through any other pointer for the
+    ///    duration of the lifetime `'a`.
+    unsafe fn value_mut<'a>(self) -> &'a mut V {
+        match self {
+            // SAFETY: Covered by the safety requirements of the containing function
+            LeafPtr::Full(leaf_ptr) => unsafe { leaf_ptr.as_value_mut() },
+            // SAFETY: Covered by the safety requirements of the containing function
+            LeafPtr::Suffix(mut leaf_ptr) => unsafe { &mut leaf_ptr.as_mut().value },
+            // SAFETY: Covered by the safety requirements of the containing function
+            LeafPtr::Inline(slot) => unsafe { &mut *inline_value_ptr(slot) },
+        }
+    }
+
+    /// Deallocate the leaf, and return its value and the bytes of the key
+    /// after the path leading to it.
+    ///
+    /// An inline value is moved out of its slot, which must then be
+    /// overwritten or removed.
+    ///
+    /// # Safety
+    ///  - This function can only be called once for a given leaf, and the leaf
+    ///    must not be accessed afterwards.
+    unsafe fn deallocate(self) -> (V, Box<[u8]>) {
+        match self {
+            LeafPtr::Full(leaf_ptr) => {
+                // SAFETY: Covered by the safety requirements of the containing function
+                let (_, value) = unsafe { NodePtr::deallocate_node_ptr(leaf_ptr) }.into_entry();
+                (value, Box::default())
+            },
+            LeafPtr::Suffix(leaf_ptr) => {
+                // SAFETY: The leaf was allocated by `allocate_leaf`, and the single call
+                // requirement is covered by the safety requirements of the containing
+                // function
+                let leaf = unsafe { Box::from_raw(leaf_ptr.as_ptr()) };
+                (leaf.value, leaf.suffix)
+            },
+            // SAFETY: The slot holds a value, which is moved out of it. The single call
+            // requirement is covered by the safety requirements of the containing
+            // function.
+            LeafPtr::Inline(slot) => (unsafe { ptr::read(inline_value_ptr(slot)) }, Box::default()),
+        }
+    }
+}
+
+/// A child of an inner node of a [`KeylessTreeMap`], or its root.
+enum Child<V, const PREFIX_LEN: usize> {
+    /// An inner node, which can be converted with
+    /// [`OpaqueNodePtr::to_node_ptr`].
+    Inner(KeylessNodePtr<V, PREFIX_LEN>),
+    /// A leaf.
+    Leaf(LeafPtr<V, PREFIX_LEN>),
+}
+
+impl<V, const PREFIX_LEN: usize> Child<V, PREFIX_LEN> {
+    /// Find out which kind of child the given child slot holds.
+    ///
+    /// An inline value is accessed through `slot`, so a copy of a slot can
+    /// only be used to read or move out the value.
+    ///
+    /// # Safety
+    ///  - `slot` must be valid for reads, and for writes if the value of the
+    ///    returned leaf is mutated. It must stay valid for as long as the
+    ///    returned child is in use.
+    unsafe fn classify(slot: NonNull<KeylessNodePtr<V, PREFIX_LEN>>) -> Self {
+        // SAFETY: Covered by the safety requirements of the containing function
+        let node = unsafe { slot.as_ptr().read() };
+
+        // The suffix leaves and inline values have a tag which is not a `NodeType`,
+        // so it must be checked before calling `to_node_ptr`
+        match node.tag() {
+            SUFFIX_LEAF_TAG => Child::Leaf(LeafPtr::Suffix(node.untagged_ptr())),
+            INLINE_VALUE_TAG => Child::Leaf(LeafPtr::Inline(slot)),
+            _ => match node.to_node_ptr() {
+                ConcreteNodePtr::LeafNode(leaf_ptr) => Child::Leaf(LeafPtr::Full(leaf_ptr)),
+                _ => Child::Inner(node),
+            },
+        }
+    }
+}
+
+/// Allocate a leaf for a key which is reached after the first `depth` bytes
+/// of `key` were spelled out by the path.
+///
+/// If `inline_values` is true and the path spells out the whole key, the
+/// value is stored in the returned child slot instead.
+///
+/// # Safety
+///  - If `inline_values` is true, `V` must be smaller than a pointer, and must
+///    not contain any uninitialized bytes.
+unsafe fn allocate_leaf<V, const PREFIX_LEN: usize>(
+    key: &[u8],
+    depth: usize,
+    value: V,
+    inline_values: bool,
+) -> KeylessNodePtr<V, PREFIX_LEN> {
+    if depth == key.len() {
+        if inline_values {
+            // SAFETY: Covered by the safety requirements of the containing function
+            unsafe { inline_value_slot(value) }
+        } else {
+            NodePtr::allocate_node_ptr(LeafNode::new((), value)).to_opaque()
+        }
+    } else {
+        let leaf = Box::new(SuffixLeaf {
+            value,
+            suffix: key[depth..].into(),
+        });
+        OpaqueNodePtr::with_free_tag(NonNull::from(Box::leak(leaf)), SUFFIX_LEAF_TAG)
+    }
+}
+
+/// Return the full prefix of the given inner node.
+///
+/// # Safety
+///  - `node` must point to an inner node, which is not mutated for the duration
+///    of the lifetime `'a`.
+unsafe fn full_prefix<'a, V, const PREFIX_LEN: usize>(
+    node: KeylessNodePtr<V, PREFIX_LEN>,
+) -> &'a [u8] {
+    // SAFETY: Covered by the safety requirements of the containing function
+    let header = unsafe { node.header_unchecked() };
+    header
+        .full_prefix()
+        .expect("the inner nodes of a keyless map should store their full prefix")
+}
+
+/// Return the number of leading bytes which are the same in both slices.
+fn common_prefix_len(left: &[u8], right: &[u8]) -> usize {
+    left.iter().zip(right).take_while(|(a, b)| a == b).count()
+}
+
+/// Create a new [`InnerNode4`] with the given prefix and two children.
+fn new_node4<V, const PREFIX_LEN: usize>(
+    prefix: &[u8],
+    first: (u8, KeylessNodePtr<V, PREFIX_LEN>),
+    second: (u8, KeylessNodePtr<V, PREFIX_LEN>),
+) -> KeylessNodePtr<V, PREFIX_LEN> {
+    let mut node = InnerNode4::from_header(Header::with_strategy(
+        prefix,
+        prefix.len(),
+        PrefixStrategy::Pessimistic,
+    ));
+    node.write_child(first.0, first.1);
+    node.write_child(second.0, second.1);
+
+    NodePtr::allocate_node_ptr(node).to_opaque()
+}
+
+/// Return a pointer to the node behind the given [`NodePtr`].
+fn node_non_null<const PREFIX_LEN: usize, N: InnerNode<PREFIX_LEN>>(
+    inner_ptr: NodePtr<PREFIX_LEN, N>,
+) -> NonNull<N> {
+    // SAFETY: A `NodePtr` is never null
+    unsafe { NonNull::new_unchecked(inner_ptr.to_ptr()) }
+}
+
+/// Search for the slot of the child of the given inner node with the key
+/// byte.
+///
+/// # Safety
+///  - `node` must point to an inner node, which is not mutated while the
+///    returned pointer is in use, other than through the pointer.
+unsafe fn lookup_child<V, const PREFIX_LEN: usize>(
+    node: KeylessNodePtr<V, PREFIX_LEN>,
+    key_byte: u8,
+) -> Option<NonNull<KeylessNodePtr<V, PREFIX_LEN>>> {
+    fn lookup<V, N, const PREFIX_LEN: usize>(
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+        key_byte: u8,
+    ) -> Option<NonNull<KeylessNodePtr<V, PREFIX_LEN>>>
+    where
+        N: InnerNode<PREFIX_LEN, Key = (), Value = V>,
+    {
+        // SAFETY: Covered by the safety requirements of the containing function
+        unsafe { N::lookup_child_slot(node_non_null(inner_ptr), key_byte) }
+    }
+
+    match node.to_node_ptr() {
+        ConcreteNodePtr::Node4(inner_ptr) => lookup(inner_ptr, key_byte),
+        ConcreteNodePtr::Node16(inner_ptr) => lookup(inner_ptr, key_byte),
+        ConcreteNodePtr::Node32(inner_ptr) => lookup(inner_ptr, key_byte),
+        ConcreteNodePtr::Node48(inner_ptr) => lookup(inner_ptr, key_byte),
+        ConcreteNodePtr::Node256(inner_ptr) => lookup(inner_ptr, key_byte),
+        ConcreteNodePtr::LeafNode(_) => panic!("A leaf pointer cannot have children"),
+    }
+}
+
+/// Write a child to the given inner node, growing the node if it is full.
+///
+/// Return the node which replaces the given node, which is the same node
+/// unless it was grown.
+///
+/// # Safety
+///  - `node` must be a unique pointer to an inner node.
+unsafe fn write_child<V, const PREFIX_LEN: usize>(
+    node: KeylessNodePtr<V, PREFIX_LEN>,
+    key_byte: u8,
+    child: KeylessNodePtr<V, PREFIX_LEN>,
+) -> KeylessNodePtr<V, PREFIX_LEN> {
+    fn write<V, N, const PREFIX_LEN: usize>(
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+        key_byte: u8,
+        child: KeylessNodePtr<V, PREFIX_LEN>,
+    ) -> KeylessNodePtr<V, PREFIX_LEN>
+    where
+        N: InnerNode<PREFIX_LEN, Key = (), Value = V>,
+    {
+        // SAFETY: Covered by the safety requirements of the containing function,
+        // the reference is not used after the node is deallocated
+        let inner_node = unsafe { inner_ptr.as_mut() };
+        if inner_node.is_full() && inner_node.lookup_child(key_byte).is_none() {
+            let mut new_node = inner_node.grow();
+            new_node.write_child(key_byte, child);
+
+            // SAFETY: The pointer is unique, so no other code will deallocate the node
+            drop(unsafe { NodePtr::deallocate_node_ptr(inner_ptr) });
+
+            NodePtr::allocate_node_ptr(new_node).to_opaque()
+        } else {
+            inner_node.write_child(key_byte, child);
+            inner_ptr.to_opaque()
+        }
+    }
+
+    match node.to_node_ptr() {
+        ConcreteNodePtr::Node4(inner_ptr) => write(inner_ptr, key_byte, child),
+        ConcreteNodePtr::Node16(inner_ptr) => write(inner_ptr, key_byte, child),
+        ConcreteNodePtr::Node32(inner_ptr) => write(inner_ptr, key_byte, child),
+        ConcreteNodePtr::Node48(inner_ptr) => write(inner_ptr, key_byte, child),
+        ConcreteNodePtr::Node256(inner_ptr) => write(inner_ptr, key_byte, child),
+        ConcreteNodePtr::LeafNode(_) => panic!("A leaf pointer cannot have children"),
+    }
+}
+
+/// Remove the child with the key byte from the given inner node, and return
+/// the node which replaces it if it was collapsed or shrunk.
+///
+/// A node with a single remaining child is collapsed into the child, which
+/// takes over the prefix and key byte of the node.
+///
+/// # Safety
+///  - `node` must be a unique pointer to an inner node, and there must not be
+///    any other references to its children either.
+unsafe fn remove_child<V, const PREFIX_LEN: usize>(
+    node: KeylessNodePtr<V, PREFIX_LEN>,
+    key_byte: u8,
+) -> Option<KeylessNodePtr<V, PREFIX_LEN>> {
+    fn remove<V, N, const PREFIX_LEN: usize>(
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+        key_byte: u8,
+    ) -> Option<KeylessNodePtr<V, PREFIX_LEN>>
+    where
+        N: InnerNode<PREFIX_LEN, Key = (), Value = V>,
+    {
+        // SAFETY: Covered by the safety requirements of the containing function,
+        // the reference is not used after the node is deallocated
+        let inner_node = unsafe { inner_ptr.as_mut() };
+        inner_node
+            .remove_child(key_byte)
+            .expect("child should be present");
+
+        let num_children = inner_node.header().num_children();
+        let new_node = if num_children == 1 {
+            let (child_key_byte, mut child) = inner_node.min();
+            let header = inner_node.header();
+            // SAFETY: The slot is a copy, which is only used to move out an inline value
+            match unsafe { Child::classify(NonNull::from(&mut child)) } {
+                Child::Inner(child) => {
+                    // SAFETY: There are no other references to the children of the node
+                    let child_header = unsafe { child.header_mut_unchecked() };
+                    child_header.prepend_prefix(header, child_key_byte);
+                    child
+                },
+                Child::Leaf(leaf_ptr) => {
+                    // SAFETY: The leaf is replaced by the new leaf, so it is not accessed
+                    // again
+                    let (value, suffix) = unsafe { leaf_ptr.deallocate() };
+                    let prefix = header
+                        .full_prefix()
+                        .expect("the inner nodes of a keyless map should store their full prefix");
+                    let key = [prefix, &[child_key_byte], &suffix].concat();
+                    // SAFETY: The key is longer than the path, so the value is not
+                    // inlined
+                    unsafe { allocate_leaf(&key, 0, value, false) }
+                },
+            }
+        } else if N::TYPE.should_shrink_inner_node(num_children) {
+            NodePtr::allocate_node_ptr(inner_node.shrink()).to_opaque()
+        } else {
+            return None;
+        };
+
+        // SAFETY: The pointer is unique, so no other code will deallocate the node
+        drop(unsafe { NodePtr::deallocate_node_ptr(inner_ptr) });
+
+        Some(new_node)
+    }
+
+    match node.to_node_ptr() {
+        ConcreteNodePtr::Node4(inner_ptr) => remove(inner_ptr, key_byte),
+        ConcreteNodePtr::Node16(inner_ptr) => remove(inner_ptr, key_byte),
+        ConcreteNodePtr::Node32(inner_ptr) => remove(inner_ptr, key_byte),
+        ConcreteNodePtr::Node48(inner_ptr) => remove(inner_ptr, key_byte),
+        ConcreteNodePtr::Node256(inner_ptr) => remove(inner_ptr, key_byte),
+        ConcreteNodePtr::LeafNode(_) => panic!("A leaf pointer cannot have children"),
+    }
+}
+
+/// Push the children of the given inner node onto the stack, in reverse
+/// order so that they are popped in order. `f` receives the key byte and the
+/// slot of each child.
+///
+/// # Safety
+///  - `node` must point to an inner node, which is not mutated while the
+///    slots are in use, other than through the slots.
+unsafe fn push_children<V, T, const PREFIX_LEN: usize>(
+    node: KeylessNodePtr<V, PREFIX_LEN>,
+    stack: &mut Vec<T>,
+    mut f: impl FnMut(u8, NonNull<KeylessNodePtr<V, PREFIX_LEN>>) -> T,
+) {
+    fn push<V, N, T, const PREFIX_LEN: usize>(
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+        stack: &mut Vec<T>,
+        mut f: impl FnMut(u8, NonNull<KeylessNodePtr<V, PREFIX_LEN>>) -> T,
+    ) where
+        N: InnerNode<PREFIX_LEN, Key = (), Value = V>,
+    {
+        // SAFETY: Covered by the safety requirements of the containing function
+        let inner_node = unsafe { inner_ptr.as_ref() };
+        stack.extend(inner_node.iter().rev().map(|(key_byte, _)| {
+            // SAFETY: Covered by the safety requirements of the containing function
+            let slot = unsafe { N::lookup_child_slot(node_non_null(inner_ptr), key_byte) };
+            f(key_byte, slot.expect("child should be present"))
+        }));
+    }
+
+    match node.to_node_ptr() {
+        ConcreteNodePtr::Node4(inner_ptr) => push(inner_ptr, stack, &mut f),
+        ConcreteNodePtr::Node16(inner_ptr) => push(inner_ptr, stack, &mut f),
+        ConcreteNodePtr::Node32(inner_ptr) => push(inner_ptr, stack, &mut f),
+        ConcreteNodePtr::Node48(inner_ptr) => push(inner_ptr, stack, &mut f),
+        ConcreteNodePtr::Node256(inner_ptr) => push(inner_ptr, stack, &mut f),
+        ConcreteNodePtr::LeafNode(_) => panic!("A leaf pointer cannot have children"),
+    }
+}
+
+/// Deallocate the given node and all of its children.
+///
+/// # Safety
+///  - This function must only be called once for this root node and all
+///    descendants, otherwise a double-free could result.
+unsafe fn deallocate_tree<V, const PREFIX_LEN: usize>(root: KeylessNodePtr<V, PREFIX_LEN>) {
+    fn deallocate_inner_node<N: InnerNode<PREFIX_LEN>, const PREFIX_LEN: usize>(
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+    ) {
+        // SAFETY: Covered by the safety requirements of the containing function
+        drop(unsafe { NodePtr::deallocate_node_ptr(inner_ptr) });
+    }
+
+    let mut stack = Vec::new();
+    stack.push(root);
+
+    while let Some(mut node) = stack.pop() {
+        // SAFETY: The slot is a copy, which is only used to move out an inline value
+        match unsafe { Child::classify(NonNull::from(&mut node)) } {
+            Child::Inner(inner) => {
+                // SAFETY: The node is deallocated after the children are copied out of
+                // their slots
+                unsafe { push_children(inner, &mut stack, |_, slot| slot.as_ptr().read()) };
+
+                match inner.to_node_ptr() {
+                    ConcreteNodePtr::Node4(inner_ptr) => deallocate_inner_node(inner_ptr),
+                    ConcreteNodePtr::Node16(inner_ptr) => deallocate_inner_node(inner_ptr),
+                    ConcreteNodePtr::Node32(inner_ptr) => deallocate_inner_node(inner_ptr),
+                    ConcreteNodePtr::Node48(inner_ptr) => deallocate_inner_node(inner_ptr),
+                    ConcreteNodePtr::Node256(inner_ptr) => deallocate_inner_node(inner_ptr),
+                    ConcreteNodePtr::LeafNode(_) => unreachable!("the node is an inner node"),
+                }
+            },
+            // SAFETY: Covered by the safety requirements of the containing function
+            Child::Leaf(leaf_ptr) => drop(unsafe { leaf_ptr.deallocate() }),
+        }
+    }
+}
+
+/// Allocate a copy of the given node and all of its children.
+///
+/// # Safety
+///  - The tree must not be mutated while this function runs.
+unsafe fn clone_tree<V: Clone, const PREFIX_LEN: usize>(
+    node: KeylessNodePtr<V, PREFIX_LEN>,
+) -> KeylessNodePtr<V, PREFIX_LEN> {
+    fn clone_inner<V: Clone, N, const PREFIX_LEN: usize>(
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+    ) -> KeylessNodePtr<V, PREFIX_LEN>
+    where
+        N: InnerNode<PREFIX_LEN, Key = (), Value = V>,
+    {
+        // SAFETY: Covered by the safety requirements of the containing function
+        let inner_node = unsafe { inner_ptr.as_ref() };
+        let mut new_node = N::from_header(inner_node.header().clone_prefix());
+        for (key_byte, child) in inner_node.iter() {
+            // SAFETY: Covered by the safety requirements of the containing function
+            new_node.write_child(key_byte, unsafe { clone_tree(child) });
+        }
+
+        NodePtr::allocate_node_ptr(new_node).to_opaque()
+    }
+
+    // SAFETY: The slot is a copy, which is only used to read an inline value
+    match unsafe { Child::classify(NonNull::from(&node)) } {
+        Child::Inner(inner) => match inner.to_node_ptr() {
+            ConcreteNodePtr::Node4(inner_ptr) => clone_inner(inner_ptr),
+            ConcreteNodePtr::Node16(inner_ptr) => clone_inner(inner_ptr),
+            ConcreteNodePtr::Node32(inner_ptr) => clone_inner(inner_ptr),
+            ConcreteNodePtr::Node48(inner_ptr) => clone_inner(inner_ptr),
+            ConcreteNodePtr::Node256(inner_ptr) => clone_inner(inner_ptr),
+            ConcreteNodePtr::LeafNode(_) => unreachable!("the node is an inner node"),
+        },
+        Child::Leaf(leaf_ptr) => {
+            // SAFETY: Covered by the safety requirements of the containing function
+            let (value, suffix) = unsafe { (leaf_ptr.value_ref(), leaf_ptr.suffix()) };
+            let inline_value = matches!(leaf_ptr, LeafPtr::Inline(_));
+            // SAFETY: If the value is inlined, then so was the value it is cloned from
+            unsafe { allocate_leaf(suffix, 0, value.clone(), inline_value) }
+        },
+    }
+}
+
+/// An ordered map based on an adaptive radix tree, whose leaves only store
+/// the values.
+///
+/// Keys are not stored, but rebuilt with [`FromBytes`] from the key bytes of
+/// the path leading to each leaf. For that, every inner node stores its full
+/// prefix, as with [`PrefixStrategy::Pessimistic`]. Leaves whose key is not
+/// completely spelled out by the path also store the remaining key bytes.
+///
+/// Since the keys are rebuilt on demand, the methods which return a key
+/// return it by value instead of by reference.
+///
+/// # Examples
+///
+/// ```rust
+/// use blart::KeylessTreeMap;
+///
+/// let mut map = KeylessTreeMap::<u64, char>::new();
+///
+/// map.insert(3, 'c');
+/// map.insert(1, 'a');
+/// map.insert(2, 'b');
+///
+/// assert_eq!(map.get(&2), Some(&'b'));
+/// assert_eq!(map.iter().collect::<Vec<_>>(), [(1, &'a'), (2, &'b'), (3, &'c')]);
+/// ```
+pub struct KeylessTreeMap<K, V, const PREFIX_LEN: usize = 16> {
+    /// The number of entries present in the tree.
+    num_entries: usize,
+    /// A pointer to the tree root, if present.
+    root: Option<KeylessNodePtr<V, PREFIX_LEN>>,
+    /// Whether the values of the leaves whose key is spelled out by the path
+    /// are stored in the child slot of their parent, see
+    /// [`KeylessTreeMap::with_inline_values`].
+    inline_values: bool,
+    /// The keys are only rebuilt from the tree, never stored.
+    marker: PhantomData<fn() -> K>,
+}
+
+impl<K, V> KeylessTreeMap<K, V> {
+    /// Create a new, empty [`KeylessTreeMap`] with the default number of
+    /// prefix bytes (16).
+    ///
+    /// This function will not pre-allocate anything.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let map = KeylessTreeMap::<u32, ()>::new();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn new() -> Self {
+        Self::with_prefix_len()
+    }
+}
+
+impl<K, V, const PREFIX_LEN: usize> KeylessTreeMap<K, V, PREFIX_LEN> {
+    /// Create a new, empty [`KeylessTreeMap`].
+    ///
+    /// This function will not pre-allocate anything.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let map = KeylessTreeMap::<u32, (), 8>::with_prefix_len();
+    /// assert!(map.is_empty());
+    /// ```
+    ///
+    /// # Panics
+    ///  - If `PREFIX_LEN` is too short to store full prefixes, see
+    ///    [`PrefixStrategy::is_supported`].
+    ///
+    /// ```rust,should_panic
+    /// use blart::KeylessTreeMap;
+    ///
+    /// // There is no room for the pointer to a spilled prefix in 4 bytes
+    /// KeylessTreeMap::<u32, (), 4>::with_prefix_len();
+    /// ```
+    pub fn with_prefix_len() -> Self {
+        assert!(
+            PrefixStrategy::Pessimistic.is_supported(PREFIX_LEN),
+            "a keyless map needs the Pessimistic prefix strategy, which is not supported with \
+             PREFIX_LEN = {PREFIX_LEN}"
+        );
+
+        KeylessTreeMap {
+            num_entries: 0,
+            root: None,
+            inline_values: false,
+            marker: PhantomData,
+        }
+    }
+
+    /// Clear the map, removing all elements.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<u32, char>::new();
+    ///
+    /// map.insert(1, 'a');
+    /// map.clear();
+    /// assert!(map.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        if let Some(root) = self.root.take() {
+            // SAFETY: Since we have a mutable reference to the map, no other code
+            // accesses the tree, and the root was removed from the map so it will not be
+            // deallocated again.
+            unsafe { deallocate_tree(root) };
+        }
+        self.num_entries = 0;
+    }
+
+    /// Search for the leaf of the given key.
+    ///
+    /// # Safety
+    ///  - The tree must not be mutated while this function runs.
+    unsafe fn search(&self, key: &[u8]) -> Option<LeafPtr<V, PREFIX_LEN>> {
+        let root = self.root.as_ref()?;
+        // SAFETY: The root is never an inline value, so the slot is not used after
+        // this
+        let mut child = unsafe { Child::classify(NonNull::from(root)) };
+        let mut depth = 0;
+
+        loop {
+            match child {
+                Child::Inner(inner) => {
+                    // SAFETY: Covered by the safety requirements of the containing function
+                    let prefix = unsafe { full_prefix(inner) };
+                    if !key[depth..].starts_with(prefix) {
+                        return None;
+                    }
+                    depth += prefix.len();
+                    let key_byte = *key.get(depth)?;
+
+                    // SAFETY: Covered by the safety requirements of the containing function
+                    child = unsafe { Child::classify(lookup_child(inner, key_byte)?) };
+                    depth += 1;
+                },
+                Child::Leaf(leaf_ptr) => {
+                    // SAFETY: Covered by the safety requirements of the containing function
+                    let suffix = unsafe { leaf_ptr.suffix() };
+                    return (key[depth..] == *suffix).then_some(leaf_ptr);
+                },
+            }
+        }
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<[u8; 3], char>::new();
+    ///
+    /// map.insert([1, 2, 3], 'a');
+    /// assert_eq!(map.get(&[1, 2, 3]), Some(&'a'));
+    /// assert_eq!(map.get(&[1, 2, 4]), None);
+    /// ```
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: AsBytes + ?Sized,
+    {
+        // SAFETY: Since we have a shared reference to the map, no mutating
+        // operations can occur on the tree. The value reference is bounded by the
+        // lifetime of the shared reference.
+        unsafe { Some(self.search(key.as_bytes())?.value_ref()) }
+    }
+
+    /// Returns a mutable reference to the value corresponding to the key.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<[u8; 3], char>::new();
+    ///
+    /// map.insert([1, 2, 3], 'a');
+    /// *map.get_mut(&[1, 2, 3]).unwrap() = 'b';
+    /// assert_eq!(map.get(&[1, 2, 3]), Some(&'b'));
+    /// ```
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: AsBytes + ?Sized,
+    {
+        // SAFETY: Since we have a mutable reference to the map, there are no other
+        // references to the tree. The value reference is bounded by the lifetime of
+        // the mutable reference.
+        unsafe { Some(self.search(key.as_bytes())?.value_mut()) }
+    }
+
+    /// Returns true if the map contains a value for the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<[u8; 3], char>::new();
+    ///
+    /// map.insert([1, 2, 3], 'a');
+    /// assert!(map.contains_key(&[1, 2, 3]));
+    /// assert!(!map.contains_key(&[1, 2, 4]));
+    /// ```
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: AsBytes + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Replace the child with the key byte of the given parent node, or the
+    /// root if there is no parent.
+    ///
+    /// # Safety
+    ///  - `parent` must be a unique pointer to an inner node of this map.
+    unsafe fn replace_child(
+        &mut self,
+        parent: Option<(KeylessNodePtr<V, PREFIX_LEN>, u8)>,
+        new_child: KeylessNodePtr<V, PREFIX_LEN>,
+    ) {
+        match parent {
+            Some((parent, key_byte)) => {
+                // SAFETY: Covered by the safety requirements of the containing function.
+                // The parent already has a child with the key byte, so it is not grown.
+                let new_parent = unsafe { write_child(parent, key_byte, new_child) };
+                debug_assert_eq!(new_parent, parent);
+            },
+            None => self.root = Some(new_child),
+        }
+    }
+
+    /// Insert the value with the bytes of a key, returning the previous value
+    /// of the key if there was one.
+    ///
+    /// # Panics
+    ///  - If the key is a prefix of an existing key, or the other way around.
+    fn insert_bytes(&mut self, key: &[u8], value: V) -> Option<V> {
+        let inline_values = self.inline_values;
+        let Some(root) = self.root.as_mut() else {
+            // SAFETY: The root is not a child slot, so the value is not inlined
+            self.root = Some(unsafe { allocate_leaf(key, 0, value, false) });
+            self.num_entries = 1;
+            return None;
+        };
+
+        let mut parent = None;
+        let mut depth = 0;
+
+        // SAFETY: Since we have a mutable reference to the map, there are no other
+        // references to the tree. The root is never an inline value, so its slot is
+        // not used after it is classified. The values are only inlined if the map
+        // was created by `with_inline_values`.
+        let new_child = unsafe {
+            let mut child = Child::classify(NonNull::from(root));
+            loop {
+                match child {
+                    Child::Inner(inner) => {
+                        let prefix = full_prefix(inner);
+                        let matched = common_prefix_len(prefix, &key[depth..]);
+                        if matched < prefix.len() {
+                            // The key leaves the prefix of the node, so there needs to be a new
+                            // node where they part. The node keeps the rest of its prefix after
+                            // the key byte which leads to it.
+                            let new_node = new_node4(
+                                &prefix[..matched],
+                                (prefix[matched], inner),
+                                (
+                                    key[depth + matched],
+                                    allocate_leaf(key, depth + matched + 1, value, inline_values),
+                                ),
+                            );
+                            inner.header_mut_unchecked().ltrim_by(matched + 1);
+                            break new_node;
+                        }
+
+                        depth += prefix.len();
+                        let key_byte = key[depth];
+                        match lookup_child(inner, key_byte) {
+                            Some(slot) => {
+                                parent = Some((inner, key_byte));
+                                child = Child::classify(slot);
+                                depth += 1;
+                            },
+                            None => {
+                                let leaf = allocate_leaf(key, depth + 1, value, inline_values);
+                                let new_inner = write_child(inner, key_byte, leaf);
+                                self.num_entries += 1;
+                                if new_inner != inner {
+                                    self.replace_child(parent, new_inner);
+                                }
+                                return None;
+                            },
+                        }
+                    },
+                    Child::Leaf(leaf_ptr) => {
+                        let suffix = leaf_ptr.suffix();
+                        if key[depth..] == *suffix {
+                            return Some(mem::replace(leaf_ptr.value_mut(), value));
+                        }
+
+                        // Split the leaf into a new node with the common bytes as the prefix,
+                        // and the existing leaf and the new leaf as the children
+                        let matched = common_prefix_len(suffix, &key[depth..]);
+                        let leaf_key_byte = suffix[matched];
+                        let key_byte = key[depth + matched];
+                        let (leaf_value, suffix) = leaf_ptr.deallocate();
+                        break new_node4(
+                            &key[depth..(depth + matched)],
+                            (
+                                leaf_key_byte,
+                                allocate_leaf(&suffix, matched + 1, leaf_value, inline_values),
+                            ),
+                            (
+                                key_byte,
+                                allocate_leaf(key, depth + matched + 1, value, inline_values),
+                            ),
+                        );
+                    },
+                }
+            }
+        };
+
+        // SAFETY: Since we have a mutable reference to the map, there are no other
+        // references to the tree.
+        unsafe { self.replace_child(parent, new_child) };
+        self.num_entries += 1;
+        None
+    }
+
+    /// Insert a key-value pair into the map.
+    ///
+    /// If the map did not have this key present, `None` is returned.
+    ///
+    /// If the map did have this key present, the value is updated, and the old
+    /// value is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<u128, char>::new();
+    ///
+    /// assert!(map.insert(123, 'a').is_none());
+    /// assert!(map.insert(234, 'b').is_none());
+    /// assert_eq!(map.insert(234, 'c'), Some('b'));
+    ///
+    /// assert_eq!(map.len(), 2);
+    /// ```
+    pub fn insert(&mut self, key: K, value: V) -> Option<V>
+    where
+        K: NoPrefixesBytes,
+    {
+        self.insert_bytes(key.as_bytes(), value)
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key
+    /// was previously in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<[u8; 3], char>::new();
+    ///
+    /// map.insert([1, 2, 3], 'a');
+    /// map.insert([2, 3, 4], 'b');
+    ///
+    /// assert_eq!(map.remove(&[2, 3, 4]), Some('b'));
+    /// assert_eq!(map.remove(&[2, 3, 4]), None);
+    /// ```
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: AsBytes + ?Sized,
+    {
+        let key = key.as_bytes();
+        let mut grandparent = None;
+        let mut parent = None;
+        let mut depth = 0;
+
+        // SAFETY: Since we have a mutable reference to the map, there are no other
+        // references to the tree. The root is never an inline value, so its slot is
+        // not used after it is classified.
+        unsafe {
+            let mut child = Child::classify(NonNull::from(self.root.as_mut()?));
+            let leaf_ptr = loop {
+                match child {
+                    Child::Inner(inner) => {
+                        let prefix = full_prefix(inner);
+                        if !key[depth..].starts_with(prefix) {
+                            return None;
+                        }
+                        depth += prefix.len();
+                        let key_byte = *key.get(depth)?;
+
+                        grandparent = parent;
+                        parent = Some((inner, key_byte));
+                        child = Child::classify(lookup_child(inner, key_byte)?);
+                        depth += 1;
+                    },
+                    Child::Leaf(leaf_ptr) => {
+                        if key[depth..] != *leaf_ptr.suffix() {
+                            return None;
+                        }
+                        break leaf_ptr;
+                    },
+                }
+            };
+
+            let (value, _) = leaf_ptr.deallocate();
+            match parent {
+                Some((parent, key_byte)) => {
+                    if let Some(new_parent) = remove_child(parent, key_byte) {
+                        self.replace_child(grandparent, new_parent);
+                    }
+                },
+                None => self.root = None,
+            }
+            self.num_entries -= 1;
+
+            Some(value)
+        }
+    }
+
+    /// Gets the given key’s corresponding entry in the map for in-place
+    /// manipulation.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<u32, u32>::new();
+    ///
+    /// for key in [1, 2, 1, 1] {
+    ///     *map.entry(key).or_default() += 1;
+    /// }
+    ///
+    /// assert_eq!(map.get(&1), Some(&3));
+    /// assert_eq!(map.get(&2), Some(&1));
+    /// ```
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, PREFIX_LEN>
+    where
+        K: NoPrefixesBytes,
+    {
+        // SAFETY: Since we have a mutable reference to the map, there are no other
+        // references to the tree.
+        match unsafe { self.search(key.as_bytes()) } {
+            Some(leaf_ptr) => Entry::Occupied(OccupiedEntry {
+                leaf_ptr,
+                map: self,
+                key,
+            }),
+            None => Entry::Vacant(VacantEntry { map: self, key }),
+        }
+    }
+
+    /// Gets an iterator over the entries of the map, in order of the key bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<[u8; 2], char>::new();
+    ///
+    /// map.insert([2, 1], 'b');
+    /// map.insert([1, 2], 'a');
+    ///
+    /// assert_eq!(map.iter().collect::<Vec<_>>(), [([1, 2], &'a'), ([2, 1], &'b')]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V, PREFIX_LEN> {
+        Iter::new(self)
+    }
+
+    /// Gets a mutable iterator over the entries of the map, in order of the key
+    /// bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<[u8; 2], u32>::new();
+    ///
+    /// map.insert([2, 1], 2);
+    /// map.insert([1, 2], 1);
+    ///
+    /// for (key, value) in map.iter_mut() {
+    ///     *value += u32::from(key[0]);
+    /// }
+    ///
+    /// assert_eq!(map.iter().collect::<Vec<_>>(), [([1, 2], &2), ([2, 1], &4)]);
+    /// ```
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V, PREFIX_LEN> {
+        IterMut::new(self)
+    }
+
+    /// Gets an iterator over the keys of the map, in order of the key bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<u8, char>::new();
+    ///
+    /// map.insert(200, 'b');
+    /// map.insert(7, 'a');
+    ///
+    /// assert_eq!(map.keys().collect::<Vec<_>>(), [7, 200]);
+    /// ```
+    pub fn keys(&self) -> Keys<'_, K, V, PREFIX_LEN> {
+        Keys(self.iter())
+    }
+
+    /// Gets an iterator over the values of the map, in order of the key bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<u8, char>::new();
+    ///
+    /// map.insert(200, 'b');
+    /// map.insert(7, 'a');
+    ///
+    /// assert_eq!(map.values().collect::<Vec<_>>(), [&'a', &'b']);
+    /// ```
+    pub fn values(&self) -> Values<'_, K, V, PREFIX_LEN> {
+        Values(self.iter())
+    }
+
+    /// Gets a mutable iterator over the values of the map, in order of the key
+    /// bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<u8, String>::new();
+    ///
+    /// map.insert(200, "b".to_string());
+    /// map.insert(7, "a".to_string());
+    ///
+    /// for value in map.values_mut() {
+    ///     value.push('!');
+    /// }
+    ///
+    /// assert_eq!(map.values().collect::<Vec<_>>(), ["a!", "b!"]);
+    /// ```
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V, PREFIX_LEN> {
+        ValuesMut(self.iter_mut())
+    }
+
+    /// Returns the number of elements in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<u32, char>::new();
+    ///
+    /// map.insert(1, 'a');
+    /// assert_eq!(map.len(), 1);
+    /// ```
+    pub fn len(&self) -> usize {
+        self.num_entries
+    }
+
+    /// Returns true if the map contains no elements.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<u32, char>::new();
+    ///
+    /// assert!(map.is_empty());
+    /// map.insert(1, 'a');
+    /// assert!(!map.is_empty());
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.num_entries == 0
+    }
+
+    /// Record the stats of all the nodes and leaves of the map, see
+    /// [`TreeStatsCollector::collect_keyless`].
+    pub(crate) fn collect_stats(&self, collector: &mut TreeStatsCollector) {
+        fn record_inner_node<V, N, const PREFIX_LEN: usize>(
+            collector: &mut TreeStatsCollector,
+            inner_ptr: NodePtr<PREFIX_LEN, N>,
+            depth: usize,
+        ) where
+            N: InnerNode<PREFIX_LEN, Key = (), Value = V>,
+        {
+            // SAFETY: Since we have a shared reference to the map, no mutating
+            // operations can occur on the tree.
+            collector.record_inner_node(unsafe { inner_ptr.as_ref() }, depth);
+        }
+
+        let Some(root) = self.root.as_ref() else {
+            return;
+        };
+
+        // The nodes which are left to visit, with the number of inner nodes and the
+        // number of key bytes on the path leading to them
+        let mut stack = Vec::new();
+        // SAFETY: The root is never an inline value, so the slot is not used after
+        // this
+        stack.push((unsafe { Child::classify(NonNull::from(root)) }, 0, 0));
+
+        while let Some((child, depth, key_len)) = stack.pop() {
+            match child {
+                Child::Inner(inner) => {
+                    match inner.to_node_ptr() {
+                        ConcreteNodePtr::Node4(p) => record_inner_node(collector, p, depth),
+                        ConcreteNodePtr::Node16(p) => record_inner_node(collector, p, depth),
+                        ConcreteNodePtr::Node32(p) => record_inner_node(collector, p, depth),
+                        ConcreteNodePtr::Node48(p) => record_inner_node(collector, p, depth),
+                        ConcreteNodePtr::Node256(p) => record_inner_node(collector, p, depth),
+                        ConcreteNodePtr::LeafNode(_) => unreachable!("the node is an inner node"),
+                    }
+
+                    // SAFETY: Since we have a shared reference to the map, no mutating
+                    // operations can occur on the tree.
+                    unsafe {
+                        let key_len = key_len + full_prefix(inner).len() + 1;
+                        push_children(inner, &mut stack, |_, slot| {
+                            (Child::classify(slot), depth + 1, key_len)
+                        });
+                    }
+                },
+                Child::Leaf(leaf_ptr) => {
+                    // SAFETY: Since we have a shared reference to the map, no mutating
+                    // operations can occur on the tree.
+                    let suffix_len = unsafe { leaf_ptr.suffix() }.len();
+                    let key_len = key_len + suffix_len;
+                    let leaf_node_size = mem::size_of::<LeafNode<(), V>>();
+                    match leaf_ptr {
+                        LeafPtr::Full(_) => collector.record_leaf(key_len, leaf_node_size, depth),
+                        LeafPtr::Suffix(_) => collector.record_leaf(
+                            key_len,
+                            mem::size_of::<SuffixLeaf<V>>() + suffix_len,
+                            depth,
+                        ),
+                        LeafPtr::Inline(_) => {
+                            collector.record_inline_leaf(key_len, leaf_node_size, depth)
+                        },
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<K, V: NoUninit, const PREFIX_LEN: usize> KeylessTreeMap<K, V, PREFIX_LEN> {
+    /// Create a new, empty [`KeylessTreeMap`] which stores small values
+    /// directly in the child slot of their parent inner node.
+    ///
+    /// This is done for the leaves whose key is completely spelled out by the
+    /// path leading to them, so that they do not need a leaf node.
+    /// [`TreeStatsCollector::collect_keyless`] reports the number of inline
+    /// values and the memory saved by them. The other leaves are allocated as
+    /// usual.
+    ///
+    /// The child slot also holds the tag which tells it apart from a pointer
+    /// to a node, so the values must be smaller than a pointer.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::KeylessTreeMap;
+    ///
+    /// let mut map = KeylessTreeMap::<u32, u16>::with_inline_values();
+    ///
+    /// for key in 0..1000 {
+    ///     map.insert(key, key as u16);
+    /// }
+    /// *map.get_mut(&7).unwrap() += 1;
+    ///
+    /// assert_eq!(map.get(&7), Some(&8));
+    /// assert_eq!(map.remove(&999), Some(999));
+    /// assert_eq!(map.len(), 999);
+    /// ```
+    ///
+    /// # Panics
+    ///  - If `V` is not smaller than a pointer.
+    ///  - If `PREFIX_LEN` is too short to store full prefixes, see
+    ///    [`PrefixStrategy::is_supported`].
+    ///
+    /// ```rust,should_panic
+    /// use blart::KeylessTreeMap;
+    ///
+    /// // A `u64` takes up the whole child slot on 64-bit platforms
+    /// KeylessTreeMap::<u32, u64>::with_inline_values();
+    /// ```
+    pub fn with_inline_values() -> Self {
+        assert!(
+            mem::size_of::<V>() < mem::size_of::<usize>(),
+            "inline values must be smaller than a pointer, but they take {} bytes",
+            mem::size_of::<V>()
+        );
+
+        let mut map = Self::with_prefix_len();
+        map.inline_values = true;
+        map
+    }
+}
+
+impl<K, V, const PREFIX_LEN: usize> Drop for KeylessTreeMap<K, V, PREFIX_LEN> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<K, V: Clone, const PREFIX_LEN: usize> Clone for KeylessTreeMap<K, V, PREFIX_LEN> {
+    fn clone(&self) -> Self {
+        KeylessTreeMap {
+            num_entries: self.num_entries,
+            // SAFETY: Since we have a shared reference to the map, no mutating
+            // operations can occur on the tree.
+            root: self.root.map(|root| unsafe { clone_tree(root) }),
+            inline_values: self.inline_values,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V, const PREFIX_LEN: usize> fmt::Debug for KeylessTreeMap<K, V, PREFIX_LEN>
+where
+    K: FromBytes + fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V, const PREFIX_LEN: usize> Default for KeylessTreeMap<K, V, PREFIX_LEN> {
+    fn default() -> Self {
+        Self::with_prefix_len()
+    }
+}
+
+impl<K, V, const PREFIX_LEN: usize> Extend<(K, V)> for KeylessTreeMap<K, V, PREFIX_LEN>
+where
+    K: NoPrefixesBytes,
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V, const PREFIX_LEN: usize> FromIterator<(K, V)> for KeylessTreeMap<K, V, PREFIX_LEN>
+where
+    K: NoPrefixesBytes,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = KeylessTreeMap::with_prefix_len();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'a, K: FromBytes, V, const PREFIX_LEN: usize> IntoIterator
+    for &'a KeylessTreeMap<K, V, PREFIX_LEN>
+{
+    type IntoIter = Iter<'a, K, V, PREFIX_LEN>;
+    type Item = (K, &'a V);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: FromBytes, V, const PREFIX_LEN: usize> IntoIterator
+    for &'a mut KeylessTreeMap<K, V, PREFIX_LEN>
+{
+    type IntoIter = IterMut<'a, K, V, PREFIX_LEN>;
+    type Item = (K, &'a mut V);
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K, V, const PREFIX_LEN: usize> PartialEq for KeylessTreeMap<K, V, PREFIX_LEN>
+where
+    V: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.num_entries == other.num_entries && iterators::trees_equal(self, other)
+    }
+}
+
+impl<K, V: Eq, const PREFIX_LEN: usize> Eq for KeylessTreeMap<K, V, PREFIX_LEN> {}
+
+// SAFETY: This is safe to implement if `V` is also `Send`. The keys are not
+// stored in the map.
+unsafe impl<K, V, const PREFIX_LEN: usize> Send for KeylessTreeMap<K, V, PREFIX_LEN> where V: Send {}
+
+// SAFETY: This is safe to implement if `V` is also `Sync`. The keys are not
+// stored in the map.
+unsafe impl<K, V, const PREFIX_LEN: usize> Sync for KeylessTreeMap<K, V, PREFIX_LEN> where V: Sync {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::ffi::CString;
+    use alloc::{format, string::String};
+
+    use crate::{Mapped, ToUBE, TreeMap};
+
+    use super::*;
+
+    #[test]
+    fn keyless_map_insert_get_remove() {
+        let mut map = KeylessTreeMap::<u32, u32>::new();
+
+        for key in 0..1000 {
+            assert_eq!(map.insert(key * 7, key), None);
+        }
+        assert_eq!(map.len(), 1000);
+        assert_eq!(map.insert(7, 100), Some(1));
+
+        for key in 0..1000 {
+            let expected = if key == 1 { 100 } else { key };
+            assert_eq!(map.get(&(key * 7)), Some(&expected));
+            assert_eq!(map.get(&(key * 7 + 1)), None);
+        }
+
+        for key in (0..1000).step_by(2) {
+            assert_eq!(map.remove(&(key * 7)), Some(key));
+            assert_eq!(map.remove(&(key * 7)), None);
+        }
+        assert_eq!(map.len(), 500);
+        assert!(map
+            .iter()
+            .all(|(key, value)| key == value * 7 || *value == 100));
+
+        for key in (1..1000).step_by(2) {
+            assert!(map.remove(&(key * 7)).is_some());
+        }
+        assert!(map.is_empty());
+        assert_eq!(map.root, None);
+    }
+
+    #[test]
+    fn keyless_map_rebuilds_keys_with_long_prefixes() {
+        let keys = [
+            "https://example.com/a/long/path/1",
+            "https://example.com/a/long/path/2",
+            "https://example.com/a/longer/path/1",
+            "https://example.com/b",
+            "https://example.org",
+            "ftp://example.com",
+        ]
+        .map(|key| CString::new(key).unwrap());
+
+        let mut map = KeylessTreeMap::<_, usize, 8>::with_prefix_len();
+        for (value, key) in keys.iter().enumerate() {
+            map.insert(key.clone(), value);
+        }
+
+        let mut expected = keys.iter().cloned().zip(0..).collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(
+            map.iter()
+                .map(|(key, value)| (key, *value))
+                .collect::<Vec<_>>(),
+            expected
+        );
+
+        // Removing keys collapses the nodes, and prepends their prefixes to the
+        // remaining children
+        for key in &keys[..4] {
+            assert!(map.remove(key).is_some());
+        }
+        assert_eq!(
+            map.keys().collect::<Vec<_>>(),
+            [keys[5].clone(), keys[4].clone()]
+        );
+        assert_eq!(map.get(&keys[4]), Some(&4));
+    }
+
+    #[test]
+    fn keyless_map_matches_tree_map() {
+        let mut keyless = KeylessTreeMap::<Mapped<ToUBE, u64>, usize>::new();
+        let mut map = TreeMap::<Mapped<ToUBE, u64>, usize>::new();
+
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        for step in 0..5000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let key = Mapped::new(state % 2000 * 0x1_0001);
+
+            if step % 3 == 0 {
+                assert_eq!(keyless.remove(&key), map.remove(&key));
+            } else {
+                assert_eq!(keyless.insert(key, step), map.insert(key, step));
+            }
+        }
+
+        assert_eq!(keyless.len(), map.len());
+        assert!(keyless
+            .iter()
+            .eq(map.iter().map(|(key, value)| (*key, value))));
+        assert_eq!(keyless.clone(), keyless);
+    }
+
+    #[test]
+    fn keyless_map_matches_tree_map_with_variable_length_keys() {
+        let mut keyless = KeylessTreeMap::<CString, usize>::new();
+        let mut map = TreeMap::<CString, usize>::new();
+
+        let mut state = 0x9E37_79B9_7F4A_7C15u64;
+        for step in 0..5000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let len = (state % 24) as usize;
+            let key = CString::new(
+                (0..len)
+                    .map(|idx| b'a' + ((state >> (idx % 16 * 4)) % 3) as u8)
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap();
+
+            if step % 3 == 0 {
+                assert_eq!(keyless.remove(&key), map.remove(&key));
+            } else {
+                assert_eq!(keyless.insert(key.clone(), step), map.insert(key, step));
+            }
+        }
+
+        assert_eq!(keyless.len(), map.len());
+        assert!(keyless
+            .iter()
+            .eq(map.iter().map(|(key, value)| (key.clone(), value))));
+    }
+
+    #[test]
+    fn keyless_map_iter_mut_and_values() {
+        let mut map: KeylessTreeMap<[u8; 2], String> =
+            [([1, 2], "a"), ([1, 3], "b"), ([4, 0], "c")]
+                .map(|(key, value)| (key, String::from(value)))
+                .into_iter()
+                .collect();
+
+        for (key, value) in &mut map {
+            value.push(char::from(b'0' + key[1]));
+        }
+
+        assert_eq!(map.values().collect::<Vec<_>>(), ["a2", "b3", "c0"]);
+        assert_eq!(
+            format!("{map:?}"),
+            r#"{[1, 2]: "a2", [1, 3]: "b3", [4, 0]: "c0"}"#
+        );
+    }
+
+    #[test]
+    #[should_panic = "a keyless map needs the Pessimistic prefix strategy"]
+    fn keyless_map_short_prefix_len_panics() {
+        KeylessTreeMap::<u32, (), 4>::with_prefix_len();
+    }
+
+    #[test]
+    fn keyless_map_inline_values_match_tree_map() {
+        let mut keyless = KeylessTreeMap::<CString, u16>::with_inline_values();
+        let mut map = TreeMap::<CString, u16>::new();
+
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        for step in 0..5000u16 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let len = (state % 6) as usize;
+            let key = CString::new(
+                (0..len)
+                    .map(|idx| b'a' + ((state >> (idx * 8)) % 4) as u8)
+                    .collect::<Vec<_>>(),
+            )
+            .unwrap();
+
+            match step % 4 {
+                0 => assert_eq!(keyless.remove(&key), map.remove(&key)),
+                1 => {
+                    if let Some(value) = map.get_mut(&key) {
+                        *value += 1;
+                    }
+                    if let Some(value) = keyless.get_mut(&key) {
+                        *value += 1;
+                    }
+                },
+                _ => assert_eq!(keyless.insert(key.clone(), step), map.insert(key, step)),
+            }
+        }
+
+        for (value, expected) in keyless.values_mut().zip(map.values_mut()) {
+            *value *= 2;
+            *expected *= 2;
+        }
+
+        assert_eq!(keyless.len(), map.len());
+        assert!(keyless
+            .iter()
+            .eq(map.iter().map(|(key, value)| (key.clone(), value))));
+        assert_eq!(keyless.clone(), keyless);
+
+        let stats = TreeStatsCollector::collect_keyless(&keyless).unwrap();
+        assert_eq!(stats.leaf.count, map.len());
+        assert!(stats.leaf.inline_count > 0);
+    }
+
+    #[test]
+    fn keyless_map_inline_values_stats() {
+        let mut keyless = KeylessTreeMap::<[u8; 3], u16>::new();
+        let mut inline = KeylessTreeMap::<[u8; 3], u16>::with_inline_values();
+        // The last byte of these keys tells them apart, so their path spells out
+        // the whole key
+        let dense_keys = (0..5000u16).map(|key| [0, (key >> 8) as u8, key as u8]);
+        // These keys are told apart by the middle byte, so they keep a suffix
+        let sparse_keys = (0..5).map(|key| [200, key, 7]);
+        for (value, key) in dense_keys.chain(sparse_keys).enumerate() {
+            keyless.insert(key, value as u16);
+            inline.insert(key, value as u16);
+        }
+
+        let keyless_stats = TreeStatsCollector::collect_keyless(&keyless).unwrap();
+        let inline_stats = TreeStatsCollector::collect_keyless(&inline).unwrap();
+
+        // The trees have the same shape, only the leaves are stored differently
+        assert_eq!(inline_stats.tree, keyless_stats.tree);
+        assert_eq!(inline_stats.leaf.count, 5005);
+        assert_eq!(inline_stats.leaf.sum_key_bytes, 5005 * 3);
+        assert_eq!(keyless_stats.leaf.inline_count, 0);
+        assert_eq!(inline_stats.leaf.inline_count, 5000);
+        assert_eq!(
+            inline_stats.leaf.inline_saved_bytes,
+            5000 * mem::size_of::<LeafNode<(), u16>>()
+        );
+        assert_eq!(
+            keyless_stats.leaf.mem_usage - inline_stats.leaf.mem_usage,
+            inline_stats.leaf.inline_saved_bytes
+        );
+        assert!(inline_stats.total_memory_usage() < keyless_stats.total_memory_usage());
+    }
+}
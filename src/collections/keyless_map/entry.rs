@@ -0,0 +1,260 @@
+use core::mem::replace;
+
+use super::{KeylessTreeMap, LeafPtr};
+use crate::NoPrefixesBytes;
+
+/// A view into an occupied entry in a [`KeylessTreeMap`]. It is part of the
+/// [`Entry`] enum.
+pub struct OccupiedEntry<'a, K, V, const PREFIX_LEN: usize> {
+    pub(super) leaf_ptr: LeafPtr<V, PREFIX_LEN>,
+    pub(super) map: &'a mut KeylessTreeMap<K, V, PREFIX_LEN>,
+    pub(super) key: K,
+}
+
+impl<'a, K, V, const PREFIX_LEN: usize> OccupiedEntry<'a, K, V, PREFIX_LEN>
+where
+    K: NoPrefixesBytes,
+{
+    /// Gets a reference to the value in the entry.
+    pub fn get(&self) -> &V {
+        // SAFETY: This is safe because `Self` has an mutable reference
+        // so it's safe to generate a shared reference from this mutable reference
+        unsafe { self.leaf_ptr.value_ref() }
+    }
+
+    /// Gets a mutable reference to the value in the entry.
+    ///
+    /// If you need a reference to the [`OccupiedEntry`] which may outlive the
+    /// destruction of the Entry value, see [`OccupiedEntry::into_mut`].
+    pub fn get_mut(&mut self) -> &mut V {
+        // SAFETY: This is safe because `Self` has an mutable reference
+        // so it's safe to generate a mutable reference from this mutable reference
+        unsafe { self.leaf_ptr.value_mut() }
+    }
+
+    /// Sets the value of the entry, and returns the entry’s old value.
+    pub fn insert(&mut self, value: V) -> V {
+        replace(self.get_mut(), value)
+    }
+
+    /// Converts the [`OccupiedEntry`] into a mutable reference to the value in
+    /// the entry with a lifetime bound to the map itself.
+    ///
+    /// If you need multiple references to the [`OccupiedEntry`], see
+    /// [`OccupiedEntry::get_mut`].
+    pub fn into_mut(self) -> &'a mut V {
+        // SAFETY: This is safe because `Self` has an mutable reference
+        // so it's safe to generate a mutable reference from this mutable reference
+        unsafe { self.leaf_ptr.value_mut() }
+    }
+
+    /// Gets a reference to the key in the entry.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Take the ownership of the key and value from the map.
+    pub fn remove_entry(self) -> (K, V) {
+        let value = self
+            .map
+            .remove(&self.key)
+            .expect("the entry should be present in the map");
+        (self.key, value)
+    }
+
+    /// Takes the value out of the entry, and returns it.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+}
+
+/// A view into a vacant entry in a [`KeylessTreeMap`]. It is part of the
+/// [`Entry`] enum.
+pub struct VacantEntry<'a, K, V, const PREFIX_LEN: usize> {
+    pub(super) map: &'a mut KeylessTreeMap<K, V, PREFIX_LEN>,
+    pub(super) key: K,
+}
+
+impl<'a, K, V, const PREFIX_LEN: usize> VacantEntry<'a, K, V, PREFIX_LEN>
+where
+    K: NoPrefixesBytes,
+{
+    /// Sets the value of the entry with the [`VacantEntry`]’s key, and returns
+    /// a mutable reference to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.insert_entry(value).into_mut()
+    }
+
+    /// Sets the value of the entry with the [`VacantEntry`]’s key, and returns
+    /// a [`OccupiedEntry`].
+    pub fn insert_entry(self, value: V) -> OccupiedEntry<'a, K, V, PREFIX_LEN> {
+        let key_bytes = self.key.as_bytes();
+        self.map.insert_bytes(key_bytes, value);
+        // SAFETY: Since we have a mutable reference to the map, there are no other
+        // references to the tree.
+        let leaf_ptr = unsafe { self.map.search(key_bytes) }
+            .expect("the entry should be present in the map after the insert");
+
+        OccupiedEntry {
+            leaf_ptr,
+            map: self.map,
+            key: self.key,
+        }
+    }
+
+    /// Take ownership of the key.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Gets a reference to the key that would be used when inserting a value
+    /// through the [`VacantEntry`].
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+/// A view into a single entry in a map, which may either be vacant or occupied.
+///
+/// This enum is constructed from the [`KeylessTreeMap::entry`].
+pub enum Entry<'a, K, V, const PREFIX_LEN: usize> {
+    /// A view into an occupied entry in a [`KeylessTreeMap`].
+    Occupied(OccupiedEntry<'a, K, V, PREFIX_LEN>),
+    /// A view into a vacant entry in a [`KeylessTreeMap`].
+    Vacant(VacantEntry<'a, K, V, PREFIX_LEN>),
+}
+
+impl<'a, K, V, const PREFIX_LEN: usize> Entry<'a, K, V, PREFIX_LEN>
+where
+    K: NoPrefixesBytes,
+{
+    /// Provides in-place mutable access to an occupied entry before any
+    /// potential inserts into the map.
+    pub fn and_modify<F>(self, f: F) -> Self
+    where
+        F: FnOnce(&mut V),
+    {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            },
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+
+    /// Sets the value of the entry, and returns an [`OccupiedEntry`].
+    pub fn insert_entry(self, value: V) -> OccupiedEntry<'a, K, V, PREFIX_LEN> {
+        match self {
+            Entry::Occupied(mut entry) => {
+                entry.insert(value);
+                entry
+            },
+            Entry::Vacant(entry) => entry.insert_entry(value),
+        }
+    }
+
+    /// Returns a reference to this entry’s key.
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default value if empty,
+    /// and returns a mutable reference to the value in the entry.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    /// Ensures a value is in the entry by inserting the default if empty, and
+    /// returns a mutable reference to the value in the entry.
+    pub fn or_insert(self, value: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(value),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of the default
+    /// function if empty, and returns a mutable reference to the value in the
+    /// entry.
+    pub fn or_insert_with<F>(self, f: F) -> &'a mut V
+    where
+        F: FnOnce() -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting, if empty, the result of
+    /// the default function, which is given a reference to the key.
+    pub fn or_insert_with_key<F>(self, f: F) -> &'a mut V
+    where
+        F: FnOnce(&K) -> V,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let value = f(entry.key());
+                entry.insert(value)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::KeylessTreeMap;
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+
+    use super::*;
+
+    #[test]
+    fn entry_insert_modify_remove() {
+        let mut map = KeylessTreeMap::<u16, String>::new();
+
+        map.entry(1).or_insert_with(|| String::from("a"));
+        map.entry(2).or_insert_with_key(|key| key.to_string());
+        map.entry(1)
+            .and_modify(|value| value.push('a'))
+            .or_default();
+        map.entry(3)
+            .and_modify(|value| value.push('a'))
+            .or_default();
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            [
+                (1, &"aa".to_string()),
+                (2, &"2".to_string()),
+                (3, &String::new())
+            ]
+        );
+
+        match map.entry(2) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.insert(String::from("b")), "2");
+                assert_eq!(entry.remove_entry(), (2, String::from("b")));
+            },
+            Entry::Vacant(_) => panic!("the entry should be occupied"),
+        }
+        match map.entry(2) {
+            Entry::Occupied(_) => panic!("the entry should be vacant"),
+            Entry::Vacant(entry) => {
+                assert_eq!(*entry.key(), 2);
+                assert_eq!(entry.insert_entry(String::from("c")).get(), "c");
+            },
+        }
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(&2).unwrap(), "c");
+    }
+}
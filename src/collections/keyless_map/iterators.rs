@@ -0,0 +1,248 @@
+use alloc::vec::Vec;
+use core::{iter::FusedIterator, marker::PhantomData, ptr::NonNull};
+
+use super::{full_prefix, push_children, Child, KeylessNodePtr, KeylessTreeMap, LeafPtr};
+use crate::FromBytes;
+
+/// A depth first traversal over the leaves of a [`KeylessTreeMap`], which
+/// keeps track of the key bytes of the path leading to the current leaf.
+struct RawIter<V, const PREFIX_LEN: usize> {
+    /// The nodes which are left to visit, with the key byte leading to them
+    /// from their parent, and the length of the path to their parent.
+    stack: Vec<(Option<u8>, Child<V, PREFIX_LEN>, usize)>,
+    /// The key bytes of the path leading to the last visited node.
+    path: Vec<u8>,
+}
+
+impl<V, const PREFIX_LEN: usize> RawIter<V, PREFIX_LEN> {
+    fn new(root: Option<KeylessNodePtr<V, PREFIX_LEN>>) -> Self {
+        RawIter {
+            stack: root
+                .map(|root| {
+                    // SAFETY: The root is never an inline value, so the slot is not used
+                    // after this
+                    let root = unsafe { Child::classify(NonNull::from(&root)) };
+                    (None, root, 0)
+                })
+                .into_iter()
+                .collect(),
+            path: Vec::new(),
+        }
+    }
+
+    /// Advance to the next leaf, after which [`RawIter::key`] returns the key
+    /// bytes of the leaf.
+    ///
+    /// # Safety
+    ///  - The tree must not be mutated while the traversal is in use, other
+    ///    than the values of the returned leaves.
+    unsafe fn next(&mut self) -> Option<LeafPtr<V, PREFIX_LEN>> {
+        while let Some((key_byte, child, depth)) = self.stack.pop() {
+            self.path.truncate(depth);
+            self.path.extend(key_byte);
+            match child {
+                Child::Inner(inner) => {
+                    // SAFETY: Covered by the safety requirements of the containing function
+                    unsafe {
+                        self.path.extend_from_slice(full_prefix(inner));
+                        let depth = self.path.len();
+                        push_children(inner, &mut self.stack, |key_byte, slot| {
+                            (Some(key_byte), Child::classify(slot), depth)
+                        });
+                    }
+                },
+                Child::Leaf(leaf_ptr) => {
+                    // SAFETY: Covered by the safety requirements of the containing function
+                    self.path.extend_from_slice(unsafe { leaf_ptr.suffix() });
+                    return Some(leaf_ptr);
+                },
+            }
+        }
+
+        None
+    }
+
+    /// Return the key bytes of the last leaf returned by [`RawIter::next`].
+    fn key(&self) -> &[u8] {
+        &self.path
+    }
+}
+
+/// Return true if the maps have the same keys and values.
+pub(super) fn trees_equal<K, V, const PREFIX_LEN: usize>(
+    left: &KeylessTreeMap<K, V, PREFIX_LEN>,
+    right: &KeylessTreeMap<K, V, PREFIX_LEN>,
+) -> bool
+where
+    V: PartialEq,
+{
+    let mut left = RawIter::new(left.root);
+    let mut right = RawIter::new(right.root);
+
+    loop {
+        // SAFETY: Since we have shared references to both maps, no mutating operations
+        // can occur on the trees.
+        match unsafe { (left.next(), right.next()) } {
+            (Some(left_leaf), Some(right_leaf)) => {
+                // SAFETY: Same as above
+                let (left_value, right_value) =
+                    unsafe { (left_leaf.value_ref(), right_leaf.value_ref()) };
+                if left.key() != right.key() || left_value != right_value {
+                    return false;
+                }
+            },
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// An iterator over all the entries in a [`KeylessTreeMap`], which rebuilds
+/// each key from the key bytes of the path leading to it.
+pub struct Iter<'a, K, V, const PREFIX_LEN: usize> {
+    raw: RawIter<V, PREFIX_LEN>,
+    remaining: usize,
+    marker: PhantomData<(fn() -> K, &'a V)>,
+}
+
+impl<'a, K, V, const PREFIX_LEN: usize> Iter<'a, K, V, PREFIX_LEN> {
+    pub(super) fn new(map: &'a KeylessTreeMap<K, V, PREFIX_LEN>) -> Self {
+        Iter {
+            raw: RawIter::new(map.root),
+            remaining: map.num_entries,
+            marker: PhantomData,
+        }
+    }
+
+    fn next_value(&mut self) -> Option<&'a V> {
+        // SAFETY: The iterator holds a shared reference to the map, so no mutating
+        // operations can occur on the tree.
+        let leaf_ptr = unsafe { self.raw.next()? };
+        self.remaining -= 1;
+        // SAFETY: Same as above
+        Some(unsafe { leaf_ptr.value_ref() })
+    }
+}
+
+impl<'a, K: FromBytes, V, const PREFIX_LEN: usize> Iterator for Iter<'a, K, V, PREFIX_LEN> {
+    type Item = (K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.next_value()?;
+        Some((K::from_bytes(self.raw.key()), value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: FromBytes, V, const PREFIX_LEN: usize> ExactSizeIterator for Iter<'_, K, V, PREFIX_LEN> {}
+
+impl<K: FromBytes, V, const PREFIX_LEN: usize> FusedIterator for Iter<'_, K, V, PREFIX_LEN> {}
+
+/// A mutable iterator over all the entries in a [`KeylessTreeMap`], which
+/// rebuilds each key from the key bytes of the path leading to it.
+pub struct IterMut<'a, K, V, const PREFIX_LEN: usize> {
+    raw: RawIter<V, PREFIX_LEN>,
+    remaining: usize,
+    marker: PhantomData<(fn() -> K, &'a mut V)>,
+}
+
+impl<'a, K, V, const PREFIX_LEN: usize> IterMut<'a, K, V, PREFIX_LEN> {
+    pub(super) fn new(map: &'a mut KeylessTreeMap<K, V, PREFIX_LEN>) -> Self {
+        IterMut {
+            raw: RawIter::new(map.root),
+            remaining: map.num_entries,
+            marker: PhantomData,
+        }
+    }
+
+    fn next_value(&mut self) -> Option<&'a mut V> {
+        // SAFETY: The iterator holds a mutable reference to the map, and only the
+        // values of the returned leaves are mutated.
+        let leaf_ptr = unsafe { self.raw.next()? };
+        self.remaining -= 1;
+        // SAFETY: Every leaf is only returned once, so there are no other references
+        // to the value
+        Some(unsafe { leaf_ptr.value_mut() })
+    }
+}
+
+impl<'a, K: FromBytes, V, const PREFIX_LEN: usize> Iterator for IterMut<'a, K, V, PREFIX_LEN> {
+    type Item = (K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.next_value()?;
+        Some((K::from_bytes(self.raw.key()), value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K: FromBytes, V, const PREFIX_LEN: usize> ExactSizeIterator for IterMut<'_, K, V, PREFIX_LEN> {}
+
+impl<K: FromBytes, V, const PREFIX_LEN: usize> FusedIterator for IterMut<'_, K, V, PREFIX_LEN> {}
+
+/// An iterator over all the keys in a [`KeylessTreeMap`].
+pub struct Keys<'a, K, V, const PREFIX_LEN: usize>(pub(super) Iter<'a, K, V, PREFIX_LEN>);
+
+impl<K: FromBytes, V, const PREFIX_LEN: usize> Iterator for Keys<'_, K, V, PREFIX_LEN> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(key, _)| key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K: FromBytes, V, const PREFIX_LEN: usize> ExactSizeIterator for Keys<'_, K, V, PREFIX_LEN> {}
+
+impl<K: FromBytes, V, const PREFIX_LEN: usize> FusedIterator for Keys<'_, K, V, PREFIX_LEN> {}
+
+/// An iterator over all the values in a [`KeylessTreeMap`].
+///
+/// The keys are not rebuilt, so this does not need [`FromBytes`].
+pub struct Values<'a, K, V, const PREFIX_LEN: usize>(pub(super) Iter<'a, K, V, PREFIX_LEN>);
+
+impl<'a, K, V, const PREFIX_LEN: usize> Iterator for Values<'a, K, V, PREFIX_LEN> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_value()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.remaining, Some(self.0.remaining))
+    }
+}
+
+impl<K, V, const PREFIX_LEN: usize> ExactSizeIterator for Values<'_, K, V, PREFIX_LEN> {}
+
+impl<K, V, const PREFIX_LEN: usize> FusedIterator for Values<'_, K, V, PREFIX_LEN> {}
+
+/// A mutable iterator over all the values in a [`KeylessTreeMap`].
+///
+/// The keys are not rebuilt, so this does not need [`FromBytes`].
+pub struct ValuesMut<'a, K, V, const PREFIX_LEN: usize>(pub(super) IterMut<'a, K, V, PREFIX_LEN>);
+
+impl<'a, K, V, const PREFIX_LEN: usize> Iterator for ValuesMut<'a, K, V, PREFIX_LEN> {
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_value()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.remaining, Some(self.0.remaining))
+    }
+}
+
+impl<K, V, const PREFIX_LEN: usize> ExactSizeIterator for ValuesMut<'_, K, V, PREFIX_LEN> {}
+
+impl<K, V, const PREFIX_LEN: usize> FusedIterator for ValuesMut<'_, K, V, PREFIX_LEN> {}
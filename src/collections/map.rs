@@ -1,21 +1,34 @@
 //! Module containing implementations of the `TreeMap` and associated
 //! iterators/etc.
 
+#[cfg(feature = "metrics")]
+use crate::Metrics;
 use crate::{
-    deallocate_tree, find_maximum_to_delete, find_minimum_to_delete, maximum_unchecked,
-    minimum_unchecked, rust_nightly_apis::hasher_write_length_prefix, search_for_delete_point,
-    search_for_insert_point, search_unchecked, AsBytes, DeletePoint, DeleteResult, InsertPoint,
-    InsertPrefixError, InsertResult, InsertSearchResultType::Exact, LeafNode, NoPrefixesBytes,
-    NodePtr, OpaqueNodePtr,
+    compact_tree, count_leaves, deallocate_tree, find_maximum_to_delete, find_minimum_to_delete,
+    maximum_unchecked, merge_trees,
+    metrics::MapMetrics,
+    minimum_unchecked,
+    rust_nightly_apis::hasher_write_length_prefix,
+    search_for_delete_point, search_for_insert_point, search_for_prefix_delete_point,
+    search_nearest_unchecked, search_unchecked, set_prefix_strategy,
+    visitor::{LookupIndexStats, MemoryUsage, ResizeStats, TreeStatsCollector},
+    AsBytes, DeletePoint, DeleteResult, HeapSize, InsertPoint, InsertPrefixError, InsertResult,
+    InsertSearchResultType::Exact,
+    LeafNode, Nearest, NoPrefixesBytes, NodePtr, OpaqueNodePtr, PrefixStrategy, ShrinkPolicy,
 };
-use std::{borrow::Borrow, fmt::Debug, hash::Hash, ops::Index};
+use alloc::vec::Vec;
+use core::{borrow::Borrow, fmt::Debug, hash::Hash, mem, ops::Index};
 
 mod entry;
 mod entry_ref;
 mod iterators;
+#[cfg(feature = "std")]
+mod lookup_index;
 pub use entry::*;
 pub use entry_ref::*;
 pub use iterators::*;
+#[cfg(feature = "std")]
+use lookup_index::{LookupIndex, MAX_LOOKUP_INDEX_DEPTH};
 
 /// An ordered map based on an adaptive radix tree.
 pub struct TreeMap<K, V, const PREFIX_LEN: usize = 16> {
@@ -23,6 +36,18 @@ pub struct TreeMap<K, V, const PREFIX_LEN: usize = 16> {
     num_entries: usize,
     /// A pointer to the tree root, if present.
     pub(crate) root: Option<OpaqueNodePtr<K, V, PREFIX_LEN>>,
+    /// How new inner nodes store prefixes longer than `PREFIX_LEN`.
+    prefix_strategy: PrefixStrategy,
+    /// When inner nodes are shrunk after a remove.
+    shrink_policy: ShrinkPolicy,
+    /// The number of times inner nodes were grown or shrunk.
+    resize_stats: ResizeStats,
+    /// Counters for the work done by the operations on the map, which are
+    /// zero-sized without the `metrics` feature.
+    metrics: MapMetrics,
+    /// An index from key prefixes to subtrees, if enabled.
+    #[cfg(feature = "std")]
+    lookup_index: Option<LookupIndex<K, V, PREFIX_LEN>>,
 }
 
 impl<K, V> TreeMap<K, V> {
@@ -60,10 +85,297 @@ impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
     /// assert!(map.is_empty());
     /// ```
     pub fn with_prefix_len() -> Self {
+        Self::with_prefix_strategy(PrefixStrategy::default())
+    }
+
+    /// Create a new, empty [`crate::TreeMap`] which stores inner node prefixes
+    /// longer than `PREFIX_LEN` using the given [`PrefixStrategy`].
+    ///
+    /// This function will not pre-allocate anything.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::{PrefixStrategy, TreeMap};
+    ///
+    /// let mut map =
+    ///     TreeMap::<Box<[u8]>, u32, 8>::with_prefix_strategy(PrefixStrategy::Pessimistic);
+    /// map.try_insert(b"https://example.com/a/long/path/1".as_slice().into(), 1).unwrap();
+    /// map.try_insert(b"https://example.com/a/long/path/2".as_slice().into(), 2).unwrap();
+    ///
+    /// assert_eq!(map.prefix_strategy(), PrefixStrategy::Pessimistic);
+    /// assert_eq!(map.get(b"https://example.com/a/long/path/2".as_slice()), Some(&2));
+    /// ```
+    ///
+    /// # Panics
+    ///  - If the strategy is not supported with `PREFIX_LEN` bytes of inline
+    ///    prefix, see [`PrefixStrategy::is_supported`].
+    ///
+    /// ```rust,should_panic
+    /// use blart::{PrefixStrategy, TreeMap};
+    ///
+    /// // There is no room for the pointer to a spilled prefix in 4 bytes
+    /// TreeMap::<Box<[u8]>, u32, 4>::with_prefix_strategy(PrefixStrategy::Pessimistic);
+    /// ```
+    pub fn with_prefix_strategy(prefix_strategy: PrefixStrategy) -> Self {
+        assert!(
+            prefix_strategy.is_supported(PREFIX_LEN),
+            "{prefix_strategy:?} prefix strategy is not supported with PREFIX_LEN = {PREFIX_LEN}"
+        );
+
         TreeMap {
             num_entries: 0,
             root: None,
+            prefix_strategy,
+            shrink_policy: ShrinkPolicy::default(),
+            resize_stats: ResizeStats::default(),
+            metrics: MapMetrics::new(),
+            #[cfg(feature = "std")]
+            lookup_index: None,
+        }
+    }
+
+    /// Return the [`PrefixStrategy`] used by this map.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::{PrefixStrategy, TreeMap};
+    ///
+    /// let map = TreeMap::<Box<[u8]>, ()>::new();
+    /// assert_eq!(map.prefix_strategy(), PrefixStrategy::Optimistic);
+    /// ```
+    pub fn prefix_strategy(&self) -> PrefixStrategy {
+        self.prefix_strategy
+    }
+
+    /// Return the [`ShrinkPolicy`] used by this map.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::{ShrinkPolicy, TreeMap};
+    ///
+    /// let map = TreeMap::<Box<[u8]>, ()>::new();
+    /// assert_eq!(map.shrink_policy(), ShrinkPolicy::Eager);
+    /// ```
+    pub fn shrink_policy(&self) -> ShrinkPolicy {
+        self.shrink_policy
+    }
+
+    /// Change when inner nodes are shrunk after a remove, see
+    /// [`ShrinkPolicy`].
+    ///
+    /// If the new policy shrinks nodes earlier than the current one, the map
+    /// is compacted with [`TreeMap::shrink_to_fit`] so that no node is larger
+    /// than the new policy allows.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::{visitor::TreeStatsCollector, ShrinkPolicy, TreeMap};
+    ///
+    /// let mut map = TreeMap::<u8, u8>::new();
+    /// map.set_shrink_policy(ShrinkPolicy::Hysteresis);
+    ///
+    /// // Oscillate around the capacity of an `InnerNode4`
+    /// for key in 0..4 {
+    ///     map.insert(key, key);
+    /// }
+    /// for _ in 0..10 {
+    ///     map.insert(4, 4);
+    ///     map.remove(&4);
+    /// }
+    ///
+    /// let stats = TreeStatsCollector::collect(&map).unwrap();
+    /// assert_eq!(stats.resizes.grow_count, 1);
+    /// assert_eq!(stats.resizes.shrink_count, 0);
+    /// ```
+    pub fn set_shrink_policy(&mut self, shrink_policy: ShrinkPolicy)
+    where
+        K: AsBytes,
+    {
+        let old_policy = core::mem::replace(&mut self.shrink_policy, shrink_policy);
+        if !shrink_policy.accepts(old_policy) {
+            self.shrink_to_fit();
+        }
+    }
+
+    /// Return the number of times inner nodes of this map were grown or
+    /// shrunk.
+    pub(crate) fn resize_stats(&self) -> ResizeStats {
+        self.resize_stats
+    }
+
+    /// Return a snapshot of the counters for the work done by the operations
+    /// on this map, see [`Metrics`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut map = TreeMap::<u8, char>::new();
+    /// for (key, value) in (0..5).zip('a'..) {
+    ///     map.insert(key, value);
+    /// }
+    /// assert_eq!(map.get(&3), Some(&'d'));
+    ///
+    /// let metrics = map.metrics();
+    /// assert_eq!(metrics.node_allocations.leaf, 5);
+    /// assert_eq!(metrics.grow_count, 1);
+    /// assert_eq!(metrics.lookups, 1);
+    /// assert_eq!(metrics.avg_lookup_depth(), 2.0);
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> Metrics {
+        self.metrics
+            .snapshot(self.resize_stats.grow_count, self.resize_stats.shrink_count)
+    }
+
+    /// Enable an index from the first `depth` bytes of the keys to the
+    /// subtree containing all the keys starting with those bytes, so that
+    /// lookups can skip the top levels of the tree.
+    ///
+    /// The index is updated by every insert and remove, which makes them
+    /// slower, and uses extra memory which is reported by
+    /// [`TreeStatsCollector`]. It is most useful for large maps where the
+    /// first `depth` bytes of the keys are spread over several levels of inner
+    /// nodes. Keys shorter than `depth` bytes are looked up without the index.
+    ///
+    /// If the index is already enabled, it is rebuilt with the new `depth`.
+    ///
+    /// # Panics
+    ///  - If `depth` is 0 or more than 4.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut map = TreeMap::<u32, u32>::new();
+    /// for key in 0..1000 {
+    ///     map.insert(key * 7919, key);
+    /// }
+    ///
+    /// map.enable_lookup_index(3);
+    /// assert_eq!(map.lookup_index_depth(), Some(3));
+    ///
+    /// map.insert(1, 1);
+    /// map.remove(&7919);
+    /// assert_eq!(map.get(&(2 * 7919)), Some(&2));
+    /// assert_eq!(map.get(&7919), None);
+    /// assert_eq!(map.get(&1), Some(&1));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn enable_lookup_index(&mut self, depth: usize)
+    where
+        K: AsBytes,
+    {
+        assert!(
+            (1..=MAX_LOOKUP_INDEX_DEPTH).contains(&depth),
+            "lookup index depth must be between 1 and {MAX_LOOKUP_INDEX_DEPTH}, got {depth}"
+        );
+
+        // SAFETY: Since we have a mutable reference to the `TreeMap`, there are no
+        // other references to any node in the tree.
+        self.lookup_index = Some(unsafe { LookupIndex::build(self.root, depth) });
+    }
+
+    /// Disable the lookup index, freeing its memory.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut map = TreeMap::<u32, u32>::new();
+    /// map.enable_lookup_index(2);
+    /// map.disable_lookup_index();
+    /// assert_eq!(map.lookup_index_depth(), None);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn disable_lookup_index(&mut self) {
+        self.lookup_index = None;
+    }
+
+    /// Return the number of key bytes used by the lookup index, if it is
+    /// enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut map = TreeMap::<u32, u32>::new();
+    /// assert_eq!(map.lookup_index_depth(), None);
+    /// map.enable_lookup_index(2);
+    /// assert_eq!(map.lookup_index_depth(), Some(2));
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn lookup_index_depth(&self) -> Option<usize> {
+        self.lookup_index.as_ref().map(LookupIndex::depth)
+    }
+
+    /// Return the stats of the lookup index, which are all zero if it is not
+    /// enabled.
+    pub(crate) fn lookup_index_stats(&self) -> LookupIndexStats {
+        #[cfg(feature = "std")]
+        if let Some(index) = &self.lookup_index {
+            return index.stats();
+        }
+
+        LookupIndexStats::default()
+    }
+
+    /// Rebuild the lookup index, if enabled, after the tree was replaced as a
+    /// whole.
+    #[cfg(feature = "std")]
+    fn rebuild_lookup_index(&mut self)
+    where
+        K: AsBytes,
+    {
+        if let Some(index) = &self.lookup_index {
+            // SAFETY: Since we have a mutable reference to the `TreeMap`, there are no
+            // other references to any node in the tree.
+            self.lookup_index = Some(unsafe { LookupIndex::build(self.root, index.depth()) });
+        }
+    }
+
+    /// Update the lookup index, if enabled, after `key` was inserted into or
+    /// removed from the tree.
+    #[cfg(feature = "std")]
+    fn refresh_lookup_index(&mut self, key: &K) {
+        if let Some(index) = &mut self.lookup_index {
+            // SAFETY: Since we have a mutable reference to the `TreeMap`, there are no
+            // other references to any node in the tree.
+            unsafe { index.refresh(self.root, key) };
+        }
+    }
+
+    /// Search for the leaf with the given key, using the lookup index if it is
+    /// enabled.
+    ///
+    /// # Safety
+    ///  - This function cannot be called concurrently with any mutating
+    ///    operation on the tree.
+    unsafe fn search_leaf(&self, key_bytes: &[u8]) -> Option<NodePtr<PREFIX_LEN, LeafNode<K, V>>>
+    where
+        K: AsBytes,
+    {
+        let root = self.root?;
+        let _metrics = self.metrics.lookup_scope(1);
+
+        #[cfg(feature = "std")]
+        if let Some(index) = &self.lookup_index {
+            // SAFETY: Covered by the safety requirement on the containing function
+            if let Some(search_result) = unsafe { index.search(key_bytes) } {
+                return search_result;
+            }
         }
+
+        // SAFETY: Covered by the safety requirement on the containing function
+        unsafe { search_unchecked(root, key_bytes) }
     }
 
     /// Clear the map, removing all elements.
@@ -93,9 +405,129 @@ impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
 
             self.num_entries = 0;
             self.root = None;
+
+            #[cfg(feature = "std")]
+            if let Some(index) = &mut self.lookup_index {
+                index.clear();
+            }
         }
     }
 
+    /// Reallocate all nodes of the map in depth-first order, rebuilding each
+    /// inner node as the smallest node type that can hold its children, and
+    /// return the number of bytes reclaimed.
+    ///
+    /// After a long series of inserts and removes the nodes of the map are
+    /// scattered over the heap, and inner nodes may be larger than their number
+    /// of children requires, depending on the [`ShrinkPolicy`]. This packs the
+    /// nodes together in the order they are visited by iteration, which
+    /// improves locality for iteration and lookups.
+    ///
+    /// The reclaimed bytes are the difference between the
+    /// [`total_memory_usage`](crate::visitor::TreeStats::total_memory_usage)
+    /// reported by [`TreeStatsCollector`] before and after the compaction. Use
+    /// [`TreeMap::shrink_to_fit`] to skip collecting the stats.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::{ShrinkPolicy, TreeMap};
+    ///
+    /// let mut map = TreeMap::<u32, u32>::new();
+    /// map.set_shrink_policy(ShrinkPolicy::Lazy);
+    /// for key in 0..10_000 {
+    ///     map.insert(key, key);
+    /// }
+    /// for key in 0..10_000 {
+    ///     if key % 100 != 0 {
+    ///         map.remove(&key);
+    ///     }
+    /// }
+    ///
+    /// let reclaimed = map.compact();
+    /// assert!(reclaimed > 0);
+    /// assert_eq!(map.len(), 100);
+    /// assert_eq!(map.get(&500), Some(&500));
+    /// ```
+    pub fn compact(&mut self) -> usize
+    where
+        K: AsBytes,
+    {
+        let before =
+            TreeStatsCollector::collect(self).map_or(0, |stats| stats.total_memory_usage());
+        self.shrink_to_fit();
+        let after = TreeStatsCollector::collect(self).map_or(0, |stats| stats.total_memory_usage());
+
+        before.saturating_sub(after)
+    }
+
+    /// Shrink the memory used by the map as much as possible, without
+    /// reporting the number of bytes reclaimed.
+    ///
+    /// See [`TreeMap::compact`] for more details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut map = TreeMap::<u32, char>::new();
+    /// map.insert(1, 'a');
+    /// map.insert(2, 'b');
+    /// map.remove(&1);
+    ///
+    /// map.shrink_to_fit();
+    /// assert_eq!(map.len(), 1);
+    /// assert_eq!(map.get(&2), Some(&'b'));
+    /// ```
+    pub fn shrink_to_fit(&mut self)
+    where
+        K: AsBytes,
+    {
+        if let Some(root) = self.root {
+            let _metrics = self.metrics.scope();
+            // SAFETY: Since we have a mutable reference to the map, there are no other
+            // references to any node in the tree, and the old nodes are not used after
+            // the root is replaced.
+            self.root = Some(unsafe { compact_tree(root) });
+
+            #[cfg(feature = "std")]
+            self.rebuild_lookup_index();
+        }
+    }
+
+    /// Return the memory used by the map, including the heap memory owned by
+    /// the keys and values.
+    ///
+    /// The result breaks down the size of the nodes, the heap memory reported
+    /// by [`HeapSize`] for the keys and values, and an estimate of the memory
+    /// lost by the allocator rounding up the size of each allocation. See
+    /// [`MemoryUsage`] for the details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut map = TreeMap::<Box<[u8]>, String>::new();
+    /// map.try_insert(Box::from(&b"abc"[..]), String::from("hello"))
+    ///     .unwrap();
+    /// map.try_insert(Box::from(&b"abd"[..]), String::from("world!"))
+    ///     .unwrap();
+    ///
+    /// let usage = map.memory_usage();
+    /// assert_eq!(usage.key_heap, 6);
+    /// assert_eq!(usage.value_heap, 11);
+    /// assert!(usage.total() > usage.node_overhead() + 17);
+    /// ```
+    pub fn memory_usage(&self) -> MemoryUsage
+    where
+        K: AsBytes + HeapSize,
+        V: HeapSize,
+    {
+        MemoryUsage::collect(self)
+    }
+
     /// Returns a reference to the value corresponding to the key.
     ///
     /// # Examples
@@ -133,22 +565,18 @@ impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
         K: Borrow<Q> + AsBytes,
         Q: AsBytes + ?Sized,
     {
-        if let Some(root) = self.root {
-            // SAFETY: Since we have an immutable reference to the `TreeMap` object, that
-            // means there can only exist other immutable references aside from this one,
-            // and no mutable references. That means that no mutating operations can occur
-            // on the root node or any child of the root node.
-            let search_result = unsafe { search_unchecked(root, key.as_bytes())? };
+        // SAFETY: Since we have an immutable reference to the `TreeMap` object, that
+        // means there can only exist other immutable references aside from this one,
+        // and no mutable references. That means that no mutating operations can occur
+        // on the root node or any child of the root node.
+        let search_result = unsafe { self.search_leaf(key.as_bytes())? };
 
-            // SAFETY: The lifetime chosen the value reference is bounded by the lifetime of
-            // the immutable reference to the `TreeMap`. The memory of the value will not be
-            // mutated since it is only owned by the `TreeMap` and there can only be other
-            // immutable references at this time (no mutable references to the `TreeMap`).
-            let (key, value) = unsafe { search_result.as_key_value_ref() };
-            Some((key, value))
-        } else {
-            None
-        }
+        // SAFETY: The lifetime chosen the value reference is bounded by the lifetime of
+        // the immutable reference to the `TreeMap`. The memory of the value will not be
+        // mutated since it is only owned by the `TreeMap` and there can only be other
+        // immutable references at this time (no mutable references to the `TreeMap`).
+        let (key, value) = unsafe { search_result.as_key_value_ref() };
+        Some((key, value))
     }
 
     /// Returns a mutable reference to the value corresponding to the key.
@@ -191,23 +619,178 @@ impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
         K: Borrow<Q> + AsBytes,
         Q: AsBytes + ?Sized,
     {
-        if let Some(root) = self.root {
-            // SAFETY: Since we have a mutable reference to the `TreeMap` object, that
-            // means there cannot exist any other reference (mutable or immutable) to the
-            // same `TreeMap`. Which means that no other mutating operations could be
-            // happening during the `search_unchecked` call.
-            let search_result = unsafe { search_unchecked(root, key.as_bytes())? };
+        // SAFETY: Since we have a mutable reference to the `TreeMap` object, that
+        // means there cannot exist any other reference (mutable or immutable) to the
+        // same `TreeMap`. Which means that no other mutating operations could be
+        // happening during the `search_leaf` call.
+        let search_result = unsafe { self.search_leaf(key.as_bytes())? };
 
-            // SAFETY: The lifetime chosen the value reference is bounded by the lifetime of
-            // the mutable reference to the `TreeMap`. The value pointed to by the returned
-            // mutable reference will not be accessed (read or written) through any other
-            // pointer because of the existing mutable reference on the `TreeMap`.
-            let leaf_node_ref = unsafe { search_result.as_mut() };
+        // SAFETY: The lifetime chosen the value reference is bounded by the lifetime of
+        // the mutable reference to the `TreeMap`. The value pointed to by the returned
+        // mutable reference will not be accessed (read or written) through any other
+        // pointer because of the existing mutable reference on the `TreeMap`.
+        let leaf_node_ref = unsafe { search_result.as_mut() };
 
-            Some(leaf_node_ref.value_mut())
-        } else {
-            None
+        Some(leaf_node_ref.value_mut())
+    }
+
+    /// Returns mutable references to the values corresponding to each of the
+    /// `N` keys, or `None` if any of the keys is missing or the same key is
+    /// given more than once.
+    ///
+    /// Distinct keys are always stored in distinct leaves, so the returned
+    /// references never alias.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut accounts: TreeMap<u32, u64> = TreeMap::from([(1, 100), (2, 50), (3, 10)]);
+    ///
+    /// let [from, to] = accounts.get_many_mut([&1, &3]).unwrap();
+    /// *from -= 30;
+    /// *to += 30;
+    /// assert_eq!(accounts.get(&1), Some(&70));
+    /// assert_eq!(accounts.get(&3), Some(&40));
+    ///
+    /// // A missing or repeated key returns `None`
+    /// assert!(accounts.get_many_mut([&1, &4]).is_none());
+    /// assert!(accounts.get_many_mut([&2, &2]).is_none());
+    /// ```
+    pub fn get_many_mut<Q, const N: usize>(&mut self, keys: [&Q; N]) -> Option<[&mut V; N]>
+    where
+        K: Borrow<Q> + AsBytes,
+        Q: AsBytes + ?Sized,
+    {
+        let leaves = self.search_leaves(keys)?;
+
+        for (idx, leaf) in leaves.iter().enumerate() {
+            if leaves[..idx].contains(leaf) {
+                return None;
+            }
         }
+
+        // SAFETY: The leaves are all distinct, so the mutable references to their
+        // values do not alias. Their lifetime is bounded by the mutable reference
+        // to the `TreeMap`, which prevents any other access to the values.
+        Some(leaves.map(|leaf| unsafe { leaf.as_value_mut() }))
+    }
+
+    /// Returns mutable references to the values corresponding to each of the
+    /// `N` keys, or `None` if any of the keys is missing, without checking
+    /// that the keys are distinct.
+    ///
+    /// For a safe alternative see [`get_many_mut`](TreeMap::get_many_mut).
+    ///
+    /// # Safety
+    ///  - The keys must all be distinct. Calling this method with the same key
+    ///    more than once is undefined behavior, even if the resulting
+    ///    references are not used.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut map: TreeMap<u32, char> = TreeMap::from([(1, 'a'), (2, 'b')]);
+    ///
+    /// // SAFETY: The keys are distinct
+    /// let [a, b] = unsafe { map.get_many_unchecked_mut([&1, &2]) }.unwrap();
+    /// core::mem::swap(a, b);
+    /// assert_eq!(map.get(&1), Some(&'b'));
+    /// ```
+    pub unsafe fn get_many_unchecked_mut<Q, const N: usize>(
+        &mut self,
+        keys: [&Q; N],
+    ) -> Option<[&mut V; N]>
+    where
+        K: Borrow<Q> + AsBytes,
+        Q: AsBytes + ?Sized,
+    {
+        let leaves = self.search_leaves(keys)?;
+
+        // SAFETY: The caller guarantees that the keys are distinct, so the leaves
+        // are distinct as well and the mutable references do not alias. Their
+        // lifetime is bounded by the mutable reference to the `TreeMap`, which
+        // prevents any other access to the values.
+        Some(leaves.map(|leaf| unsafe { leaf.as_value_mut() }))
+    }
+
+    /// Search for the leaves of all the given keys, returning `None` if any of
+    /// them is missing.
+    fn search_leaves<Q, const N: usize>(
+        &mut self,
+        keys: [&Q; N],
+    ) -> Option<[NodePtr<PREFIX_LEN, LeafNode<K, V>>; N]>
+    where
+        K: Borrow<Q> + AsBytes,
+        Q: AsBytes + ?Sized,
+    {
+        let mut leaves = [None; N];
+        for (leaf, key) in leaves.iter_mut().zip(keys) {
+            // SAFETY: Since we have a mutable reference to the `TreeMap` object, there
+            // cannot be any mutating operation happening during the search.
+            *leaf = Some(unsafe { self.search_leaf(key.as_bytes())? });
+        }
+
+        // PANIC SAFETY: Every leaf was found by the loop above
+        Some(leaves.map(Option::unwrap))
+    }
+
+    /// Returns references to the values corresponding to each of the keys, in
+    /// the same order as the keys.
+    ///
+    /// This is faster than calling [`get`](TreeMap::get) for each key on
+    /// large trees, since the searches for several keys are interleaved so
+    /// that their cache misses overlap. See
+    /// [`get_many_iter`](TreeMap::get_many_iter) for a lazy version.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let map: TreeMap<u32, char> = TreeMap::from([(1, 'a'), (2, 'b'), (3, 'c')]);
+    ///
+    /// assert_eq!(map.get_many(&[&3, &4, &1]), [Some(&'c'), None, Some(&'a')]);
+    /// ```
+    pub fn get_many<Q>(&self, keys: &[&Q]) -> Vec<Option<&V>>
+    where
+        K: Borrow<Q> + AsBytes,
+        Q: AsBytes + ?Sized,
+    {
+        self.get_many_iter(keys.iter().copied()).collect()
+    }
+
+    /// Returns an iterator over references to the values corresponding to
+    /// each of the keys, in the same order as the keys.
+    ///
+    /// The keys are read from `keys` in batches, and the searches of each
+    /// batch are interleaved so that their cache misses overlap. The next node
+    /// of each search is prefetched before it is visited.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let map: TreeMap<u32, u32> = (0..100).map(|key| (key, key * 2)).collect();
+    /// let keys: Vec<u32> = (90..110).collect();
+    ///
+    /// let found: u32 = map.get_many_iter(&keys).flatten().sum();
+    /// assert_eq!(found, (90..100).map(|key| key * 2).sum());
+    /// ```
+    pub fn get_many_iter<'a, 'k, Q, I>(
+        &'a self,
+        keys: I,
+    ) -> GetMany<'a, 'k, K, V, Q, I::IntoIter, PREFIX_LEN>
+    where
+        K: Borrow<Q> + AsBytes,
+        Q: AsBytes + ?Sized + 'k,
+        I: IntoIterator<Item = &'k Q>,
+    {
+        GetMany::new(self, keys.into_iter())
     }
 
     /// Makes a fuzzy search in the tree by `key`,
@@ -527,10 +1110,144 @@ impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
         }
     }
 
+    /// Returns the entry with the greatest key strictly less than the given
+    /// key.
+    ///
+    /// The search key does not need to be present in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut map = TreeMap::<Box<[u8]>, char>::new();
+    ///
+    /// map.try_insert(Box::new([1, 2, 3]), 'a').unwrap();
+    /// map.try_insert(Box::new([2, 3, 4]), 'b').unwrap();
+    ///
+    /// assert_eq!(map.predecessor([2, 3, 4].as_ref()), Some((&Box::from([1, 2, 3]), &'a')));
+    /// assert_eq!(map.predecessor([1, 2, 3].as_ref()), None);
+    /// assert_eq!(map.predecessor([9].as_ref()), Some((&Box::from([2, 3, 4]), &'b')));
+    /// ```
+    pub fn predecessor<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q> + AsBytes,
+        Q: AsBytes + ?Sized,
+    {
+        self.search_nearest(key.as_bytes(), Nearest::Predecessor)
+    }
+
+    /// Returns the entry with the greatest key less than or equal to the given
+    /// key.
+    ///
+    /// The search key does not need to be present in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut map = TreeMap::<Box<[u8]>, char>::new();
+    ///
+    /// map.try_insert(Box::new([1, 2, 3]), 'a').unwrap();
+    /// map.try_insert(Box::new([2, 3, 4]), 'b').unwrap();
+    ///
+    /// assert_eq!(map.floor([2, 3, 4].as_ref()), Some((&Box::from([2, 3, 4]), &'b')));
+    /// assert_eq!(map.floor([2].as_ref()), Some((&Box::from([1, 2, 3]), &'a')));
+    /// assert_eq!(map.floor([0].as_ref()), None);
+    /// ```
+    pub fn floor<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q> + AsBytes,
+        Q: AsBytes + ?Sized,
+    {
+        self.search_nearest(key.as_bytes(), Nearest::Floor)
+    }
+
+    /// Returns the entry with the least key greater than or equal to the given
+    /// key.
+    ///
+    /// The search key does not need to be present in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut map = TreeMap::<Box<[u8]>, char>::new();
+    ///
+    /// map.try_insert(Box::new([1, 2, 3]), 'a').unwrap();
+    /// map.try_insert(Box::new([2, 3, 4]), 'b').unwrap();
+    ///
+    /// assert_eq!(map.ceiling([1, 2, 3].as_ref()), Some((&Box::from([1, 2, 3]), &'a')));
+    /// assert_eq!(map.ceiling([1, 2, 3, 4].as_ref()), Some((&Box::from([2, 3, 4]), &'b')));
+    /// assert_eq!(map.ceiling([3].as_ref()), None);
+    /// ```
+    pub fn ceiling<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q> + AsBytes,
+        Q: AsBytes + ?Sized,
+    {
+        self.search_nearest(key.as_bytes(), Nearest::Ceiling)
+    }
+
+    /// Returns the entry with the least key strictly greater than the given
+    /// key.
+    ///
+    /// The search key does not need to be present in the map.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut map = TreeMap::<Box<[u8]>, char>::new();
+    ///
+    /// map.try_insert(Box::new([1, 2, 3]), 'a').unwrap();
+    /// map.try_insert(Box::new([2, 3, 4]), 'b').unwrap();
+    ///
+    /// assert_eq!(map.successor([1, 2, 3].as_ref()), Some((&Box::from([2, 3, 4]), &'b')));
+    /// assert_eq!(map.successor([0].as_ref()), Some((&Box::from([1, 2, 3]), &'a')));
+    /// assert_eq!(map.successor([2, 3, 4].as_ref()), None);
+    /// ```
+    pub fn successor<Q>(&self, key: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q> + AsBytes,
+        Q: AsBytes + ?Sized,
+    {
+        self.search_nearest(key.as_bytes(), Nearest::Successor)
+    }
+
+    fn search_nearest(&self, key_bytes: &[u8], nearest: Nearest) -> Option<(&K, &V)>
+    where
+        K: AsBytes,
+    {
+        let root = self.root?;
+        let _metrics = self.metrics.scope();
+        // SAFETY: Since we have an immutable reference to the `TreeMap` object, that
+        // means there can only exist other immutable references aside from this one,
+        // and no mutable references. That means that no mutating operations can occur
+        // on the root node or any child of the root node.
+        let leaf = unsafe { search_nearest_unchecked(root, key_bytes, nearest)? };
+
+        // SAFETY: The lifetime chosen the value reference is bounded by the lifetime of
+        // the immutable reference to the `TreeMap`. The memory of the value will not be
+        // mutated since it is only owned by the `TreeMap` and there can only be other
+        // immutable references at this time (no mutable references to the `TreeMap`).
+        let (key, value) = unsafe { leaf.as_key_value_ref() };
+        Some((key, value))
+    }
+
     fn init_tree(&mut self, key: K, value: V) -> NodePtr<PREFIX_LEN, LeafNode<K, V>> {
+        let _metrics = self.metrics.scope();
         let leaf = NodePtr::allocate_node_ptr(LeafNode::new(key, value));
         self.root = Some(leaf.to_opaque());
         self.num_entries = 1;
+
+        #[cfg(feature = "std")]
+        // SAFETY: The leaf was just allocated, and there are no other references to it
+        self.refresh_lookup_index(unsafe { leaf.as_ref() }.key_ref());
+
         leaf
     }
 
@@ -539,13 +1256,22 @@ impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
         insert_point: InsertPoint<K, V, PREFIX_LEN>,
         key: K,
         value: V,
-    ) -> InsertResult<K, V, PREFIX_LEN>
+    ) -> InsertResult<'_, K, V, PREFIX_LEN>
     where
         K: AsBytes,
     {
-        let insert_result = insert_point.apply(key, value);
+        let _metrics = self.metrics.scope();
+        let insert_result = insert_point.apply(key, value, self.prefix_strategy);
 
         self.root = Some(insert_result.new_root);
+        if insert_result.inner_node_grown {
+            self.resize_stats.grow_count += 1;
+        }
+
+        #[cfg(feature = "std")]
+        // SAFETY: Since we have a mutable reference to the `TreeMap`, there are no
+        // other references to the new leaf.
+        self.refresh_lookup_index(unsafe { insert_result.leaf_node_ptr.as_ref() }.key_ref());
 
         if insert_result.existing_leaf.is_none() {
             // this was a strict add, not a replace. If there was an existing leaf we are
@@ -560,14 +1286,22 @@ impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
         &mut self,
         delete_point: DeletePoint<K, V, PREFIX_LEN>,
     ) -> DeleteResult<K, V, PREFIX_LEN> {
+        let _metrics = self.metrics.scope();
         // SAFETY: The root is sure to not be `None`, since the we somehow got a
         // `DeletePoint`. So the caller must have checked this
-        let delete_result = delete_point.apply(unsafe { self.root.unwrap_unchecked() });
+        let delete_result =
+            delete_point.apply(unsafe { self.root.unwrap_unchecked() }, self.shrink_policy);
 
         self.root = delete_result.new_root;
+        if delete_result.inner_node_shrunk {
+            self.resize_stats.shrink_count += 1;
+        }
 
         self.num_entries -= 1;
 
+        #[cfg(feature = "std")]
+        self.refresh_lookup_index(delete_result.deleted_leaf.key_ref());
+
         delete_result
     }
 
@@ -634,6 +1368,7 @@ impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
     where
         K: AsBytes,
     {
+        let _metrics = self.metrics.scope();
         if let Some(root) = self.root {
             // SAFETY: Since we have a mutable reference to the `TreeMap`, we are guaranteed
             // that there are no other references (mutable or immutable) to this same
@@ -668,6 +1403,7 @@ impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
         K: Borrow<Q> + AsBytes,
         Q: AsBytes + ?Sized,
     {
+        let _metrics = self.metrics.scope();
         if let Some(root) = self.root {
             // SAFETY: Since we have a mutable reference to the `TreeMap`, we are guaranteed
             // that there are no other references (mutable or immutable) to this same
@@ -705,6 +1441,423 @@ impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
         self.remove_entry(key).map(|(_, v)| v)
     }
 
+    /// Removes all the entries whose key starts with `prefix` from the map,
+    /// and returns them in a new map.
+    ///
+    /// The entries are removed by detaching the subtree containing them, so
+    /// the tree is only searched once instead of once per removed key.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut map = TreeMap::<[u8; 3], char>::new();
+    /// map.insert([1, 2, 3], 'a');
+    /// map.insert([1, 2, 4], 'b');
+    /// map.insert([1, 3, 4], 'c');
+    /// map.insert([2, 3, 4], 'd');
+    ///
+    /// let removed = map.remove_prefix(&[1, 2]);
+    ///
+    /// assert_eq!(removed.len(), 2);
+    /// assert_eq!(removed.into_iter().collect::<Vec<_>>(), [([1, 2, 3], 'a'), ([1, 2, 4], 'b')]);
+    /// assert_eq!(map.into_iter().collect::<Vec<_>>(), [([1, 3, 4], 'c'), ([2, 3, 4], 'd')]);
+    /// ```
+    pub fn remove_prefix(&mut self, prefix: &[u8]) -> TreeMap<K, V, PREFIX_LEN>
+    where
+        K: AsBytes,
+    {
+        let mut removed = TreeMap::with_prefix_strategy(self.prefix_strategy);
+        removed.shrink_policy = self.shrink_policy;
+        #[cfg(feature = "std")]
+        if let Some(depth) = self.lookup_index_depth() {
+            removed.enable_lookup_index(depth);
+        }
+
+        let Some(root) = self.root else {
+            return removed;
+        };
+        let _metrics = self.metrics.scope();
+
+        // SAFETY: Since we have a mutable reference to the `TreeMap`, we are guaranteed
+        // that there are no other references (mutable or immutable) to this same
+        // object. Meaning that our access to the root node is unique and there are no
+        // other accesses to any node in the tree.
+        let Some(delete_point) = (unsafe { search_for_prefix_delete_point(root, prefix) }) else {
+            return removed;
+        };
+        // SAFETY: Same as above
+        let delete_result = unsafe { delete_point.apply(root, prefix, self.shrink_policy) };
+        // SAFETY: The removed subtree is no longer part of this tree, and we still
+        // have unique access to it.
+        let num_removed = unsafe { count_leaves(delete_result.removed_root) };
+
+        self.root = delete_result.new_root;
+        self.num_entries -= num_removed;
+        if delete_result.inner_node_shrunk {
+            self.resize_stats.shrink_count += 1;
+        }
+        removed.root = Some(delete_result.removed_root);
+        removed.num_entries = num_removed;
+
+        #[cfg(feature = "std")]
+        if let Some(index) = &mut self.lookup_index {
+            // SAFETY: Same as above
+            unsafe { index.remove_prefix(self.root, prefix) };
+            removed.rebuild_lookup_index();
+        }
+
+        removed
+    }
+
+    /// Retains only the entries whose key starts with `prefix`, removing all
+    /// other entries from the map.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut map = TreeMap::<[u8; 3], char>::new();
+    /// map.insert([1, 2, 3], 'a');
+    /// map.insert([1, 2, 4], 'b');
+    /// map.insert([1, 3, 4], 'c');
+    ///
+    /// map.retain_prefix(&[1, 2]);
+    ///
+    /// assert_eq!(map.into_iter().collect::<Vec<_>>(), [([1, 2, 3], 'a'), ([1, 2, 4], 'b')]);
+    /// ```
+    pub fn retain_prefix(&mut self, prefix: &[u8])
+    where
+        K: AsBytes,
+    {
+        let mut other = self.remove_prefix(prefix);
+        // Keep the settings, resize counters and metrics of this map, and only take
+        // the retained entries
+        mem::swap(&mut self.root, &mut other.root);
+        mem::swap(&mut self.num_entries, &mut other.num_entries);
+        #[cfg(feature = "std")]
+        self.rebuild_lookup_index();
+    }
+
+    /// Returns the number of entries whose key starts with `prefix`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut map = TreeMap::<[u8; 3], char>::new();
+    /// map.insert([1, 2, 3], 'a');
+    /// map.insert([1, 2, 4], 'b');
+    /// map.insert([1, 3, 4], 'c');
+    ///
+    /// assert_eq!(map.count_prefix(&[1]), 3);
+    /// assert_eq!(map.count_prefix(&[1, 2]), 2);
+    /// assert_eq!(map.count_prefix(&[2]), 0);
+    /// ```
+    pub fn count_prefix(&self, prefix: &[u8]) -> usize
+    where
+        K: AsBytes,
+    {
+        let Some(root) = self.root else {
+            return 0;
+        };
+        let _metrics = self.metrics.scope();
+
+        // SAFETY: We hold a shared reference to the `TreeMap`, so there can be no
+        // concurrent mutation of any node in the tree.
+        unsafe {
+            search_for_prefix_delete_point(root, prefix)
+                .map_or(0, |point| count_leaves(point.node_ptr))
+        }
+    }
+
+    /// Merge `other` into this map, reusing the nodes of both trees.
+    ///
+    /// The trees are merged by walking both of them at the same time, so that
+    /// subtrees present in only one of the maps are moved into the result as
+    /// a whole. For every key present in both maps, the closure `f` is called
+    /// with the key and the two values, and its result is stored as the
+    /// value.
+    ///
+    /// This requires keys which can never be a prefix of each other, see
+    /// [`TreeMap::union_with_conflicts`] for other keys.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut a = TreeMap::<u16, u32>::from([(1, 1), (2, 2), (3, 3)]);
+    /// let b = TreeMap::<u16, u32>::from([(3, 30), (4, 40)]);
+    ///
+    /// a.union_with(b, |_, left, right| left + right);
+    ///
+    /// assert_eq!(
+    ///     a.into_iter().collect::<Vec<_>>(),
+    ///     [(1, 1), (2, 2), (3, 33), (4, 40)]
+    /// );
+    /// ```
+    pub fn union_with<F>(&mut self, other: TreeMap<K, V, PREFIX_LEN>, f: F)
+    where
+        K: NoPrefixesBytes,
+        F: FnMut(&K, V, V) -> V,
+    {
+        // This will never reject an entry because of the safety contract of
+        // `NoPrefixesBytes`
+        let _ = self.union_with_conflicts(other, f);
+    }
+
+    /// Merge `other` into this map like [`TreeMap::union_with`], for keys
+    /// which may be a prefix of each other.
+    ///
+    /// An entry of `other` is not merged if its key is a prefix of a key in
+    /// this map, or a key in this map is a prefix of its key, the same as if
+    /// it was rejected by [`TreeMap::try_insert`]. The rejected entries are
+    /// returned in a new map.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut a = TreeMap::<Box<[u8]>, u32>::new();
+    /// a.try_insert(Box::from(*b"ab"), 1).unwrap();
+    /// a.try_insert(Box::from(*b"cd"), 2).unwrap();
+    /// let mut b = TreeMap::<Box<[u8]>, u32>::new();
+    /// b.try_insert(Box::from(*b"abc"), 10).unwrap();
+    /// b.try_insert(Box::from(*b"cd"), 20).unwrap();
+    /// b.try_insert(Box::from(*b"e"), 30).unwrap();
+    ///
+    /// let rejected = a.union_with_conflicts(b, |_, left, right| left + right);
+    ///
+    /// assert_eq!(
+    ///     a.into_iter().collect::<Vec<_>>(),
+    ///     [(Box::from(*b"ab"), 1), (Box::from(*b"cd"), 22), (Box::from(*b"e"), 30)]
+    /// );
+    /// assert_eq!(rejected.into_iter().collect::<Vec<_>>(), [(Box::from(*b"abc"), 10)]);
+    /// ```
+    pub fn union_with_conflicts<F>(
+        &mut self,
+        other: TreeMap<K, V, PREFIX_LEN>,
+        mut f: F,
+    ) -> TreeMap<K, V, PREFIX_LEN>
+    where
+        K: AsBytes,
+        F: FnMut(&K, V, V) -> V,
+    {
+        self.merge(other, true, true, |key, left, right| {
+            Some(f(key, left, right))
+        })
+    }
+
+    /// Retain only the keys of this map that are also present in `other`,
+    /// reusing the nodes of both trees.
+    ///
+    /// For every key present in both maps, the closure `f` is called with the
+    /// key and the two values, and its result is stored as the value.
+    ///
+    /// The keys may be a prefix of each other, since only the keys present
+    /// in both maps are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut a = TreeMap::<u16, u32>::from([(1, 1), (2, 2), (3, 3)]);
+    /// let b = TreeMap::<u16, u32>::from([(2, 20), (3, 30), (4, 40)]);
+    ///
+    /// a.intersection_with(b, |_, left, right| left * right);
+    ///
+    /// assert_eq!(a.into_iter().collect::<Vec<_>>(), [(2, 40), (3, 90)]);
+    /// ```
+    pub fn intersection_with<F>(&mut self, other: TreeMap<K, V, PREFIX_LEN>, mut f: F)
+    where
+        K: AsBytes,
+        F: FnMut(&K, V, V) -> V,
+    {
+        let _ = self.merge(other, false, false, |key, left, right| {
+            Some(f(key, left, right))
+        });
+    }
+
+    /// Remove the keys of `other` from this map, reusing the nodes of both
+    /// trees.
+    ///
+    /// Keys only present in this map are kept. For every key present in both
+    /// maps, the closure `f` is called with the key and the two values, and
+    /// the key is kept with the returned value if it returns `Some`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut a = TreeMap::<u16, u32>::from([(1, 1), (2, 2), (3, 3)]);
+    /// let b = TreeMap::<u16, u32>::from([(2, 2), (3, 1), (4, 4)]);
+    ///
+    /// a.difference(b, |_, left, right| left.checked_sub(right).filter(|v| *v > 0));
+    ///
+    /// assert_eq!(a.into_iter().collect::<Vec<_>>(), [(1, 1), (3, 2)]);
+    /// ```
+    pub fn difference<F>(&mut self, other: TreeMap<K, V, PREFIX_LEN>, f: F)
+    where
+        K: AsBytes,
+        F: FnMut(&K, V, V) -> Option<V>,
+    {
+        let _ = self.merge(other, true, false, f);
+    }
+
+    /// Keep the keys which are present in exactly one of this map and
+    /// `other`, reusing the nodes of both trees.
+    ///
+    /// For every key present in both maps, the closure `f` is called with the
+    /// key and the two values, and the key is kept with the returned value if
+    /// it returns `Some`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut a = TreeMap::<u16, u32>::from([(1, 1), (2, 2), (3, 3)]);
+    /// let b = TreeMap::<u16, u32>::from([(3, 30), (4, 40)]);
+    ///
+    /// a.symmetric_difference(b, |_, _, _| None);
+    ///
+    /// assert_eq!(
+    ///     a.into_iter().collect::<Vec<_>>(),
+    ///     [(1, 1), (2, 2), (4, 40)]
+    /// );
+    /// ```
+    pub fn symmetric_difference<F>(&mut self, other: TreeMap<K, V, PREFIX_LEN>, f: F)
+    where
+        K: NoPrefixesBytes,
+        F: FnMut(&K, V, V) -> Option<V>,
+    {
+        // This will never reject an entry because of the safety contract of
+        // `NoPrefixesBytes`
+        let _ = self.symmetric_difference_with_conflicts(other, f);
+    }
+
+    /// Keep the keys which are present in exactly one of this map and `other`
+    /// like [`TreeMap::symmetric_difference`], for keys which may be a prefix
+    /// of each other.
+    ///
+    /// An entry of `other` is not kept if its key is a prefix of a key in this
+    /// map, or a key in this map is a prefix of its key. The rejected entries
+    /// are returned in a new map.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::TreeMap;
+    ///
+    /// let mut a = TreeMap::<Box<[u8]>, u32>::new();
+    /// a.try_insert(Box::from(*b"ab"), 1).unwrap();
+    /// a.try_insert(Box::from(*b"cd"), 2).unwrap();
+    /// let mut b = TreeMap::<Box<[u8]>, u32>::new();
+    /// b.try_insert(Box::from(*b"a"), 10).unwrap();
+    /// b.try_insert(Box::from(*b"cd"), 20).unwrap();
+    /// b.try_insert(Box::from(*b"e"), 30).unwrap();
+    ///
+    /// let rejected = a.symmetric_difference_with_conflicts(b, |_, _, _| None);
+    ///
+    /// assert_eq!(
+    ///     a.into_iter().collect::<Vec<_>>(),
+    ///     [(Box::from(*b"ab"), 1), (Box::from(*b"e"), 30)]
+    /// );
+    /// assert_eq!(rejected.into_iter().collect::<Vec<_>>(), [(Box::from(*b"a"), 10)]);
+    /// ```
+    pub fn symmetric_difference_with_conflicts<F>(
+        &mut self,
+        other: TreeMap<K, V, PREFIX_LEN>,
+        f: F,
+    ) -> TreeMap<K, V, PREFIX_LEN>
+    where
+        K: AsBytes,
+        F: FnMut(&K, V, V) -> Option<V>,
+    {
+        self.merge(other, true, true, f)
+    }
+
+    /// Merge the trees of both maps with [`merge_trees`], and return the
+    /// entries of `other` which were rejected because their keys conflict with
+    /// the keys of this map.
+    fn merge<F>(
+        &mut self,
+        mut other: TreeMap<K, V, PREFIX_LEN>,
+        keep_left_only: bool,
+        keep_right_only: bool,
+        f: F,
+    ) -> TreeMap<K, V, PREFIX_LEN>
+    where
+        K: AsBytes,
+        F: FnMut(&K, V, V) -> Option<V>,
+    {
+        let _metrics = self.metrics.scope();
+        // Take both trees out of the maps first, so that if `f` panics both
+        // maps are left empty and the nodes are leaked instead of freed twice.
+        let left_len = core::mem::take(&mut self.num_entries);
+        let right_len = core::mem::take(&mut other.num_entries);
+        let left = self.root.take();
+        let right = other.root.take();
+
+        if let Some(right) = right.filter(|_| other.prefix_strategy != self.prefix_strategy) {
+            // SAFETY: We own `other`, so there are no other references to any node of
+            // its tree. The nodes of this tree are reused in the merged tree, so they
+            // have to store their prefixes the same way as the nodes of this map.
+            unsafe { set_prefix_strategy(right, 0, self.prefix_strategy) };
+        }
+
+        // SAFETY: Since we have a mutable reference to this map and ownership of
+        // `other`, there are no other references to any node of either tree. The
+        // roots were taken out of both maps, so `other` will not deallocate them
+        // when dropped.
+        let merge_result = unsafe {
+            merge_trees(
+                left,
+                right,
+                keep_left_only,
+                keep_right_only,
+                self.prefix_strategy,
+                f,
+            )
+        };
+
+        let mut num_entries = merge_result.num_matched_kept;
+        if keep_left_only {
+            num_entries += left_len - merge_result.num_matched;
+        }
+        if keep_right_only {
+            num_entries += right_len - merge_result.num_matched - merge_result.right_rejected.len();
+        }
+
+        self.root = merge_result.new_root;
+        self.num_entries = num_entries;
+
+        if !self.shrink_policy.accepts(other.shrink_policy) {
+            // The subtrees moved from `other` may have nodes which are larger
+            // than this map allows
+            self.shrink_to_fit();
+        }
+
+        #[cfg(feature = "std")]
+        self.rebuild_lookup_index();
+
+        let mut rejected = TreeMap::with_prefix_strategy(self.prefix_strategy);
+        rejected.shrink_policy = self.shrink_policy;
+        for (key, value) in merge_result.right_rejected {
+            rejected
+                .try_insert(key, value)
+                .expect("the rejected entries are from a single map, so they do not conflict");
+        }
+        rejected
+    }
+
     /*
     /// Retains only the elements specified by the predicate.
     ///
@@ -766,7 +1919,7 @@ impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
     //
     // ```rust,should_panic
     // use blart::TreeMap;
-    // use std::ops::Bound::Included;
+    // use core::ops::Bound::Included;
     //
     // let mut map = TreeMap::<u8, _>::new();
     // map.try_insert(3, "a").unwrap();
@@ -1146,6 +2299,45 @@ impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
         PrefixMut::new(self, prefix)
     }
 
+    /// Gets an iterator over the differences between this map and `other`,
+    /// in key order.
+    ///
+    /// Keys only present in `other` are yielded as [`DiffItem::Added`], keys
+    /// only present in this map as [`DiffItem::Removed`], and keys present in
+    /// both maps with unequal values as [`DiffItem::Changed`].
+    ///
+    /// Both trees are walked at the same time, comparing node prefixes and
+    /// child key bytes, so subtrees present in only one of the maps are not
+    /// compared to anything, and nodes shared by both trees are skipped
+    /// entirely.
+    ///
+    /// # Example
+    /// ```rust
+    /// use blart::{map::DiffItem, TreeMap};
+    ///
+    /// let old = TreeMap::<u16, char>::from([(1, 'a'), (2, 'b'), (3, 'c')]);
+    /// let new = TreeMap::<u16, char>::from([(2, 'b'), (3, 'C'), (4, 'd')]);
+    ///
+    /// let diff: Vec<_> = old.diff(&new).collect();
+    ///
+    /// assert_eq!(
+    ///     diff,
+    ///     vec![
+    ///         DiffItem::Removed(&1, &'a'),
+    ///         DiffItem::Changed { key: &3, old: &'c', new: &'C' },
+    ///         DiffItem::Added(&4, &'d'),
+    ///     ]
+    /// );
+    /// assert_eq!(old.diff(&old).next(), None);
+    /// ```
+    pub fn diff<'a>(&'a self, other: &'a TreeMap<K, V, PREFIX_LEN>) -> Diff<'a, K, V, PREFIX_LEN>
+    where
+        K: AsBytes,
+        V: PartialEq,
+    {
+        Diff::new(self, other)
+    }
+
     /// Gets an iterator over the keys of the map that start with `prefix`
     ///
     /// # Example
@@ -1263,10 +2455,11 @@ impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
 impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
     /// Tries to get the given key’s corresponding entry in the map for in-place
     /// manipulation.
-    pub fn try_entry(&mut self, key: K) -> Result<Entry<K, V, PREFIX_LEN>, InsertPrefixError>
+    pub fn try_entry(&mut self, key: K) -> Result<Entry<'_, K, V, PREFIX_LEN>, InsertPrefixError>
     where
         K: AsBytes,
     {
+        let _metrics = self.metrics.scope();
         let entry = match self.root {
             Some(root) => {
                 // SAFETY: Since we have a mutable reference to the `TreeMap`, we are guaranteed
@@ -1308,6 +2501,7 @@ impl<K, V, const PREFIX_LEN: usize> TreeMap<K, V, PREFIX_LEN> {
         K: AsBytes + Borrow<Q> + From<&'b Q>,
         Q: AsBytes + ?Sized,
     {
+        let _metrics = self.metrics.scope();
         let entry = match self.root {
             Some(root) => {
                 // SAFETY: Since we have a mutable reference to the `TreeMap`, we are guaranteed
@@ -1373,14 +2567,22 @@ where
     V: Clone,
 {
     fn clone(&self) -> Self {
+        let mut clone = Self::with_prefix_strategy(self.prefix_strategy);
+        clone.shrink_policy = self.shrink_policy;
+        clone.resize_stats = self.resize_stats;
+        clone.metrics.copy_from(&self.metrics);
         if let Some(root) = self.root {
-            Self {
-                root: Some(root.deep_clone()),
-                num_entries: self.num_entries,
-            }
-        } else {
-            Self::with_prefix_len()
+            let _metrics = clone.metrics.scope();
+            clone.root = Some(root.deep_clone());
+            clone.num_entries = self.num_entries;
+        }
+
+        #[cfg(feature = "std")]
+        if let Some(depth) = self.lookup_index_depth() {
+            clone.enable_lookup_index(depth);
         }
+
+        clone
     }
 }
 
@@ -1389,7 +2591,7 @@ where
     K: Debug + AsBytes,
     V: Debug,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_map().entries(self.iter()).finish()
     }
 }
@@ -1454,7 +2656,7 @@ where
     K: Hash + AsBytes,
     V: Hash,
 {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
         hasher_write_length_prefix(state, self.num_entries);
         for elt in self {
             elt.hash(state);
@@ -1508,7 +2710,7 @@ where
     K: Ord + AsBytes,
     V: Ord,
 {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.iter().cmp(other.iter())
     }
 }
@@ -1518,7 +2720,7 @@ where
     K: PartialOrd + AsBytes,
     V: PartialOrd,
 {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         self.iter().partial_cmp(other.iter())
     }
 }
@@ -1536,7 +2738,7 @@ where
     V: PartialEq,
 {
     fn eq(&self, other: &Self) -> bool {
-        self.iter().eq(other.iter()) && self.num_entries == other.num_entries
+        self.num_entries == other.num_entries && iterators::trees_equal(self, other)
     }
 }
 
@@ -1562,7 +2764,10 @@ where
 
 #[cfg(test)]
 mod tests {
-    use std::{cmp::Ordering, collections::hash_map::RandomState, hash::BuildHasher};
+    use alloc::ffi::CString;
+    use alloc::{boxed::Box, format, string::String};
+    use core::{cmp::Ordering, hash::BuildHasher};
+    use std::collections::hash_map::RandomState;
 
     use crate::{
         tests_common::{
@@ -1982,4 +3187,298 @@ mod tests {
         let new_tree = tree.clone();
         assert!(tree == new_tree);
     }
+
+    fn url_keys(section_filter: impl Fn(usize) -> bool) -> Vec<CString> {
+        let sections = ["catalogue", "checkout", "customer-accounts", "customers"];
+        sections
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| section_filter(*idx))
+            .flat_map(|(_, section)| {
+                (0..40).map(move |id| {
+                    CString::new(format!(
+                        "https://example.com/{section}/items/{id:03}/details"
+                    ))
+                    .unwrap()
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pessimistic_prefix_strategy_spills_and_matches_optimistic() {
+        let keys = url_keys(|_| true);
+        let mut optimistic = TreeMap::<CString, usize, 8>::with_prefix_len();
+        let mut pessimistic =
+            TreeMap::<CString, usize, 8>::with_prefix_strategy(PrefixStrategy::Pessimistic);
+        for (value, key) in keys.iter().enumerate() {
+            optimistic.try_insert(key.clone(), value).unwrap();
+            pessimistic.try_insert(key.clone(), value).unwrap();
+        }
+
+        // The root prefix is "https://example.com/c", which is only stored in full
+        // by the pessimistic map
+        let root = pessimistic.root.unwrap();
+        // SAFETY: The header reference is dropped before the map is modified
+        assert!(unsafe { root.header_mut() }.unwrap().is_prefix_spilled());
+        // SAFETY: Same as above
+        assert!(!unsafe { optimistic.root.unwrap().header_mut() }
+            .unwrap()
+            .is_prefix_spilled());
+
+        crate::visitor::WellFormedChecker::check(&pessimistic).unwrap();
+        assert!(pessimistic == optimistic);
+        for (value, key) in keys.iter().enumerate() {
+            assert_eq!(pessimistic.get(key), Some(&value));
+        }
+        let absent_key = CString::new("https://example.com/customers/items/040/details").unwrap();
+        assert_eq!(pessimistic.get(&absent_key), None);
+
+        for key in keys.iter().step_by(3) {
+            assert_eq!(pessimistic.remove(key), optimistic.remove(key));
+        }
+        crate::visitor::WellFormedChecker::check(&pessimistic).unwrap();
+        assert!(pessimistic == optimistic);
+
+        let removed = pessimistic.remove_prefix(b"https://example.com/customer");
+        assert_eq!(removed.prefix_strategy(), PrefixStrategy::Pessimistic);
+        assert!(removed == optimistic.remove_prefix(b"https://example.com/customer"));
+        crate::visitor::WellFormedChecker::check(&removed).unwrap();
+        crate::visitor::WellFormedChecker::check(&pessimistic).unwrap();
+        assert!(pessimistic == optimistic);
+
+        let clone = pessimistic.clone();
+        assert_eq!(clone.prefix_strategy(), PrefixStrategy::Pessimistic);
+        assert!(clone == pessimistic);
+
+        pessimistic.union_with(removed, |_, l, _| l);
+        crate::visitor::WellFormedChecker::check(&pessimistic).unwrap();
+        for key in url_keys(|_| true).iter().skip(1).step_by(3) {
+            assert!(pessimistic.contains_key(key));
+        }
+    }
+
+    #[test]
+    fn get_many_matches_get() {
+        let empty = TreeMap::<[u8; 3], usize>::new();
+        assert_eq!(empty.get_many(&[&[1, 2, 3], &[4, 5, 6]]), [None, None]);
+        assert_eq!(empty.get_many::<[u8; 3]>(&[]), []);
+
+        let keys: Vec<_> = generate_key_fixed_length([15, 7, 3]).collect();
+        let mut tree = TreeMap::<Box<[u8]>, usize>::new();
+        for (idx, key) in keys.iter().enumerate() {
+            tree.try_insert(key.clone(), idx).unwrap();
+        }
+
+        let mut searches: Vec<&[u8]> = keys.iter().map(AsRef::as_ref).step_by(5).collect();
+        searches.extend([&[16, 0, 0][..], &[0, 8, 0], &[15, 7, 4], &[0, 0]]);
+        searches.rotate_left(7);
+
+        let expected: Vec<_> = searches.iter().map(|key| tree.get(*key)).collect();
+        assert_eq!(tree.get_many(&searches), expected);
+
+        let mut iter = tree.get_many_iter(searches.iter().copied());
+        assert_eq!(iter.size_hint(), (searches.len(), Some(searches.len())));
+        assert_eq!(iter.next(), Some(expected[0]));
+        assert_eq!(
+            iter.size_hint(),
+            (searches.len() - 1, Some(searches.len() - 1))
+        );
+        assert_eq!(iter.collect::<Vec<_>>(), expected[1..]);
+    }
+
+    #[test]
+    fn get_many_mut_disjoint_keys() {
+        let mut tree: TreeMap<u16, u32> = (0..1000).map(|key| (key, u32::from(key))).collect();
+
+        assert_eq!(tree.get_many_mut::<u16, 0>([]), Some([]));
+
+        let [a, b, c] = tree.get_many_mut([&3, &300, &999]).unwrap();
+        (*a, *b, *c) = (*c, *a, *b);
+        assert_eq!(tree.get(&3), Some(&999));
+        assert_eq!(tree.get(&300), Some(&3));
+        assert_eq!(tree.get(&999), Some(&300));
+
+        assert!(tree.get_many_mut([&3, &1000]).is_none());
+        assert!(tree.get_many_mut([&3, &300, &3]).is_none());
+
+        // SAFETY: The keys are distinct
+        let [a, b] = unsafe { tree.get_many_unchecked_mut([&1, &256]) }.unwrap();
+        *a += 1000;
+        *b += 1000;
+        assert_eq!(tree.get(&1), Some(&1001));
+        assert_eq!(tree.get(&256), Some(&1256));
+        // SAFETY: The keys are distinct
+        assert!(unsafe { tree.get_many_unchecked_mut([&1, &2000]) }.is_none());
+
+        let mut empty = TreeMap::<u16, u32>::new();
+        assert!(empty.get_many_mut([&1]).is_none());
+    }
+
+    #[test]
+    fn retain_prefix_keeps_map_state() {
+        use crate::visitor::{TreeStatsCollector, WellFormedChecker};
+
+        let mut tree = TreeMap::<[u8; 2], u32>::new();
+        tree.set_shrink_policy(ShrinkPolicy::Lazy);
+        #[cfg(feature = "std")]
+        tree.enable_lookup_index(1);
+        for a in 0..3u8 {
+            for b in 0..=255u8 {
+                tree.insert([a, b], u32::from(b));
+            }
+        }
+        let grow_count = TreeStatsCollector::collect(&tree)
+            .unwrap()
+            .resizes
+            .grow_count;
+        assert!(grow_count > 0);
+        #[cfg(feature = "metrics")]
+        let leaf_allocations = tree.metrics().node_allocations.leaf;
+
+        tree.retain_prefix(&[1]);
+
+        assert_eq!(tree.len(), 256);
+        assert_eq!(tree.shrink_policy(), ShrinkPolicy::Lazy);
+        #[cfg(feature = "std")]
+        assert_eq!(tree.lookup_index_depth(), Some(1));
+        let stats = TreeStatsCollector::collect(&tree).unwrap();
+        assert_eq!(stats.resizes.grow_count, grow_count);
+        #[cfg(feature = "metrics")]
+        assert_eq!(tree.metrics().node_allocations.leaf, leaf_allocations);
+        WellFormedChecker::check(&tree).unwrap();
+        assert_eq!(tree.get(&[1, 7]), Some(&7));
+        assert_eq!(tree.get(&[0, 7]), None);
+        assert_eq!(tree.get(&[2, 7]), None);
+    }
+
+    #[test]
+    fn compact_shrinks_oversized_nodes() {
+        use crate::{
+            visitor::{TreeStatsCollector, WellFormedChecker},
+            ConcreteNodePtr, InnerNode,
+        };
+
+        let mut tree: TreeMap<u8, u32> = [(1, 10), (2, 20), (3, 30)].into_iter().collect();
+        #[cfg(feature = "std")]
+        tree.enable_lookup_index(1);
+
+        // Replace the `InnerNode4` root with an `InnerNode256` holding the same
+        // children, like a node that was never shrunk.
+        let ConcreteNodePtr::Node4(root) = tree.root.unwrap().to_node_ptr() else {
+            panic!("expected the root to be an InnerNode4");
+        };
+        // SAFETY: There are no other references to the root node, and the old root
+        // is deallocated before the new root is used.
+        unsafe {
+            let mut grown = crate::InnerNode256::from_header(root.as_ref().header().clone_prefix());
+            for (key_fragment, child) in root.as_ref().iter() {
+                grown.write_child(key_fragment, child);
+            }
+            drop(NodePtr::deallocate_node_ptr(root));
+            tree.root = Some(NodePtr::allocate_node_ptr(grown).to_opaque());
+        }
+        #[cfg(feature = "std")]
+        tree.rebuild_lookup_index();
+
+        let stats = TreeStatsCollector::collect(&tree).unwrap();
+        assert_eq!(stats.node256.count, 1);
+
+        let reclaimed = tree.compact();
+        let stats = TreeStatsCollector::collect(&tree).unwrap();
+        assert_eq!(stats.node256.count, 0);
+        assert_eq!(stats.node4.count, 1);
+        assert_eq!(
+            reclaimed,
+            core::mem::size_of::<crate::InnerNode256<u8, u32, 16>>()
+                - core::mem::size_of::<crate::InnerNode4<u8, u32, 16>>()
+        );
+
+        WellFormedChecker::check(&tree).unwrap();
+        assert_eq!(tree.get(&2), Some(&20));
+        assert_eq!(tree.compact(), 0);
+
+        let mut empty = TreeMap::<u8, u32>::new();
+        assert_eq!(empty.compact(), 0);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn compact_keeps_entries() {
+        use crate::visitor::WellFormedChecker;
+
+        let mut tree: TreeMap<Box<[u8]>, usize> = TreeMap::new();
+        for (value, key) in generate_key_fixed_length([15, 15, 7]).enumerate() {
+            if value % 3 != 0 {
+                tree.try_insert(key, value).unwrap();
+            }
+        }
+        let keys: Vec<Box<[u8]>> = tree.keys().cloned().collect();
+        for key in keys.iter().step_by(2) {
+            tree.remove(key).unwrap();
+        }
+        let expected: Vec<_> = tree.iter().map(|(k, v)| (k.clone(), *v)).collect();
+
+        tree.shrink_to_fit();
+
+        WellFormedChecker::check(&tree).unwrap();
+        let entries: Vec<_> = tree.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        assert_eq!(entries, expected);
+        for (key, value) in &expected {
+            assert_eq!(tree.get(key), Some(value));
+        }
+    }
+
+    #[test]
+    fn shrink_policies_churn() {
+        use crate::visitor::{TreeStatsCollector, WellFormedChecker};
+        use std::collections::BTreeMap;
+
+        let policies = [
+            ShrinkPolicy::Eager,
+            ShrinkPolicy::Hysteresis,
+            ShrinkPolicy::Lazy,
+        ];
+        let mut shrink_counts = Vec::new();
+        for policy in policies {
+            let mut tree = TreeMap::<u16, u16>::new();
+            tree.set_shrink_policy(policy);
+            let mut oracle = BTreeMap::new();
+
+            // Grow nodes to every size, then churn around the thresholds of
+            // the node types while shrinking them again.
+            let mut state = 0x2545_f491u32;
+            for round in 0..40u16 {
+                let limit = 300 - round * 7;
+                for _ in 0..400 {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    let key = (state % u32::from(limit)) as u16;
+                    if state & (1 << 20) == 0 {
+                        assert_eq!(tree.insert(key, round), oracle.insert(key, round));
+                    } else {
+                        assert_eq!(tree.remove(&key), oracle.remove(&key));
+                    }
+                }
+                for key in limit..300 {
+                    assert_eq!(tree.remove(&key), oracle.remove(&key));
+                }
+                WellFormedChecker::check(&tree).unwrap();
+            }
+            assert!(tree.iter().map(|(k, v)| (*k, *v)).eq(oracle.clone()));
+
+            let stats = TreeStatsCollector::collect(&tree).unwrap();
+            assert!(stats.resizes.grow_count > 0);
+            shrink_counts.push(stats.resizes.shrink_count);
+
+            // Switching to a stricter policy shrinks the existing nodes
+            tree.set_shrink_policy(ShrinkPolicy::Eager);
+            WellFormedChecker::check(&tree).unwrap();
+            assert!(tree.iter().map(|(k, v)| (*k, *v)).eq(oracle));
+        }
+
+        assert!(shrink_counts[0] > shrink_counts[1], "{shrink_counts:?}");
+        assert_eq!(shrink_counts[2], 0);
+    }
 }
@@ -1,4 +1,4 @@
-use std::mem::replace;
+use core::mem::replace;
 
 use crate::{AsBytes, DeletePoint, InsertPoint, LeafNode, NodePtr, OpaqueNodePtr, TreeMap};
 
@@ -299,7 +299,8 @@ where
 
 #[cfg(test)]
 mod tests {
-    use std::ffi::CString;
+    use alloc::ffi::CString;
+    use alloc::string::String;
 
     use crate::TreeMap;
 
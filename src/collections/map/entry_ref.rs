@@ -1,4 +1,4 @@
-use std::{borrow::Borrow, mem::replace};
+use core::{borrow::Borrow, mem::replace};
 
 use crate::{AsBytes, DeletePoint, InsertPoint, LeafNode, NodePtr, OpaqueNodePtr, TreeMap};
 
@@ -302,7 +302,8 @@ where
 
 #[cfg(test)]
 mod tests {
-    use std::ffi::CString;
+    use alloc::ffi::CString;
+    use alloc::string::String;
 
     use crate::TreeMap;
 
@@ -335,9 +336,9 @@ mod tests {
         tree.insert(a.clone(), String::from("a"));
         tree.insert(b.clone(), String::from("b"));
 
-        assert_eq!(tree.entry_ref(&a).key(), a.borrow());
-        assert_eq!(tree.entry_ref(&b).key(), b.borrow());
-        assert_eq!(tree.entry_ref(&c).key(), c.borrow());
+        assert_eq!(tree.entry_ref(&a).key(), &a);
+        assert_eq!(tree.entry_ref(&b).key(), &b);
+        assert_eq!(tree.entry_ref(&c).key(), &c);
     }
 
     #[test]
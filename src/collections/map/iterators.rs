@@ -10,6 +10,12 @@ pub use into_iter::*;
 mod fuzzy;
 pub use fuzzy::*;
 
+mod diff;
+pub use diff::*;
+
+mod get_many;
+pub use get_many::*;
+
 /*
 /// An iterator over a sub-range of entries in a `TreeMap`.
 ///
@@ -0,0 +1,581 @@
+use crate::{
+    read_path, AsBytes, ConcreteNodePtr, Header, InnerNode, LeafNode, NodePtr, OpaqueNodePtr,
+    TreeMap,
+};
+use alloc::vec::Vec;
+use core::{cmp::Ordering, iter::FusedIterator};
+
+/// A single difference between two maps, produced by the [`Diff`] iterator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffItem<'a, K, V> {
+    /// The key is only present in the new map.
+    Added(&'a K, &'a V),
+    /// The key is only present in the old map.
+    Removed(&'a K, &'a V),
+    /// The key is present in both maps, with different values.
+    Changed {
+        /// The key present in both maps.
+        key: &'a K,
+        /// The value in the old map.
+        old: &'a V,
+        /// The value in the new map.
+        new: &'a V,
+    },
+}
+
+impl<'a, K, V> DiffItem<'a, K, V> {
+    /// Return the key this difference is about.
+    pub fn key(&self) -> &'a K {
+        match self {
+            DiffItem::Added(key, _) | DiffItem::Removed(key, _) => key,
+            DiffItem::Changed { key, .. } => key,
+        }
+    }
+}
+
+/// A pending comparison in the [`Diff`] iterator.
+enum DiffNode<K, V, const PREFIX_LEN: usize> {
+    /// Compare two subtrees which both match the key up to `depth`.
+    ///
+    /// Each node is paired with the depth its own compressed path starts at,
+    /// which may be less than `depth` if a part of its path was already
+    /// matched against the other tree.
+    Both {
+        old: (OpaqueNodePtr<K, V, PREFIX_LEN>, usize),
+        new: (OpaqueNodePtr<K, V, PREFIX_LEN>, usize),
+        depth: usize,
+    },
+    /// All leaves of this subtree are only present in the old map.
+    Removed(OpaqueNodePtr<K, V, PREFIX_LEN>),
+    /// All leaves of this subtree are only present in the new map.
+    Added(OpaqueNodePtr<K, V, PREFIX_LEN>),
+}
+
+/// Copy the children of the given node into `children` and return the
+/// header, or return `None` if the node is a leaf.
+///
+/// # Safety
+///  - There must be no concurrent mutation of the given node during the
+///    returned lifetime.
+unsafe fn read_inner_node<'a, K: 'a, V: 'a, const PREFIX_LEN: usize>(
+    node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    children: &mut Vec<(u8, OpaqueNodePtr<K, V, PREFIX_LEN>)>,
+) -> Option<&'a Header<PREFIX_LEN>> {
+    fn read<'a, N: InnerNode<PREFIX_LEN> + 'a, const PREFIX_LEN: usize>(
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+        children: &mut Vec<(u8, OpaqueNodePtr<N::Key, N::Value, PREFIX_LEN>)>,
+    ) -> &'a Header<PREFIX_LEN> {
+        // SAFETY: Covered by the safety requirements of the containing function
+        let inner_node = unsafe { inner_ptr.as_ref() };
+        children.extend(inner_node.iter());
+        inner_node.header()
+    }
+
+    match node.to_node_ptr() {
+        ConcreteNodePtr::Node4(inner_ptr) => Some(read(inner_ptr, children)),
+        ConcreteNodePtr::Node16(inner_ptr) => Some(read(inner_ptr, children)),
+        ConcreteNodePtr::Node32(inner_ptr) => Some(read(inner_ptr, children)),
+        ConcreteNodePtr::Node48(inner_ptr) => Some(read(inner_ptr, children)),
+        ConcreteNodePtr::Node256(inner_ptr) => Some(read(inner_ptr, children)),
+        ConcreteNodePtr::LeafNode(_) => None,
+    }
+}
+
+/// An iterator over the differences between two maps, in key order.
+///
+/// This `struct` is created by the [`diff`] method on `TreeMap`. See its
+/// documentation for more.
+///
+/// [`diff`]: TreeMap::diff
+pub struct Diff<'a, K, V, const PREFIX_LEN: usize> {
+    stack: Vec<DiffNode<K, V, PREFIX_LEN>>,
+    _old: &'a TreeMap<K, V, PREFIX_LEN>,
+    _new: &'a TreeMap<K, V, PREFIX_LEN>,
+}
+
+impl<'a, K, V, const PREFIX_LEN: usize> Diff<'a, K, V, PREFIX_LEN> {
+    /// Create a new iterator over the differences from `old` to `new`.
+    pub(crate) fn new(
+        old: &'a TreeMap<K, V, PREFIX_LEN>,
+        new: &'a TreeMap<K, V, PREFIX_LEN>,
+    ) -> Self {
+        let stack = match (old.root, new.root) {
+            (Some(old), Some(new)) => [DiffNode::Both {
+                old: (old, 0),
+                new: (new, 0),
+                depth: 0,
+            }]
+            .into(),
+            (Some(old), None) => [DiffNode::Removed(old)].into(),
+            (None, Some(new)) => [DiffNode::Added(new)].into(),
+            (None, None) => Vec::new(),
+        };
+
+        Self {
+            stack,
+            _old: old,
+            _new: new,
+        }
+    }
+
+    /// Push the children of both nodes onto the stack, pairing up the
+    /// children with the same key byte.
+    fn push_children(
+        &mut self,
+        old_children: &[(u8, OpaqueNodePtr<K, V, PREFIX_LEN>, usize)],
+        new_children: &[(u8, OpaqueNodePtr<K, V, PREFIX_LEN>, usize)],
+        depth: usize,
+    ) {
+        // The stack is popped from the back, so the children are pushed in
+        // descending key byte order.
+        let mut old_children = old_children.iter().rev().peekable();
+        let mut new_children = new_children.iter().rev().peekable();
+
+        loop {
+            let next = match (old_children.peek(), new_children.peek()) {
+                (Some(old), Some(new)) => old.0.cmp(&new.0),
+                (Some(_), None) => Ordering::Greater,
+                (None, Some(_)) => Ordering::Less,
+                (None, None) => break,
+            };
+
+            let node = match next {
+                Ordering::Equal => {
+                    let (_, old, old_depth) = *old_children.next().unwrap();
+                    let (_, new, new_depth) = *new_children.next().unwrap();
+                    DiffNode::Both {
+                        old: (old, old_depth),
+                        new: (new, new_depth),
+                        depth,
+                    }
+                },
+                Ordering::Greater => DiffNode::Removed(old_children.next().unwrap().1),
+                Ordering::Less => DiffNode::Added(new_children.next().unwrap().1),
+            };
+            self.stack.push(node);
+        }
+    }
+
+    /// Compare two subtrees, either returning a difference between two leaves
+    /// or pushing the remaining work onto the stack.
+    fn compare(
+        &mut self,
+        (old, old_depth): (OpaqueNodePtr<K, V, PREFIX_LEN>, usize),
+        (new, new_depth): (OpaqueNodePtr<K, V, PREFIX_LEN>, usize),
+        depth: usize,
+    ) -> Option<DiffItem<'a, K, V>>
+    where
+        K: AsBytes,
+        V: PartialEq,
+    {
+        if old == new && old_depth == new_depth {
+            // The same node in the same position must have the same contents
+            return None;
+        }
+
+        let (matched_bytes, old_byte, new_byte) = {
+            // SAFETY: Since `Self` holds shared references to both trees, there can
+            // be no mutation of any node while the paths are alive.
+            let (old_path, _) = unsafe { read_path(old, old_depth) };
+            // SAFETY: Same as above
+            let (new_path, _) = unsafe { read_path(new, new_depth) };
+            let old_path = &old_path[(depth - old_depth)..];
+            let new_path = &new_path[(depth - new_depth)..];
+
+            let matched_bytes = old_path
+                .iter()
+                .zip(new_path)
+                .take_while(|(a, b)| **a == **b)
+                .count();
+            (
+                matched_bytes,
+                old_path.get(matched_bytes).copied(),
+                new_path.get(matched_bytes).copied(),
+            )
+        };
+
+        let child_depth = depth + matched_bytes + 1;
+        let mut old_children = Vec::new();
+        let mut new_children = Vec::new();
+        // SAFETY: Since `Self` holds shared references to both trees, there can be
+        // no mutation of any node while the headers are alive.
+        let old_is_inner = unsafe { read_inner_node(old, &mut old_children) }.is_some();
+        // SAFETY: Same as above
+        let new_is_inner = unsafe { read_inner_node(new, &mut new_children) }.is_some();
+
+        let with_depth = |children: Vec<(u8, OpaqueNodePtr<K, V, PREFIX_LEN>)>| {
+            children
+                .into_iter()
+                .map(|(key_byte, child)| (key_byte, child, child_depth))
+                .collect::<Vec<_>>()
+        };
+
+        match (old_byte, new_byte) {
+            (None, None) if !old_is_inner && !new_is_inner => {
+                let (Some(old), Some(new)) =
+                    (old.cast::<LeafNode<K, V>>(), new.cast::<LeafNode<K, V>>())
+                else {
+                    unreachable!("both nodes should be leaves");
+                };
+                // SAFETY: Since `Self` holds shared references to both trees, there can
+                // be no mutation of the leaves during the lifetime `'a`.
+                let (key, old) = unsafe { old.as_key_value_ref() };
+                // SAFETY: Same as above
+                let new = unsafe { new.as_value_ref() };
+
+                (old != new).then_some(DiffItem::Changed { key, old, new })
+            },
+            (None, None) if old_is_inner && new_is_inner => {
+                self.push_children(
+                    &with_depth(old_children),
+                    &with_depth(new_children),
+                    child_depth,
+                );
+                None
+            },
+            (None, Some(new_byte)) if old_is_inner => {
+                // The new node continues below one of the children of the old node
+                self.push_children(
+                    &with_depth(old_children),
+                    &[(new_byte, new, new_depth)],
+                    child_depth,
+                );
+                None
+            },
+            (Some(old_byte), None) if new_is_inner => {
+                // The old node continues below one of the children of the new node
+                self.push_children(
+                    &[(old_byte, old, old_depth)],
+                    &with_depth(new_children),
+                    child_depth,
+                );
+                None
+            },
+            _ => {
+                // The subtrees have no keys in common. A path that ends first
+                // (`None`) belongs to a key that is a prefix of the other keys, and
+                // sorts first.
+                let old_first = match old_byte.cmp(&new_byte) {
+                    Ordering::Equal => !old_is_inner,
+                    ordering => ordering.is_lt(),
+                };
+                if old_first {
+                    self.stack.push(DiffNode::Added(new));
+                    self.stack.push(DiffNode::Removed(old));
+                } else {
+                    self.stack.push(DiffNode::Removed(old));
+                    self.stack.push(DiffNode::Added(new));
+                }
+                None
+            },
+        }
+    }
+
+    /// Push the children of the given node onto the stack, or return the leaf
+    /// contents if it is a leaf.
+    fn expand(
+        &mut self,
+        node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        wrap: fn(OpaqueNodePtr<K, V, PREFIX_LEN>) -> DiffNode<K, V, PREFIX_LEN>,
+    ) -> Option<(&'a K, &'a V)> {
+        let mut children = Vec::new();
+        // SAFETY: Since `Self` holds shared references to both trees, there can be
+        // no mutation of any node while the header is alive.
+        match unsafe { read_inner_node(node, &mut children) } {
+            Some(_) => {
+                self.stack
+                    .extend(children.into_iter().rev().map(|(_, child)| wrap(child)));
+                None
+            },
+            None => {
+                let leaf = node
+                    .cast::<LeafNode<K, V>>()
+                    .expect("node should be a leaf");
+                // SAFETY: Since `Self` holds shared references to both trees, there can
+                // be no mutation of the leaf during the lifetime `'a`.
+                Some(unsafe { leaf.as_key_value_ref() })
+            },
+        }
+    }
+}
+
+impl<'a, K: AsBytes, V: PartialEq, const PREFIX_LEN: usize> Iterator
+    for Diff<'a, K, V, PREFIX_LEN>
+{
+    type Item = DiffItem<'a, K, V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(node) = self.stack.pop() {
+            let item = match node {
+                DiffNode::Both { old, new, depth } => self.compare(old, new, depth),
+                DiffNode::Removed(node) => self
+                    .expand(node, DiffNode::Removed)
+                    .map(|(key, value)| DiffItem::Removed(key, value)),
+                DiffNode::Added(node) => self
+                    .expand(node, DiffNode::Added)
+                    .map(|(key, value)| DiffItem::Added(key, value)),
+            };
+
+            if item.is_some() {
+                return item;
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a, K: AsBytes, V: PartialEq, const PREFIX_LEN: usize> FusedIterator
+    for Diff<'a, K, V, PREFIX_LEN>
+{
+}
+
+/// Check if the two trees contain the same entries.
+///
+/// The trees are compared structurally, node by node, instead of through their
+/// iterators: the prefixes and child key bytes of the inner nodes are compared
+/// before descending to the leaves, and nodes that are the same in both trees
+/// are skipped.
+///
+/// The types of the inner nodes are not compared, since they depend on the
+/// history of each map and not only on its contents: a node with the same
+/// children can be of a different type under another [`ShrinkPolicy`], or
+/// before and after [`TreeMap::compact`]. The prefixes and child key bytes
+/// only depend on the keys, so two trees with the same entries always compare
+/// equal.
+///
+/// [`ShrinkPolicy`]: crate::ShrinkPolicy
+pub(crate) fn trees_equal<K, V, const PREFIX_LEN: usize>(
+    left: &TreeMap<K, V, PREFIX_LEN>,
+    right: &TreeMap<K, V, PREFIX_LEN>,
+) -> bool
+where
+    K: PartialEq,
+    V: PartialEq,
+{
+    let (left, right) = match (left.root, right.root) {
+        (Some(left), Some(right)) => (left, right),
+        (None, None) => return true,
+        _ => return false,
+    };
+
+    let mut stack = Vec::from([(left, right)]);
+    let mut left_children = Vec::new();
+    let mut right_children = Vec::new();
+
+    while let Some((left, right)) = stack.pop() {
+        if left == right {
+            continue;
+        }
+
+        left_children.clear();
+        right_children.clear();
+        // SAFETY: We hold shared references to both trees, so there can be no
+        // mutation of any node while the headers are alive.
+        let left_header = unsafe { read_inner_node(left, &mut left_children) };
+        // SAFETY: Same as above
+        let right_header = unsafe { read_inner_node(right, &mut right_children) };
+
+        match (left_header, right_header) {
+            (Some(left_header), Some(right_header)) => {
+                // Inner nodes may be of different types for the same children, so
+                // only the header contents are compared.
+                if left_header.prefix_len() != right_header.prefix_len()
+                    || left_header.read_prefix() != right_header.read_prefix()
+                    || left_children.len() != right_children.len()
+                    || left_children
+                        .iter()
+                        .zip(&right_children)
+                        .any(|(l, r)| l.0 != r.0)
+                {
+                    return false;
+                }
+
+                stack.extend(
+                    left_children
+                        .iter()
+                        .zip(&right_children)
+                        .map(|(l, r)| (l.1, r.1)),
+                );
+            },
+            (None, None) => {
+                let (Some(left), Some(right)) = (
+                    left.cast::<LeafNode<K, V>>(),
+                    right.cast::<LeafNode<K, V>>(),
+                ) else {
+                    unreachable!("both nodes should be leaves");
+                };
+                // SAFETY: We hold shared references to both trees, so there can be no
+                // mutation of the leaves while the references are alive.
+                let (left, right) = unsafe { (left.as_key_value_ref(), right.as_key_value_ref()) };
+                if left != right {
+                    return false;
+                }
+            },
+            _ => return false,
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests_common::{generate_key_with_prefix, PrefixExpansion};
+    use alloc::{boxed::Box, collections::BTreeMap};
+
+    /// Compute the expected diff between two maps using their iterators.
+    fn expected_diff<'a, K: Ord, V: PartialEq>(
+        old: &'a BTreeMap<K, V>,
+        new: &'a BTreeMap<K, V>,
+    ) -> Vec<DiffItem<'a, K, V>> {
+        let mut items: Vec<_> = old
+            .iter()
+            .filter_map(|(key, old)| match new.get(key) {
+                Some(new) if old != new => Some(DiffItem::Changed { key, old, new }),
+                Some(_) => None,
+                None => Some(DiffItem::Removed(key, old)),
+            })
+            .chain(
+                new.iter()
+                    .filter(|(key, _)| !old.contains_key(*key))
+                    .map(|(key, value)| DiffItem::Added(key, value)),
+            )
+            .collect();
+        items.sort_by(|a, b| a.key().cmp(b.key()));
+        items
+    }
+
+    fn check_diff<const PREFIX_LEN: usize>(
+        old: &BTreeMap<Box<[u8]>, u32>,
+        new: &BTreeMap<Box<[u8]>, u32>,
+    ) {
+        let build = |entries: &BTreeMap<Box<[u8]>, u32>| {
+            let mut tree = TreeMap::<_, _, PREFIX_LEN>::with_prefix_len();
+            for (key, value) in entries {
+                tree.try_insert(key.clone(), *value).unwrap();
+            }
+            tree
+        };
+        let old_tree = build(old);
+        let new_tree = build(new);
+
+        let expected = expected_diff(old, new);
+        let actual: Vec<_> = old_tree.diff(&new_tree).collect();
+        assert_eq!(actual, expected);
+        assert_eq!(old_tree == new_tree, expected.is_empty());
+        assert_eq!(new_tree == old_tree, expected.is_empty());
+    }
+
+    fn keys() -> Vec<Box<[u8]>> {
+        generate_key_with_prefix(
+            [3, 4, 3],
+            [
+                PrefixExpansion {
+                    base_index: 1,
+                    expanded_length: 6,
+                },
+                PrefixExpansion {
+                    base_index: 2,
+                    expanded_length: 3,
+                },
+            ],
+        )
+        .collect()
+    }
+
+    #[test]
+    fn diff_matches_iterator_comparison() {
+        let keys = keys();
+        let cases: [(fn(usize) -> bool, fn(usize) -> bool); 5] = [
+            (|_| true, |_| true),
+            (|idx| idx % 2 == 0, |idx| idx % 3 == 0),
+            (|idx| idx < 20, |idx| idx >= 20),
+            (|_| true, |_| false),
+            (|idx| idx % 5 != 1, |idx| idx % 5 != 2),
+        ];
+
+        for (old_filter, new_filter) in cases {
+            let select = |filter: fn(usize) -> bool, offset: u32| {
+                keys.iter()
+                    .enumerate()
+                    .filter(|(idx, _)| filter(*idx))
+                    .map(|(idx, key)| (key.clone(), idx as u32 % 7 + offset))
+                    .collect::<BTreeMap<_, _>>()
+            };
+
+            let old = select(old_filter, 0);
+            check_diff::<16>(&old, &select(new_filter, 0));
+            check_diff::<16>(&old, &select(new_filter, 1));
+            check_diff::<2>(&old, &select(new_filter, 0));
+            check_diff::<2>(&old, &select(new_filter, 1));
+        }
+    }
+
+    #[test]
+    fn diff_with_prefix_keys() {
+        let old: BTreeMap<Box<[u8]>, u32> =
+            [(Box::from([1, 2, 3]), 0), (Box::from([1, 2, 4, 5]), 1)].into();
+        let new: BTreeMap<Box<[u8]>, u32> = [
+            (Box::from([1, 2]), 2),
+            (Box::from([1, 3, 5]), 3),
+            (Box::from([0]), 4),
+        ]
+        .into();
+
+        check_diff::<16>(&old, &new);
+        check_diff::<16>(&new, &old);
+        check_diff::<1>(&old, &new);
+    }
+
+    #[test]
+    fn diff_same_tree() {
+        let mut tree = TreeMap::new();
+        for (key, value) in keys().into_iter().zip(0u32..) {
+            tree.try_insert(key, value).unwrap();
+        }
+        assert_eq!(tree.diff(&tree).next(), None);
+        assert!(trees_equal(&tree, &tree));
+
+        let empty = TreeMap::new();
+        assert_eq!(
+            tree.diff(&empty).count(),
+            tree.len(),
+            "all entries should be removed"
+        );
+        assert!(empty
+            .diff(&tree)
+            .all(|item| matches!(item, DiffItem::Added(..))));
+    }
+
+    #[test]
+    fn trees_equal_ignores_node_types() {
+        let keys = keys();
+
+        // Grow inner nodes past the size of their children, by inserting extra
+        // keys and removing them again without shrinking
+        let mut lazy = TreeMap::new();
+        lazy.set_shrink_policy(crate::ShrinkPolicy::Lazy);
+        for (key, value) in keys.iter().cloned().zip(0u32..) {
+            lazy.try_insert(key, value).unwrap();
+        }
+        let extra: Vec<Box<[u8]>> = (0..=255u8).map(|byte| Box::from([200, byte])).collect();
+        for key in &extra {
+            lazy.try_insert(key.clone(), 0).unwrap();
+        }
+        for key in &extra {
+            lazy.remove(key).unwrap();
+        }
+
+        let mut eager = TreeMap::new();
+        for (key, value) in keys.into_iter().zip(0u32..) {
+            eager.try_insert(key, value).unwrap();
+        }
+
+        assert!(trees_equal(&lazy, &eager));
+        assert!(lazy.compact() > 0);
+        assert!(trees_equal(&lazy, &eager));
+        assert!(trees_equal(&eager, &lazy));
+    }
+}
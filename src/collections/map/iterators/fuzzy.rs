@@ -3,7 +3,8 @@ use crate::{
     AsBytes, ConcreteNodePtr, InnerNode, InnerNode256, InnerNode48, InnerNodeCompressed, LeafNode,
     OpaqueNodePtr, TreeMap,
 };
-use std::{iter::FusedIterator, mem::MaybeUninit};
+use alloc::{boxed::Box, vec::Vec};
+use core::{iter::FusedIterator, mem::MaybeUninit};
 
 struct StackArena {
     data: Vec<MaybeUninit<usize>>,
@@ -73,7 +74,7 @@ impl StackArena {
         // expect after the `Self::push` call that the returned buffer is
         // filled with initialized data, them is safe to transmute here
         Some(unsafe {
-            std::mem::transmute::<&mut &mut [std::mem::MaybeUninit<usize>], &mut &mut [usize]>(
+            core::mem::transmute::<&mut &mut [core::mem::MaybeUninit<usize>], &mut &mut [usize]>(
                 buffer,
             )
         })
@@ -145,9 +146,9 @@ fn edit_dist(
 unsafe fn swap(old_row: &mut &mut [usize], new_row: &mut &mut [MaybeUninit<usize>]) {
     // SAFETY: It's safe to transmute initialized data to uninitialized
     let temp = unsafe {
-        std::mem::transmute::<&mut &mut [usize], &mut &mut [std::mem::MaybeUninit<usize>]>(old_row)
+        core::mem::transmute::<&mut &mut [usize], &mut &mut [MaybeUninit<usize>]>(old_row)
     };
-    std::mem::swap(temp, new_row);
+    core::mem::swap(temp, new_row);
 }
 
 trait FuzzySearch<K: AsBytes, V, const PREFIX_LEN: usize> {
@@ -383,6 +384,19 @@ macro_rules! gen_iter {
                                 self.max_edit_dist,
                             );
                         },
+                        ConcreteNodePtr::Node32(inner_ptr) => {
+                            // SAFETY: Since `Self` holds a mutable/shared reference
+                            // is safe to create a shared reference from it
+                            let inner_node = unsafe { inner_ptr.as_ref() };
+                            inner_node.fuzzy_search(
+                                &mut self.arena,
+                                self.key,
+                                old_row,
+                                &mut new_row,
+                                &mut self.nodes_to_search,
+                                self.max_edit_dist,
+                            );
+                        },
                         ConcreteNodePtr::Node48(inner_ptr) => {
                             // SAFETY: Since `Self` holds a mutable/shared reference
                             // is safe to create a shared reference from it
@@ -482,7 +496,8 @@ gen_iter!(
 
 #[cfg(test)]
 mod tests {
-    use std::ffi::CString;
+    use alloc::ffi::CString;
+    use alloc::{format, vec::Vec};
 
     use crate::TreeMap;
 
@@ -0,0 +1,100 @@
+use crate::{
+    metrics::MetricsHandle, search_batch_unchecked, AsBytes, LeafNode, NodePtr, OpaqueNodePtr,
+    TreeMap, SEARCH_BATCH_LEN,
+};
+use core::{borrow::Borrow, marker::PhantomData};
+
+/// An iterator over the values of a sequence of keys in a `TreeMap`, which
+/// searches for several keys at the same time.
+///
+/// This `struct` is created by the [`get_many_iter`] method on `TreeMap`.
+/// See its documentation for more.
+///
+/// [`get_many_iter`]: TreeMap::get_many_iter
+pub struct GetMany<'a, 'k, K, V, Q: ?Sized, I, const PREFIX_LEN: usize> {
+    root: Option<OpaqueNodePtr<K, V, PREFIX_LEN>>,
+    metrics: MetricsHandle,
+    keys: I,
+    /// The results of the last batch of searches
+    results: [Option<NodePtr<PREFIX_LEN, LeafNode<K, V>>>; SEARCH_BATCH_LEN],
+    /// The index of the next result to return
+    next_result: usize,
+    /// The number of keys in the last batch of searches
+    num_results: usize,
+    marker: PhantomData<(&'a TreeMap<K, V, PREFIX_LEN>, &'k Q)>,
+}
+
+impl<'a, 'k, K, V, Q: ?Sized, I, const PREFIX_LEN: usize> GetMany<'a, 'k, K, V, Q, I, PREFIX_LEN> {
+    /// Create a new iterator over the values of `keys` in the given tree.
+    pub(crate) fn new(tree: &'a TreeMap<K, V, PREFIX_LEN>, keys: I) -> Self {
+        Self {
+            root: tree.root,
+            metrics: tree.metrics.handle(),
+            keys,
+            results: [None; SEARCH_BATCH_LEN],
+            next_result: 0,
+            num_results: 0,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, 'k, K, V, Q, I, const PREFIX_LEN: usize> Iterator
+    for GetMany<'a, 'k, K, V, Q, I, PREFIX_LEN>
+where
+    K: Borrow<Q> + AsBytes,
+    Q: AsBytes + ?Sized + 'k,
+    I: Iterator<Item = &'k Q>,
+{
+    type Item = Option<&'a V>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next_result == self.num_results {
+            let mut key_bytes: [&[u8]; SEARCH_BATCH_LEN] = [&[]; SEARCH_BATCH_LEN];
+            let mut num_keys = 0;
+            for (slot, key) in key_bytes.iter_mut().zip(&mut self.keys) {
+                *slot = key.as_bytes();
+                num_keys += 1;
+            }
+
+            if num_keys == 0 {
+                return None;
+            }
+
+            if let Some(root) = self.root {
+                // The iterator borrows the tree, so it cannot outlive the counters
+                let _metrics = self.metrics.scope(num_keys);
+                // SAFETY: The iterator holds a shared reference to the tree for `'a`, so
+                // there can be no concurrent mutation of any node in the tree.
+                unsafe {
+                    search_batch_unchecked(
+                        root,
+                        &key_bytes[..num_keys],
+                        &mut self.results[..num_keys],
+                    )
+                };
+            } else {
+                self.results[..num_keys].fill(None);
+            }
+
+            self.next_result = 0;
+            self.num_results = num_keys;
+        }
+
+        let search_result = self.results[self.next_result];
+        self.next_result += 1;
+
+        // SAFETY: The lifetime of the value reference is bounded by the shared
+        // reference to the tree, which prevents any mutation of the value.
+        Some(search_result.map(|leaf| unsafe { leaf.as_value_ref() }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let buffered = self.num_results - self.next_result;
+        let (lower, upper) = self.keys.size_hint();
+        (
+            lower.saturating_add(buffered),
+            upper.and_then(|upper| upper.checked_add(buffered)),
+        )
+    }
+}
@@ -1,5 +1,6 @@
 use crate::{ConcreteNodePtr, InnerNode, NodePtr, OpaqueNodePtr, TreeMap};
-use std::{collections::VecDeque, iter::FusedIterator};
+use alloc::collections::VecDeque;
+use core::iter::FusedIterator;
 
 macro_rules! gen_iter {
     ($name:ident, $tree:ty, $ret:ty, $op:ident) => {
@@ -59,6 +60,7 @@ macro_rules! gen_iter {
                     match node.to_node_ptr() {
                         ConcreteNodePtr::Node4(inner) => self.push_back_rev_iter(inner),
                         ConcreteNodePtr::Node16(inner) => self.push_back_rev_iter(inner),
+                        ConcreteNodePtr::Node32(inner) => self.push_back_rev_iter(inner),
                         ConcreteNodePtr::Node48(inner) => self.push_back_rev_iter(inner),
                         ConcreteNodePtr::Node256(inner) => self.push_back_rev_iter(inner),
                         ConcreteNodePtr::LeafNode(inner) => {
@@ -91,6 +93,7 @@ macro_rules! gen_iter {
                     match node.to_node_ptr() {
                         ConcreteNodePtr::Node4(inner) => self.push_front(inner),
                         ConcreteNodePtr::Node16(inner) => self.push_front(inner),
+                        ConcreteNodePtr::Node32(inner) => self.push_front(inner),
                         ConcreteNodePtr::Node48(inner) => self.push_front(inner),
                         ConcreteNodePtr::Node256(inner) => self.push_front(inner),
                         ConcreteNodePtr::LeafNode(inner) => {
@@ -152,6 +155,7 @@ gen_iter!(
 #[cfg(test)]
 mod tests {
     use crate::{tests_common::generate_key_fixed_length, TreeMap};
+    use alloc::{boxed::Box, vec, vec::Vec};
 
     #[test]
     fn small_tree_iterator_front_and_back() {
@@ -1,5 +1,6 @@
 use crate::{AsBytes, ConcreteNodePtr, InnerNode, LeafNode, NodePtr, OpaqueNodePtr, TreeMap};
-use std::{collections::VecDeque, iter::FusedIterator};
+use alloc::collections::VecDeque;
+use core::iter::FusedIterator;
 
 macro_rules! gen_add_children {
     ($name:ident, $f1:ident, $f2:ident) => {
@@ -156,6 +157,7 @@ macro_rules! gen_iter {
                     match node.to_node_ptr() {
                         ConcreteNodePtr::Node4(inner) => self.add_children(inner, current_depth),
                         ConcreteNodePtr::Node16(inner) => self.add_children(inner, current_depth),
+                        ConcreteNodePtr::Node32(inner) => self.add_children(inner, current_depth),
                         ConcreteNodePtr::Node48(inner) => self.add_children(inner, current_depth),
                         ConcreteNodePtr::Node256(inner) => self.add_children(inner, current_depth),
                         ConcreteNodePtr::LeafNode(inner) => {
@@ -192,6 +194,9 @@ macro_rules! gen_iter {
                         ConcreteNodePtr::Node16(inner) => {
                             self.add_children_rev(inner, current_depth)
                         },
+                        ConcreteNodePtr::Node32(inner) => {
+                            self.add_children_rev(inner, current_depth)
+                        },
                         ConcreteNodePtr::Node48(inner) => {
                             self.add_children_rev(inner, current_depth)
                         },
@@ -259,6 +264,7 @@ gen_iter!(
 #[cfg(test)]
 mod tests {
     use crate::TreeMap;
+    use alloc::{vec, vec::Vec};
 
     #[test]
     fn prefix() {
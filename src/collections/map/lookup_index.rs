@@ -0,0 +1,543 @@
+//! An optional index from fixed length key prefixes to the subtree containing
+//! the keys with that prefix, used to skip the top levels of the tree on
+//! lookups.
+
+use crate::{
+    minimum_unchecked, search_unchecked_from, visitor::LookupIndexStats, AsBytes, ConcreteNodePtr,
+    InnerNode, LeafNode, NodePtr, OpaqueNodePtr,
+};
+use alloc::vec::Vec;
+use std::{
+    collections::HashMap,
+    hash::{BuildHasherDefault, Hasher},
+};
+
+/// The longest key prefix that can be indexed, so that the prefixes can be
+/// packed into a `u32`.
+pub(crate) const MAX_LOOKUP_INDEX_DEPTH: usize = 4;
+
+/// Where to continue a lookup for the keys with a given prefix.
+struct IndexEntry<K, V, const PREFIX_LEN: usize> {
+    /// The first node on the path of the prefix which is either a leaf, or an
+    /// inner node whose prefix reaches past the indexed bytes.
+    node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    /// The depth of the key byte that picks a child of `node`.
+    ///
+    /// Unlike the depth that `node` starts at, this does not change when
+    /// `node` is split by an insert or absorbs its parent during a delete.
+    /// It is always 0 for leaves, which start at a varying depth.
+    child_depth: usize,
+}
+
+/// A [`Hasher`] for packed prefixes, which only needs to spread the bits of a
+/// single `u32` across the hash.
+#[derive(Default)]
+struct PrefixHasher(u64);
+
+impl PrefixHasher {
+    /// 2^64 divided by the golden ratio, as used by Fibonacci hashing.
+    const MULTIPLIER: u64 = 0x9E37_79B9_7F4A_7C15;
+}
+
+impl Hasher for PrefixHasher {
+    fn finish(&self) -> u64 {
+        // The low bits of the product only depend on the low bits of the prefix,
+        // so mix the high bits back in.
+        self.0 ^ (self.0 >> 32)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0.rotate_left(8) ^ u64::from(byte)).wrapping_mul(Self::MULTIPLIER);
+        }
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.0 = u64::from(n).wrapping_mul(Self::MULTIPLIER);
+    }
+}
+
+/// Pack up to [`MAX_LOOKUP_INDEX_DEPTH`] bytes into a `u32`, with the first
+/// byte being the most significant one.
+fn pack(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |packed, &byte| (packed << 8) | u32::from(byte))
+}
+
+/// An index from the first `depth` bytes of the keys in a tree to the subtree
+/// containing all the keys starting with those bytes.
+///
+/// There is an entry for exactly the prefixes present in the tree, so a key
+/// with a prefix missing from the index is not in the tree. Since all keys
+/// below an entry node share its prefix, no node can be the entry for two
+/// prefixes.
+pub(crate) struct LookupIndex<K, V, const PREFIX_LEN: usize> {
+    /// The number of key bytes used as the index key.
+    depth: usize,
+    /// The entries, indexed by the packed key prefix.
+    entries: HashMap<u32, IndexEntry<K, V, PREFIX_LEN>, BuildHasherDefault<PrefixHasher>>,
+    /// [`AsBytes::as_bytes`] for the key type, so that the index can be
+    /// updated by the map operations which do not require it.
+    key_bytes: fn(&K) -> &[u8],
+}
+
+impl<K, V, const PREFIX_LEN: usize> LookupIndex<K, V, PREFIX_LEN> {
+    /// Build an index of the first `depth` bytes of all the keys in the tree
+    /// with the given root.
+    ///
+    /// # Panics
+    ///  - If `depth` is 0 or more than [`MAX_LOOKUP_INDEX_DEPTH`].
+    ///
+    /// # Safety
+    ///  - This function cannot be called concurrently with any mutating
+    ///    operation on `root` or any child node of `root`.
+    pub(crate) unsafe fn build(root: Option<OpaqueNodePtr<K, V, PREFIX_LEN>>, depth: usize) -> Self
+    where
+        K: AsBytes,
+    {
+        assert!(
+            (1..=MAX_LOOKUP_INDEX_DEPTH).contains(&depth),
+            "lookup index depth must be between 1 and {MAX_LOOKUP_INDEX_DEPTH}, got {depth}"
+        );
+
+        let mut index = LookupIndex {
+            depth,
+            entries: HashMap::default(),
+            key_bytes: K::as_bytes,
+        };
+
+        if let Some(root) = root {
+            let mut path = Vec::with_capacity(depth);
+            // SAFETY: Covered by the safety requirement on the containing function
+            unsafe { index.insert_subtree(root, 0, &mut path) };
+        }
+
+        index
+    }
+
+    /// Return the number of key bytes used as the index key.
+    pub(crate) fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Return the number of entries and an estimate of the memory used by the
+    /// index.
+    pub(crate) fn stats(&self) -> LookupIndexStats {
+        LookupIndexStats {
+            depth: self.depth,
+            count: self.entries.len(),
+            // Every bucket of the table holds an entry and a control byte.
+            mem_usage: self.entries.capacity()
+                * (core::mem::size_of::<(u32, IndexEntry<K, V, PREFIX_LEN>)>() + 1),
+        }
+    }
+
+    /// Remove all entries, after the tree was cleared.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Insert the entries for all the prefixes in the subtree rooted at
+    /// `node`, which starts at `path.len()` bytes.
+    ///
+    /// # Safety
+    ///  - This function cannot be called concurrently with any mutating
+    ///    operation on `node` or any child node of `node`.
+    unsafe fn insert_subtree(
+        &mut self,
+        node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        depth: usize,
+        path: &mut Vec<u8>,
+    ) where
+        K: AsBytes,
+    {
+        // SAFETY: Covered by the safety requirement on the containing function
+        unsafe {
+            match node.to_node_ptr() {
+                ConcreteNodePtr::Node4(inner_ptr) => {
+                    self.insert_inner_subtree(node, inner_ptr.as_ref(), depth, path)
+                },
+                ConcreteNodePtr::Node16(inner_ptr) => {
+                    self.insert_inner_subtree(node, inner_ptr.as_ref(), depth, path)
+                },
+                ConcreteNodePtr::Node32(inner_ptr) => {
+                    self.insert_inner_subtree(node, inner_ptr.as_ref(), depth, path)
+                },
+                ConcreteNodePtr::Node48(inner_ptr) => {
+                    self.insert_inner_subtree(node, inner_ptr.as_ref(), depth, path)
+                },
+                ConcreteNodePtr::Node256(inner_ptr) => {
+                    self.insert_inner_subtree(node, inner_ptr.as_ref(), depth, path)
+                },
+                ConcreteNodePtr::LeafNode(leaf_ptr) => {
+                    let key_bytes = leaf_ptr.as_ref().key_ref().as_bytes();
+                    if key_bytes.len() >= self.depth {
+                        self.entries.insert(
+                            pack(&key_bytes[..self.depth]),
+                            IndexEntry {
+                                node,
+                                child_depth: 0,
+                            },
+                        );
+                    }
+                },
+            }
+        }
+    }
+
+    /// Same as [`LookupIndex::insert_subtree`], for inner nodes.
+    ///
+    /// # Safety
+    ///  - This function cannot be called concurrently with any mutating
+    ///    operation on `node` or any child node of `node`.
+    unsafe fn insert_inner_subtree<N>(
+        &mut self,
+        node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        inner_node: &N,
+        depth: usize,
+        path: &mut Vec<u8>,
+    ) where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+        K: AsBytes,
+    {
+        let (prefix, _) = inner_node.read_full_prefix(depth);
+        let child_depth = depth + prefix.len();
+
+        if child_depth >= self.depth {
+            path.extend_from_slice(&prefix[..(self.depth - depth)]);
+            self.entries
+                .insert(pack(path), IndexEntry { node, child_depth });
+        } else {
+            path.extend_from_slice(prefix);
+            for (key_byte, child) in inner_node.iter() {
+                path.push(key_byte);
+                // SAFETY: Covered by the safety requirement on the containing function
+                unsafe { self.insert_subtree(child, child_depth + 1, path) };
+                path.pop();
+            }
+        }
+
+        path.truncate(depth);
+    }
+
+    /// Update the entry for the prefix of `key`, after `key` was inserted into
+    /// or removed from the tree.
+    ///
+    /// Inserting or removing a key only changes the nodes on the path of that
+    /// key. The only one of those which can be the entry for another prefix
+    /// is an inner node split by the insert, and it keeps its child depth.
+    ///
+    /// # Safety
+    ///  - This function cannot be called concurrently with any mutating
+    ///    operation on `root` or any child node of `root`.
+    pub(crate) unsafe fn refresh(
+        &mut self,
+        root: Option<OpaqueNodePtr<K, V, PREFIX_LEN>>,
+        key: &K,
+    ) {
+        let key_bytes = (self.key_bytes)(key);
+        if key_bytes.len() < self.depth {
+            return;
+        }
+
+        // SAFETY: Covered by the safety requirement on the containing function
+        match root.and_then(|root| unsafe { self.find_entry(root, key_bytes) }) {
+            Some(entry) => self.entries.insert(pack(&key_bytes[..self.depth]), entry),
+            None => self.entries.remove(&pack(&key_bytes[..self.depth])),
+        };
+    }
+
+    /// Update the entries for the keys starting with `prefix`, after they were
+    /// removed from the tree.
+    ///
+    /// # Safety
+    ///  - This function cannot be called concurrently with any mutating
+    ///    operation on `root` or any child node of `root`.
+    pub(crate) unsafe fn remove_prefix(
+        &mut self,
+        root: Option<OpaqueNodePtr<K, V, PREFIX_LEN>>,
+        prefix: &[u8],
+    ) {
+        if prefix.len() >= self.depth {
+            let key_bytes = &prefix[..self.depth];
+            // SAFETY: Covered by the safety requirement on the containing function
+            match root.and_then(|root| unsafe { self.find_entry(root, key_bytes) }) {
+                Some(entry) => self.entries.insert(pack(key_bytes), entry),
+                None => self.entries.remove(&pack(key_bytes)),
+            };
+        } else {
+            let shift = 8 * (self.depth - prefix.len()) as u32;
+            let packed_prefix = pack(prefix);
+            self.entries
+                .retain(|&packed, _| packed.checked_shr(shift).unwrap_or(0) != packed_prefix);
+        }
+    }
+
+    /// Find the entry for the first `depth` bytes of `key_bytes`, by following
+    /// them from the root.
+    ///
+    /// Returns `None` if there is no key with that prefix in the tree.
+    ///
+    /// # Safety
+    ///  - This function cannot be called concurrently with any mutating
+    ///    operation on `root` or any child node of `root`.
+    unsafe fn find_entry(
+        &self,
+        root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        key_bytes: &[u8],
+    ) -> Option<IndexEntry<K, V, PREFIX_LEN>> {
+        let mut node = root;
+        let mut depth = 0;
+
+        let entry = loop {
+            // SAFETY: Covered by the safety requirement on the containing function
+            let Some(prefix_len) = (unsafe { prefix_len(node) }) else {
+                break IndexEntry {
+                    node,
+                    child_depth: 0,
+                };
+            };
+
+            let child_depth = depth + prefix_len;
+            if child_depth >= self.depth {
+                break IndexEntry { node, child_depth };
+            }
+
+            // SAFETY: Covered by the safety requirement on the containing function
+            node = unsafe { lookup_child(node, key_bytes[child_depth]) }?;
+            depth = child_depth + 1;
+        };
+
+        // The inner node prefixes were skipped on the way down, so check them
+        // against any key below the entry node.
+        // SAFETY: Covered by the safety requirement on the containing function
+        let leaf = unsafe { minimum_unchecked(entry.node).as_ref() };
+        (self.key_bytes)(leaf.key_ref())
+            .starts_with(&key_bytes[..self.depth])
+            .then_some(entry)
+    }
+
+    /// Search for the leaf with the given key, starting from the entry for its
+    /// prefix.
+    ///
+    /// Returns `None` if the key is shorter than the indexed prefix, so that
+    /// the index cannot be used.
+    ///
+    /// # Safety
+    ///  - This function cannot be called concurrently with any mutating
+    ///    operation on the tree this index was built for.
+    pub(crate) unsafe fn search(
+        &self,
+        key_bytes: &[u8],
+    ) -> Option<Option<NodePtr<PREFIX_LEN, LeafNode<K, V>>>>
+    where
+        K: AsBytes,
+    {
+        let prefix = key_bytes.get(..self.depth)?;
+        let Some(entry) = self.entries.get(&pack(prefix)) else {
+            // Every prefix in the tree has an entry, so the key cannot be present
+            return Some(None);
+        };
+
+        if let ConcreteNodePtr::LeafNode(leaf_ptr) = entry.node.to_node_ptr() {
+            // SAFETY: Covered by the safety requirement on the containing function
+            let leaf = unsafe { leaf_ptr.as_ref() };
+            return Some(leaf.matches_full_key(key_bytes).then_some(leaf_ptr));
+        }
+
+        // The indexed bytes of the entry node prefix are known to match, and the
+        // rest are checked against the key of the leaf at the end of the search.
+        // So the prefix of the entry node can be skipped entirely.
+        let Some(&key_byte) = key_bytes.get(entry.child_depth) else {
+            return Some(None);
+        };
+        // SAFETY: Covered by the safety requirement on the containing function
+        let Some(child) = (unsafe { lookup_child(entry.node, key_byte) }) else {
+            return Some(None);
+        };
+
+        // SAFETY: Covered by the safety requirement on the containing function
+        Some(unsafe { search_unchecked_from(child, entry.child_depth + 1, key_bytes) })
+    }
+}
+
+/// Return the full prefix length of the given node, or `None` if it is a leaf.
+///
+/// # Safety
+///  - No mutation of `node` can happen while this function runs.
+unsafe fn prefix_len<K, V, const PREFIX_LEN: usize>(
+    node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+) -> Option<usize> {
+    // SAFETY: Covered by the safety requirement on the containing function
+    unsafe {
+        match node.to_node_ptr() {
+            ConcreteNodePtr::Node4(inner_ptr) => Some(inner_ptr.as_ref().header().prefix_len()),
+            ConcreteNodePtr::Node16(inner_ptr) => Some(inner_ptr.as_ref().header().prefix_len()),
+            ConcreteNodePtr::Node32(inner_ptr) => Some(inner_ptr.as_ref().header().prefix_len()),
+            ConcreteNodePtr::Node48(inner_ptr) => Some(inner_ptr.as_ref().header().prefix_len()),
+            ConcreteNodePtr::Node256(inner_ptr) => Some(inner_ptr.as_ref().header().prefix_len()),
+            ConcreteNodePtr::LeafNode(_) => None,
+        }
+    }
+}
+
+/// Return the child of the given inner node for the key byte, or `None` if
+/// there is no such child or `node` is a leaf.
+///
+/// # Safety
+///  - No mutation of `node` can happen while this function runs.
+unsafe fn lookup_child<K, V, const PREFIX_LEN: usize>(
+    node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    key_byte: u8,
+) -> Option<OpaqueNodePtr<K, V, PREFIX_LEN>> {
+    // SAFETY: Covered by the safety requirement on the containing function
+    unsafe {
+        match node.to_node_ptr() {
+            ConcreteNodePtr::Node4(inner_ptr) => inner_ptr.as_ref().lookup_child(key_byte),
+            ConcreteNodePtr::Node16(inner_ptr) => inner_ptr.as_ref().lookup_child(key_byte),
+            ConcreteNodePtr::Node32(inner_ptr) => inner_ptr.as_ref().lookup_child(key_byte),
+            ConcreteNodePtr::Node48(inner_ptr) => inner_ptr.as_ref().lookup_child(key_byte),
+            ConcreteNodePtr::Node256(inner_ptr) => inner_ptr.as_ref().lookup_child(key_byte),
+            ConcreteNodePtr::LeafNode(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TreeMap;
+    use alloc::{boxed::Box, collections::BTreeMap};
+
+    /// Check that the index of the map has the same entries as a freshly built
+    /// index.
+    fn assert_index_up_to_date<const PREFIX_LEN: usize>(map: &TreeMap<Box<[u8]>, u32, PREFIX_LEN>) {
+        let index = map.lookup_index.as_ref().unwrap();
+        let fresh = unsafe { LookupIndex::build(map.root, index.depth()) };
+        for (packed, entry) in &fresh.entries {
+            let Some(existing) = index.entries.get(packed) else {
+                panic!("missing entry for prefix {packed:#x}");
+            };
+            assert_eq!(existing.node, entry.node, "prefix {packed:#x}");
+            assert_eq!(
+                existing.child_depth, entry.child_depth,
+                "prefix {packed:#x}"
+            );
+        }
+        assert_eq!(index.entries.len(), fresh.entries.len());
+    }
+
+    /// Generate keys from a small alphabet, so that they share prefixes of
+    /// different lengths and the nodes in the indexed levels of the tree are
+    /// split, grown, shrunk and collapsed.
+    fn random_keys(seed: u64) -> impl Iterator<Item = Box<[u8]>> {
+        let mut state = seed;
+        core::iter::repeat_with(move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let len = 1 + (state % 7) as usize;
+            (0..len)
+                .map(|idx| ((state >> (8 + 3 * idx)) % 5) as u8)
+                .collect()
+        })
+    }
+
+    fn check_lookup_index<const PREFIX_LEN: usize>(depth: usize) {
+        let mut map = TreeMap::<Box<[u8]>, u32, PREFIX_LEN>::with_prefix_len();
+        let mut oracle = BTreeMap::new();
+        map.enable_lookup_index(depth);
+
+        for (idx, key) in random_keys(0x1234_5678 + depth as u64)
+            .take(2000)
+            .enumerate()
+        {
+            let idx = idx as u32;
+            if idx % 3 == 2 {
+                assert_eq!(map.remove(&key), oracle.remove(&key));
+            } else if map.try_insert(key.clone(), idx).is_ok() {
+                oracle.insert(key, idx);
+            }
+
+            if idx % 50 == 0 {
+                assert_index_up_to_date(&map);
+            }
+        }
+        assert_index_up_to_date(&map);
+
+        for key in random_keys(0x9876_5432).take(500) {
+            assert_eq!(map.get(&key), oracle.get(&key), "{key:?}");
+        }
+        for (key, value) in &oracle {
+            assert_eq!(map.get(key), Some(value), "{key:?}");
+        }
+
+        let clone = map.clone();
+        assert_eq!(clone.lookup_index_depth(), Some(depth));
+        assert_index_up_to_date(&clone);
+
+        for prefix in [&[1, 2, 3][..], &[2, 2], &[3]] {
+            let removed = map.remove_prefix(prefix);
+            assert_index_up_to_date(&map);
+            assert_index_up_to_date(&removed);
+            assert!(removed.iter().all(|(key, _)| key.starts_with(prefix)));
+            assert!(removed
+                .keys()
+                .all(|key| map.get(key).is_none() && removed.get(key).is_some()));
+        }
+
+        map.merge(clone, true, true, |_, left, _| Some(left));
+        assert_index_up_to_date(&map);
+        for (key, value) in &oracle {
+            assert_eq!(map.get(key), Some(value), "{key:?}");
+        }
+
+        while let Some((key, _)) = map.pop_first() {
+            assert_eq!(map.get(&key), None);
+            if map.len() % 20 == 0 {
+                assert_index_up_to_date(&map);
+            }
+        }
+        assert!(map.lookup_index.as_ref().unwrap().entries.is_empty());
+    }
+
+    #[test]
+    fn lookup_index_matches_tree() {
+        check_lookup_index::<16>(1);
+        check_lookup_index::<16>(2);
+        check_lookup_index::<1>(3);
+        check_lookup_index::<4>(4);
+    }
+
+    #[test]
+    fn lookup_index_stats() {
+        let mut map = TreeMap::<[u8; 2], u32>::new();
+        for key in 0..=u16::MAX {
+            map.insert(key.to_be_bytes(), u32::from(key));
+        }
+
+        let stats = crate::visitor::TreeStatsCollector::collect(&map).unwrap();
+        assert_eq!(stats.lookup_index, LookupIndexStats::default());
+
+        map.enable_lookup_index(1);
+        let stats = crate::visitor::TreeStatsCollector::collect(&map).unwrap();
+        assert_eq!(stats.lookup_index.depth, 1);
+        assert_eq!(stats.lookup_index.count, 256);
+        assert!(stats.lookup_index.mem_usage >= 256 * 24);
+        assert_eq!(
+            stats.total_memory_usage(),
+            stats.tree.mem_usage + stats.leaf.mem_usage + stats.lookup_index.mem_usage
+        );
+
+        map.clear();
+        assert!(map.lookup_index.as_ref().unwrap().entries.is_empty());
+        assert_eq!(map.get(&[0, 0]), None);
+    }
+
+    #[test]
+    #[should_panic = "lookup index depth must be between 1 and 4, got 5"]
+    fn lookup_index_depth_too_large() {
+        TreeMap::<u64, u32>::new().enable_lookup_index(5);
+    }
+}
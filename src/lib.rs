@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![cfg_attr(
     feature = "nightly",
     feature(
@@ -40,18 +41,46 @@
 //!    [Link to PDF][ART paper]
 //!
 //! [ART paper]: http://web.archive.org/web/20240508000744/https://db.in.tum.de/~leis/papers/ART.pdf
+//!
+//! # Features
+//!
+//!  - `std` (enabled by default): implements [`AsBytes`] for the OS string and
+//!    path types, enables the [`visitor::DotPrinter`] (which writes to an
+//!    [`std::io::Write`]), the [`TreeMap::enable_lookup_index`] hash index and
+//!    implements [`std::error::Error`] for the error types. Without it the
+//!    crate only depends on `core` and `alloc`.
+//!  - `metrics`: counts the node allocations, resizes, prefix reads from leaves
+//!    and lookup depths of each map, see `TreeMap::metrics`. This requires
+//!    `std`, and without it the counting compiles to nothing.
+//!  - `nightly`: use nightly-only APIs, like portable SIMD, for the inner node
+//!    searches.
+
+extern crate alloc;
+
+// The tests use `std` even without the `std` feature, but only through explicit
+// paths, so that the library code is still checked against a `no_std` prelude
+#[cfg(all(test, not(feature = "std")))]
+extern crate std;
 
 mod bytes;
 mod collections;
+mod metrics;
 mod nodes;
 mod rust_nightly_apis;
 mod tagged_pointer;
 
+#[cfg(any(test, feature = "std"))]
 #[doc(hidden)]
 pub mod tests_common;
 
+#[cfg(any(test, feature = "std"))]
+#[doc(hidden)]
+pub mod tests_model;
+
 pub use bytes::*;
 pub use collections::*;
+#[cfg(feature = "metrics")]
+pub use metrics::{Metrics, NodeAllocations};
 pub use nodes::{visitor, *};
 
 #[doc = include_str!("../README.md")]
@@ -0,0 +1,617 @@
+//! Counters for the work done by the operations on a
+//! [`TreeMap`](crate::TreeMap), enabled by the `metrics` feature.
+//!
+//! The node operations do not know which map they are working on, so they
+//! record events with the `record_*` hooks into counters local to the current
+//! thread. Each map operation opens a [`MetricsScope`], which moves the events
+//! recorded while it was open into the counters of the map when it is
+//! dropped.
+//!
+//! Without the `metrics` feature, the hooks and scopes are empty and the
+//! per-map state is zero-sized, so they compile to nothing.
+
+use crate::NodeType;
+
+#[cfg(feature = "metrics")]
+use alloc::boxed::Box;
+#[cfg(feature = "metrics")]
+use core::{
+    cell::Cell,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicPtr, AtomicUsize, Ordering},
+};
+
+/// The number of [`NodeType`] variants, which are numbered from 0.
+#[cfg(feature = "metrics")]
+const NUM_NODE_TYPES: usize = 6;
+
+/// Record the allocation of a node of the given type.
+#[inline(always)]
+pub(crate) fn record_node_allocation(node_type: NodeType) {
+    #[cfg(feature = "metrics")]
+    update_pending(|pending| pending.node_allocations[node_type as usize] += 1);
+
+    #[cfg(not(feature = "metrics"))]
+    let _ = node_type;
+}
+
+/// Record a read of the prefix of an inner node from one of its leaves,
+/// because the prefix was too long to be stored in the node.
+#[inline(always)]
+pub(crate) fn record_prefix_read() {
+    #[cfg(feature = "metrics")]
+    update_pending(|pending| pending.prefix_reads += 1);
+}
+
+/// Record a node visited by a lookup.
+#[inline(always)]
+pub(crate) fn record_lookup_step() {
+    #[cfg(feature = "metrics")]
+    update_pending(|pending| pending.lookup_steps += 1);
+}
+
+/// The events recorded on the current thread since the innermost open
+/// [`MetricsScope`] was opened.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default)]
+struct PendingCounts {
+    node_allocations: [usize; NUM_NODE_TYPES],
+    prefix_reads: usize,
+    lookup_steps: usize,
+}
+
+#[cfg(feature = "metrics")]
+impl PendingCounts {
+    const ZERO: PendingCounts = PendingCounts {
+        node_allocations: [0; NUM_NODE_TYPES],
+        prefix_reads: 0,
+        lookup_steps: 0,
+    };
+
+    fn is_zero(&self) -> bool {
+        self.node_allocations.iter().all(|count| *count == 0)
+            && self.prefix_reads == 0
+            && self.lookup_steps == 0
+    }
+}
+
+#[cfg(feature = "metrics")]
+std::thread_local! {
+    static PENDING: Cell<PendingCounts> = const { Cell::new(PendingCounts::ZERO) };
+}
+
+#[cfg(feature = "metrics")]
+#[inline(always)]
+fn update_pending(f: impl FnOnce(&mut PendingCounts)) {
+    // If the thread local was already destroyed, the event is dropped
+    let _ = PENDING.try_with(|pending| {
+        let mut counts = pending.get();
+        f(&mut counts);
+        pending.set(counts);
+    });
+}
+
+/// The counters of a single map, which are shared by all of its operations.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Default)]
+struct Counters {
+    node_allocations: [AtomicUsize; NUM_NODE_TYPES],
+    prefix_reads: AtomicUsize,
+    lookups: AtomicUsize,
+    lookup_steps: AtomicUsize,
+}
+
+#[cfg(feature = "metrics")]
+impl Counters {
+    fn add(&self, pending: &PendingCounts, lookups: usize) {
+        for (counter, count) in self.node_allocations.iter().zip(pending.node_allocations) {
+            counter.fetch_add(count, Ordering::Relaxed);
+        }
+        self.prefix_reads
+            .fetch_add(pending.prefix_reads, Ordering::Relaxed);
+        self.lookups.fetch_add(lookups, Ordering::Relaxed);
+        self.lookup_steps
+            .fetch_add(pending.lookup_steps, Ordering::Relaxed);
+    }
+
+    fn copy_from(&self, other: &Counters) {
+        for (counter, other) in self.node_allocations.iter().zip(&other.node_allocations) {
+            counter.store(other.load(Ordering::Relaxed), Ordering::Relaxed);
+        }
+        self.prefix_reads.store(
+            other.prefix_reads.load(Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
+        self.lookups
+            .store(other.lookups.load(Ordering::Relaxed), Ordering::Relaxed);
+        self.lookup_steps.store(
+            other.lookup_steps.load(Ordering::Relaxed),
+            Ordering::Relaxed,
+        );
+    }
+}
+
+/// The operation counters owned by a [`TreeMap`](crate::TreeMap).
+///
+/// The counters are only allocated when the first event is recorded for the
+/// map, so that maps which are never used do not allocate. The pointer is
+/// atomic because the lookups of a map can run on several threads at once.
+pub(crate) struct MapMetrics {
+    #[cfg(feature = "metrics")]
+    counters: AtomicPtr<Counters>,
+}
+
+impl MapMetrics {
+    /// Create a set of counters which are all zero.
+    pub(crate) fn new() -> Self {
+        MapMetrics {
+            #[cfg(feature = "metrics")]
+            counters: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Return a handle to the counters, which can outlive the borrow of the
+    /// map but must not outlive the map itself, and the map must not be
+    /// moved while it exists.
+    #[inline(always)]
+    pub(crate) fn handle(&self) -> MetricsHandle {
+        MetricsHandle {
+            #[cfg(feature = "metrics")]
+            counters: NonNull::from(&self.counters),
+        }
+    }
+
+    /// Open a scope which moves the events recorded until it is dropped into
+    /// these counters.
+    ///
+    /// The returned scope must be dropped before the map.
+    #[inline(always)]
+    pub(crate) fn scope(&self) -> MetricsScope {
+        self.handle().scope(0)
+    }
+
+    /// Like [`MapMetrics::scope`], and also count the given number of
+    /// lookups.
+    #[inline(always)]
+    pub(crate) fn lookup_scope(&self, lookups: usize) -> MetricsScope {
+        self.handle().scope(lookups)
+    }
+
+    /// Replace the value of these counters by the value of the `other`
+    /// counters.
+    pub(crate) fn copy_from(&mut self, other: &MapMetrics) {
+        #[cfg(feature = "metrics")]
+        match (self.counters(), other.counters()) {
+            (_, Some(other)) => counters_or_init(&self.counters).copy_from(other),
+            (Some(counters), None) => counters.copy_from(&Counters::default()),
+            (None, None) => {},
+        }
+
+        #[cfg(not(feature = "metrics"))]
+        let _ = other;
+    }
+
+    /// Return a snapshot of the counters, combined with the given resize
+    /// counts which are tracked by the map itself.
+    #[cfg(feature = "metrics")]
+    pub(crate) fn snapshot(&self, grow_count: usize, shrink_count: usize) -> Metrics {
+        let Some(counters) = self.counters() else {
+            return Metrics {
+                grow_count,
+                shrink_count,
+                ..Metrics::default()
+            };
+        };
+        let [node4, node16, node48, node256, leaf, node32] = [
+            NodeType::Node4,
+            NodeType::Node16,
+            NodeType::Node48,
+            NodeType::Node256,
+            NodeType::Leaf,
+            NodeType::Node32,
+        ]
+        .map(|node_type| counters.node_allocations[node_type as usize].load(Ordering::Relaxed));
+
+        Metrics {
+            node_allocations: NodeAllocations {
+                node4,
+                node16,
+                node32,
+                node48,
+                node256,
+                leaf,
+            },
+            grow_count,
+            shrink_count,
+            prefix_reads: counters.prefix_reads.load(Ordering::Relaxed),
+            lookups: counters.lookups.load(Ordering::Relaxed),
+            lookup_steps: counters.lookup_steps.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Return the counters, or `None` if no event was recorded yet.
+    #[cfg(feature = "metrics")]
+    fn counters(&self) -> Option<&Counters> {
+        // SAFETY: The counters are only deallocated when `self` is dropped, and
+        // are only accessed through shared references.
+        unsafe { self.counters.load(Ordering::Acquire).as_ref() }
+    }
+}
+
+/// Return the counters in the slot, allocating them if no event was recorded
+/// yet.
+#[cfg(feature = "metrics")]
+fn counters_or_init(slot: &AtomicPtr<Counters>) -> &Counters {
+    let mut counters = slot.load(Ordering::Acquire);
+    if counters.is_null() {
+        let new = Box::into_raw(Box::<Counters>::default());
+        counters = match slot.compare_exchange(
+            ptr::null_mut(),
+            new,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => new,
+            Err(existing) => {
+                // SAFETY: Another thread allocated the counters first, so `new`
+                // was never shared
+                drop(unsafe { Box::from_raw(new) });
+                existing
+            },
+        };
+    }
+
+    // SAFETY: The counters are only deallocated when the `MapMetrics` which
+    // owns the slot is dropped, and are only accessed through shared
+    // references.
+    unsafe { &*counters }
+}
+
+#[cfg(feature = "metrics")]
+impl Drop for MapMetrics {
+    fn drop(&mut self) {
+        let counters = *self.counters.get_mut();
+        if !counters.is_null() {
+            // SAFETY: The counters were allocated by `Box` in `counters_or_init`,
+            // and every `MetricsHandle` to them is required to be dropped before
+            // the map.
+            drop(unsafe { Box::from_raw(counters) });
+        }
+    }
+}
+
+/// A copy of the pointer to the counters of a map, see
+/// [`MapMetrics::handle`].
+#[derive(Clone, Copy)]
+pub(crate) struct MetricsHandle {
+    #[cfg(feature = "metrics")]
+    counters: NonNull<AtomicPtr<Counters>>,
+}
+
+impl MetricsHandle {
+    /// Open a scope which moves the events recorded until it is dropped into
+    /// the counters of the map, and counts the given number of lookups.
+    #[inline(always)]
+    pub(crate) fn scope(self, lookups: usize) -> MetricsScope {
+        #[cfg(not(feature = "metrics"))]
+        let _ = lookups;
+
+        MetricsScope {
+            #[cfg(feature = "metrics")]
+            handle: self,
+            #[cfg(feature = "metrics")]
+            lookups,
+            #[cfg(feature = "metrics")]
+            outer: PENDING
+                .try_with(|pending| pending.replace(PendingCounts::ZERO))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// The events recorded while a map operation runs, see
+/// [`MapMetrics::scope`].
+///
+/// Scopes can be nested: an inner scope only takes the events recorded while
+/// it was open, and the events pending for the outer scope are restored when
+/// the inner scope is dropped.
+#[must_use]
+pub(crate) struct MetricsScope {
+    #[cfg(feature = "metrics")]
+    handle: MetricsHandle,
+    #[cfg(feature = "metrics")]
+    lookups: usize,
+    /// The events pending for the outer scope when this scope was opened
+    #[cfg(feature = "metrics")]
+    outer: PendingCounts,
+}
+
+#[cfg(feature = "metrics")]
+impl Drop for MetricsScope {
+    fn drop(&mut self) {
+        let pending = PENDING
+            .try_with(|pending| pending.replace(self.outer))
+            .unwrap_or_default();
+
+        if pending.is_zero() && self.lookups == 0 {
+            return;
+        }
+
+        // SAFETY: The scope is dropped before the map which owns the counters,
+        // and the map is not moved while the scope is open
+        let slot = unsafe { self.handle.counters.as_ref() };
+        counters_or_init(slot).add(&pending, self.lookups);
+    }
+}
+
+/// The number of nodes allocated by a map, for each type of node.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct NodeAllocations {
+    /// The number of [`NodeType::Node4`] allocated
+    pub node4: usize,
+    /// The number of [`NodeType::Node16`] allocated
+    pub node16: usize,
+    /// The number of [`NodeType::Node32`] allocated
+    pub node32: usize,
+    /// The number of [`NodeType::Node48`] allocated
+    pub node48: usize,
+    /// The number of [`NodeType::Node256`] allocated
+    pub node256: usize,
+    /// The number of [`NodeType::Leaf`] allocated
+    pub leaf: usize,
+}
+
+#[cfg(feature = "metrics")]
+impl NodeAllocations {
+    /// The number of inner nodes allocated, of any type.
+    pub fn inner_nodes(&self) -> usize {
+        self.node4 + self.node16 + self.node32 + self.node48 + self.node256
+    }
+
+    /// The number of nodes allocated, of any type.
+    pub fn total(&self) -> usize {
+        self.inner_nodes() + self.leaf
+    }
+}
+
+/// A snapshot of the operation counters of a [`TreeMap`](crate::TreeMap), as
+/// returned by [`TreeMap::metrics`](crate::TreeMap::metrics).
+///
+/// The counters start at zero when the map is created, and are copied when
+/// the map is cloned.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Metrics {
+    /// The number of nodes allocated by inserts, removes, clones and
+    /// compaction, for each type of node
+    pub node_allocations: NodeAllocations,
+
+    /// The number of times an inner node was grown into a larger node type
+    /// by an insert
+    pub grow_count: usize,
+
+    /// The number of times an inner node was shrunk into a smaller node type
+    /// by a remove
+    pub shrink_count: usize,
+
+    /// The number of times the prefix of an inner node was read from one of
+    /// its leaves, because it was longer than the `PREFIX_LEN` bytes stored in
+    /// the node
+    pub prefix_reads: usize,
+
+    /// The number of point lookups, like [`TreeMap::get`](crate::TreeMap::get)
+    /// or [`TreeMap::get_many`](crate::TreeMap::get_many)
+    pub lookups: usize,
+
+    /// The total number of nodes visited by the point lookups, including the
+    /// leaves
+    pub lookup_steps: usize,
+}
+
+#[cfg(feature = "metrics")]
+impl Metrics {
+    /// The average number of nodes visited by a point lookup, or `0.0` if
+    /// there were no lookups.
+    pub fn avg_lookup_depth(&self) -> f64 {
+        if self.lookups == 0 {
+            0.0
+        } else {
+            self.lookup_steps as f64 / self.lookups as f64
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl core::fmt::Display for Metrics {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let allocations = &self.node_allocations;
+        writeln!(
+            f,
+            "node allocations: {} (node4 {}, node16 {}, node32 {}, node48 {}, node256 {}, leaf {})",
+            allocations.total(),
+            allocations.node4,
+            allocations.node16,
+            allocations.node32,
+            allocations.node48,
+            allocations.node256,
+            allocations.leaf
+        )?;
+        writeln!(
+            f,
+            "grows: {}, shrinks: {}",
+            self.grow_count, self.shrink_count
+        )?;
+        writeln!(f, "prefix reads: {}", self.prefix_reads)?;
+        write!(
+            f,
+            "lookups: {} (average depth {:.2})",
+            self.lookups,
+            self.avg_lookup_depth()
+        )
+    }
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use crate::{tests_common::generate_key_fixed_length, PrefixStrategy, TreeMap};
+    use alloc::{boxed::Box, vec::Vec};
+
+    #[test]
+    fn counts_node_allocations_and_resizes() {
+        let mut tree = TreeMap::<[u8; 1], usize>::new();
+        assert_eq!(tree.metrics(), Metrics::default());
+
+        for idx in 0..=255u8 {
+            tree.insert([idx], usize::from(idx));
+        }
+
+        let metrics = tree.metrics();
+        assert_eq!(
+            metrics.node_allocations,
+            NodeAllocations {
+                node4: 1,
+                node16: 1,
+                node32: 1,
+                node48: 1,
+                node256: 1,
+                leaf: 256,
+            }
+        );
+        assert_eq!(metrics.grow_count, 4);
+        assert_eq!(metrics.shrink_count, 0);
+
+        for idx in 0..=255u8 {
+            tree.remove(&[idx]);
+        }
+        assert_eq!(tree.metrics().shrink_count, 4);
+    }
+
+    #[test]
+    fn counts_lookup_depth() {
+        let mut tree = TreeMap::new();
+        for (value, key) in generate_key_fixed_length([3, 3]).enumerate() {
+            tree.try_insert(key, value).unwrap();
+        }
+        let before = tree.metrics();
+        assert_eq!(before.lookups, 0);
+
+        let keys: Vec<_> = generate_key_fixed_length([3, 3]).collect();
+        for key in &keys {
+            assert!(tree.get(key).is_some());
+        }
+        let missing: Box<[u8]> = Box::new([4, 0]);
+        assert!(tree.get(&missing).is_none());
+
+        let metrics = tree.metrics();
+        assert_eq!(metrics.lookups, 17);
+        // Every key is found under the root and one more inner node, and the
+        // missing key stops at the root
+        assert_eq!(metrics.lookup_steps, 16 * 3 + 1);
+        assert_eq!(metrics.avg_lookup_depth(), 49.0 / 17.0);
+
+        let found = tree.get_many(&keys.iter().collect::<Vec<_>>());
+        assert!(found.iter().all(Option::is_some));
+        assert_eq!(tree.metrics().lookups, 33);
+        assert_eq!(tree.metrics().lookup_steps, 49 + 16 * 3);
+
+        // The allocations and lookups are not affected by the lookups
+        assert_eq!(metrics.node_allocations, before.node_allocations);
+    }
+
+    #[test]
+    fn counts_prefix_reads() {
+        let mut tree: TreeMap<[u8; 20], (), 4> =
+            TreeMap::with_prefix_strategy(PrefixStrategy::Optimistic);
+        let mut key = [7; 20];
+        tree.try_insert(key, ()).unwrap();
+        key[19] = 8;
+        tree.try_insert(key, ()).unwrap();
+        let after_inserts = tree.metrics().prefix_reads;
+
+        assert!(tree.contains_key(&key));
+        key[0] = 0;
+        assert!(!tree.contains_key(&key));
+        assert!(tree.metrics().prefix_reads > after_inserts);
+
+        let mut short: TreeMap<[u8; 2], ()> = TreeMap::new();
+        short.insert([1, 2], ());
+        short.insert([1, 3], ());
+        assert!(short.contains_key(&[1, 3]));
+        assert_eq!(short.metrics().prefix_reads, 0);
+    }
+
+    #[test]
+    fn nested_scopes_credit_each_map_once() {
+        let first = MapMetrics::new();
+        let second = MapMetrics::new();
+
+        {
+            let _outer = first.lookup_scope(1);
+            record_lookup_step();
+            {
+                let _inner = second.lookup_scope(2);
+                record_lookup_step();
+                record_node_allocation(NodeType::Leaf);
+            }
+            record_prefix_read();
+        }
+
+        let first = first.snapshot(0, 0);
+        let second = second.snapshot(0, 0);
+        assert_eq!(
+            (first.lookups, first.lookup_steps, first.prefix_reads),
+            (1, 1, 1)
+        );
+        assert_eq!(first.node_allocations.total(), 0);
+        assert_eq!(
+            (second.lookups, second.lookup_steps, second.prefix_reads),
+            (2, 1, 0)
+        );
+        assert_eq!(second.node_allocations.leaf, 1);
+    }
+
+    #[test]
+    fn counters_are_allocated_on_first_event() {
+        let metrics = MapMetrics::new();
+        drop(metrics.scope());
+        assert!(metrics.counters().is_none());
+
+        let mut copy = MapMetrics::new();
+        copy.copy_from(&metrics);
+        assert!(copy.counters().is_none());
+
+        {
+            let _scope = metrics.scope();
+            record_prefix_read();
+        }
+        assert_eq!(metrics.snapshot(0, 0).prefix_reads, 1);
+
+        copy.copy_from(&metrics);
+        assert_eq!(copy.snapshot(0, 0).prefix_reads, 1);
+        copy.copy_from(&MapMetrics::new());
+        assert_eq!(copy.snapshot(0, 0), Metrics::default());
+    }
+
+    #[test]
+    fn clone_copies_counters() {
+        let mut tree = TreeMap::<u16, ()>::new();
+        for key in 0..100 {
+            tree.insert(key, ());
+        }
+        assert!(tree.get(&3).is_some());
+
+        let metrics = tree.metrics();
+        let clone = tree.clone();
+        let clone_metrics = clone.metrics();
+        assert_eq!(clone_metrics.lookups, metrics.lookups);
+        assert_eq!(clone_metrics.grow_count, metrics.grow_count);
+        // The clone also counts the nodes allocated by the deep copy
+        assert_eq!(
+            clone_metrics.node_allocations.leaf,
+            metrics.node_allocations.leaf + 100
+        );
+        assert_eq!(tree.metrics(), metrics);
+    }
+}
@@ -1,6 +1,7 @@
 //! Trie node lookup and manipulation
 
 use crate::{ConcreteNodePtr, InnerNode, NodePtr, OpaqueNodePtr};
+use alloc::vec::Vec;
 
 mod insert;
 pub(crate) use insert::*;
@@ -14,6 +15,12 @@ pub(crate) use lookup::*;
 mod delete;
 pub(crate) use delete::*;
 
+mod merge;
+pub(crate) use merge::*;
+
+mod compact;
+pub(crate) use compact::*;
+
 /// Deallocate the given node and all children of the given node.
 ///
 /// This will also deallocate the leaf nodes with their value type data.
@@ -58,6 +65,7 @@ pub unsafe fn deallocate_tree<K, V, const PREFIX_LEN: usize>(
         match next_node_ptr.to_node_ptr() {
             ConcreteNodePtr::Node4(inner_ptr) => deallocate_inner_node(&mut stack, inner_ptr),
             ConcreteNodePtr::Node16(inner_ptr) => deallocate_inner_node(&mut stack, inner_ptr),
+            ConcreteNodePtr::Node32(inner_ptr) => deallocate_inner_node(&mut stack, inner_ptr),
             ConcreteNodePtr::Node48(inner_ptr) => deallocate_inner_node(&mut stack, inner_ptr),
             ConcreteNodePtr::Node256(inner_ptr) => deallocate_inner_node(&mut stack, inner_ptr),
             ConcreteNodePtr::LeafNode(inner) => {
@@ -68,3 +76,44 @@ pub unsafe fn deallocate_tree<K, V, const PREFIX_LEN: usize>(
         }
     }
 }
+
+/// Count the number of leaf nodes in the tree rooted at the given node.
+///
+/// The leaf nodes themselves are not read, since the node type is stored in
+/// the pointer.
+///
+/// # Safety
+///  - This function cannot be called concurrently with any mutating operation
+///    on `root` or any child node of `root`.
+pub unsafe fn count_leaves<K, V, const PREFIX_LEN: usize>(
+    root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+) -> usize {
+    fn push_children<K, V, N, const PREFIX_LEN: usize>(
+        stack: &mut Vec<OpaqueNodePtr<K, V, PREFIX_LEN>>,
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+    ) where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        // SAFETY: Covered by the safety requirements of the containing function
+        let inner_node = unsafe { inner_ptr.as_ref() };
+        stack.extend(inner_node.iter().map(|(_, child)| child));
+    }
+
+    let mut num_leaves = 0;
+    let mut stack = Vec::new();
+
+    stack.push(root);
+
+    while let Some(next_node_ptr) = stack.pop() {
+        match next_node_ptr.to_node_ptr() {
+            ConcreteNodePtr::Node4(inner_ptr) => push_children(&mut stack, inner_ptr),
+            ConcreteNodePtr::Node16(inner_ptr) => push_children(&mut stack, inner_ptr),
+            ConcreteNodePtr::Node32(inner_ptr) => push_children(&mut stack, inner_ptr),
+            ConcreteNodePtr::Node48(inner_ptr) => push_children(&mut stack, inner_ptr),
+            ConcreteNodePtr::Node256(inner_ptr) => push_children(&mut stack, inner_ptr),
+            ConcreteNodePtr::LeafNode(_) => num_leaves += 1,
+        }
+    }
+
+    num_leaves
+}
@@ -0,0 +1,159 @@
+use crate::{
+    ConcreteNodePtr, Header, InnerNode, InnerNode16, InnerNode256, InnerNode32, InnerNode4,
+    InnerNode48, NodePtr, OpaqueNodePtr,
+};
+use alloc::vec::Vec;
+use core::{mem, mem::ManuallyDrop};
+
+/// Reallocate every node of the tree rooted at `root` in depth-first order,
+/// rebuilding each inner node as the smallest node type that can hold its
+/// children.
+///
+/// Nodes are allocated in the order they are visited (parents before
+/// children, children in key order), so that a traversal of the compacted
+/// tree touches memory in roughly ascending order. Keys and values are moved
+/// into the new leaf nodes without being cloned or dropped.
+///
+/// Returns the root of the compacted tree.
+///
+/// # Safety
+///  - There must be no other references to any node in the tree rooted at
+///    `root`.
+///  - After this function returns, the old nodes of the tree must not be used,
+///    only the returned root.
+pub unsafe fn compact_tree<K, V, const PREFIX_LEN: usize>(
+    root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+) -> OpaqueNodePtr<K, V, PREFIX_LEN> {
+    /// A node of the old tree, along with the new parent node and key
+    /// fragment that the reallocated node should be written to.
+    type Pending<K, V, const PREFIX_LEN: usize> = (
+        OpaqueNodePtr<K, V, PREFIX_LEN>,
+        Option<(OpaqueNodePtr<K, V, PREFIX_LEN>, u8)>,
+    );
+
+    fn reallocate_inner_node<K, V, N, const PREFIX_LEN: usize>(
+        stack: &mut Vec<Pending<K, V, PREFIX_LEN>>,
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+    ) -> OpaqueNodePtr<K, V, PREFIX_LEN>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        let new_node = {
+            // SAFETY: The scope of this reference is bounded and ends before the
+            // node is deallocated. By the safety requirements of the containing
+            // function, no other mutation of this node can happen while it is live.
+            let inner_node = unsafe { inner_ptr.as_ref() };
+            let header = inner_node.header();
+            let new_node = allocate_empty_inner_node(header.clone_prefix(), header.num_children());
+
+            // Push the children in reverse order, so that they are popped and
+            // allocated in key order.
+            let start = stack.len();
+            stack.extend(
+                inner_node
+                    .iter()
+                    .map(|(key_fragment, child)| (child, Some((new_node, key_fragment)))),
+            );
+            stack[start..].reverse();
+
+            new_node
+        };
+
+        // SAFETY: The single call per node requirement is enforced by the safety
+        // requirements on the containing function. The children have been moved
+        // to the stack and are not touched by this.
+        drop(unsafe { NodePtr::deallocate_node_ptr(inner_ptr) });
+
+        new_node
+    }
+
+    let mut new_root = None;
+    let mut stack: Vec<Pending<K, V, PREFIX_LEN>> = Vec::new();
+
+    stack.push((root, None));
+
+    while let Some((old_node, parent)) = stack.pop() {
+        let new_node = match old_node.to_node_ptr() {
+            ConcreteNodePtr::Node4(inner_ptr) => reallocate_inner_node(&mut stack, inner_ptr),
+            ConcreteNodePtr::Node16(inner_ptr) => reallocate_inner_node(&mut stack, inner_ptr),
+            ConcreteNodePtr::Node32(inner_ptr) => reallocate_inner_node(&mut stack, inner_ptr),
+            ConcreteNodePtr::Node48(inner_ptr) => reallocate_inner_node(&mut stack, inner_ptr),
+            ConcreteNodePtr::Node256(inner_ptr) => reallocate_inner_node(&mut stack, inner_ptr),
+            ConcreteNodePtr::LeafNode(leaf_ptr) => {
+                // Allocate the new leaf before freeing the old one, so the new leaf does
+                // not just reuse the old allocation.
+                let leaf = ManuallyDrop::into_inner(leaf_ptr.read());
+                let new_leaf = NodePtr::allocate_node_ptr(leaf);
+
+                // SAFETY: The single call per node requirement is enforced by the safety
+                // requirements on this function. The contents of the leaf were moved to
+                // the new leaf, so they are forgotten instead of dropped.
+                mem::forget(unsafe { NodePtr::deallocate_node_ptr(leaf_ptr) });
+
+                new_leaf.to_opaque()
+            },
+        };
+
+        match parent {
+            // SAFETY: The new parent node was allocated by this function and there are no
+            // other references to it.
+            Some((parent, key_fragment)) => unsafe { write_child(parent, key_fragment, new_node) },
+            None => new_root = Some(new_node),
+        }
+    }
+
+    new_root.expect("the root node is always reallocated")
+}
+
+/// Allocate an empty inner node with the given header, using the smallest node
+/// type that can hold `num_children` children.
+fn allocate_empty_inner_node<K, V, const PREFIX_LEN: usize>(
+    header: Header<PREFIX_LEN>,
+    num_children: usize,
+) -> OpaqueNodePtr<K, V, PREFIX_LEN> {
+    fn allocate<N: InnerNode<PREFIX_LEN>, const PREFIX_LEN: usize>(
+        header: Header<PREFIX_LEN>,
+    ) -> OpaqueNodePtr<N::Key, N::Value, PREFIX_LEN> {
+        NodePtr::allocate_node_ptr(N::from_header(header)).to_opaque()
+    }
+
+    match num_children {
+        0..=4 => allocate::<InnerNode4<K, V, PREFIX_LEN>, PREFIX_LEN>(header),
+        5..=16 => allocate::<InnerNode16<K, V, PREFIX_LEN>, PREFIX_LEN>(header),
+        17..=32 => allocate::<InnerNode32<K, V, PREFIX_LEN>, PREFIX_LEN>(header),
+        33..=48 => allocate::<InnerNode48<K, V, PREFIX_LEN>, PREFIX_LEN>(header),
+        _ => allocate::<InnerNode256<K, V, PREFIX_LEN>, PREFIX_LEN>(header),
+    }
+}
+
+/// Write a child pointer with key fragment to the given inner node.
+///
+/// # Safety
+///  - There must be no other references to `parent`.
+///
+/// # Panics
+///  - Panics if `parent` is a leaf node.
+unsafe fn write_child<K, V, const PREFIX_LEN: usize>(
+    parent: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    key_fragment: u8,
+    child: OpaqueNodePtr<K, V, PREFIX_LEN>,
+) {
+    fn write<N: InnerNode<PREFIX_LEN>, const PREFIX_LEN: usize>(
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+        key_fragment: u8,
+        child: OpaqueNodePtr<N::Key, N::Value, PREFIX_LEN>,
+    ) {
+        // SAFETY: Covered by the safety requirements of the containing function
+        let inner_node = unsafe { inner_ptr.as_mut() };
+        inner_node.write_child(key_fragment, child);
+    }
+
+    match parent.to_node_ptr() {
+        ConcreteNodePtr::Node4(inner_ptr) => write(inner_ptr, key_fragment, child),
+        ConcreteNodePtr::Node16(inner_ptr) => write(inner_ptr, key_fragment, child),
+        ConcreteNodePtr::Node32(inner_ptr) => write(inner_ptr, key_fragment, child),
+        ConcreteNodePtr::Node48(inner_ptr) => write(inner_ptr, key_fragment, child),
+        ConcreteNodePtr::Node256(inner_ptr) => write(inner_ptr, key_fragment, child),
+        ConcreteNodePtr::LeafNode(_) => panic!("cannot write a child to a leaf node"),
+    }
+}
@@ -1,14 +1,16 @@
 use crate::{
     nodes::operations::lookup, AsBytes, ConcreteNodePtr, InnerNode, LeafNode, NodePtr,
-    OpaqueNodePtr,
+    OpaqueNodePtr, ShrinkPolicy,
 };
 
-/// Remove a child node from the given inner node, return the child node
-/// pointer if it was compressed.
+/// Remove a child node from the given inner node, return the node pointer
+/// which replaces the inner node if it was compressed or shrunk, and whether
+/// it was shrunk.
 ///
 /// The inner node will be compressed if there was only a single child
 /// remaining after the delete. Compressing the node involves prepending the
 /// inner node key prefix and child key byte to the child's key prefix.
+/// Otherwise the inner node is shrunk if the `shrink_policy` says so.
 ///
 /// # Safety
 ///  - `inner_node_ptr` must be a unique pointer to the node and must not have
@@ -21,7 +23,8 @@ unsafe fn remove_child_from_inner_node_and_compress<
 >(
     inner_node_ptr: NodePtr<PREFIX_LEN, N>,
     key_fragment: u8,
-) -> Option<OpaqueNodePtr<N::Key, N::Value, PREFIX_LEN>> {
+    shrink_policy: ShrinkPolicy,
+) -> (Option<OpaqueNodePtr<N::Key, N::Value, PREFIX_LEN>>, bool) {
     // SAFETY: The `inner_node` reference is scoped to this function and dropped
     // before cases where the inner node is deallocated. It is a unique reference,
     // by the safety requirements of the containing function.
@@ -51,24 +54,7 @@ unsafe fn remove_child_from_inner_node_and_compress<
         if let Some(child_header) = unsafe { child_node_ptr.header_mut() } {
             // Construct the new prefix, by concatenating the parent header, child_key_byte,
             // child header.
-            //
-            // Here we can use the fact that the header of both the child and the parent can
-            // hold up to PREFIX_LEN, so if for example the parent header len >=
-            // PREFIX_LEN, the new child header will hold only this bytes since
-            // the size is already greater than the capacity.
-            //
-            // so we can "clear" the child prefix, by setting the len to 0 and repopulate by
-            // pushing the parent header, child_key_byte, child prefix. If the
-            // header if already full we don't copy, just increment the len by
-            // the size.
-            let parent_header = inner_node.header();
-            let parent_prefix = parent_header.read_prefix();
-            let parent_len = parent_header.prefix_len();
-
-            let (old_prefix, old_len, old_capped_len) = child_header.clear_prefix();
-            child_header.push_prefix(parent_prefix, parent_len);
-            child_header.push_prefix(&[child_key_byte], 1);
-            child_header.push_prefix(&old_prefix[..old_capped_len], old_len);
+            child_header.prepend_prefix(inner_node.header(), child_key_byte);
         }
         // the else case here is that the child does not have a header, and
         // is a leaf
@@ -79,8 +65,8 @@ unsafe fn remove_child_from_inner_node_and_compress<
             drop(NodePtr::deallocate_node_ptr(inner_node_ptr));
         }
 
-        Some(child_node_ptr)
-    } else if N::TYPE.should_shrink_inner_node(inner_node.header().num_children()) {
+        (Some(child_node_ptr), false)
+    } else if shrink_policy.should_shrink_inner_node(N::TYPE, inner_node.header().num_children()) {
         let new_inner_node = inner_node.shrink();
 
         let new_inner_node_ptr = NodePtr::allocate_node_ptr(new_inner_node).to_opaque();
@@ -91,13 +77,15 @@ unsafe fn remove_child_from_inner_node_and_compress<
             drop(NodePtr::deallocate_node_ptr(inner_node_ptr));
         }
 
-        Some(new_inner_node_ptr)
+        (Some(new_inner_node_ptr), true)
     } else {
-        None
+        (None, false)
     }
 }
 
-/// Delete the given non-root leaf node.
+/// Remove the given child from its parent node, compressing or shrinking the
+/// parent if needed, and return the new root of the tree and whether the
+/// parent was shrunk.
 ///
 /// # Safety
 ///  - `parent_node_ptr` must be a unique pointer to the node and must not have
@@ -106,30 +94,52 @@ unsafe fn remove_child_from_inner_node_and_compress<
 ///    `parent_node_ptr` either.
 ///  - `grandparent_node_ptr` must be a unique pointer to the node and must not
 ///    have any other mutable references.
-///  - `leaf_node_ptr` must be a unique pointer to the node and not have any
-///    other mutable references.
-unsafe fn inner_delete_non_root_unchecked<K, V, const PREFIX_LEN: usize>(
-    leaf_node_ptr: NodePtr<PREFIX_LEN, LeafNode<K, V>>,
+unsafe fn remove_child_and_fix_up<K, V, const PREFIX_LEN: usize>(
     (parent_node_ptr, parent_key_byte): (OpaqueNodePtr<K, V, PREFIX_LEN>, u8),
     grandparent_node_ptr: Option<(OpaqueNodePtr<K, V, PREFIX_LEN>, u8)>,
     original_root: OpaqueNodePtr<K, V, PREFIX_LEN>,
-) -> DeleteResult<K, V, PREFIX_LEN> {
-    let new_parent_node_ptr = match parent_node_ptr.to_node_ptr() {
+    shrink_policy: ShrinkPolicy,
+) -> (OpaqueNodePtr<K, V, PREFIX_LEN>, bool) {
+    let (new_parent_node_ptr, parent_shrunk) = match parent_node_ptr.to_node_ptr() {
         ConcreteNodePtr::Node4(parent_node_ptr) => unsafe {
             // SAFETY: Covered by containing function safety doc
-            remove_child_from_inner_node_and_compress(parent_node_ptr, parent_key_byte)
+            remove_child_from_inner_node_and_compress(
+                parent_node_ptr,
+                parent_key_byte,
+                shrink_policy,
+            )
         },
         ConcreteNodePtr::Node16(parent_node_ptr) => unsafe {
             // SAFETY: Covered by containing function safety doc
-            remove_child_from_inner_node_and_compress(parent_node_ptr, parent_key_byte)
+            remove_child_from_inner_node_and_compress(
+                parent_node_ptr,
+                parent_key_byte,
+                shrink_policy,
+            )
+        },
+        ConcreteNodePtr::Node32(parent_node_ptr) => unsafe {
+            // SAFETY: Covered by containing function safety doc
+            remove_child_from_inner_node_and_compress(
+                parent_node_ptr,
+                parent_key_byte,
+                shrink_policy,
+            )
         },
         ConcreteNodePtr::Node48(parent_node_ptr) => unsafe {
             // SAFETY: Covered by containing function safety doc
-            remove_child_from_inner_node_and_compress(parent_node_ptr, parent_key_byte)
+            remove_child_from_inner_node_and_compress(
+                parent_node_ptr,
+                parent_key_byte,
+                shrink_policy,
+            )
         },
         ConcreteNodePtr::Node256(parent_node_ptr) => unsafe {
             // SAFETY: Covered by containing function safety doc
-            remove_child_from_inner_node_and_compress(parent_node_ptr, parent_key_byte)
+            remove_child_from_inner_node_and_compress(
+                parent_node_ptr,
+                parent_key_byte,
+                shrink_policy,
+            )
         },
         ConcreteNodePtr::LeafNode(_) => panic!("Cannot have delete from leaf node"),
     };
@@ -153,6 +163,13 @@ unsafe fn inner_delete_non_root_unchecked<K, V, const PREFIX_LEN: usize>(
                     let inner_node = unsafe { inner_node_ptr.as_mut() };
                     inner_node.write_child(grandparent_key_byte, new_parent_node_ptr);
                 },
+                ConcreteNodePtr::Node32(inner_node_ptr) => {
+                    // SAFETY: The scope of the mutable reference is limited to this block, and
+                    // the containing function safety requirements mean that there are no other
+                    // mutable references to the same node.
+                    let inner_node = unsafe { inner_node_ptr.as_mut() };
+                    inner_node.write_child(grandparent_key_byte, new_parent_node_ptr);
+                },
                 ConcreteNodePtr::Node48(inner_node_ptr) => {
                     // SAFETY: The scope of the mutable reference is limited to this block, and
                     // the containing function safety requirements mean that there are no other
@@ -174,18 +191,51 @@ unsafe fn inner_delete_non_root_unchecked<K, V, const PREFIX_LEN: usize>(
         }
     }
 
-    // SAFETY: `leaf_node_ptr` is a unique pointer to the leaf node, no other code
-    // will deallocate this
-    let leaf_node = unsafe { NodePtr::deallocate_node_ptr(leaf_node_ptr) };
-
     let new_root = match (new_parent_node_ptr, grandparent_node_ptr) {
         (Some(new_parent_node_ptr), None) => new_parent_node_ptr,
         _ => original_root,
     };
 
+    (new_root, parent_shrunk)
+}
+
+/// Delete the given non-root leaf node, shrinking its parent according to
+/// the `shrink_policy`.
+///
+/// # Safety
+///  - `parent_node_ptr` must be a unique pointer to the node and must not have
+///    any other mutable references.
+///  - There must not be any other mutable references to any children of the
+///    `parent_node_ptr` either.
+///  - `grandparent_node_ptr` must be a unique pointer to the node and must not
+///    have any other mutable references.
+///  - `leaf_node_ptr` must be a unique pointer to the node and not have any
+///    other mutable references.
+unsafe fn inner_delete_non_root_unchecked<K, V, const PREFIX_LEN: usize>(
+    leaf_node_ptr: NodePtr<PREFIX_LEN, LeafNode<K, V>>,
+    parent_node_ptr: (OpaqueNodePtr<K, V, PREFIX_LEN>, u8),
+    grandparent_node_ptr: Option<(OpaqueNodePtr<K, V, PREFIX_LEN>, u8)>,
+    original_root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    shrink_policy: ShrinkPolicy,
+) -> DeleteResult<K, V, PREFIX_LEN> {
+    // SAFETY: Covered by containing function safety doc
+    let (new_root, inner_node_shrunk) = unsafe {
+        remove_child_and_fix_up(
+            parent_node_ptr,
+            grandparent_node_ptr,
+            original_root,
+            shrink_policy,
+        )
+    };
+
+    // SAFETY: `leaf_node_ptr` is a unique pointer to the leaf node, no other code
+    // will deallocate this
+    let leaf_node = unsafe { NodePtr::deallocate_node_ptr(leaf_node_ptr) };
+
     DeleteResult {
         new_root: Some(new_root),
         deleted_leaf: leaf_node,
+        inner_node_shrunk,
     }
 }
 
@@ -198,6 +248,9 @@ pub struct DeleteResult<K, V, const PREFIX_LEN: usize> {
     pub new_root: Option<OpaqueNodePtr<K, V, PREFIX_LEN>>,
     /// The leaf node that was successfully deleted.
     pub deleted_leaf: LeafNode<K, V>,
+    /// True if the parent of the deleted leaf was shrunk into a smaller node
+    /// type.
+    pub inner_node_shrunk: bool,
 }
 
 pub struct DeletePoint<K, V, const PREFIX_LEN: usize> {
@@ -217,8 +270,8 @@ pub struct DeletePoint<K, V, const PREFIX_LEN: usize> {
     pub leaf_node_ptr: NodePtr<PREFIX_LEN, LeafNode<K, V>>,
 }
 
-impl<K, V, const PREFIX_LEN: usize> std::fmt::Debug for DeletePoint<K, V, PREFIX_LEN> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl<K, V, const PREFIX_LEN: usize> core::fmt::Debug for DeletePoint<K, V, PREFIX_LEN> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("DeleteSearchResult")
             .field(
                 "grandparent_node",
@@ -232,7 +285,7 @@ impl<K, V, const PREFIX_LEN: usize> std::fmt::Debug for DeletePoint<K, V, PREFIX
 
 impl<K, V, const PREFIX_LEN: usize> DeletePoint<K, V, PREFIX_LEN> {
     /// Handle the logic of deleting a leaf node from the tree, after it has
-    /// been found.
+    /// been found, shrinking its parent according to the `shrink_policy`.
     ///
     /// # Safety
     ///  - The `root` [`OpaqueNodePtr`] must be a unique pointer to the
@@ -240,7 +293,11 @@ impl<K, V, const PREFIX_LEN: usize> DeletePoint<K, V, PREFIX_LEN> {
     ///  - This function cannot be called concurrently to any reads or writes of
     ///    the `root` node or any child node of `root`. This function will
     ///    arbitrarily read or write to any child in the given tree.
-    pub fn apply(self, root: OpaqueNodePtr<K, V, PREFIX_LEN>) -> DeleteResult<K, V, PREFIX_LEN> {
+    pub fn apply(
+        self,
+        root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        shrink_policy: ShrinkPolicy,
+    ) -> DeleteResult<K, V, PREFIX_LEN> {
         let DeletePoint {
             grandparent_ptr_and_parent_key_byte: grandparent_node_ptr,
             parent_ptr_and_child_key_byte: parent_node_ptr,
@@ -258,6 +315,7 @@ impl<K, V, const PREFIX_LEN: usize> DeletePoint<K, V, PREFIX_LEN> {
                 DeleteResult {
                     new_root: None,
                     deleted_leaf: leaf_node,
+                    inner_node_shrunk: false,
                 }
             },
             (None, Some(grandparent_node_ptr)) => {
@@ -276,6 +334,7 @@ impl<K, V, const PREFIX_LEN: usize> DeletePoint<K, V, PREFIX_LEN> {
                     parent_node_ptr,
                     grandparent_node_ptr,
                     root,
+                    shrink_policy,
                 )
             },
         }
@@ -316,6 +375,11 @@ where
                 // containing function
                 lookup::check_prefix_lookup_child(inner_ptr, key_bytes, &mut current_depth)
             },
+            ConcreteNodePtr::Node32(inner_ptr) => unsafe {
+                // SAFETY: The safety requirement is covered by the safety requirement on the
+                // containing function
+                lookup::check_prefix_lookup_child(inner_ptr, key_bytes, &mut current_depth)
+            },
             ConcreteNodePtr::Node48(inner_ptr) => unsafe {
                 // SAFETY: The safety requirement is covered by the safety requirement on the
                 // containing function
@@ -378,6 +442,7 @@ pub unsafe fn find_minimum_to_delete<K, V, const PREFIX_LEN: usize>(
         let (last_key_byte, next_node) = match current_node.to_node_ptr() {
             ConcreteNodePtr::Node4(inner_node) => unsafe { inner_node.as_ref().min() },
             ConcreteNodePtr::Node16(inner_node) => unsafe { inner_node.as_ref().min() },
+            ConcreteNodePtr::Node32(inner_node) => unsafe { inner_node.as_ref().min() },
             ConcreteNodePtr::Node48(inner_node) => unsafe { inner_node.as_ref().min() },
             ConcreteNodePtr::Node256(inner_node) => unsafe { inner_node.as_ref().min() },
             ConcreteNodePtr::LeafNode(leaf_node_ptr) => {
@@ -416,6 +481,7 @@ pub unsafe fn find_maximum_to_delete<K, V, const PREFIX_LEN: usize>(
         let (last_key_byte, next_node) = match current_node.to_node_ptr() {
             ConcreteNodePtr::Node4(inner_node) => unsafe { inner_node.as_ref().max() },
             ConcreteNodePtr::Node16(inner_node) => unsafe { inner_node.as_ref().max() },
+            ConcreteNodePtr::Node32(inner_node) => unsafe { inner_node.as_ref().max() },
             ConcreteNodePtr::Node48(inner_node) => unsafe { inner_node.as_ref().max() },
             ConcreteNodePtr::Node256(inner_node) => unsafe { inner_node.as_ref().max() },
             ConcreteNodePtr::LeafNode(leaf_node_ptr) => {
@@ -433,5 +499,223 @@ pub unsafe fn find_maximum_to_delete<K, V, const PREFIX_LEN: usize>(
     }
 }
 
+/// The results of a successful prefix delete operation
+#[derive(Debug)]
+pub struct PrefixDeleteResult<K, V, const PREFIX_LEN: usize> {
+    /// The new root node for the tree, after the subtree has been removed.
+    ///
+    /// If `None`, that means the tree is now empty.
+    pub new_root: Option<OpaqueNodePtr<K, V, PREFIX_LEN>>,
+    /// The root of the removed subtree, containing all keys which start with
+    /// the prefix. The prefix of this node is extended so that it is a valid
+    /// root of a separate tree.
+    pub removed_root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    /// True if the parent of the removed subtree was shrunk into a smaller
+    /// node type.
+    pub inner_node_shrunk: bool,
+}
+
+/// The location of the subtree containing all keys that start with a given
+/// prefix, along with the information required to remove it from the tree.
+pub struct PrefixDeletePoint<K, V, const PREFIX_LEN: usize> {
+    /// The grandparent node of the subtree that will be removed and the key
+    /// byte that was used to continue search.
+    ///
+    /// If there is no grandparent, this value is `None`.
+    pub grandparent_ptr_and_parent_key_byte: Option<(OpaqueNodePtr<K, V, PREFIX_LEN>, u8)>,
+    /// The parent node of the subtree that will be removed and the key byte
+    /// that was used to continue search.
+    ///
+    /// If the subtree is the entire tree, then this value is `None`. If the
+    /// grandparent node is present, this value also must be present.
+    pub parent_ptr_and_child_key_byte: Option<(OpaqueNodePtr<K, V, PREFIX_LEN>, u8)>,
+    /// The root of the subtree to remove.
+    pub node_ptr: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    /// The number of key bytes that come before the prefix of `node_ptr`.
+    pub node_depth: usize,
+}
+
+impl<K, V, const PREFIX_LEN: usize> core::fmt::Debug for PrefixDeletePoint<K, V, PREFIX_LEN> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("PrefixDeletePoint")
+            .field(
+                "grandparent_node",
+                &self.grandparent_ptr_and_parent_key_byte,
+            )
+            .field("parent_node", &self.parent_ptr_and_child_key_byte)
+            .field("node", &self.node_ptr)
+            .field("node_depth", &self.node_depth)
+            .finish()
+    }
+}
+
+impl<K, V, const PREFIX_LEN: usize> PrefixDeletePoint<K, V, PREFIX_LEN> {
+    /// Detach the subtree from the tree, after it has been found, shrinking
+    /// its parent according to the `shrink_policy`.
+    ///
+    /// `prefix` must be the same prefix that was used to find this point.
+    ///
+    /// # Safety
+    ///  - The `root` [`OpaqueNodePtr`] must be a unique pointer to the
+    ///    underlying tree
+    ///  - This function cannot be called concurrently to any reads or writes of
+    ///    the `root` node or any child node of `root`. This function will
+    ///    arbitrarily read or write to any child in the given tree.
+    pub unsafe fn apply(
+        self,
+        root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        prefix: &[u8],
+        shrink_policy: ShrinkPolicy,
+    ) -> PrefixDeleteResult<K, V, PREFIX_LEN> {
+        let PrefixDeletePoint {
+            grandparent_ptr_and_parent_key_byte: grandparent_node_ptr,
+            parent_ptr_and_child_key_byte: parent_node_ptr,
+            node_ptr,
+            node_depth,
+        } = self;
+
+        let (new_root, inner_node_shrunk) = match (parent_node_ptr, grandparent_node_ptr) {
+            (None, None) => (None, false),
+            (None, Some(grandparent_node_ptr)) => {
+                // search_for_prefix_delete_point should maintain this invariant
+                panic!(
+                    "This should be impossible, to have missing parent node and present \
+                     grandparent node [{grandparent_node_ptr:?}]",
+                );
+            },
+            (Some(parent_node_ptr), grandparent_node_ptr) => {
+                // SAFETY: `root` is a unique pointer to the tree and there will be no
+                // concurrent reads or writes to any portion of the tree, so all these child
+                // nodes will be unique pointers and not read/written.
+                let (new_root, inner_node_shrunk) = unsafe {
+                    remove_child_and_fix_up(
+                        parent_node_ptr,
+                        grandparent_node_ptr,
+                        root,
+                        shrink_policy,
+                    )
+                };
+                (Some(new_root), inner_node_shrunk)
+            },
+        };
+
+        // The removed node is now the root of its own tree, so the key bytes
+        // leading up to it have to become part of its prefix. All of them are
+        // part of the searched prefix, since the search stops as soon as the
+        // prefix runs out.
+        //
+        // SAFETY: The node was detached from the tree above, and the safety
+        // requirements of this function guarantee there are no other references to
+        // it.
+        if let Some(header) = unsafe { node_ptr.header_mut() } {
+            header.prepend_bytes(&prefix[..node_depth]);
+        }
+
+        PrefixDeleteResult {
+            new_root,
+            removed_root: node_ptr,
+            inner_node_shrunk,
+        }
+    }
+}
+
+/// Search in the given tree for the subtree containing all the keys that start
+/// with `prefix`, returning `None` if there are no such keys.
+///
+/// # Safety
+///  - This function cannot be called concurrently with any mutating operation
+///    on `root` or any child node of `root`. This function will arbitrarily
+///    read to any child in the given tree.
+pub unsafe fn search_for_prefix_delete_point<K, V, const PREFIX_LEN: usize>(
+    root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    prefix: &[u8],
+) -> Option<PrefixDeletePoint<K, V, PREFIX_LEN>>
+where
+    K: AsBytes,
+{
+    /// Match the node prefix against the remaining search prefix, returning
+    /// `Ok(None)` if the search prefix ends inside this node, `Ok(Some(..))`
+    /// with the next child and depth to continue with, or `Err(())` if no key
+    /// starts with the search prefix.
+    fn match_inner_node<N: InnerNode<PREFIX_LEN>, const PREFIX_LEN: usize>(
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+        prefix: &[u8],
+        depth: usize,
+    ) -> Result<Option<(u8, OpaqueNodePtr<N::Key, N::Value, PREFIX_LEN>, usize)>, ()>
+    where
+        N::Key: AsBytes,
+    {
+        // SAFETY: The safety requirement is covered by the safety requirement on the
+        // containing function
+        let inner_node = unsafe { inner_ptr.as_ref() };
+        let (node_prefix, _) = inner_node.read_full_prefix(depth);
+        let remaining = &prefix[depth..];
+
+        let matched_bytes = node_prefix
+            .iter()
+            .zip(remaining)
+            .take_while(|(a, b)| **a == **b)
+            .count();
+
+        if matched_bytes == remaining.len() {
+            Ok(None)
+        } else if matched_bytes < node_prefix.len() {
+            Err(())
+        } else {
+            let key_byte = remaining[matched_bytes];
+            let child = inner_node.lookup_child(key_byte).ok_or(())?;
+            Ok(Some((key_byte, child, depth + matched_bytes + 1)))
+        }
+    }
+
+    let mut current_grandparent = None;
+    let mut current_parent = None;
+    let mut current_node = root;
+    let mut current_depth = 0;
+
+    loop {
+        let next = match current_node.to_node_ptr() {
+            ConcreteNodePtr::Node4(inner_ptr) => match_inner_node(inner_ptr, prefix, current_depth),
+            ConcreteNodePtr::Node16(inner_ptr) => {
+                match_inner_node(inner_ptr, prefix, current_depth)
+            },
+            ConcreteNodePtr::Node32(inner_ptr) => {
+                match_inner_node(inner_ptr, prefix, current_depth)
+            },
+            ConcreteNodePtr::Node48(inner_ptr) => {
+                match_inner_node(inner_ptr, prefix, current_depth)
+            },
+            ConcreteNodePtr::Node256(inner_ptr) => {
+                match_inner_node(inner_ptr, prefix, current_depth)
+            },
+            ConcreteNodePtr::LeafNode(leaf_node_ptr) => {
+                // SAFETY: The safety requirement is covered by the safety requirement on the
+                // containing function
+                let key = unsafe { leaf_node_ptr.as_key_ref() }.as_bytes();
+                if key.starts_with(prefix) {
+                    Ok(None)
+                } else {
+                    Err(())
+                }
+            },
+        }
+        .ok()?;
+
+        let Some((key_byte, next_node, next_depth)) = next else {
+            return Some(PrefixDeletePoint {
+                grandparent_ptr_and_parent_key_byte: current_grandparent,
+                parent_ptr_and_child_key_byte: current_parent,
+                node_ptr: current_node,
+                node_depth: current_depth,
+            });
+        };
+
+        current_grandparent = current_parent;
+        current_parent = Some((current_node, key_byte));
+        current_node = next_node;
+        current_depth = next_depth;
+    }
+}
+
 #[cfg(test)]
 mod tests;
@@ -1,5 +1,11 @@
 use super::*;
 use crate::{deallocate_tree, search_unchecked, tests_common::setup_tree_from_entries, NodeType};
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 
 #[test]
 fn delete_singleton_tree_leaf() {
@@ -18,7 +24,7 @@ fn delete_singleton_tree_leaf() {
 
         let delete_result = search_for_delete_point(root, [1, 2, 3, 4].as_ref())
             .unwrap()
-            .apply(root);
+            .apply(root, ShrinkPolicy::Eager);
         assert!(delete_result.new_root.is_none());
         assert_eq!(delete_result.deleted_leaf.key_ref().as_ref(), &[1, 2, 3, 4]);
         assert_eq!(delete_result.deleted_leaf.value_ref(), &"1234");
@@ -50,7 +56,7 @@ fn delete_entire_small_tree() {
 
         let delete_result = search_for_delete_point(root, [1, 2, 3, 4, 7, 8].as_ref())
             .unwrap()
-            .apply(root);
+            .apply(root, ShrinkPolicy::Eager);
         assert_eq!(
             delete_result.deleted_leaf.key_ref().as_ref(),
             &[1, 2, 3, 4, 7, 8]
@@ -72,7 +78,7 @@ fn delete_entire_small_tree() {
 
         let delete_result = search_for_delete_point(root, [1, 2, 3, 4, 5, 9].as_ref())
             .unwrap()
-            .apply(root);
+            .apply(root, ShrinkPolicy::Eager);
         assert_eq!(
             delete_result.deleted_leaf.key_ref().as_ref(),
             &[1, 2, 3, 4, 5, 9]
@@ -84,7 +90,7 @@ fn delete_entire_small_tree() {
 
         let delete_result = search_for_delete_point(root, [2, 4, 6, 8, 10, 12].as_ref())
             .unwrap()
-            .apply(root);
+            .apply(root, ShrinkPolicy::Eager);
         assert_eq!(
             delete_result.deleted_leaf.key_ref().as_ref(),
             &[2, 4, 6, 8, 10, 12]
@@ -97,7 +103,7 @@ fn delete_entire_small_tree() {
 
         let delete_result = search_for_delete_point(root, [1, 2, 3, 4, 5, 6].as_ref())
             .unwrap()
-            .apply(root);
+            .apply(root, ShrinkPolicy::Eager);
         assert_eq!(
             delete_result.deleted_leaf.key_ref().as_ref(),
             &[1, 2, 3, 4, 5, 6]
@@ -133,7 +139,7 @@ fn delete_one_entry_n16_remains() {
     unsafe {
         let delete_result = search_for_delete_point(root, [1, 2, 3, 9, 5, 6].as_ref())
             .unwrap()
-            .apply(root);
+            .apply(root, ShrinkPolicy::Eager);
         assert_eq!(delete_result.new_root.unwrap(), root);
         assert_eq!(delete_result.deleted_leaf.value_ref(), &'E');
         assert_eq!(
@@ -149,17 +155,17 @@ fn delete_one_entry_n16_remains() {
 }
 
 #[test]
-fn delete_one_entry_n48_shrinks() {
+fn delete_one_entry_n32_shrinks() {
     let entries_it = (1..=17).map(|value| (Box::<[u8]>::from(&[1, 2, 3, value, 5, 6][..]), value));
 
     let mut root: OpaqueNodePtr<Box<[u8]>, u8, 16> = setup_tree_from_entries(entries_it);
 
-    assert_eq!(root.node_type(), NodeType::Node48);
+    assert_eq!(root.node_type(), NodeType::Node32);
 
     unsafe {
         let delete_result = search_for_delete_point(root, [1, 2, 3, 9, 5, 6].as_ref())
             .unwrap()
-            .apply(root);
+            .apply(root, ShrinkPolicy::Eager);
 
         assert_ne!(delete_result.new_root.unwrap(), root);
         assert_eq!(delete_result.deleted_leaf.value_ref(), &9);
@@ -175,6 +181,33 @@ fn delete_one_entry_n48_shrinks() {
     }
 }
 
+#[test]
+fn delete_one_entry_n48_shrinks() {
+    let entries_it = (1..=33).map(|value| (Box::<[u8]>::from(&[1, 2, 3, value, 5, 6][..]), value));
+
+    let mut root: OpaqueNodePtr<Box<[u8]>, u8, 16> = setup_tree_from_entries(entries_it);
+
+    assert_eq!(root.node_type(), NodeType::Node48);
+
+    unsafe {
+        let delete_result = search_for_delete_point(root, [1, 2, 3, 9, 5, 6].as_ref())
+            .unwrap()
+            .apply(root, ShrinkPolicy::Eager);
+
+        assert_ne!(delete_result.new_root.unwrap(), root);
+        assert_eq!(delete_result.deleted_leaf.value_ref(), &9);
+        assert_eq!(
+            delete_result.deleted_leaf.key_ref().as_ref(),
+            &[1, 2, 3, 9, 5, 6]
+        );
+
+        root = delete_result.new_root.unwrap();
+        assert_eq!(root.node_type(), NodeType::Node32);
+
+        deallocate_tree(root);
+    }
+}
+
 #[test]
 fn delete_one_entry_n256_shrinks() {
     let entries_it = (1..=49).map(|value| (Box::<[u8]>::from(&[1, 2, 3, value, 5, 6][..]), value));
@@ -186,7 +219,7 @@ fn delete_one_entry_n256_shrinks() {
     let delete = unsafe {
         search_for_delete_point(root, [1, 2, 3, 24, 5, 6].as_ref())
             .unwrap()
-            .apply(root)
+            .apply(root, ShrinkPolicy::Eager)
     };
 
     assert_ne!(delete.new_root.unwrap(), root);
@@ -207,7 +240,7 @@ fn delete_minimum_singleton_tree() {
 
     let root = first_leaf.to_opaque();
 
-    let delete_result = unsafe { find_minimum_to_delete(root).apply(root) };
+    let delete_result = unsafe { find_minimum_to_delete(root).apply(root, ShrinkPolicy::Eager) };
     assert!(delete_result.new_root.is_none());
     assert_eq!(delete_result.deleted_leaf.key_ref().as_ref(), &[1, 2, 3, 4]);
     assert_eq!(delete_result.deleted_leaf.value_ref(), &"1234");
@@ -231,7 +264,7 @@ fn delete_minimum_entire_small_tree() {
 
     assert_eq!(root.node_type(), NodeType::Node4);
 
-    let d1 = unsafe { find_minimum_to_delete(root).apply(root) };
+    let d1 = unsafe { find_minimum_to_delete(root).apply(root, ShrinkPolicy::Eager) };
     assert_eq!(d1.deleted_leaf.value_ref(), &'A');
     assert_eq!(d1.deleted_leaf.key_ref().as_ref(), &[1, 2, 3, 4, 5, 6]);
 
@@ -249,14 +282,14 @@ fn delete_minimum_entire_small_tree() {
         }
     }
 
-    let d2 = unsafe { find_minimum_to_delete(root).apply(root) };
+    let d2 = unsafe { find_minimum_to_delete(root).apply(root, ShrinkPolicy::Eager) };
     assert_eq!(d2.deleted_leaf.value_ref(), &'D');
     assert_eq!(d2.deleted_leaf.key_ref().as_ref(), &[1, 2, 3, 4, 5, 9]);
     let new_root = d2.new_root.unwrap();
     assert_eq!(new_root, root);
     root = new_root;
 
-    let d3 = unsafe { find_minimum_to_delete(root).apply(root) };
+    let d3 = unsafe { find_minimum_to_delete(root).apply(root, ShrinkPolicy::Eager) };
     assert_eq!(d3.deleted_leaf.value_ref(), &'C');
     assert_eq!(d3.deleted_leaf.key_ref().as_ref(), &[1, 2, 3, 4, 7, 8]);
     let new_root = d3.new_root.unwrap();
@@ -264,7 +297,7 @@ fn delete_minimum_entire_small_tree() {
     root = new_root;
     assert_eq!(root.node_type(), NodeType::Leaf);
 
-    let d4 = unsafe { find_minimum_to_delete(root).apply(root) };
+    let d4 = unsafe { find_minimum_to_delete(root).apply(root, ShrinkPolicy::Eager) };
     assert_eq!(d4.deleted_leaf.value_ref(), &'B');
     assert_eq!(d4.deleted_leaf.key_ref().as_ref(), &[2, 4, 6, 8, 10, 12]);
     assert!(d4.new_root.is_none());
@@ -278,7 +311,7 @@ fn delete_maximum_singleton_tree() {
 
     let root = first_leaf.to_opaque();
 
-    let delete_result = unsafe { find_maximum_to_delete(root).apply(root) };
+    let delete_result = unsafe { find_maximum_to_delete(root).apply(root, ShrinkPolicy::Eager) };
     assert!(delete_result.new_root.is_none());
     assert_eq!(delete_result.deleted_leaf.key_ref().as_ref(), &[1, 2, 3, 4]);
     assert_eq!(delete_result.deleted_leaf.value_ref(), &"1234");
@@ -302,7 +335,7 @@ fn delete_maximum_entire_small_tree() {
 
     assert_eq!(root.node_type(), NodeType::Node4);
 
-    let d1 = unsafe { find_maximum_to_delete(root).apply(root) };
+    let d1 = unsafe { find_maximum_to_delete(root).apply(root, ShrinkPolicy::Eager) };
     assert_eq!(d1.deleted_leaf.value_ref(), &'B');
     assert_eq!(d1.deleted_leaf.key_ref().as_ref(), &[2, 4, 6, 8, 10, 12]);
 
@@ -321,7 +354,7 @@ fn delete_maximum_entire_small_tree() {
         }
     }
 
-    let d2 = unsafe { find_maximum_to_delete(root).apply(root) };
+    let d2 = unsafe { find_maximum_to_delete(root).apply(root, ShrinkPolicy::Eager) };
     assert_eq!(d2.deleted_leaf.value_ref(), &'C');
     assert_eq!(d2.deleted_leaf.key_ref().as_ref(), &[1, 2, 3, 4, 7, 8]);
     let new_root = d2.new_root.unwrap();
@@ -329,7 +362,7 @@ fn delete_maximum_entire_small_tree() {
     assert_ne!(new_root, root);
     root = new_root;
 
-    let d3 = unsafe { find_maximum_to_delete(root).apply(root) };
+    let d3 = unsafe { find_maximum_to_delete(root).apply(root, ShrinkPolicy::Eager) };
     assert_eq!(d3.deleted_leaf.value_ref(), &'D');
     assert_eq!(d3.deleted_leaf.key_ref().as_ref(), &[1, 2, 3, 4, 5, 9]);
     let new_root = d3.new_root.unwrap();
@@ -337,8 +370,82 @@ fn delete_maximum_entire_small_tree() {
     root = new_root;
     assert_eq!(root.node_type(), NodeType::Leaf);
 
-    let d4 = unsafe { find_maximum_to_delete(root).apply(root) };
+    let d4 = unsafe { find_maximum_to_delete(root).apply(root, ShrinkPolicy::Eager) };
     assert_eq!(d4.deleted_leaf.value_ref(), &'A');
     assert_eq!(d4.deleted_leaf.key_ref().as_ref(), &[1, 2, 3, 4, 5, 6]);
     assert!(d4.new_root.is_none());
 }
+
+#[test]
+fn remove_prefix_matches_filter() {
+    use crate::{
+        tests_common::{generate_key_with_prefix, PrefixExpansion},
+        visitor::WellFormedChecker,
+        TreeMap,
+    };
+
+    fn check<const PREFIX_LEN: usize>(keys: &[Box<[u8]>], prefix: &[u8]) {
+        let mut tree = TreeMap::<Box<[u8]>, usize, PREFIX_LEN>::with_prefix_len();
+        for (idx, key) in keys.iter().enumerate() {
+            tree.try_insert(key.clone(), idx).unwrap();
+        }
+
+        let (expected_removed, expected_kept): (Vec<_>, Vec<_>) = tree
+            .iter()
+            .map(|(k, v)| (k.clone(), *v))
+            .partition(|(k, _)| k.starts_with(prefix));
+        assert_eq!(tree.count_prefix(prefix), expected_removed.len());
+
+        let mut kept = tree.clone();
+        kept.retain_prefix(prefix);
+        let removed = tree.remove_prefix(prefix);
+
+        for (tree, expected) in [
+            (&tree, &expected_kept),
+            (&removed, &expected_removed),
+            (&kept, &expected_removed),
+        ] {
+            assert_eq!(tree.len(), expected.len());
+            if !tree.is_empty() {
+                assert_eq!(WellFormedChecker::check(tree).map(|_| ()), Ok(()));
+            }
+            assert_eq!(
+                tree.iter()
+                    .map(|(k, v)| (k.clone(), *v))
+                    .collect::<Vec<_>>(),
+                *expected
+            );
+            for (key, value) in expected {
+                assert_eq!(tree.get(key), Some(value));
+            }
+        }
+    }
+
+    let keys: Vec<_> = generate_key_with_prefix(
+        [3, 4, 5],
+        [
+            PrefixExpansion {
+                base_index: 0,
+                expanded_length: 4,
+            },
+            PrefixExpansion {
+                base_index: 1,
+                expanded_length: 6,
+            },
+        ],
+    )
+    .collect();
+
+    let mut prefixes: Vec<Vec<u8>> = vec![vec![], vec![255]];
+    for key in keys.iter().step_by(7) {
+        for len in 0..=key.len() {
+            prefixes.push(key[..len].to_vec());
+        }
+    }
+    prefixes.push(keys[0][..3].iter().copied().chain([200]).collect());
+
+    for prefix in &prefixes {
+        check::<16>(&keys, prefix);
+        check::<2>(&keys, prefix);
+    }
+}
@@ -1,9 +1,10 @@
 use crate::{
     rust_nightly_apis::{assume, likely, unlikely},
-    AsBytes, ConcreteNodePtr, InnerNode, InnerNode4, LeafNode, MatchPrefixResult, Mismatch,
-    NodePtr, OpaqueNodePtr,
+    AsBytes, ConcreteNodePtr, Header, InnerNode, InnerNode4, LeafNode, MatchPrefixResult, Mismatch,
+    NodePtr, OpaqueNodePtr, PrefixStrategy,
 };
-use std::{error::Error, fmt, marker::PhantomData, ops::ControlFlow};
+use alloc::boxed::Box;
+use core::{fmt, marker::PhantomData, ops::ControlFlow};
 
 /// The results of a successful tree insert
 #[derive(Debug)]
@@ -14,6 +15,9 @@ pub struct InsertResult<'a, K, V, const PREFIX_LEN: usize> {
     pub existing_leaf: Option<LeafNode<K, V>>,
     /// The new tree root after the successful insert
     pub new_root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    /// True if the inner node the leaf was inserted into was full, and was
+    /// grown into a larger node type
+    pub inner_node_grown: bool,
 
     pub marker: PhantomData<(&'a mut K, &'a V)>,
 }
@@ -37,7 +41,8 @@ impl fmt::Display for InsertPrefixError {
     }
 }
 
-impl Error for InsertPrefixError {}
+#[cfg(feature = "std")]
+impl std::error::Error for InsertPrefixError {}
 
 /// This struct contains the results from searching for an insert point for
 /// a new node in the tree.
@@ -72,7 +77,14 @@ pub struct InsertPoint<K, V, const PREFIX_LEN: usize> {
 }
 
 impl<K, V, const PREFIX_LEN: usize> InsertPoint<K, V, PREFIX_LEN> {
-    pub fn apply<'a>(self, key: K, value: V) -> InsertResult<'a, K, V, PREFIX_LEN>
+    /// Insert the key and value at this point, storing the prefixes of new
+    /// inner nodes with the given [`PrefixStrategy`].
+    pub fn apply<'a>(
+        self,
+        key: K,
+        value: V,
+        prefix_strategy: PrefixStrategy,
+    ) -> InsertResult<'a, K, V, PREFIX_LEN>
     where
         K: AsBytes + 'a,
         V: 'a,
@@ -138,6 +150,9 @@ impl<K, V, const PREFIX_LEN: usize> InsertPoint<K, V, PREFIX_LEN> {
                 ConcreteNodePtr::Node16(inner_ptr) => {
                     write_new_child_in_existing_inner_node(inner_ptr, new_leaf_node, key_bytes_used)
                 },
+                ConcreteNodePtr::Node32(inner_ptr) => {
+                    write_new_child_in_existing_inner_node(inner_ptr, new_leaf_node, key_bytes_used)
+                },
                 ConcreteNodePtr::Node48(inner_ptr) => {
                     write_new_child_in_existing_inner_node(inner_ptr, new_leaf_node, key_bytes_used)
                 },
@@ -182,6 +197,9 @@ impl<K, V, const PREFIX_LEN: usize> InsertPoint<K, V, PREFIX_LEN> {
                 ConcreteNodePtr::Node16(inner_ptr) => {
                     write_inner_node(inner_ptr, key_byte, new_child)
                 },
+                ConcreteNodePtr::Node32(inner_ptr) => {
+                    write_inner_node(inner_ptr, key_byte, new_child)
+                },
                 ConcreteNodePtr::Node48(inner_ptr) => {
                     write_inner_node(inner_ptr, key_byte, new_child)
                 },
@@ -205,6 +223,7 @@ impl<K, V, const PREFIX_LEN: usize> InsertPoint<K, V, PREFIX_LEN> {
             ..
         } = self;
 
+        let mut inner_node_grown = false;
         let (new_inner_node, leaf_node_ptr) = match insert_type {
             InsertSearchResultType::MismatchPrefix {
                 mismatch,
@@ -233,15 +252,19 @@ impl<K, V, const PREFIX_LEN: usize> InsertPoint<K, V, PREFIX_LEN> {
                 let header = unsafe { mismatched_inner_node_ptr.header_mut_unchecked() };
                 let key_byte = key_bytes[key_bytes_used + mismatch.matched_bytes];
 
+                // prefix mismatch, need to split prefix into two separate nodes and take the
+                // common prefix into a new parent node. The matched bytes are the same in the
+                // key, so the full prefix can be copied from there.
+                let prefix = &key_bytes[key_bytes_used..(key_bytes_used + mismatch.matched_bytes)];
+                let mut new_n4 = InnerNode4::from_header(Header::with_strategy(
+                    prefix,
+                    mismatch.matched_bytes,
+                    prefix_strategy,
+                ));
+
                 let new_leaf_pointer = NodePtr::allocate_node_ptr(LeafNode::new(key, value));
                 let new_leaf_pointer_opaque = new_leaf_pointer.to_opaque();
 
-                // prefix mismatch, need to split prefix into two separate nodes and take the
-                // common prefix into a new parent node
-                let prefix = &header.read_prefix();
-                let prefix = &prefix[..prefix.len().min(mismatch.matched_bytes)];
-                let mut new_n4 = InnerNode4::from_prefix(prefix, mismatch.matched_bytes);
-
                 unsafe {
                     // SAFETY: This is a new node 4 so it's empty and we have
                     // space for writing new children. We also check the order
@@ -287,6 +310,7 @@ impl<K, V, const PREFIX_LEN: usize> InsertPoint<K, V, PREFIX_LEN> {
                     // the root is guaranteed to be unchanged, even if
                     // the old leaf was the root.
                     new_root: root,
+                    inner_node_grown: false,
                     marker: PhantomData,
                 };
             },
@@ -315,10 +339,11 @@ impl<K, V, const PREFIX_LEN: usize> InsertPoint<K, V, PREFIX_LEN> {
                     assume!(key_bytes_used <= new_key_bytes_used);
                 }
 
-                let mut new_n4 = InnerNode4::from_prefix(
+                let mut new_n4 = InnerNode4::from_header(Header::with_strategy(
                     &key_bytes[key_bytes_used..new_key_bytes_used],
                     new_key_bytes_used - key_bytes_used,
-                );
+                    prefix_strategy,
+                ));
 
                 let leaf_node_key_byte = leaf_bytes[new_key_bytes_used];
                 let new_leaf_node_key_byte = key_bytes[new_key_bytes_used];
@@ -349,11 +374,14 @@ impl<K, V, const PREFIX_LEN: usize> InsertPoint<K, V, PREFIX_LEN> {
                 )
             },
             InsertSearchResultType::IntoExisting { inner_node_ptr } => {
-                write_new_child_in_existing_node(
+                let (new_inner_node, leaf_node_ptr) = write_new_child_in_existing_node(
                     inner_node_ptr,
                     LeafNode::new(key, value),
                     key_bytes_used,
-                )
+                );
+                inner_node_grown = new_inner_node != inner_node_ptr;
+
+                (new_inner_node, leaf_node_ptr)
             },
         };
 
@@ -370,6 +398,7 @@ impl<K, V, const PREFIX_LEN: usize> InsertPoint<K, V, PREFIX_LEN> {
                 leaf_node_ptr,
                 existing_leaf: None,
                 new_root: root,
+                inner_node_grown,
                 marker: PhantomData,
             }
         } else {
@@ -379,6 +408,7 @@ impl<K, V, const PREFIX_LEN: usize> InsertPoint<K, V, PREFIX_LEN> {
                 leaf_node_ptr,
                 existing_leaf: None,
                 new_root: new_inner_node,
+                inner_node_grown,
                 marker: PhantomData,
             }
         }
@@ -508,6 +538,9 @@ where
             ConcreteNodePtr::Node16(inner_ptr) => {
                 test_prefix_identify_insert(inner_ptr, key_bytes, &mut current_depth)
             },
+            ConcreteNodePtr::Node32(inner_ptr) => {
+                test_prefix_identify_insert(inner_ptr, key_bytes, &mut current_depth)
+            },
             ConcreteNodePtr::Node48(inner_ptr) => {
                 test_prefix_identify_insert(inner_ptr, key_bytes, &mut current_depth)
             },
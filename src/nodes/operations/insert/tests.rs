@@ -4,6 +4,10 @@ use crate::{
     InnerNode, InnerNode4, InnerNodeCompressed, InsertPrefixError, LeafNode, NodePtr, NodeType,
     OpaqueNodePtr,
 };
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+};
 
 #[test]
 fn insert_to_small_trees() {
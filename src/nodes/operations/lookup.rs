@@ -1,6 +1,8 @@
 use crate::{
-    AsBytes, ConcreteNodePtr, InnerNode, LeafNode, MatchPrefixResult, NodePtr, OpaqueNodePtr,
+    metrics, AsBytes, ConcreteNodePtr, InnerNode, LeafNode, MatchPrefixResult, NodePtr,
+    OpaqueNodePtr,
 };
+use core::ops::ControlFlow;
 
 /// Search in the given tree for the value stored with the given key.
 ///
@@ -15,43 +17,156 @@ pub unsafe fn search_unchecked<K, V, const PREFIX_LEN: usize>(
 where
     K: AsBytes,
 {
-    let mut current_node = root;
-    let mut current_depth = 0;
+    // SAFETY: Covered by the safety requirement on the containing function
+    unsafe { search_unchecked_from(root, 0, key_bytes) }
+}
+
+/// Search for the value stored with the given key, starting from a node in the
+/// middle of the tree which is reached after `depth` bytes of the key.
+///
+/// # Safety
+///  - This function cannot be called concurrently with any mutating operation
+///    on `node` or any child node of `node`. This function will arbitrarily
+///    read to any child in the given tree.
+pub(crate) unsafe fn search_unchecked_from<K, V, const PREFIX_LEN: usize>(
+    node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    depth: usize,
+    key_bytes: &[u8],
+) -> Option<NodePtr<PREFIX_LEN, LeafNode<K, V>>>
+where
+    K: AsBytes,
+{
+    let mut current_node = node;
+    let mut current_depth = depth;
 
     loop {
-        current_node = match current_node.to_node_ptr() {
-            ConcreteNodePtr::Node4(inner_ptr) => unsafe {
-                // SAFETY: The safety requirement is covered by the safety requirement on the
-                // containing function
-                check_prefix_lookup_child(inner_ptr, key_bytes, &mut current_depth)
-            },
-            ConcreteNodePtr::Node16(inner_ptr) => unsafe {
-                // SAFETY: The safety requirement is covered by the safety requirement on the
-                // containing function
-                check_prefix_lookup_child(inner_ptr, key_bytes, &mut current_depth)
-            },
-            ConcreteNodePtr::Node48(inner_ptr) => unsafe {
-                // SAFETY: The safety requirement is covered by the safety requirement on the
-                // containing function
-                check_prefix_lookup_child(inner_ptr, key_bytes, &mut current_depth)
-            },
-            ConcreteNodePtr::Node256(inner_ptr) => unsafe {
-                // SAFETY: The safety requirement is covered by the safety requirement on the
-                // containing function
-                check_prefix_lookup_child(inner_ptr, key_bytes, &mut current_depth)
-            },
-            ConcreteNodePtr::LeafNode(leaf_node_ptr) => {
-                let leaf_node = leaf_node_ptr.read();
-
-                // Specifically we are matching the leaf node stored key against the full search
-                // key to confirm that it is the right value.
-                if leaf_node.matches_full_key(key_bytes) {
-                    return Some(leaf_node_ptr);
-                } else {
-                    return None;
-                }
-            },
-        }?;
+        // SAFETY: Covered by the safety requirement on the containing function
+        match unsafe { search_step(current_node, key_bytes, &mut current_depth) } {
+            ControlFlow::Continue(next_node) => current_node = next_node,
+            ControlFlow::Break(search_result) => return search_result,
+        }
+    }
+}
+
+/// The number of searches that [`search_batch_unchecked`] interleaves.
+pub(crate) const SEARCH_BATCH_LEN: usize = 16;
+
+/// Search in the given tree for the values stored with several keys, writing
+/// the result for each key to the same position in `results`.
+///
+/// Instead of finishing each search before starting the next one, the
+/// searches take turns advancing by one node, and the next node of each
+/// search is prefetched before its next turn. This way the cache misses of
+/// the different searches overlap, instead of being waited for one after the
+/// other.
+///
+/// # Panics
+///  - If there are more than [`SEARCH_BATCH_LEN`] keys, or `results` does not
+///    have the same length as `keys`.
+///
+/// # Safety
+///  - This function cannot be called concurrently with any mutating operation
+///    on `root` or any child node of `root`. This function will arbitrarily
+///    read to any child in the given tree.
+pub(crate) unsafe fn search_batch_unchecked<K, V, const PREFIX_LEN: usize>(
+    root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    keys: &[&[u8]],
+    results: &mut [Option<NodePtr<PREFIX_LEN, LeafNode<K, V>>>],
+) where
+    K: AsBytes,
+{
+    assert!(keys.len() <= SEARCH_BATCH_LEN);
+    assert_eq!(keys.len(), results.len());
+
+    let mut nodes = [root; SEARCH_BATCH_LEN];
+    let mut depths = [0; SEARCH_BATCH_LEN];
+    // The indices of the searches which are not finished yet, in the first
+    // `num_pending` slots
+    let mut pending: [usize; SEARCH_BATCH_LEN] = core::array::from_fn(|idx| idx);
+    let mut num_pending = keys.len();
+
+    root.prefetch();
+
+    while num_pending > 0 {
+        let mut pending_idx = 0;
+        while pending_idx < num_pending {
+            let search = pending[pending_idx];
+            // SAFETY: Covered by the safety requirement on the containing function
+            match unsafe { search_step(nodes[search], keys[search], &mut depths[search]) } {
+                ControlFlow::Continue(next_node) => {
+                    next_node.prefetch();
+                    nodes[search] = next_node;
+                    pending_idx += 1;
+                },
+                ControlFlow::Break(search_result) => {
+                    results[search] = search_result;
+                    num_pending -= 1;
+                    pending[pending_idx] = pending[num_pending];
+                },
+            }
+        }
+    }
+}
+
+/// Advance the search for the given key by one node, returning either the
+/// next node to search or the result of the search.
+///
+/// # Safety
+///  - This function cannot be called concurrently with any mutating operation
+///    on `node`.
+#[inline(always)]
+unsafe fn search_step<K, V, const PREFIX_LEN: usize>(
+    node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    key_bytes: &[u8],
+    current_depth: &mut usize,
+) -> ControlFlow<Option<NodePtr<PREFIX_LEN, LeafNode<K, V>>>, OpaqueNodePtr<K, V, PREFIX_LEN>>
+where
+    K: AsBytes,
+{
+    metrics::record_lookup_step();
+
+    let next_node = match node.to_node_ptr() {
+        ConcreteNodePtr::Node4(inner_ptr) => unsafe {
+            // SAFETY: The safety requirement is covered by the safety requirement on the
+            // containing function
+            check_prefix_lookup_child(inner_ptr, key_bytes, current_depth)
+        },
+        ConcreteNodePtr::Node16(inner_ptr) => unsafe {
+            // SAFETY: The safety requirement is covered by the safety requirement on the
+            // containing function
+            check_prefix_lookup_child(inner_ptr, key_bytes, current_depth)
+        },
+        ConcreteNodePtr::Node32(inner_ptr) => unsafe {
+            // SAFETY: The safety requirement is covered by the safety requirement on the
+            // containing function
+            check_prefix_lookup_child(inner_ptr, key_bytes, current_depth)
+        },
+        ConcreteNodePtr::Node48(inner_ptr) => unsafe {
+            // SAFETY: The safety requirement is covered by the safety requirement on the
+            // containing function
+            check_prefix_lookup_child(inner_ptr, key_bytes, current_depth)
+        },
+        ConcreteNodePtr::Node256(inner_ptr) => unsafe {
+            // SAFETY: The safety requirement is covered by the safety requirement on the
+            // containing function
+            check_prefix_lookup_child(inner_ptr, key_bytes, current_depth)
+        },
+        ConcreteNodePtr::LeafNode(leaf_node_ptr) => {
+            let leaf_node = leaf_node_ptr.read();
+
+            // Specifically we are matching the leaf node stored key against the full search
+            // key to confirm that it is the right value.
+            if leaf_node.matches_full_key(key_bytes) {
+                return ControlFlow::Break(Some(leaf_node_ptr));
+            } else {
+                return ControlFlow::Break(None);
+            }
+        },
+    };
+
+    match next_node {
+        Some(next_node) => ControlFlow::Continue(next_node),
+        None => ControlFlow::Break(None),
     }
 }
 
@@ -1,7 +1,9 @@
 use crate::{
-    nodes::NodePtr, search_unchecked, InnerNode, InnerNode16, InnerNode256, InnerNode4,
-    InnerNode48, LeafNode, OpaqueNodePtr,
+    nodes::NodePtr, search_batch_unchecked, search_unchecked, tests_common::generate_keys_skewed,
+    InnerNode, InnerNode16, InnerNode256, InnerNode4, InnerNode48, LeafNode, OpaqueNodePtr,
+    TreeMap, SEARCH_BATCH_LEN,
 };
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
 
 #[test]
 fn lookup_on_non_copy_leaf() {
@@ -677,3 +679,30 @@ fn lookup_on_n4_n4_layer_tree() {
         assert!(search_unchecked(root, [1, 2, 4, 7, 80, 3].as_ref()).is_none());
     }
 }
+
+#[test]
+fn batch_lookup_matches_single_lookup() {
+    let mut tree = TreeMap::<Box<[u8]>, usize>::new();
+    for (idx, key) in generate_keys_skewed(64).enumerate() {
+        tree.try_insert(key, idx).unwrap();
+    }
+    let root = tree.root.unwrap();
+
+    let mut keys: Vec<Vec<u8>> = generate_keys_skewed(64).step_by(3).map(Vec::from).collect();
+    // Keys which are missing, or a prefix of an existing key
+    keys.extend([vec![], vec![0], vec![255, 0], vec![255; 65]]);
+    let key_bytes: Vec<&[u8]> = keys.iter().map(Vec::as_slice).collect();
+
+    for batch_len in [1, 2, 7, SEARCH_BATCH_LEN] {
+        for batch in key_bytes.chunks(batch_len) {
+            let mut results = vec![None; batch.len()];
+            // SAFETY: There are no concurrent mutations of the tree
+            unsafe {
+                search_batch_unchecked(root, batch, &mut results);
+                for (key, result) in batch.iter().zip(results) {
+                    assert_eq!(result, search_unchecked(root, key), "{key:?}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,652 @@
+use crate::{
+    deallocate_tree, AsBytes, ConcreteNodePtr, Header, InnerNode, InnerNode16, InnerNode256,
+    InnerNode32, InnerNode4, InnerNode48, LeafNode, NodePtr, OpaqueNodePtr, PrefixStrategy,
+};
+use alloc::vec::Vec;
+
+/// The results of a successful merge operation.
+#[derive(Debug)]
+pub struct MergeResult<K, V, const PREFIX_LEN: usize> {
+    /// The new root node for the merged tree.
+    ///
+    /// If `None`, that means the merged tree is empty.
+    pub new_root: Option<OpaqueNodePtr<K, V, PREFIX_LEN>>,
+    /// The number of keys that were present in both trees.
+    pub num_matched: usize,
+    /// The number of keys present in both trees that were kept in the merged
+    /// tree.
+    pub num_matched_kept: usize,
+    /// The entries from the right tree that were not merged because their key
+    /// conflicted with a key from the left tree, in key order, see
+    /// [`merge_trees`].
+    pub right_rejected: Vec<(K, V)>,
+}
+
+/// Merge two trees into a single tree, reusing the nodes of both trees.
+///
+/// Keys that are present in both trees are passed to `merge_values` with both
+/// values, and are kept in the merged tree (with the left key) if it returns
+/// `Some`. Subtrees which are only present in one of the trees are moved into
+/// the merged tree as a whole if the corresponding `keep_left_only` or
+/// `keep_right_only` flag is set, otherwise they are deallocated. New inner
+/// nodes store their prefixes using `prefix_strategy`, but the nodes which are
+/// reused keep their own strategy, so both trees should already use
+/// `prefix_strategy`, see [`set_prefix_strategy`].
+///
+/// If both `keep_left_only` and `keep_right_only` are set and a key from one
+/// tree is a prefix of a key from the other tree, the left entries are kept and
+/// the conflicting right entries are removed from the tree, the same as if the
+/// right entries had been inserted into the left tree with
+/// [`TreeMap::try_insert`](crate::TreeMap::try_insert) and rejected with an
+/// [`InsertPrefixError`](crate::InsertPrefixError). They are returned in
+/// [`MergeResult::right_rejected`].
+///
+/// # Safety
+///  - `left` and `right` must be the roots of two distinct trees, and there
+///    must be no other references to any node in either tree.
+///  - After this function returns, the nodes of both trees must not be used
+///    except through the returned root.
+///
+/// # Panics
+///  - If `merge_values` panics, the nodes of both trees are leaked.
+pub unsafe fn merge_trees<K, V, F, const PREFIX_LEN: usize>(
+    left: Option<OpaqueNodePtr<K, V, PREFIX_LEN>>,
+    right: Option<OpaqueNodePtr<K, V, PREFIX_LEN>>,
+    keep_left_only: bool,
+    keep_right_only: bool,
+    prefix_strategy: PrefixStrategy,
+    merge_values: F,
+) -> MergeResult<K, V, PREFIX_LEN>
+where
+    K: AsBytes,
+    F: FnMut(&K, V, V) -> Option<V>,
+{
+    let mut merger = Merger {
+        keep_left_only,
+        keep_right_only,
+        prefix_strategy,
+        merge_values,
+        num_matched: 0,
+        num_matched_kept: 0,
+        right_rejected: Vec::new(),
+    };
+
+    let new_root = match (left, right) {
+        // SAFETY: Covered by the safety requirements of the containing function
+        (Some(left), Some(right)) => unsafe { merger.merge_nodes(left, right, 0) },
+        // SAFETY: Covered by the safety requirements of the containing function
+        (Some(left), None) => unsafe { merger.keep_or_deallocate(left, keep_left_only) },
+        // SAFETY: Covered by the safety requirements of the containing function
+        (None, Some(right)) => unsafe { merger.keep_or_deallocate(right, keep_right_only) },
+        (None, None) => None,
+    };
+
+    MergeResult {
+        new_root,
+        num_matched: merger.num_matched,
+        num_matched_kept: merger.num_matched_kept,
+        right_rejected: merger.right_rejected,
+    }
+}
+
+/// The state carried through a single merge of two trees.
+struct Merger<K, V, F> {
+    keep_left_only: bool,
+    keep_right_only: bool,
+    prefix_strategy: PrefixStrategy,
+    merge_values: F,
+    num_matched: usize,
+    num_matched_kept: usize,
+    right_rejected: Vec<(K, V)>,
+}
+
+/// The result of comparing the compressed paths of two nodes which start at
+/// the same depth.
+///
+/// The path of an inner node is its full prefix, and the path of a leaf node
+/// is the remainder of its key.
+struct PathComparison<K, V, const PREFIX_LEN: usize> {
+    /// The number of bytes that are equal in both paths.
+    matched_bytes: usize,
+    /// The byte in the left path after the matched bytes, if the left path is
+    /// longer than the matched bytes.
+    left_byte: Option<u8>,
+    /// The byte in the right path after the matched bytes, if the right path
+    /// is longer than the matched bytes.
+    right_byte: Option<u8>,
+    /// A leaf used to reconstruct the prefix of the left node, if the prefix
+    /// is longer than `PREFIX_LEN`.
+    left_leaf: Option<NodePtr<PREFIX_LEN, LeafNode<K, V>>>,
+    /// A leaf used to reconstruct the prefix of the right node, if the prefix
+    /// is longer than `PREFIX_LEN`.
+    right_leaf: Option<NodePtr<PREFIX_LEN, LeafNode<K, V>>>,
+}
+
+/// Read the compressed path of the given node starting at `depth`, along
+/// with the leaf used to reconstruct it, if any.
+///
+/// # Safety
+///  - The returned slice must not outlive any of the nodes in the subtree
+///    rooted at `node`, and there must be no concurrent mutation of them.
+pub(crate) unsafe fn read_path<'a, K: AsBytes + 'a, V: 'a, const PREFIX_LEN: usize>(
+    node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    depth: usize,
+) -> (&'a [u8], Option<NodePtr<PREFIX_LEN, LeafNode<K, V>>>) {
+    fn inner_path<'a, N: InnerNode<PREFIX_LEN> + 'a, const PREFIX_LEN: usize>(
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+        depth: usize,
+    ) -> (
+        &'a [u8],
+        Option<NodePtr<PREFIX_LEN, LeafNode<N::Key, N::Value>>>,
+    )
+    where
+        N::Key: AsBytes,
+    {
+        // SAFETY: Covered by the safety requirements of the containing function
+        let inner_node = unsafe { inner_ptr.as_ref() };
+        inner_node.read_full_prefix(depth)
+    }
+
+    match node.to_node_ptr() {
+        ConcreteNodePtr::Node4(inner_ptr) => inner_path(inner_ptr, depth),
+        ConcreteNodePtr::Node16(inner_ptr) => inner_path(inner_ptr, depth),
+        ConcreteNodePtr::Node32(inner_ptr) => inner_path(inner_ptr, depth),
+        ConcreteNodePtr::Node48(inner_ptr) => inner_path(inner_ptr, depth),
+        ConcreteNodePtr::Node256(inner_ptr) => inner_path(inner_ptr, depth),
+        ConcreteNodePtr::LeafNode(leaf_ptr) => {
+            // SAFETY: Covered by the safety requirements of the containing function
+            let key = unsafe { leaf_ptr.as_key_ref() }.as_bytes();
+            (&key[depth..], None)
+        },
+    }
+}
+
+/// Remove `len` bytes from the front of the prefix of the given node, if it is
+/// an inner node.
+///
+/// # Safety
+///  - There must be no other references to the given node.
+///  - `leaf_ptr` must be present if the prefix of the node is longer than
+///    `PREFIX_LEN`, and must be a leaf of the subtree rooted at `node`.
+unsafe fn trim_prefix<K: AsBytes, V, const PREFIX_LEN: usize>(
+    node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    len: usize,
+    depth: usize,
+    leaf_ptr: Option<NodePtr<PREFIX_LEN, LeafNode<K, V>>>,
+) {
+    // SAFETY: Covered by the safety requirements of the containing function
+    if let Some(header) = unsafe { node.header_mut() } {
+        match leaf_ptr {
+            Some(leaf_ptr) if header.prefix_len() > PREFIX_LEN => {
+                header.ltrim_by_with_leaf(len, depth, leaf_ptr)
+            },
+            _ => header.ltrim_by(len),
+        }
+    }
+}
+
+/// Change the prefix strategy of every inner node in the subtree rooted at
+/// `root`, which starts at `depth`.
+///
+/// With [`PrefixStrategy::Pessimistic`], the prefixes longer than `PREFIX_LEN`
+/// are spilled using a leaf of their node, the same as if the subtree had been
+/// built with that strategy.
+///
+/// # Safety
+///  - There must be no other references to any node in the subtree.
+pub unsafe fn set_prefix_strategy<K: AsBytes, V, const PREFIX_LEN: usize>(
+    root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    depth: usize,
+    prefix_strategy: PrefixStrategy,
+) {
+    fn push_children<N: InnerNode<PREFIX_LEN>, const PREFIX_LEN: usize>(
+        stack: &mut Vec<(OpaqueNodePtr<N::Key, N::Value, PREFIX_LEN>, usize)>,
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+        depth: usize,
+    ) {
+        // SAFETY: Covered by the safety requirements of the containing function
+        let inner_node = unsafe { inner_ptr.as_ref() };
+        let child_depth = depth + inner_node.header().prefix_len() + 1;
+        stack.extend(inner_node.iter().map(|(_, child)| (child, child_depth)));
+    }
+
+    let mut stack = Vec::from([(root, depth)]);
+    while let Some((node, depth)) = stack.pop() {
+        if node.is::<LeafNode<K, V>>() {
+            continue;
+        }
+
+        let leaf_ptr = if prefix_strategy == PrefixStrategy::Pessimistic {
+            // SAFETY: The path is not used, only the leaf which is returned if the
+            // prefix is longer than `PREFIX_LEN` and not spilled yet
+            unsafe { read_path(node, depth) }.1
+        } else {
+            None
+        };
+
+        // SAFETY: Covered by the safety requirements of the containing function, and
+        // the reference is not used after the node is read below
+        if let Some(header) = unsafe { node.header_mut() } {
+            header.set_strategy(prefix_strategy);
+            if let Some(leaf_ptr) = leaf_ptr {
+                header.ltrim_by_with_leaf(0, depth, leaf_ptr);
+            }
+        }
+
+        match node.to_node_ptr() {
+            ConcreteNodePtr::Node4(inner_ptr) => push_children(&mut stack, inner_ptr, depth),
+            ConcreteNodePtr::Node16(inner_ptr) => push_children(&mut stack, inner_ptr, depth),
+            ConcreteNodePtr::Node32(inner_ptr) => push_children(&mut stack, inner_ptr, depth),
+            ConcreteNodePtr::Node48(inner_ptr) => push_children(&mut stack, inner_ptr, depth),
+            ConcreteNodePtr::Node256(inner_ptr) => push_children(&mut stack, inner_ptr, depth),
+            ConcreteNodePtr::LeafNode(_) => unreachable!("leaves are skipped above"),
+        }
+    }
+}
+
+/// Copy the header of the given inner node and collect its children, then
+/// deallocate the node without touching the children.
+///
+/// # Safety
+///  - `node` must be an inner node, and there must be no other references to
+///    it. The node must not be used after this function returns.
+unsafe fn take_children<K, V, const PREFIX_LEN: usize>(
+    node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+) -> (
+    Header<PREFIX_LEN>,
+    Vec<(u8, OpaqueNodePtr<K, V, PREFIX_LEN>)>,
+) {
+    fn take<N: InnerNode<PREFIX_LEN>, const PREFIX_LEN: usize>(
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+    ) -> (
+        Header<PREFIX_LEN>,
+        Vec<(u8, OpaqueNodePtr<N::Key, N::Value, PREFIX_LEN>)>,
+    ) {
+        // SAFETY: Covered by the safety requirements of the containing function,
+        // the reference is dropped before the node is deallocated.
+        let inner_node = unsafe { inner_ptr.as_ref() };
+        let header = inner_node.header();
+        let header = header.clone_prefix();
+        let children = inner_node.iter().collect();
+
+        // SAFETY: Covered by the safety requirements of the containing function
+        drop(unsafe { NodePtr::deallocate_node_ptr(inner_ptr) });
+
+        (header, children)
+    }
+
+    match node.to_node_ptr() {
+        ConcreteNodePtr::Node4(inner_ptr) => take(inner_ptr),
+        ConcreteNodePtr::Node16(inner_ptr) => take(inner_ptr),
+        ConcreteNodePtr::Node32(inner_ptr) => take(inner_ptr),
+        ConcreteNodePtr::Node48(inner_ptr) => take(inner_ptr),
+        ConcreteNodePtr::Node256(inner_ptr) => take(inner_ptr),
+        ConcreteNodePtr::LeafNode(_) => panic!("cannot take children of a leaf node"),
+    }
+}
+
+/// Move the entries of the subtree rooted at `node` to `entries` in key order,
+/// and deallocate all the nodes of the subtree.
+///
+/// # Safety
+///  - There must be no other references to any node in the subtree. The nodes
+///    must not be used after this function returns.
+unsafe fn take_entries<K, V, const PREFIX_LEN: usize>(
+    node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    entries: &mut Vec<(K, V)>,
+) {
+    let mut stack = Vec::from([node]);
+    while let Some(node) = stack.pop() {
+        match node.cast::<LeafNode<K, V>>() {
+            Some(leaf_ptr) => {
+                // SAFETY: Covered by the safety requirements of the containing function
+                entries.push(unsafe { NodePtr::deallocate_node_ptr(leaf_ptr) }.into_entry());
+            },
+            None => {
+                // SAFETY: Covered by the safety requirements of the containing function
+                let (_, children) = unsafe { take_children(node) };
+                stack.extend(children.into_iter().rev().map(|(_, child)| child));
+            },
+        }
+    }
+}
+
+/// Build an inner node from the given header and children, using the
+/// smallest node type that can hold all the children.
+///
+/// If there are no children, return `None`. If there is only a single child,
+/// no inner node is allocated and the header is prepended to the child.
+///
+/// # Safety
+///  - There must be no other references to any of the children.
+unsafe fn build_inner_node<K, V, const PREFIX_LEN: usize>(
+    header: Header<PREFIX_LEN>,
+    children: &[(u8, OpaqueNodePtr<K, V, PREFIX_LEN>)],
+) -> Option<OpaqueNodePtr<K, V, PREFIX_LEN>> {
+    fn build<N: InnerNode<PREFIX_LEN>, const PREFIX_LEN: usize>(
+        header: Header<PREFIX_LEN>,
+        children: &[(u8, OpaqueNodePtr<N::Key, N::Value, PREFIX_LEN>)],
+    ) -> OpaqueNodePtr<N::Key, N::Value, PREFIX_LEN> {
+        let mut inner_node = N::from_header(header);
+        for (key_fragment, child_pointer) in children.iter().copied() {
+            inner_node.write_child(key_fragment, child_pointer);
+        }
+        NodePtr::allocate_node_ptr(inner_node).to_opaque()
+    }
+
+    match children {
+        [] => None,
+        [(key_byte, child)] => {
+            // SAFETY: Covered by the safety requirements of the containing function
+            if let Some(child_header) = unsafe { child.header_mut() } {
+                child_header.prepend_prefix(&header, *key_byte);
+            }
+            Some(*child)
+        },
+        _ if children.len() <= 4 => Some(build::<InnerNode4<K, V, PREFIX_LEN>, PREFIX_LEN>(
+            header, children,
+        )),
+        _ if children.len() <= 16 => Some(build::<InnerNode16<K, V, PREFIX_LEN>, PREFIX_LEN>(
+            header, children,
+        )),
+        _ if children.len() <= 32 => Some(build::<InnerNode32<K, V, PREFIX_LEN>, PREFIX_LEN>(
+            header, children,
+        )),
+        _ if children.len() <= 48 => Some(build::<InnerNode48<K, V, PREFIX_LEN>, PREFIX_LEN>(
+            header, children,
+        )),
+        _ => Some(build::<InnerNode256<K, V, PREFIX_LEN>, PREFIX_LEN>(
+            header, children,
+        )),
+    }
+}
+
+impl<K, V, F> Merger<K, V, F> {
+    /// Return the given subtree if `keep` is set, otherwise deallocate it.
+    ///
+    /// # Safety
+    ///  - There must be no other references to any node in the subtree.
+    unsafe fn keep_or_deallocate<const PREFIX_LEN: usize>(
+        &mut self,
+        node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        keep: bool,
+    ) -> Option<OpaqueNodePtr<K, V, PREFIX_LEN>> {
+        if keep {
+            Some(node)
+        } else {
+            // SAFETY: Covered by the safety requirements of the containing function
+            unsafe { deallocate_tree(node) };
+            None
+        }
+    }
+
+    /// Merge the two subtrees which both start at `depth`.
+    ///
+    /// # Safety
+    ///  - There must be no other references to any node in either subtree.
+    unsafe fn merge_nodes<const PREFIX_LEN: usize>(
+        &mut self,
+        left: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        right: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        depth: usize,
+    ) -> Option<OpaqueNodePtr<K, V, PREFIX_LEN>>
+    where
+        K: AsBytes,
+        F: FnMut(&K, V, V) -> Option<V>,
+    {
+        let comparison = {
+            // SAFETY: The paths are only used in this block, before any node is
+            // modified or deallocated.
+            let (left_path, left_leaf) = unsafe { read_path(left, depth) };
+            // SAFETY: Same as above
+            let (right_path, right_leaf) = unsafe { read_path(right, depth) };
+
+            let matched_bytes = left_path
+                .iter()
+                .zip(right_path)
+                .take_while(|(a, b)| **a == **b)
+                .count();
+
+            PathComparison {
+                matched_bytes,
+                left_byte: left_path.get(matched_bytes).copied(),
+                right_byte: right_path.get(matched_bytes).copied(),
+                left_leaf,
+                right_leaf,
+            }
+        };
+
+        let left_is_leaf = left.is::<LeafNode<K, V>>();
+        let right_is_leaf = right.is::<LeafNode<K, V>>();
+        let child_depth = depth + comparison.matched_bytes + 1;
+
+        match (comparison.left_byte, comparison.right_byte) {
+            (None, None) if left_is_leaf && right_is_leaf => {
+                // SAFETY: Covered by the safety requirements of the containing function
+                unsafe { self.merge_leaves(left, right) }
+            },
+            (None, None) if !left_is_leaf && !right_is_leaf => {
+                // Both prefixes are equal, merge the children of both nodes
+                // SAFETY: Covered by the safety requirements of the containing function
+                let (header, left_children) = unsafe { take_children(left) };
+                // SAFETY: Covered by the safety requirements of the containing function
+                let (_, right_children) = unsafe { take_children(right) };
+                // SAFETY: Covered by the safety requirements of the containing function
+                unsafe { self.merge_children(header, left_children, right_children, child_depth) }
+            },
+            (None, Some(right_byte)) if !left_is_leaf => {
+                // The left prefix is a prefix of the right path, the right node
+                // becomes a child of the left node
+                // SAFETY: Covered by the safety requirements of the containing function
+                unsafe {
+                    trim_prefix(
+                        right,
+                        comparison.matched_bytes + 1,
+                        depth,
+                        comparison.right_leaf,
+                    )
+                };
+                // SAFETY: Covered by the safety requirements of the containing function
+                let (header, left_children) = unsafe { take_children(left) };
+                // SAFETY: Covered by the safety requirements of the containing function
+                unsafe {
+                    self.merge_children(
+                        header,
+                        left_children,
+                        [(right_byte, right)].into(),
+                        child_depth,
+                    )
+                }
+            },
+            (Some(left_byte), None) if !right_is_leaf => {
+                // The right prefix is a prefix of the left path, the left node
+                // becomes a child of the right node
+                // SAFETY: Covered by the safety requirements of the containing function
+                unsafe {
+                    trim_prefix(
+                        left,
+                        comparison.matched_bytes + 1,
+                        depth,
+                        comparison.left_leaf,
+                    )
+                };
+                // SAFETY: Covered by the safety requirements of the containing function
+                let (header, right_children) = unsafe { take_children(right) };
+                // SAFETY: Covered by the safety requirements of the containing function
+                unsafe {
+                    self.merge_children(
+                        header,
+                        [(left_byte, left)].into(),
+                        right_children,
+                        child_depth,
+                    )
+                }
+            },
+            // SAFETY: Covered by the safety requirements of the containing function
+            _ => unsafe { self.merge_disjoint(left, right, depth, comparison) },
+        }
+    }
+
+    /// Merge two leaves with the same key.
+    ///
+    /// # Safety
+    ///  - There must be no other references to either leaf.
+    unsafe fn merge_leaves<const PREFIX_LEN: usize>(
+        &mut self,
+        left: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        right: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    ) -> Option<OpaqueNodePtr<K, V, PREFIX_LEN>>
+    where
+        F: FnMut(&K, V, V) -> Option<V>,
+    {
+        let (Some(left), Some(right)) = (
+            left.cast::<LeafNode<K, V>>(),
+            right.cast::<LeafNode<K, V>>(),
+        ) else {
+            unreachable!("both nodes should be leaves");
+        };
+
+        // SAFETY: Covered by the safety requirements of the containing function
+        let (key, left_value) = unsafe { NodePtr::deallocate_node_ptr(left) }.into_entry();
+        // SAFETY: Covered by the safety requirements of the containing function
+        let (_, right_value) = unsafe { NodePtr::deallocate_node_ptr(right) }.into_entry();
+
+        self.num_matched += 1;
+        let value = (self.merge_values)(&key, left_value, right_value)?;
+        self.num_matched_kept += 1;
+
+        Some(NodePtr::allocate_node_ptr(LeafNode::new(key, value)).to_opaque())
+    }
+
+    /// Merge two subtrees which do not share any keys.
+    ///
+    /// # Safety
+    ///  - There must be no other references to any node in either subtree.
+    unsafe fn merge_disjoint<const PREFIX_LEN: usize>(
+        &mut self,
+        left: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        right: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        depth: usize,
+        comparison: PathComparison<K, V, PREFIX_LEN>,
+    ) -> Option<OpaqueNodePtr<K, V, PREFIX_LEN>>
+    where
+        K: AsBytes,
+    {
+        match (self.keep_left_only, self.keep_right_only) {
+            (true, true) => {
+                let (Some(left_byte), Some(right_byte)) =
+                    (comparison.left_byte, comparison.right_byte)
+                else {
+                    // One of the paths ends inside the other, so one of the nodes is
+                    // a leaf whose key is a prefix of every key in the other subtree.
+                    // Keep the left subtree, like an insert of the right keys would.
+                    // SAFETY: Covered by the safety requirements of the containing
+                    // function
+                    unsafe { take_entries(right, &mut self.right_rejected) };
+                    return Some(left);
+                };
+
+                let mut new_node = {
+                    // SAFETY: The path is only used in this block, before any node is
+                    // modified.
+                    let (left_path, _) = unsafe { read_path(left, depth) };
+                    InnerNode4::from_header(Header::with_strategy(
+                        &left_path[..comparison.matched_bytes],
+                        comparison.matched_bytes,
+                        self.prefix_strategy,
+                    ))
+                };
+
+                // SAFETY: Covered by the safety requirements of the containing function
+                unsafe {
+                    trim_prefix(
+                        left,
+                        comparison.matched_bytes + 1,
+                        depth,
+                        comparison.left_leaf,
+                    );
+                    trim_prefix(
+                        right,
+                        comparison.matched_bytes + 1,
+                        depth,
+                        comparison.right_leaf,
+                    );
+                }
+
+                new_node.write_child(left_byte, left);
+                new_node.write_child(right_byte, right);
+
+                Some(NodePtr::allocate_node_ptr(new_node).to_opaque())
+            },
+            (keep_left, keep_right) => {
+                // SAFETY: Covered by the safety requirements of the containing function
+                unsafe {
+                    let left = self.keep_or_deallocate(left, keep_left);
+                    let right = self.keep_or_deallocate(right, keep_right);
+                    left.or(right)
+                }
+            },
+        }
+    }
+
+    /// Merge the children of two inner nodes with the same prefix, and build a
+    /// new inner node with the given header from the results.
+    ///
+    /// # Safety
+    ///  - There must be no other references to any of the children, or any node
+    ///    in their subtrees.
+    unsafe fn merge_children<const PREFIX_LEN: usize>(
+        &mut self,
+        header: Header<PREFIX_LEN>,
+        left_children: Vec<(u8, OpaqueNodePtr<K, V, PREFIX_LEN>)>,
+        right_children: Vec<(u8, OpaqueNodePtr<K, V, PREFIX_LEN>)>,
+        child_depth: usize,
+    ) -> Option<OpaqueNodePtr<K, V, PREFIX_LEN>>
+    where
+        K: AsBytes,
+        F: FnMut(&K, V, V) -> Option<V>,
+    {
+        let mut children = Vec::with_capacity(left_children.len() + right_children.len());
+        let mut left_children = left_children.into_iter().peekable();
+        let mut right_children = right_children.into_iter().peekable();
+
+        loop {
+            let merged = match (left_children.peek(), right_children.peek()) {
+                (Some((left_byte, _)), Some((right_byte, _))) if left_byte == right_byte => {
+                    let (key_byte, left) = left_children.next().unwrap();
+                    let (_, right) = right_children.next().unwrap();
+                    // SAFETY: Covered by the safety requirements of the containing function
+                    unsafe { self.merge_nodes(left, right, child_depth) }
+                        .map(|child| (key_byte, child))
+                },
+                (Some((left_byte, _)), Some((right_byte, _))) if left_byte > right_byte => {
+                    let (key_byte, right) = right_children.next().unwrap();
+                    // SAFETY: Covered by the safety requirements of the containing function
+                    unsafe { self.keep_or_deallocate(right, self.keep_right_only) }
+                        .map(|child| (key_byte, child))
+                },
+                (Some(_), _) => {
+                    let (key_byte, left) = left_children.next().unwrap();
+                    // SAFETY: Covered by the safety requirements of the containing function
+                    unsafe { self.keep_or_deallocate(left, self.keep_left_only) }
+                        .map(|child| (key_byte, child))
+                },
+                (None, Some(_)) => {
+                    let (key_byte, right) = right_children.next().unwrap();
+                    // SAFETY: Covered by the safety requirements of the containing function
+                    unsafe { self.keep_or_deallocate(right, self.keep_right_only) }
+                        .map(|child| (key_byte, child))
+                },
+                (None, None) => break,
+            };
+
+            children.extend(merged);
+        }
+
+        // SAFETY: Covered by the safety requirements of the containing function
+        unsafe { build_inner_node(header, &children) }
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,343 @@
+use crate::{
+    tests_common::{
+        generate_key_fixed_length, generate_key_with_prefix, generate_keys_skewed, PrefixExpansion,
+    },
+    visitor::WellFormedChecker,
+    PrefixStrategy, TreeMap,
+};
+use alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
+
+type Oracle<K> = BTreeMap<K, u32>;
+
+fn build<K: Clone + Ord + crate::AsBytes, const PREFIX_LEN: usize>(
+    keys: &[K],
+    filter: impl Fn(usize) -> bool,
+    offset: u32,
+) -> (TreeMap<K, u32, PREFIX_LEN>, Oracle<K>) {
+    let mut tree = TreeMap::with_prefix_len();
+    let mut oracle = BTreeMap::new();
+    for (idx, key) in keys.iter().enumerate().filter(|(idx, _)| filter(*idx)) {
+        tree.try_insert(key.clone(), idx as u32 + offset).unwrap();
+        oracle.insert(key.clone(), idx as u32 + offset);
+    }
+    (tree, oracle)
+}
+
+fn assert_matches_oracle<K, const PREFIX_LEN: usize>(
+    tree: &TreeMap<K, u32, PREFIX_LEN>,
+    oracle: &Oracle<K>,
+) where
+    K: Clone + Ord + crate::AsBytes + core::fmt::Debug,
+{
+    if tree.is_empty() {
+        assert!(tree.root.is_none());
+    } else {
+        WellFormedChecker::check(tree).expect("merged tree should be well-formed");
+    }
+    assert_eq!(tree.len(), oracle.len());
+    assert_eq!(
+        tree.iter()
+            .map(|(k, v)| (k.clone(), *v))
+            .collect::<Vec<_>>(),
+        oracle
+            .iter()
+            .map(|(k, v)| (k.clone(), *v))
+            .collect::<Vec<_>>()
+    );
+}
+
+/// Run all set operations with left/right subsets of the given keys selected by
+/// the filters, comparing the results against a `BTreeMap`.
+fn check_all_operations<K, const PREFIX_LEN: usize>(
+    keys: &[K],
+    left_filter: impl Fn(usize) -> bool + Copy,
+    right_filter: impl Fn(usize) -> bool + Copy,
+) where
+    K: Clone + Ord + crate::NoPrefixesBytes + core::fmt::Debug,
+{
+    let merge = |_: &K, l: u32, r: u32| l.wrapping_mul(31).wrapping_add(r);
+    let keep_odd = |_: &K, l: u32, r: u32| Some(l + r).filter(|v| v % 2 == 1);
+
+    // union
+    let (mut tree, mut oracle) = build::<K, PREFIX_LEN>(keys, left_filter, 0);
+    let (other_tree, other_oracle) = build::<K, PREFIX_LEN>(keys, right_filter, 1000);
+    tree.union_with(other_tree, merge);
+    for (k, r) in other_oracle {
+        let v = match oracle.remove(&k) {
+            Some(l) => merge(&k, l, r),
+            None => r,
+        };
+        oracle.insert(k, v);
+    }
+    assert_matches_oracle(&tree, &oracle);
+
+    // intersection
+    let (mut tree, oracle) = build::<K, PREFIX_LEN>(keys, left_filter, 0);
+    let (other_tree, other_oracle) = build::<K, PREFIX_LEN>(keys, right_filter, 1000);
+    tree.intersection_with(other_tree, merge);
+    let expected = oracle
+        .into_iter()
+        .filter_map(|(k, l)| other_oracle.get(&k).map(|r| (k.clone(), merge(&k, l, *r))))
+        .collect();
+    assert_matches_oracle(&tree, &expected);
+
+    // difference
+    let (mut tree, oracle) = build::<K, PREFIX_LEN>(keys, left_filter, 0);
+    let (other_tree, other_oracle) = build::<K, PREFIX_LEN>(keys, right_filter, 1000);
+    tree.difference(other_tree, keep_odd);
+    let expected = oracle
+        .into_iter()
+        .filter_map(|(k, l)| match other_oracle.get(&k) {
+            Some(r) => keep_odd(&k, l, *r).map(|v| (k, v)),
+            None => Some((k, l)),
+        })
+        .collect();
+    assert_matches_oracle(&tree, &expected);
+
+    // symmetric difference
+    let (mut tree, oracle) = build::<K, PREFIX_LEN>(keys, left_filter, 0);
+    let (other_tree, mut other_oracle) = build::<K, PREFIX_LEN>(keys, right_filter, 1000);
+    tree.symmetric_difference(other_tree, keep_odd);
+    let mut expected: Oracle<K> = oracle
+        .into_iter()
+        .filter_map(|(k, l)| match other_oracle.remove(&k) {
+            Some(r) => keep_odd(&k, l, r).map(|v| (k, v)),
+            None => Some((k, l)),
+        })
+        .collect();
+    expected.extend(other_oracle);
+    assert_matches_oracle(&tree, &expected);
+}
+
+fn to_arrays<const N: usize>(keys: impl Iterator<Item = Box<[u8]>>) -> Vec<[u8; N]> {
+    keys.map(|key| (*key).try_into().unwrap()).collect()
+}
+
+#[test]
+fn merge_fixed_length_keys() {
+    let keys = to_arrays::<3>(generate_key_fixed_length([15, 3, 200]));
+
+    for (left, right) in [
+        (|idx| idx % 2 == 0, |idx| idx % 3 == 0),
+        (|idx| idx < 500, |idx| idx >= 500),
+        (|idx| idx % 7 != 0, |_| false),
+        (|_| false, |idx| idx % 5 != 0),
+        (|_| true, |_| true),
+    ] as [(fn(usize) -> bool, fn(usize) -> bool); 5]
+    {
+        check_all_operations::<_, 16>(&keys, left, right);
+        check_all_operations::<_, 1>(&keys, left, right);
+    }
+}
+
+#[test]
+fn merge_keys_with_long_prefixes() {
+    let keys = to_arrays::<11>(generate_key_with_prefix(
+        [3, 2, 4],
+        [
+            PrefixExpansion {
+                base_index: 0,
+                expanded_length: 5,
+            },
+            PrefixExpansion {
+                base_index: 2,
+                expanded_length: 5,
+            },
+        ],
+    ));
+
+    for (left, right) in [
+        (|idx| idx % 2 == 0, |idx| idx % 3 == 0),
+        (|idx| idx < 10, |idx| idx >= 10),
+        (|idx| idx % 4 == 1, |idx| idx % 4 == 1),
+    ] as [(fn(usize) -> bool, fn(usize) -> bool); 3]
+    {
+        check_all_operations::<_, 16>(&keys, left, right);
+        check_all_operations::<_, 2>(&keys, left, right);
+    }
+}
+
+#[test]
+fn merge_divergent_prefixes() {
+    // Left keys all share a long prefix, right keys diverge from it in the middle
+    let left: Vec<[u8; 8]> = (0..=255).map(|b| [1, 2, 3, 4, 5, 6, 7, b]).collect();
+    let right: Vec<[u8; 8]> = (0..=255).map(|b| [1, 2, 3, 9, 5, 6, b, 0]).collect();
+    let keys: Vec<_> = left.iter().chain(&right).copied().collect();
+
+    check_all_operations::<_, 16>(&keys, |idx| idx < 256, |idx| idx >= 256);
+    check_all_operations::<_, 4>(&keys, |idx| idx < 256, |idx| idx >= 128);
+    check_all_operations::<_, 4>(&keys, |idx| idx % 2 == 0, |idx| idx >= 200);
+}
+
+#[test]
+fn intersection_and_difference_with_prefix_keys() {
+    let keys: Vec<Box<[u8]>> = generate_keys_skewed(32).collect();
+    let mut left = TreeMap::<Box<[u8]>, u32>::new();
+    let mut right = TreeMap::<Box<[u8]>, u32>::new();
+    left.try_insert(Box::from([1]), 1).unwrap();
+    right.try_insert(Box::from([1, 2]), 2).unwrap();
+    for (idx, key) in keys.iter().enumerate() {
+        left.try_insert(key.clone(), idx as u32).unwrap();
+        if idx % 2 == 0 {
+            right.try_insert(key.clone(), 100).unwrap();
+        }
+    }
+
+    let mut intersection = left.clone();
+    intersection.intersection_with(right.clone(), |_, l, r| l + r);
+    let expected: Oracle<Box<[u8]>> = keys
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| idx % 2 == 0)
+        .map(|(idx, key)| (key.clone(), idx as u32 + 100))
+        .collect();
+    assert_matches_oracle(&intersection, &expected);
+
+    left.difference(right, |_, _, _| None);
+    let mut expected: Oracle<Box<[u8]>> = keys
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| idx % 2 == 1)
+        .map(|(idx, key)| (key.clone(), idx as u32))
+        .collect();
+    expected.insert(Box::from([1]), 1);
+    assert_matches_oracle(&left, &expected);
+}
+
+#[test]
+fn merge_with_empty_trees() {
+    let mut empty = TreeMap::<u32, u32>::new();
+    empty.union_with(TreeMap::new(), |_, l, _| l);
+    assert!(empty.is_empty());
+
+    let mut tree: TreeMap<u32, u32> = (0..100).map(|i| (i, i)).collect();
+    tree.union_with(TreeMap::new(), |_, l, _| l);
+    assert_eq!(tree.len(), 100);
+
+    empty.union_with(tree.clone(), |_, l, _| l);
+    assert_eq!(empty, tree);
+
+    tree.intersection_with(TreeMap::new(), |_, l, _| l);
+    assert!(tree.is_empty());
+    assert!(tree.root.is_none());
+}
+
+#[test]
+fn union_prefix_keys_keeps_left() {
+    let entries = |keys: &[&[u8]], offset: u32| -> Oracle<Box<[u8]>> {
+        keys.iter()
+            .zip(offset..)
+            .map(|(key, value)| (Box::from(*key), value))
+            .collect()
+    };
+    let to_tree = |oracle: &Oracle<Box<[u8]>>| -> TreeMap<Box<[u8]>, u32> {
+        let mut tree = TreeMap::new();
+        for (key, value) in oracle {
+            tree.try_insert(key.clone(), *value).unwrap();
+        }
+        tree
+    };
+
+    // Prefixes in both directions, at the root and below inner nodes, next to
+    // a matching key and keys only present in one of the trees
+    let left = entries(
+        &[
+            &[1],
+            &[2, 3, 4, 5],
+            &[2, 3, 9],
+            &[4, 0, 0],
+            &[4, 1],
+            &[7, 7],
+        ],
+        0,
+    );
+    let right = entries(
+        &[
+            &[1, 2],
+            &[1, 3, 3],
+            &[2, 3],
+            &[4, 0, 0],
+            &[4, 1, 1, 1],
+            &[4, 2],
+            &[8],
+        ],
+        100,
+    );
+
+    for (left, right) in [(&left, &right), (&right, &left)] {
+        let mut expected_union = left.clone();
+        let mut expected_symmetric = left.clone();
+        let mut expected_rejected = Oracle::new();
+        for (key, value) in right {
+            let conflicts = left.keys().any(|left_key| {
+                left_key != key && (left_key.starts_with(key) || key.starts_with(left_key))
+            });
+            if conflicts {
+                expected_rejected.insert(key.clone(), *value);
+            } else if expected_symmetric.remove(key).is_none() {
+                expected_union.insert(key.clone(), *value);
+                expected_symmetric.insert(key.clone(), *value);
+            } else {
+                *expected_union.get_mut(key).unwrap() += value;
+            }
+        }
+        assert!(!expected_rejected.is_empty());
+
+        let mut union = to_tree(left);
+        let rejected = union.union_with_conflicts(to_tree(right), |_, l, r| l + r);
+        assert_matches_oracle(&union, &expected_union);
+        assert_matches_oracle(&rejected, &expected_rejected);
+
+        let mut symmetric = to_tree(left);
+        let rejected =
+            symmetric.symmetric_difference_with_conflicts(to_tree(right), |_, _, _| None);
+        assert_matches_oracle(&symmetric, &expected_symmetric);
+        assert_matches_oracle(&rejected, &expected_rejected);
+    }
+}
+
+#[test]
+fn merge_uses_the_prefix_strategy_of_the_left_tree() {
+    // Each group of keys shares a 12 byte prefix, which is longer than the
+    // `PREFIX_LEN` of 8
+    let keys: Vec<[u8; 16]> = (0..8u8)
+        .flat_map(|group| {
+            (0..4u8).map(move |b| [group, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, b, 0, 0])
+        })
+        .collect();
+
+    for (left_strategy, right_strategy) in [
+        (PrefixStrategy::Pessimistic, PrefixStrategy::Optimistic),
+        (PrefixStrategy::Optimistic, PrefixStrategy::Pessimistic),
+    ] {
+        let to_tree = |strategy, filter: fn(usize) -> bool| {
+            let mut tree = TreeMap::<_, u32, 8>::with_prefix_strategy(strategy);
+            for (idx, key) in keys.iter().enumerate().filter(|(idx, _)| filter(*idx)) {
+                tree.try_insert(*key, idx as u32).unwrap();
+            }
+            tree
+        };
+        let expected = to_tree(left_strategy, |_| true);
+
+        let mut union = to_tree(left_strategy, |idx| idx < 16);
+        union.union_with(to_tree(right_strategy, |idx| idx >= 16), |_, l, _| l);
+        assert_eq!(union, expected);
+        WellFormedChecker::check(&union).expect("merged tree should be well-formed");
+        assert_eq!(
+            union.memory_usage().spilled_prefixes,
+            expected.memory_usage().spilled_prefixes
+        );
+
+        // The inner nodes below the root take their header from the right tree,
+        // since their prefix is a part of the path to the left keys
+        let mut intersection = to_tree(left_strategy, |idx| idx % 4 < 2);
+        intersection.intersection_with(to_tree(right_strategy, |_| true), |_, l, _| l);
+        WellFormedChecker::check(&intersection).expect("merged tree should be well-formed");
+        assert_eq!(
+            intersection.memory_usage().spilled_prefixes,
+            to_tree(left_strategy, |idx| idx % 4 < 2)
+                .memory_usage()
+                .spilled_prefixes
+        );
+    }
+}
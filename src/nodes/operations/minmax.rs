@@ -1,4 +1,5 @@
-use crate::{ConcreteNodePtr, InnerNode, LeafNode, NodePtr, OpaqueNodePtr};
+use crate::{AsBytes, ConcreteNodePtr, InnerNode, LeafNode, NodePtr, OpaqueNodePtr};
+use core::cmp::Ordering;
 
 /// Search for the leaf with the minimum key, by lexicographic ordering.
 ///
@@ -16,6 +17,7 @@ pub unsafe fn minimum_unchecked<K, V, const PREFIX_LEN: usize>(
         current_node = match current_node.to_node_ptr() {
             ConcreteNodePtr::Node4(inner_node) => unsafe { inner_node.as_ref().min().1 },
             ConcreteNodePtr::Node16(inner_node) => unsafe { inner_node.as_ref().min().1 },
+            ConcreteNodePtr::Node32(inner_node) => unsafe { inner_node.as_ref().min().1 },
             ConcreteNodePtr::Node48(inner_node) => unsafe { inner_node.as_ref().min().1 },
             ConcreteNodePtr::Node256(inner_node) => unsafe { inner_node.as_ref().min().1 },
             ConcreteNodePtr::LeafNode(inner_node) => {
@@ -41,6 +43,7 @@ pub unsafe fn maximum_unchecked<K, V, const PREFIX_LEN: usize>(
         current_node = match current_node.to_node_ptr() {
             ConcreteNodePtr::Node4(inner_node) => unsafe { inner_node.as_ref().max().1 },
             ConcreteNodePtr::Node16(inner_node) => unsafe { inner_node.as_ref().max().1 },
+            ConcreteNodePtr::Node32(inner_node) => unsafe { inner_node.as_ref().max().1 },
             ConcreteNodePtr::Node48(inner_node) => unsafe { inner_node.as_ref().max().1 },
             ConcreteNodePtr::Node256(inner_node) => unsafe { inner_node.as_ref().max().1 },
             ConcreteNodePtr::LeafNode(inner_node) => {
@@ -50,5 +53,209 @@ pub unsafe fn maximum_unchecked<K, V, const PREFIX_LEN: usize>(
     }
 }
 
+/// Which of the stored keys nearest to a search key should be found by
+/// [`search_nearest_unchecked`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nearest {
+    /// The greatest key less than the search key.
+    Predecessor,
+    /// The greatest key less than or equal to the search key.
+    Floor,
+    /// The least key greater than or equal to the search key.
+    Ceiling,
+    /// The least key greater than the search key.
+    Successor,
+}
+
+impl Nearest {
+    /// Return true if this searches for keys below the search key.
+    fn is_below(self) -> bool {
+        matches!(self, Nearest::Predecessor | Nearest::Floor)
+    }
+
+    /// Return true if a key equal to the search key is accepted.
+    fn is_inclusive(self) -> bool {
+        matches!(self, Nearest::Floor | Nearest::Ceiling)
+    }
+
+    /// Return true if a stored key which compares to the search key with the
+    /// given ordering is accepted.
+    fn accepts(self, ordering: Ordering) -> bool {
+        match ordering {
+            Ordering::Less => self.is_below(),
+            Ordering::Equal => self.is_inclusive(),
+            Ordering::Greater => !self.is_below(),
+        }
+    }
+
+    /// Return the leaf in the given subtree which is closest to the search
+    /// key, given that all keys of the subtree are accepted.
+    ///
+    /// # Safety
+    ///  - Same as [`minimum_unchecked`] and [`maximum_unchecked`].
+    unsafe fn closest_in<K, V, const PREFIX_LEN: usize>(
+        self,
+        node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    ) -> NodePtr<PREFIX_LEN, LeafNode<K, V>> {
+        if self.is_below() {
+            // SAFETY: Covered by the safety requirements of the containing function
+            unsafe { maximum_unchecked(node) }
+        } else {
+            // SAFETY: Covered by the safety requirements of the containing function
+            unsafe { minimum_unchecked(node) }
+        }
+    }
+}
+
+/// Search for the leaf with the stored key that is nearest to `key_bytes` in
+/// the direction given by `nearest`, by lexicographic ordering.
+///
+/// The tree is descended once along the path of the search key. On the way
+/// down, the closest sibling subtree on the searched side is remembered, so
+/// that if the path ends the answer is the maximum or minimum of that
+/// subtree.
+///
+/// # Safety
+///  - This function cannot be called concurrently with any mutating operation
+///    on `root` or any child node of `root`. This function will arbitrarily
+///    read to any child in the given tree.
+pub unsafe fn search_nearest_unchecked<K, V, const PREFIX_LEN: usize>(
+    root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    key_bytes: &[u8],
+    nearest: Nearest,
+) -> Option<NodePtr<PREFIX_LEN, LeafNode<K, V>>>
+where
+    K: AsBytes,
+{
+    /// The result of visiting a single inner node
+    enum Step<K, V, const PREFIX_LEN: usize> {
+        /// Continue the search with the given child node
+        Descend(OpaqueNodePtr<K, V, PREFIX_LEN>),
+        /// All the keys in the current node are accepted
+        AcceptNode,
+        /// None of the keys in the current node are accepted
+        RejectNode,
+    }
+
+    fn visit_inner_node<N: InnerNode<PREFIX_LEN>, const PREFIX_LEN: usize>(
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+        key_bytes: &[u8],
+        current_depth: &mut usize,
+        nearest: Nearest,
+        candidate: &mut Option<OpaqueNodePtr<N::Key, N::Value, PREFIX_LEN>>,
+    ) -> Step<N::Key, N::Value, PREFIX_LEN>
+    where
+        N::Key: AsBytes,
+    {
+        // SAFETY: The safety requirement is covered by the safety requirement on the
+        // containing function
+        let inner_node = unsafe { inner_ptr.as_ref() };
+        let (prefix, _) = inner_node.read_full_prefix(*current_depth);
+        let remaining = &key_bytes[*current_depth..];
+
+        // Compare the node prefix against the same span of the search key. If
+        // the search key runs out first, it is a prefix of every key in this
+        // node, so they are all greater than it.
+        let ordering = match prefix.iter().zip(remaining).find(|(a, b)| a != b) {
+            Some((a, b)) => a.cmp(b),
+            None if remaining.len() <= prefix.len() => Ordering::Greater,
+            None => Ordering::Equal,
+        };
+
+        match ordering {
+            Ordering::Equal => {},
+            ordering if nearest.accepts(ordering) => return Step::AcceptNode,
+            _ => return Step::RejectNode,
+        }
+
+        *current_depth += prefix.len();
+        let key_byte = key_bytes[*current_depth];
+
+        // Remember the closest child on the searched side, in case the search
+        // path ends below this node
+        let closest_sibling = if nearest.is_below() {
+            key_byte
+                .checked_sub(1)
+                .and_then(|upper| inner_node.range(..=upper).next_back())
+        } else {
+            key_byte
+                .checked_add(1)
+                .and_then(|lower| inner_node.range(lower..).next())
+        };
+        if let Some((_, sibling)) = closest_sibling {
+            *candidate = Some(sibling);
+        }
+
+        match inner_node.lookup_child(key_byte) {
+            Some(child) => {
+                *current_depth += 1;
+                Step::Descend(child)
+            },
+            None => Step::RejectNode,
+        }
+    }
+
+    let mut candidate = None;
+    let mut current_node = root;
+    let mut current_depth = 0;
+
+    loop {
+        let step = match current_node.to_node_ptr() {
+            ConcreteNodePtr::Node4(inner_ptr) => visit_inner_node(
+                inner_ptr,
+                key_bytes,
+                &mut current_depth,
+                nearest,
+                &mut candidate,
+            ),
+            ConcreteNodePtr::Node16(inner_ptr) => visit_inner_node(
+                inner_ptr,
+                key_bytes,
+                &mut current_depth,
+                nearest,
+                &mut candidate,
+            ),
+            ConcreteNodePtr::Node32(inner_ptr) => visit_inner_node(
+                inner_ptr,
+                key_bytes,
+                &mut current_depth,
+                nearest,
+                &mut candidate,
+            ),
+            ConcreteNodePtr::Node48(inner_ptr) => visit_inner_node(
+                inner_ptr,
+                key_bytes,
+                &mut current_depth,
+                nearest,
+                &mut candidate,
+            ),
+            ConcreteNodePtr::Node256(inner_ptr) => visit_inner_node(
+                inner_ptr,
+                key_bytes,
+                &mut current_depth,
+                nearest,
+                &mut candidate,
+            ),
+            ConcreteNodePtr::LeafNode(leaf_node_ptr) => {
+                // SAFETY: The safety requirement is covered by the safety requirement on the
+                // containing function
+                let leaf_key = unsafe { leaf_node_ptr.as_key_ref() }.as_bytes();
+                if nearest.accepts(leaf_key.cmp(key_bytes)) {
+                    return Some(leaf_node_ptr);
+                }
+                Step::RejectNode
+            },
+        };
+
+        match step {
+            Step::Descend(child) => current_node = child,
+            // SAFETY: Covered by the safety requirements of the containing function
+            Step::AcceptNode => return Some(unsafe { nearest.closest_in(current_node) }),
+            // SAFETY: Covered by the safety requirements of the containing function
+            Step::RejectNode => return candidate.map(|node| unsafe { nearest.closest_in(node) }),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests;
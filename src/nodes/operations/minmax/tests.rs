@@ -3,6 +3,12 @@ use crate::{
     tests_common::{generate_key_fixed_length, generate_keys_skewed, insert_unchecked},
     LeafNode, NodePtr, OpaqueNodePtr,
 };
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 
 #[test]
 fn leaf_tree_min_max_same() {
@@ -85,3 +91,103 @@ fn skewed_tree_min_max() {
 
     unsafe { deallocate_tree(root) }
 }
+
+fn check_nearest_against_oracle<const PREFIX_LEN: usize>(
+    keys: impl Iterator<Item = Box<[u8]>>,
+    queries: &[Box<[u8]>],
+) {
+    use crate::TreeMap;
+    use alloc::collections::BTreeMap;
+    use core::ops::Bound::{Excluded, Included, Unbounded};
+
+    let mut tree = TreeMap::<Box<[u8]>, usize, PREFIX_LEN>::with_prefix_len();
+    let mut oracle = BTreeMap::new();
+    for (idx, key) in keys.enumerate() {
+        tree.try_insert(key.clone(), idx).unwrap();
+        oracle.insert(key, idx);
+    }
+
+    let stored: Vec<Box<[u8]>> = oracle.keys().cloned().collect();
+    for query in stored.iter().chain(queries) {
+        let query = query.as_ref();
+        let expected_predecessor = oracle
+            .range::<[u8], _>((Unbounded, Excluded(query)))
+            .next_back();
+        let expected_floor = oracle
+            .range::<[u8], _>((Unbounded, Included(query)))
+            .next_back();
+        let expected_ceiling = oracle.range::<[u8], _>((Included(query), Unbounded)).next();
+        let expected_successor = oracle.range::<[u8], _>((Excluded(query), Unbounded)).next();
+
+        assert_eq!(tree.predecessor(query), expected_predecessor, "{query:?}");
+        assert_eq!(tree.floor(query), expected_floor, "{query:?}");
+        assert_eq!(tree.ceiling(query), expected_ceiling, "{query:?}");
+        assert_eq!(tree.successor(query), expected_successor, "{query:?}");
+    }
+}
+
+fn nearest_queries() -> Vec<Box<[u8]>> {
+    let mut queries: Vec<Box<[u8]>> = vec![Box::from([]), Box::from([0]), Box::from([255; 8])];
+    for a in [0u8, 1, 2, 3, 4, 5, 127, 255] {
+        queries.push(Box::from([a]));
+        for b in [0u8, 1, 3, 254, 255] {
+            queries.push(Box::from([a, b]));
+            queries.push(Box::from([a, b, 2, 1]));
+            queries.push(Box::from([a, b, 255, 255, 0]));
+        }
+    }
+    queries
+}
+
+#[test]
+fn nearest_fixed_length_keys() {
+    #[cfg(not(miri))]
+    const VALUE_STOPS: u8 = 5;
+    #[cfg(miri)]
+    const VALUE_STOPS: u8 = 2;
+
+    let queries = nearest_queries();
+    check_nearest_against_oracle::<16>(generate_key_fixed_length([VALUE_STOPS; 3]), &queries);
+    check_nearest_against_oracle::<2>(generate_key_fixed_length([VALUE_STOPS; 3]), &queries);
+}
+
+#[test]
+fn nearest_skewed_keys() {
+    #[cfg(not(miri))]
+    const KEY_LENGTH_LIMIT: usize = 64;
+    #[cfg(miri)]
+    const KEY_LENGTH_LIMIT: usize = 8;
+
+    let queries = nearest_queries();
+    check_nearest_against_oracle::<16>(generate_keys_skewed(KEY_LENGTH_LIMIT), &queries);
+    check_nearest_against_oracle::<2>(generate_keys_skewed(KEY_LENGTH_LIMIT), &queries);
+}
+
+#[test]
+fn nearest_keys_with_long_prefixes() {
+    // All keys share a prefix longer than the stored prefix, so the implicit part
+    // of the prefix has to be compared against the search key
+    let keys = (0..=255u8)
+        .step_by(3)
+        .map(|b| Box::from([7, 7, 7, 7, 7, 7, 7, 7, b, 1]) as Box<[u8]>);
+    let mut queries = nearest_queries();
+    queries.extend([
+        Box::from([7u8, 7, 7, 7]) as Box<[u8]>,
+        Box::from([7, 7, 7, 7, 6, 255]),
+        Box::from([7, 7, 7, 7, 8]),
+        Box::from([7, 7, 7, 7, 7, 7, 7, 7, 4]),
+        Box::from([7, 7, 7, 7, 7, 7, 7, 7, 3, 0]),
+        Box::from([7, 7, 7, 7, 7, 7, 7, 7, 3, 2]),
+    ]);
+    check_nearest_against_oracle::<16>(keys.clone(), &queries);
+    check_nearest_against_oracle::<2>(keys, &queries);
+}
+
+#[test]
+fn nearest_empty_tree() {
+    let tree = crate::TreeMap::<Box<[u8]>, ()>::new();
+    assert_eq!(tree.predecessor([1].as_ref()), None);
+    assert_eq!(tree.floor([1].as_ref()), None);
+    assert_eq!(tree.ceiling([1].as_ref()), None);
+    assert_eq!(tree.successor([1].as_ref()), None);
+}
@@ -1,7 +1,8 @@
 //! Trie node representation
 
-use crate::{rust_nightly_apis::assume, tagged_pointer::TaggedPointer, AsBytes};
-use std::{
+use crate::{metrics, rust_nightly_apis::assume, tagged_pointer::TaggedPointer, AsBytes};
+use alloc::boxed::Box;
+use core::{
     fmt,
     hash::Hash,
     iter::FusedIterator,
@@ -12,6 +13,7 @@ use std::{
 };
 
 mod header;
+pub use header::PrefixStrategy;
 pub(crate) use header::*;
 
 mod inner_node_256;
@@ -23,6 +25,9 @@ pub use inner_node_48::*;
 mod inner_node_compressed;
 pub use inner_node_compressed::*;
 
+#[cfg(any(test, not(feature = "nightly")))]
+mod simd;
+
 /// The representation of inner nodes
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -31,7 +36,12 @@ pub enum NodeType {
     Node4 = 0b000,
     /// Node that references between 5 and 16 children
     Node16 = 0b001, // 0b001
-    /// Node that references between 17 and 49 children
+    /// Node that references between 17 and 32 children
+    ///
+    /// This takes the `0b101` value of the 3 bit tag that is stored in the low
+    /// bits of every [`OpaqueNodePtr`], which leaves `0b110` and `0b111` free.
+    Node32 = 0b101, // 0b101
+    /// Node that references between 33 and 48 children
     Node48 = 0b010, // 0b010
     /// Node that references between 49 and 256 children
     Node256 = 0b011, // 0b011
@@ -46,6 +56,7 @@ impl NodeType {
         match self {
             NodeType::Node4 => 4,
             NodeType::Node16 => 16,
+            NodeType::Node32 => 32,
             NodeType::Node48 => 48,
             NodeType::Node256 => 256,
             NodeType::Leaf => 0,
@@ -58,7 +69,7 @@ impl NodeType {
     ///  - `src` must be a valid variant from the enum
     pub const unsafe fn from_u8(src: u8) -> NodeType {
         // SAFETY: `NodeType` is repr(u8)
-        unsafe { std::mem::transmute::<u8, NodeType>(src) }
+        unsafe { core::mem::transmute::<u8, NodeType>(src) }
     }
 
     /// Return true if an [`InnerNode`] with the given [`NodeType`] and
@@ -70,7 +81,8 @@ impl NodeType {
         match self {
             NodeType::Node4 => false,
             NodeType::Node16 => num_children <= 4,
-            NodeType::Node48 => num_children <= 16,
+            NodeType::Node32 => num_children <= 16,
+            NodeType::Node48 => num_children <= 32,
             NodeType::Node256 => num_children <= 48,
             NodeType::Leaf => panic!("cannot shrink leaf"),
         }
@@ -81,16 +93,112 @@ impl NodeType {
         match self {
             NodeType::Node4 => Range { start: 1, end: 5 },
             NodeType::Node16 => Range { start: 5, end: 17 },
-            NodeType::Node48 => Range { start: 17, end: 49 },
+            NodeType::Node32 => Range { start: 17, end: 33 },
+            NodeType::Node48 => Range { start: 33, end: 49 },
             NodeType::Node256 => Range {
                 start: 49,
-                end: 256,
+                end: 257,
             },
             NodeType::Leaf => Range { start: 0, end: 0 },
         }
     }
 }
 
+/// Controls when an inner node is shrunk into a smaller node type after one of
+/// its children is removed.
+///
+/// Inner nodes are always grown into the next larger node type when a child
+/// is inserted into a full node, this only changes how eagerly they are
+/// shrunk again. [`TreeMap::compact`](crate::TreeMap::compact) always rebuilds
+/// every inner node as the smallest node type that can hold its children.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShrinkPolicy {
+    /// Shrink a node as soon as its children fit in the next smaller node
+    /// type.
+    ///
+    /// This uses the least memory, but a workload which inserts and removes
+    /// keys around the capacity of a node type grows and shrinks the same node
+    /// over and over.
+    #[default]
+    Eager,
+    /// Shrink a node once its children fit in half of the next smaller node
+    /// type.
+    ///
+    /// For example, an [`InnerNode16`] grown from a full [`InnerNode4`] is
+    /// only shrunk once it drops to 2 children, so that adding and removing a
+    /// few children does not copy the node every time.
+    Hysteresis,
+    /// Never shrink nodes on remove.
+    ///
+    /// Nodes are still removed when they drop to a single child. Use
+    /// [`TreeMap::compact`](crate::TreeMap::compact) to shrink them later.
+    Lazy,
+}
+
+impl ShrinkPolicy {
+    /// Return true if an [`InnerNode`] with the given [`NodeType`] and
+    /// specified number of children should be shrunk under this policy.
+    ///
+    /// # Panics
+    ///  - Panics if `node_type` equals [`NodeType::Leaf`]
+    pub fn should_shrink_inner_node(self, node_type: NodeType, num_children: usize) -> bool {
+        match self {
+            ShrinkPolicy::Eager => node_type.should_shrink_inner_node(num_children),
+            ShrinkPolicy::Hysteresis => match node_type {
+                NodeType::Node4 => false,
+                NodeType::Node16 => num_children <= 2,
+                NodeType::Node32 => num_children <= 8,
+                NodeType::Node48 => num_children <= 16,
+                NodeType::Node256 => num_children <= 24,
+                NodeType::Leaf => panic!("cannot shrink leaf"),
+            },
+            ShrinkPolicy::Lazy => {
+                assert_ne!(node_type, NodeType::Leaf, "cannot shrink leaf");
+                false
+            },
+        }
+    }
+
+    /// Return the range of number of children that each node type can have
+    /// in a tree using this policy.
+    pub const fn capacity_range(self, node_type: NodeType) -> Range<usize> {
+        let eager = node_type.capacity_range();
+        let start = match (self, node_type) {
+            (ShrinkPolicy::Eager, _) | (_, NodeType::Node4 | NodeType::Leaf) => eager.start,
+            (ShrinkPolicy::Hysteresis, NodeType::Node16) => 3,
+            (ShrinkPolicy::Hysteresis, NodeType::Node32) => 9,
+            (ShrinkPolicy::Hysteresis, NodeType::Node48) => 17,
+            (ShrinkPolicy::Hysteresis, NodeType::Node256) => 25,
+            (ShrinkPolicy::Lazy, _) => 2,
+        };
+
+        Range {
+            start,
+            end: eager.end,
+        }
+    }
+
+    /// Return true if every tree built using the `other` policy is also a
+    /// valid tree under this policy.
+    pub(crate) fn accepts(self, other: ShrinkPolicy) -> bool {
+        matches!(
+            (self, other),
+            (ShrinkPolicy::Lazy, _)
+                | (
+                    ShrinkPolicy::Hysteresis,
+                    ShrinkPolicy::Hysteresis | ShrinkPolicy::Eager
+                )
+                | (ShrinkPolicy::Eager, ShrinkPolicy::Eager)
+        )
+    }
+}
+
+/// The tags of an [`OpaqueNodePtr`] which are not used by any [`NodeType`].
+///
+/// Collections can use them for children which are not nodes, see
+/// [`OpaqueNodePtr::with_free_tag`].
+pub(crate) const FREE_NODE_TAGS: [usize; 2] = [0b110, 0b111];
+
 /// A placeholder type that has the required amount of alignment.
 ///
 /// An alignment of 8 gives us 3 unused bits in any pointer to this type.
@@ -137,11 +245,23 @@ impl<K, V, const PREFIX_LEN: usize> PartialEq for OpaqueNodePtr<K, V, PREFIX_LEN
 }
 
 impl<K, V, const PREFIX_LEN: usize> Hash for OpaqueNodePtr<K, V, PREFIX_LEN> {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
         self.0.hash(state);
     }
 }
 
+impl<K, V, const PREFIX_LEN: usize> Ord for OpaqueNodePtr<K, V, PREFIX_LEN> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl<K, V, const PREFIX_LEN: usize> PartialOrd for OpaqueNodePtr<K, V, PREFIX_LEN> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl<K, V, const PREFIX_LEN: usize> OpaqueNodePtr<K, V, PREFIX_LEN> {
     /// Construct a new opaque node pointer from an existing non-null node
     /// pointer.
@@ -182,6 +302,9 @@ impl<K, V, const PREFIX_LEN: usize> OpaqueNodePtr<K, V, PREFIX_LEN> {
             NodeType::Node16 => ConcreteNodePtr::Node16(NodePtr(
                 self.0.cast::<InnerNode16<K, V, PREFIX_LEN>>().into(),
             )),
+            NodeType::Node32 => ConcreteNodePtr::Node32(NodePtr(
+                self.0.cast::<InnerNode32<K, V, PREFIX_LEN>>().into(),
+            )),
             NodeType::Node48 => ConcreteNodePtr::Node48(NodePtr(
                 self.0.cast::<InnerNode48<K, V, PREFIX_LEN>>().into(),
             )),
@@ -194,6 +317,37 @@ impl<K, V, const PREFIX_LEN: usize> OpaqueNodePtr<K, V, PREFIX_LEN> {
         }
     }
 
+    /// Hint to the CPU that the node will be read soon, so that it starts
+    /// loading the first cache line of the node.
+    ///
+    /// This is a no-op on architectures other than `x86_64` and `aarch64`.
+    #[inline(always)]
+    pub fn prefetch(self) {
+        let ptr = self.0.to_ptr().cast::<u8>().cast_const();
+
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: Prefetching has no observable effect, and does not fault on any
+        // address. SSE is part of the baseline `x86_64` target.
+        unsafe {
+            use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+            _mm_prefetch::<_MM_HINT_T0>(ptr.cast::<i8>());
+        }
+
+        #[cfg(target_arch = "aarch64")]
+        // SAFETY: Prefetching has no observable effect, and does not fault on any
+        // address.
+        unsafe {
+            core::arch::asm!(
+                "prfm pldl1keep, [{ptr}]",
+                ptr = in(reg) ptr,
+                options(nostack, readonly, preserves_flags)
+            );
+        }
+
+        #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+        let _ = ptr;
+    }
+
     /// Retrieve the runtime node type information.
     pub fn node_type(self) -> NodeType {
         // SAFETY: We know that we can convert the usize into a `NodeType` because
@@ -212,9 +366,11 @@ impl<K, V, const PREFIX_LEN: usize> OpaqueNodePtr<K, V, PREFIX_LEN> {
     ///    (read or written) through any other pointer.
     pub(crate) unsafe fn header_mut<'h>(self) -> Option<&'h mut Header<PREFIX_LEN>> {
         let header_ptr = match self.node_type() {
-            NodeType::Node4 | NodeType::Node16 | NodeType::Node48 | NodeType::Node256 => unsafe {
-                self.header_mut_unchecked()
-            },
+            NodeType::Node4
+            | NodeType::Node16
+            | NodeType::Node32
+            | NodeType::Node48
+            | NodeType::Node256 => unsafe { self.header_mut_unchecked() },
             NodeType::Leaf => {
                 return None;
             },
@@ -240,6 +396,51 @@ impl<K, V, const PREFIX_LEN: usize> OpaqueNodePtr<K, V, PREFIX_LEN> {
         unsafe { &mut *self.0.cast::<Header<PREFIX_LEN>>().to_ptr() }
     }
 
+    /// Get a shared reference to the header, this doesn't check if the pointer
+    /// is to an inner node.
+    ///
+    /// # Safety
+    ///  - The pointer must be to an inner node
+    ///  - You must enforce Rust’s aliasing rules, since the returned lifetime
+    ///    'h is arbitrarily chosen and does not necessarily reflect the actual
+    ///    lifetime of the data. In particular, for the duration of this
+    ///    lifetime, the memory the pointer points to must not get mutated.
+    pub(crate) unsafe fn header_unchecked<'h>(self) -> &'h Header<PREFIX_LEN> {
+        unsafe { &*self.0.cast::<Header<PREFIX_LEN>>().to_ptr() }
+    }
+
+    /// Create an opaque pointer to a value which is not a [`Node`], tagged
+    /// with one of the [`FREE_NODE_TAGS`].
+    ///
+    /// The returned pointer must never be passed to
+    /// [`OpaqueNodePtr::node_type`] or any function which calls it, since the
+    /// tag is not a valid [`NodeType`].
+    ///
+    /// # Panics
+    ///  - If `tag` is not one of the [`FREE_NODE_TAGS`].
+    ///  - If `pointer` is not aligned to 8 bytes.
+    pub(crate) fn with_free_tag<T>(pointer: NonNull<T>, tag: usize) -> Self {
+        assert!(
+            FREE_NODE_TAGS.contains(&tag),
+            "the tag {tag:#05b} is used by a node type"
+        );
+        let mut tagged_ptr = TaggedPointer::from(pointer.cast::<OpaqueValue>());
+        tagged_ptr.set_data(tag);
+
+        OpaqueNodePtr(tagged_ptr, PhantomData)
+    }
+
+    /// Return the tag of this pointer, without checking that it is a valid
+    /// [`NodeType`].
+    pub(crate) fn tag(self) -> usize {
+        self.0.to_data()
+    }
+
+    /// Return the pointer without its tag, cast to the given type.
+    pub(crate) fn untagged_ptr<T>(self) -> NonNull<T> {
+        NonNull::from(self.0).cast::<T>()
+    }
+
     /// Do a deep clone recursively, by allocating new nodes
     pub fn deep_clone(&self) -> Self
     where
@@ -251,6 +452,7 @@ impl<K, V, const PREFIX_LEN: usize> OpaqueNodePtr<K, V, PREFIX_LEN> {
         match self.to_node_ptr() {
             ConcreteNodePtr::Node4(inner) => unsafe { inner.as_ref().deep_clone().to_opaque() },
             ConcreteNodePtr::Node16(inner) => unsafe { inner.as_ref().deep_clone().to_opaque() },
+            ConcreteNodePtr::Node32(inner) => unsafe { inner.as_ref().deep_clone().to_opaque() },
             ConcreteNodePtr::Node48(inner) => unsafe { inner.as_ref().deep_clone().to_opaque() },
             ConcreteNodePtr::Node256(inner) => unsafe { inner.as_ref().deep_clone().to_opaque() },
             ConcreteNodePtr::LeafNode(inner) => unsafe {
@@ -266,7 +468,9 @@ pub enum ConcreteNodePtr<K, V, const PREFIX_LEN: usize> {
     Node4(NodePtr<PREFIX_LEN, InnerNode4<K, V, PREFIX_LEN>>),
     /// Node that references between 5 and 16 children
     Node16(NodePtr<PREFIX_LEN, InnerNode16<K, V, PREFIX_LEN>>),
-    /// Node that references between 17 and 49 children
+    /// Node that references between 17 and 32 children
+    Node32(NodePtr<PREFIX_LEN, InnerNode32<K, V, PREFIX_LEN>>),
+    /// Node that references between 33 and 48 children
     Node48(NodePtr<PREFIX_LEN, InnerNode48<K, V, PREFIX_LEN>>),
     /// Node that references between 49 and 256 children
     Node256(NodePtr<PREFIX_LEN, InnerNode256<K, V, PREFIX_LEN>>),
@@ -279,6 +483,7 @@ impl<K, V, const PREFIX_LEN: usize> fmt::Debug for ConcreteNodePtr<K, V, PREFIX_
         match self {
             Self::Node4(arg0) => f.debug_tuple("Node4").field(arg0).finish(),
             Self::Node16(arg0) => f.debug_tuple("Node16").field(arg0).finish(),
+            Self::Node32(arg0) => f.debug_tuple("Node32").field(arg0).finish(),
             Self::Node48(arg0) => f.debug_tuple("Node48").field(arg0).finish(),
             Self::Node256(arg0) => f.debug_tuple("Node256").field(arg0).finish(),
             Self::LeafNode(arg0) => f.debug_tuple("LeafNode").field(arg0).finish(),
@@ -302,9 +507,11 @@ impl<const PREFIX_LEN: usize, N: Node<PREFIX_LEN>> NodePtr<PREFIX_LEN, N> {
         unsafe { NodePtr(NonNull::new_unchecked(ptr)) }
     }
 
-    /// Allocate the given [`Node`] on the [`std::alloc::Global`] heap and
+    /// Allocate the given [`Node`] on the [`alloc::alloc::Global`] heap and
     /// return a [`NodePtr`] that wrap the raw pointer.
     pub fn allocate_node_ptr(node: N) -> Self {
+        metrics::record_node_allocation(N::TYPE);
+
         // SAFETY: The pointer from [`Box::into_raw`] is non-null, aligned, and valid
         // for reads and writes of the [`Node`] `N`.
         unsafe { NodePtr::new(Box::into_raw(Box::new(node))) }
@@ -531,6 +738,7 @@ pub(crate) mod private {
 
     impl<K, V, const PREFIX_LEN: usize> Sealed for super::InnerNode4<K, V, PREFIX_LEN> {}
     impl<K, V, const PREFIX_LEN: usize> Sealed for super::InnerNode16<K, V, PREFIX_LEN> {}
+    impl<K, V, const PREFIX_LEN: usize> Sealed for super::InnerNode32<K, V, PREFIX_LEN> {}
     impl<K, V, const PREFIX_LEN: usize> Sealed for super::InnerNode48<K, V, PREFIX_LEN> {}
     impl<K, V, const PREFIX_LEN: usize> Sealed for super::InnerNode256<K, V, PREFIX_LEN> {}
     impl<K, V> Sealed for super::LeafNode<K, V> {}
@@ -643,6 +851,20 @@ pub trait InnerNode<const PREFIX_LEN: usize>: Node<PREFIX_LEN> + Sized {
         key_fragment: u8,
     ) -> Option<OpaqueNodePtr<Self::Key, Self::Value, PREFIX_LEN>>;
 
+    /// Search through the node for the slot holding the child that
+    /// corresponds to the given key fragment, and return a pointer to it.
+    ///
+    /// This lets a collection access data which it stores in the child slot
+    /// itself, without creating a reference to the whole node.
+    ///
+    /// # Safety
+    ///  - `node` must point to a valid inner node, which is not mutated while
+    ///    the returned pointer is in use, other than through the pointer.
+    unsafe fn lookup_child_slot(
+        node: NonNull<Self>,
+        key_fragment: u8,
+    ) -> Option<NonNull<OpaqueNodePtr<Self::Key, Self::Value, PREFIX_LEN>>>;
+
     /// Write a child pointer with key fragment to this inner node.
     ///
     /// If the key fragment already exists in the node, overwrite the existing
@@ -847,10 +1069,24 @@ impl<K, V, const PREFIX_LEN: usize> Node<PREFIX_LEN> for LeafNode<K, V> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::mem;
+    use crate::rust_nightly_apis::ptr_addr;
+    use alloc::{vec, vec::Vec};
+    use core::mem;
 
-    #[cfg(not(feature = "nightly"))]
-    use sptr::Strict;
+    #[test]
+    fn capacity_range_includes_full_nodes() {
+        for (node_type, capacity) in [
+            (NodeType::Node4, 4),
+            (NodeType::Node16, 16),
+            (NodeType::Node32, 32),
+            (NodeType::Node48, 48),
+            (NodeType::Node256, 256),
+        ] {
+            let range = node_type.capacity_range();
+            assert!(range.contains(&capacity), "{node_type:?} {range:?}");
+            assert!(!range.contains(&(capacity + 1)), "{node_type:?} {range:?}");
+        }
+    }
 
     // This test is important because it verifies that we can transform a tagged
     // pointer to a type with large and small alignment and back without issues.
@@ -899,16 +1135,20 @@ mod tests {
     fn opaque_node_ptr_is_correct() {
         let mut n4 = InnerNode4::<Box<[u8]>, usize, 16>::empty();
         let mut n16 = InnerNode16::<Box<[u8]>, usize, 16>::empty();
+        let mut n32 = InnerNode32::<Box<[u8]>, usize, 16>::empty();
         let mut n48 = InnerNode48::<Box<[u8]>, usize, 16>::empty();
         let mut n256 = InnerNode256::<Box<[u8]>, usize, 16>::empty();
 
         let n4_ptr = NodePtr::from(&mut n4).to_opaque();
         let n16_ptr = NodePtr::from(&mut n16).to_opaque();
+        let n32_ptr = NodePtr::from(&mut n32).to_opaque();
         let n48_ptr = NodePtr::from(&mut n48).to_opaque();
         let n256_ptr = NodePtr::from(&mut n256).to_opaque();
 
         assert!(n4_ptr.is::<InnerNode4<Box<[u8]>, usize, 16>>());
         assert!(n16_ptr.is::<InnerNode16<Box<[u8]>, usize, 16>>());
+        assert!(n32_ptr.is::<InnerNode32<Box<[u8]>, usize, 16>>());
+        assert!(!n32_ptr.is::<InnerNode16<Box<[u8]>, usize, 16>>());
         assert!(n48_ptr.is::<InnerNode48<Box<[u8]>, usize, 16>>());
         assert!(n256_ptr.is::<InnerNode256<Box<[u8]>, usize, 16>>());
     }
@@ -938,6 +1178,12 @@ mod tests {
             mem::size_of::<InnerNode16<Box<[u8]>, usize, DEFAULT_PREFIX_LEN>>(),
             EXPECTED_HEADER_SIZE + 144
         );
+        // key map: 32 * (1 byte) = 32 bytes
+        // child map: 32 * (8 bytes (on 64-bit platform)) = 256
+        assert_eq!(
+            mem::size_of::<InnerNode32<Box<[u8]>, usize, DEFAULT_PREFIX_LEN>>(),
+            EXPECTED_HEADER_SIZE + 288
+        );
         // key map: 256 * (1 byte) = 256 bytes
         // child map: 48 * (8 bytes (on 64-bit platform)) = 384
         assert_eq!(
@@ -965,6 +1211,7 @@ mod tests {
     fn node_alignment() {
         assert_eq!(mem::align_of::<InnerNode4<Box<[u8]>, u8, 16>>(), 8);
         assert_eq!(mem::align_of::<InnerNode16<Box<[u8]>, u8, 16>>(), 8);
+        assert_eq!(mem::align_of::<InnerNode32<Box<[u8]>, u8, 16>>(), 8);
         assert_eq!(mem::align_of::<InnerNode48<Box<[u8]>, u8, 16>>(), 8);
         assert_eq!(mem::align_of::<InnerNode256<Box<[u8]>, u8, 16>>(), 8);
         assert_eq!(mem::align_of::<LeafNode<Box<[u8]>, u8>>(), 8);
@@ -978,6 +1225,10 @@ mod tests {
             mem::align_of::<InnerNode16<Box<[u8]>, u8, 16>>(),
             mem::align_of::<OpaqueValue>()
         );
+        assert_eq!(
+            mem::align_of::<InnerNode32<Box<[u8]>, u8, 16>>(),
+            mem::align_of::<OpaqueValue>()
+        );
         assert_eq!(
             mem::align_of::<InnerNode48<Box<[u8]>, u8, 16>>(),
             mem::align_of::<OpaqueValue>()
@@ -996,10 +1247,10 @@ mod tests {
         let n48 = InnerNode4::<Box<[u8]>, (), 16>::empty();
         let n256 = InnerNode4::<Box<[u8]>, (), 16>::empty();
 
-        let n4_ptr = (&n4 as *const InnerNode4<Box<[u8]>, (), 16>).addr();
-        let n16_ptr = (&n16 as *const InnerNode4<Box<[u8]>, (), 16>).addr();
-        let n48_ptr = (&n48 as *const InnerNode4<Box<[u8]>, (), 16>).addr();
-        let n256_ptr = (&n256 as *const InnerNode4<Box<[u8]>, (), 16>).addr();
+        let n4_ptr = ptr_addr(&n4 as *const InnerNode4<Box<[u8]>, (), 16>);
+        let n16_ptr = ptr_addr(&n16 as *const InnerNode4<Box<[u8]>, (), 16>);
+        let n48_ptr = ptr_addr(&n48 as *const InnerNode4<Box<[u8]>, (), 16>);
+        let n256_ptr = ptr_addr(&n256 as *const InnerNode4<Box<[u8]>, (), 16>);
 
         // Ensure that there are 3 bits of unused space in the node pointer because of
         // the alignment.
@@ -1,15 +1,73 @@
 //! Different header type
 
-use std::fmt::Debug;
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    fmt::{self, Debug},
+    mem::{self, MaybeUninit},
+    ptr::{self, NonNull},
+    slice,
+};
 
 use crate::{
-    minimum_unchecked,
-    rust_nightly_apis::{assume, likely},
-    AsBytes, InnerNode, LeafNode, NodePtr,
+    metrics, minimum_unchecked,
+    rust_nightly_apis::{assume, likely, unlikely},
+    AsBytes, HeapSize, InnerNode, LeafNode, NodePtr,
 };
 
+/// How inner nodes store prefixes which are longer than the `PREFIX_LEN`
+/// bytes available in the node header.
+///
+/// The first `PREFIX_LEN` bytes of a prefix are always stored in the header,
+/// this only changes what happens to the remaining bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrefixStrategy {
+    /// Only store the length of the prefix, and reconstruct the missing bytes
+    /// from a leaf of the node whenever they are needed.
+    ///
+    /// This uses no extra memory, but every search which reads past the first
+    /// `PREFIX_LEN` bytes of the prefix has to descend to the minimum leaf of
+    /// the node.
+    #[default]
+    Optimistic,
+    /// Copy the full prefix to a separate heap allocation owned by the node,
+    /// so the prefix can always be read without visiting a leaf.
+    ///
+    /// This costs an allocation per node with a long prefix, and is a better
+    /// fit for keys with long shared segments, like URLs or file paths.
+    ///
+    /// The pointer to the allocation is stored in place of the inline prefix
+    /// bytes, so this strategy needs a `PREFIX_LEN` of at least
+    /// [`PrefixStrategy::MIN_PESSIMISTIC_PREFIX_LEN`] bytes.
+    Pessimistic,
+}
+
+impl PrefixStrategy {
+    /// The smallest `PREFIX_LEN` which supports the
+    /// [`PrefixStrategy::Pessimistic`] strategy, which is the size of a
+    /// pointer.
+    pub const MIN_PESSIMISTIC_PREFIX_LEN: usize = mem::size_of::<NonNull<u8>>();
+
+    /// Return true if this strategy can be used by nodes with `prefix_len`
+    /// bytes of inline prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::PrefixStrategy;
+    ///
+    /// assert!(PrefixStrategy::Optimistic.is_supported(1));
+    /// assert!(PrefixStrategy::Pessimistic.is_supported(16));
+    /// assert!(!PrefixStrategy::Pessimistic.is_supported(4));
+    /// ```
+    pub const fn is_supported(self, prefix_len: usize) -> bool {
+        match self {
+            PrefixStrategy::Optimistic => true,
+            PrefixStrategy::Pessimistic => prefix_len >= Self::MIN_PESSIMISTIC_PREFIX_LEN,
+        }
+    }
+}
+
 /// The common header for all inner nodes
-#[derive(Debug, Clone, PartialEq, Eq)]
 #[repr(align(8))]
 pub struct Header<const PREFIX_LEN: usize> {
     /// Number of children of this inner node. This field has no meaning for
@@ -18,23 +76,54 @@ pub struct Header<const PREFIX_LEN: usize> {
     /// This needs to be a [`u16`], since a node 256 can hold up to 256 children
     /// if this was a [`u8`] (0-255) it would overflow when adding the last
     /// element
+    ///
+    /// Only the bits in [`Self::COUNT_MASK`] hold the count, the bits above
+    /// them are the [`Self::PESSIMISTIC`] and [`Self::SPILLED`] flags.
     num_children: u16,
     /// Number of bytes used by the prefix
     prefix_len: u32,
     /// The key prefix for this node.
-    prefix: [u8; PREFIX_LEN],
+    ///
+    /// If the [`Self::SPILLED`] flag is set, the first bytes hold a pointer
+    /// to a heap allocation of `prefix_len` bytes with the full prefix
+    /// instead. The bytes are [`MaybeUninit`] so that they can carry the
+    /// pointer.
+    prefix: [MaybeUninit<u8>; PREFIX_LEN],
 }
 
 impl<const PREFIX_LEN: usize> Header<PREFIX_LEN> {
+    /// Prefixes can only be spilled if the pointer fits in the prefix bytes.
+    const CAN_SPILL: bool = PrefixStrategy::Pessimistic.is_supported(PREFIX_LEN);
+    /// The bits of `num_children` which hold the number of children.
+    const COUNT_MASK: u16 = 0x1FF;
+    /// Set if the node uses the [`PrefixStrategy::Pessimistic`] strategy.
+    const PESSIMISTIC: u16 = 1 << 9;
+    /// Set if the full prefix was spilled to the heap.
+    const SPILLED: u16 = 1 << 10;
+
     #[inline(always)]
     pub fn new(prefix: &[u8], prefix_len: usize) -> Self {
-        let mut header = Self {
-            num_children: 0,
-            prefix_len: prefix_len as u32,
-            prefix: [0; PREFIX_LEN],
-        };
-        let len = prefix.len().min(PREFIX_LEN);
-        header.prefix[..len].copy_from_slice(&prefix[..len]);
+        Self::with_strategy(prefix, prefix_len, PrefixStrategy::Optimistic)
+    }
+
+    /// Create a new `Header` which stores long prefixes using the given
+    /// strategy.
+    ///
+    /// If `prefix` contains all `prefix_len` bytes and the strategy is
+    /// [`PrefixStrategy::Pessimistic`], prefixes longer than `PREFIX_LEN` are
+    /// spilled to the heap.
+    #[inline(always)]
+    pub fn with_strategy(prefix: &[u8], prefix_len: usize, strategy: PrefixStrategy) -> Self {
+        let mut header = Self::empty();
+        if strategy == PrefixStrategy::Pessimistic {
+            header.num_children |= Self::PESSIMISTIC;
+        }
+        if prefix.len() == prefix_len {
+            header.set_full_prefix(prefix);
+        } else {
+            header.prefix_len = prefix_len as u32;
+            header.write_inline(prefix);
+        }
 
         header
     }
@@ -45,14 +134,148 @@ impl<const PREFIX_LEN: usize> Header<PREFIX_LEN> {
         Self {
             num_children: 0,
             prefix_len: 0,
-            prefix: [0; PREFIX_LEN],
+            prefix: [MaybeUninit::new(0); PREFIX_LEN],
+        }
+    }
+
+    /// Create a new `Header` with the same prefix and strategy as this one,
+    /// but no children.
+    #[inline(always)]
+    pub fn clone_prefix(&self) -> Self {
+        let mut header = self.clone();
+        header.num_children &= !Self::COUNT_MASK;
+        header
+    }
+
+    /// Copy the first `PREFIX_LEN` bytes of `prefix` to the header.
+    ///
+    /// The header must not have a spilled prefix.
+    #[inline(always)]
+    fn write_inline(&mut self, prefix: &[u8]) {
+        let len = prefix.len().min(PREFIX_LEN);
+        for (dst, src) in self.prefix[..len].iter_mut().zip(prefix) {
+            *dst = MaybeUninit::new(*src);
+        }
+    }
+
+    /// Return the spilled prefix, if there is one.
+    #[inline(always)]
+    fn spilled(&self) -> Option<&[u8]> {
+        if unlikely!(self.num_children & Self::SPILLED != 0) {
+            // SAFETY: The `SPILLED` flag is only set by `set_full_prefix`, which
+            // writes a pointer to an allocation of `prefix_len` bytes to the start
+            // of the prefix bytes
+            Some(unsafe {
+                let ptr = self.prefix.as_ptr().cast::<*mut u8>().read_unaligned();
+                slice::from_raw_parts(ptr, self.prefix_len())
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Remove the spilled prefix from the header and return it, leaving the
+    /// first `PREFIX_LEN` bytes of it inline.
+    #[inline(always)]
+    fn take_spilled(&mut self) -> Option<Box<[u8]>> {
+        if likely!(self.num_children & Self::SPILLED == 0) {
+            return None;
+        }
+
+        self.num_children &= !Self::SPILLED;
+        // SAFETY: Same as in `spilled`, and the `SPILLED` flag was cleared so the
+        // allocation is only owned by the returned box
+        let spilled = unsafe {
+            let ptr = self.prefix.as_ptr().cast::<*mut u8>().read_unaligned();
+            Box::from_raw(ptr::slice_from_raw_parts_mut(ptr, self.prefix_len()))
+        };
+        self.write_inline(&spilled);
+        Some(spilled)
+    }
+
+    /// Return the strategy used to store prefixes longer than `PREFIX_LEN`.
+    #[inline(always)]
+    pub fn strategy(&self) -> PrefixStrategy {
+        if self.num_children & Self::PESSIMISTIC != 0 {
+            PrefixStrategy::Pessimistic
+        } else {
+            PrefixStrategy::Optimistic
+        }
+    }
+
+    /// Change the strategy used to store prefixes longer than `PREFIX_LEN`.
+    ///
+    /// A spilled prefix is freed when switching to
+    /// [`PrefixStrategy::Optimistic`]. When switching to
+    /// [`PrefixStrategy::Pessimistic`] the prefix is only spilled the next time
+    /// it is rewritten from a leaf, see [`Header::ltrim_by_with_leaf`].
+    #[inline(always)]
+    pub fn set_strategy(&mut self, strategy: PrefixStrategy) {
+        match strategy {
+            PrefixStrategy::Optimistic => {
+                drop(self.take_spilled());
+                self.num_children &= !Self::PESSIMISTIC;
+            },
+            PrefixStrategy::Pessimistic => self.num_children |= Self::PESSIMISTIC,
+        }
+    }
+
+    /// Return true if the full prefix is stored in a separate heap allocation.
+    #[inline(always)]
+    pub fn is_prefix_spilled(&self) -> bool {
+        self.num_children & Self::SPILLED != 0
+    }
+
+    /// Return the full prefix if it is available without reading a leaf.
+    #[inline(always)]
+    pub fn full_prefix(&self) -> Option<&[u8]> {
+        if self.prefix_len() <= PREFIX_LEN {
+            Some(self.read_prefix())
+        } else {
+            self.spilled()
+        }
+    }
+
+    /// Replace the prefix with `full_prefix`, which must contain every byte
+    /// of the new prefix.
+    ///
+    /// The bytes which don't fit in the header are only kept if the strategy
+    /// is [`PrefixStrategy::Pessimistic`], and `PREFIX_LEN` is large enough to
+    /// hold a pointer.
+    #[inline(always)]
+    fn set_full_prefix(&mut self, full_prefix: &[u8]) {
+        drop(self.take_spilled());
+        self.prefix_len = full_prefix.len() as u32;
+        if Self::CAN_SPILL
+            && full_prefix.len() > PREFIX_LEN
+            && self.num_children & Self::PESSIMISTIC != 0
+        {
+            let ptr = Box::into_raw(Box::<[u8]>::from(full_prefix)).cast::<u8>();
+            // SAFETY: `CAN_SPILL` checks that the prefix bytes are large enough to
+            // hold the pointer
+            unsafe {
+                self.prefix
+                    .as_mut_ptr()
+                    .cast::<*mut u8>()
+                    .write_unaligned(ptr)
+            };
+            self.num_children |= Self::SPILLED;
+        } else {
+            self.write_inline(full_prefix);
         }
     }
 
     /// Read the initialized portion of the prefix present in the header.
     #[inline(always)]
     pub fn read_prefix(&self) -> &[u8] {
-        &self.prefix[0..self.capped_prefix_len()]
+        if let Some(spilled) = self.spilled() {
+            return &spilled[..PREFIX_LEN];
+        }
+
+        // SAFETY: Without a spilled prefix, all the prefix bytes are initialized
+        unsafe {
+            slice::from_raw_parts(self.prefix.as_ptr().cast::<u8>(), self.capped_prefix_len())
+        }
     }
 
     /// Get the number of bytes in the prefix.
@@ -70,7 +293,7 @@ impl<const PREFIX_LEN: usize> Header<PREFIX_LEN> {
     /// Return the number of children of this node.
     #[inline(always)]
     pub fn num_children(&self) -> usize {
-        usize::from(self.num_children)
+        usize::from(self.num_children & Self::COUNT_MASK)
     }
 
     /// Left trim by `len`, copies the remaining data to the beginning of the
@@ -80,6 +303,11 @@ impl<const PREFIX_LEN: usize> Header<PREFIX_LEN> {
     ///  - If `len` > length of the prefix
     #[inline(always)]
     pub fn ltrim_by(&mut self, len: usize) {
+        if let Some(spilled) = self.take_spilled() {
+            self.set_full_prefix(&spilled[len..]);
+            return;
+        }
+
         self.prefix_len -= len as u32;
 
         let begin = len;
@@ -101,25 +329,85 @@ impl<const PREFIX_LEN: usize> Header<PREFIX_LEN> {
 
     /// Set the length of the prefix to 0 and returns a copy of the
     /// prefix, length and capped length
+    ///
+    /// Any spilled prefix is dropped.
     #[inline(always)]
     pub fn clear_prefix(&mut self) -> ([u8; PREFIX_LEN], usize, usize) {
+        drop(self.take_spilled());
         let len = self.prefix_len();
         let capped_len = self.capped_prefix_len();
         self.prefix_len = 0;
 
-        (self.prefix, len, capped_len)
+        // SAFETY: Without a spilled prefix, all the prefix bytes are initialized
+        (
+            unsafe { mem::transmute_copy(&self.prefix) },
+            len,
+            capped_len,
+        )
     }
 
     /// Append `new` to the prefix and sums `new_len` to the prefix length
+    ///
+    /// Any spilled prefix is dropped, so if the new prefix is longer than
+    /// `PREFIX_LEN` it will be reconstructed from a leaf.
     #[inline(always)]
     pub fn push_prefix(&mut self, new: &[u8], new_len: usize) {
+        drop(self.take_spilled());
         let begin = self.capped_prefix_len();
         let end = (begin + new.len()).min(PREFIX_LEN);
-        let len = end - begin;
-        self.prefix[begin..end].copy_from_slice(&new[..len]);
+        for (dst, src) in self.prefix[begin..end].iter_mut().zip(new) {
+            *dst = MaybeUninit::new(*src);
+        }
         self.prefix_len += new_len as u32;
     }
 
+    /// Prepend the prefix of `parent` and the `key_byte` that leads from
+    /// `parent` to this node onto this prefix.
+    ///
+    /// This is used when a parent node with a single remaining child is
+    /// collapsed into that child.
+    ///
+    /// Both headers can hold up to `PREFIX_LEN` bytes, so if the parent prefix
+    /// already fills the capacity the new prefix will contain only those bytes
+    /// and the rest is only accounted for in the length. With the
+    /// [`PrefixStrategy::Pessimistic`] strategy the full prefix is spilled
+    /// instead, if both full prefixes are available.
+    #[inline(always)]
+    pub fn prepend_prefix(&mut self, parent: &Self, key_byte: u8) {
+        if self.strategy() == PrefixStrategy::Pessimistic {
+            if let (Some(parent_prefix), Some(prefix)) = (parent.full_prefix(), self.full_prefix())
+            {
+                let full_prefix = [parent_prefix, &[key_byte], prefix].concat();
+                self.set_full_prefix(&full_prefix);
+                return;
+            }
+        }
+
+        let (old_prefix, old_len, old_capped_len) = self.clear_prefix();
+        self.push_prefix(parent.read_prefix(), parent.prefix_len());
+        self.push_prefix(&[key_byte], 1);
+        self.push_prefix(&old_prefix[..old_capped_len], old_len);
+    }
+
+    /// Prepend `bytes` onto this prefix, all of which must be known.
+    ///
+    /// This is used when a subtree is detached from its parent, and the key
+    /// bytes leading up to it become part of its prefix.
+    #[inline(always)]
+    pub fn prepend_bytes(&mut self, bytes: &[u8]) {
+        if self.strategy() == PrefixStrategy::Pessimistic {
+            if let Some(prefix) = self.full_prefix() {
+                let full_prefix: Vec<u8> = [bytes, prefix].concat();
+                self.set_full_prefix(&full_prefix);
+                return;
+            }
+        }
+
+        let (old_prefix, old_len, old_capped_len) = self.clear_prefix();
+        self.push_prefix(bytes, bytes.len());
+        self.push_prefix(&old_prefix[..old_capped_len], old_len);
+    }
+
     /// Increments the number of children
     #[inline(always)]
     pub fn inc_num_children(&mut self) {
@@ -139,6 +427,11 @@ impl<const PREFIX_LEN: usize> Header<PREFIX_LEN> {
         depth: usize,
         leaf_ptr: NodePtr<PREFIX_LEN, LeafNode<K, V>>,
     ) {
+        if self.is_prefix_spilled() {
+            self.ltrim_by(len);
+            return;
+        }
+
         self.prefix_len -= len as u32;
 
         // SAFETY: Since have a mutable reference
@@ -161,9 +454,16 @@ impl<const PREFIX_LEN: usize> Header<PREFIX_LEN> {
             assume!(begin <= end);
         }
 
+        if self.strategy() == PrefixStrategy::Pessimistic {
+            // The leaf has every byte of the trimmed prefix, so this is a chance to
+            // spill it
+            self.set_full_prefix(&leaf_key[begin..(begin + self.prefix_len())]);
+            return;
+        }
+
         let leaf_key = &leaf_key[begin..end];
-        let leaf_key = &leaf_key[..leaf_key.len().min(PREFIX_LEN)];
-        self.prefix[..len.min(PREFIX_LEN)].copy_from_slice(leaf_key)
+        debug_assert_eq!(leaf_key.len(), len);
+        self.write_inline(leaf_key)
     }
 
     #[inline(always)]
@@ -181,7 +481,11 @@ impl<const PREFIX_LEN: usize> Header<PREFIX_LEN> {
         let len = self.prefix_len();
         if likely!(len <= PREFIX_LEN) {
             (self.read_prefix(), None)
+        } else if let Some(spilled) = self.spilled() {
+            (spilled, None)
         } else {
+            metrics::record_prefix_read();
+
             // SAFETY: By construction a InnerNode, must have >= 1 children, this
             // is even more strict since in the case of 1 child the node can be
             // collapsed, so a InnerNode must have >= 2 children, so it's safe
@@ -216,6 +520,57 @@ impl<const PREFIX_LEN: usize> Header<PREFIX_LEN> {
     }
 }
 
+impl<const PREFIX_LEN: usize> Clone for Header<PREFIX_LEN> {
+    fn clone(&self) -> Self {
+        let mut header = Self {
+            num_children: self.num_children & !Self::SPILLED,
+            prefix_len: self.prefix_len,
+            prefix: [MaybeUninit::new(0); PREFIX_LEN],
+        };
+        match self.spilled() {
+            Some(spilled) => header.set_full_prefix(spilled),
+            None => header.write_inline(self.read_prefix()),
+        }
+        header
+    }
+}
+
+impl<const PREFIX_LEN: usize> Drop for Header<PREFIX_LEN> {
+    fn drop(&mut self) {
+        drop(self.take_spilled());
+    }
+}
+
+impl<const PREFIX_LEN: usize> PartialEq for Header<PREFIX_LEN> {
+    fn eq(&self, other: &Self) -> bool {
+        self.num_children == other.num_children
+            && self.prefix_len == other.prefix_len
+            && self.read_prefix() == other.read_prefix()
+            && self.spilled() == other.spilled()
+    }
+}
+
+impl<const PREFIX_LEN: usize> Eq for Header<PREFIX_LEN> {}
+
+impl<const PREFIX_LEN: usize> Debug for Header<PREFIX_LEN> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Header")
+            .field("num_children", &self.num_children())
+            .field("prefix_len", &self.prefix_len)
+            .field("strategy", &self.strategy())
+            .field("prefix", &self.read_prefix())
+            .field("spilled", &self.spilled())
+            .finish()
+    }
+}
+
+/// The heap memory owned by a header is the spilled prefix, if there is one.
+impl<const PREFIX_LEN: usize> HeapSize for Header<PREFIX_LEN> {
+    fn heap_size(&self) -> usize {
+        self.spilled().map_or(0, <[u8]>::len)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -252,4 +607,108 @@ mod tests {
 
         h.ltrim_by(10);
     }
+
+    #[test]
+    fn header_prepend_prefix() {
+        let parent = Header::<4>::new(&[1, 2], 2);
+        let mut h = Header::<4>::new(&[4, 5, 6], 3);
+        h.prepend_prefix(&parent, 3);
+        assert_eq!(h.read_prefix(), &[1, 2, 3, 4]);
+        assert_eq!(h.prefix_len(), 6);
+
+        let parent = Header::<4>::new(&[1, 2, 3, 4], 10);
+        let mut h = Header::<4>::new(&[], 0);
+        h.prepend_prefix(&parent, 7);
+        assert_eq!(h.read_prefix(), &[1, 2, 3, 4]);
+        assert_eq!(h.prefix_len(), 11);
+    }
+
+    #[test]
+    fn header_pessimistic_spills_long_prefix() {
+        const P: usize = 8;
+        let long_prefix = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+
+        let h = Header::<P>::with_strategy(&[1, 2, 3], 3, PrefixStrategy::Pessimistic);
+        assert!(!h.is_prefix_spilled());
+        assert_eq!(h.full_prefix(), Some([1, 2, 3].as_slice()));
+
+        let h = Header::<P>::with_strategy(&long_prefix, 11, PrefixStrategy::Optimistic);
+        assert!(!h.is_prefix_spilled());
+        assert_eq!(h.read_prefix(), &long_prefix[..P]);
+        assert_eq!(h.full_prefix(), None);
+
+        let mut h = Header::<P>::with_strategy(&long_prefix, 11, PrefixStrategy::Pessimistic);
+        assert!(h.is_prefix_spilled());
+        assert_eq!(h.strategy(), PrefixStrategy::Pessimistic);
+        assert_eq!(h.num_children(), 0);
+        assert_eq!(h.read_prefix(), &long_prefix[..P]);
+        assert_eq!(h.full_prefix(), Some(long_prefix.as_slice()));
+        assert_eq!(h.heap_size(), 11);
+
+        let clone = h.clone();
+        assert!(clone.is_prefix_spilled());
+        assert_eq!(clone, h);
+
+        h.inc_num_children();
+        assert_eq!(h.num_children(), 1);
+        let prefix_clone = h.clone_prefix();
+        assert_eq!(prefix_clone.num_children(), 0);
+        assert_eq!(prefix_clone.full_prefix(), Some(long_prefix.as_slice()));
+
+        h.ltrim_by(1);
+        assert!(h.is_prefix_spilled());
+        assert_eq!(h.read_prefix(), &long_prefix[1..(P + 1)]);
+        assert_eq!(h.full_prefix(), Some(&long_prefix[1..]));
+
+        // Back under `PREFIX_LEN`, so the prefix moves inline
+        h.ltrim_by(3);
+        assert!(!h.is_prefix_spilled());
+        assert_eq!(h.read_prefix(), &long_prefix[4..]);
+        assert_eq!(h.prefix_len(), 7);
+        assert_eq!(h.heap_size(), 0);
+
+        // The pushed bytes are not all known, so the spill can't be kept
+        let mut h = Header::<P>::with_strategy(&long_prefix, 11, PrefixStrategy::Pessimistic);
+        h.push_prefix(&[], 2);
+        assert!(!h.is_prefix_spilled());
+        assert_eq!(h.read_prefix(), &long_prefix[..P]);
+        assert_eq!(h.prefix_len(), 13);
+        assert_eq!(h.full_prefix(), None);
+
+        // There is no room for the pointer in a smaller header
+        let h = Header::<4>::with_strategy(&long_prefix, 11, PrefixStrategy::Pessimistic);
+        assert!(!h.is_prefix_spilled());
+        assert_eq!(h.read_prefix(), &long_prefix[..4]);
+        assert_eq!(h.full_prefix(), None);
+    }
+
+    #[test]
+    fn header_pessimistic_prepend() {
+        const P: usize = 8;
+        let parent = Header::<P>::with_strategy(&[1, 2], 2, PrefixStrategy::Pessimistic);
+        let mut h = Header::<P>::with_strategy(&[4, 5, 6, 7, 8, 9], 6, PrefixStrategy::Pessimistic);
+        h.prepend_prefix(&parent, 3);
+        assert!(h.is_prefix_spilled());
+        assert_eq!(h.read_prefix(), &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(
+            h.full_prefix(),
+            Some([1, 2, 3, 4, 5, 6, 7, 8, 9].as_slice())
+        );
+
+        h.prepend_bytes(&[11, 10]);
+        assert!(h.is_prefix_spilled());
+        assert_eq!(h.read_prefix(), &[11, 10, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(
+            h.full_prefix(),
+            Some([11, 10, 1, 2, 3, 4, 5, 6, 7, 8, 9].as_slice())
+        );
+
+        // Without the full parent prefix, this falls back to the implicit prefix
+        let parent = Header::<P>::new(&[1, 2, 3, 4, 5, 6, 7, 8], 10);
+        let mut h = Header::<P>::with_strategy(&[5], 1, PrefixStrategy::Pessimistic);
+        h.prepend_prefix(&parent, 7);
+        assert!(!h.is_prefix_spilled());
+        assert_eq!(h.read_prefix(), &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(h.prefix_len(), 12);
+    }
 }
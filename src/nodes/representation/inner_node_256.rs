@@ -2,14 +2,15 @@ use crate::{
     rust_nightly_apis::maybe_uninit_uninit_array, Header, InnerNode, InnerNode48, Node, NodePtr,
     NodeType, OpaqueNodePtr, RestrictedNodeIndex,
 };
-use std::{
+use core::{
     fmt,
     iter::{Enumerate, FusedIterator},
+    ptr::{self, NonNull},
     slice::Iter,
 };
 
 #[cfg(feature = "nightly")]
-use std::{
+use core::{
     iter::FilterMap,
     simd::{cmp::SimdPartialEq, usizex64},
 };
@@ -71,6 +72,24 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode256<K, V,
         self.child_pointers[usize::from(key_fragment)]
     }
 
+    unsafe fn lookup_child_slot(
+        node: NonNull<Self>,
+        key_fragment: u8,
+    ) -> Option<NonNull<OpaqueNodePtr<K, V, PREFIX_LEN>>> {
+        // SAFETY: Covered by the safety requirements of the containing function
+        let slot = unsafe {
+            ptr::addr_of_mut!((*node.as_ptr()).child_pointers[usize::from(key_fragment)])
+        };
+        // SAFETY: Same as above
+        if unsafe { (*slot).is_none() } {
+            return None;
+        }
+
+        // SAFETY: The slot holds a child, so the `Option` has the same layout as the
+        // child pointer. The pointer is derived from `node` so it is not null.
+        Some(unsafe { NonNull::new_unchecked(slot.cast()) })
+    }
+
     fn write_child(&mut self, key_fragment: u8, child_pointer: OpaqueNodePtr<K, V, PREFIX_LEN>) {
         let key_fragment_idx = usize::from(key_fragment);
         let existing_pointer = self.child_pointers[key_fragment_idx];
@@ -141,14 +160,14 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode256<K, V,
 
     fn range(
         &self,
-        bound: impl std::ops::RangeBounds<u8>,
+        bound: impl core::ops::RangeBounds<u8>,
     ) -> impl DoubleEndedIterator<Item = (u8, OpaqueNodePtr<Self::Key, Self::Value, PREFIX_LEN>)>
            + FusedIterator {
         let start = bound.start_bound().map(|val| usize::from(*val));
         let key_offset = match bound.start_bound() {
-            std::ops::Bound::Included(val) => *val,
-            std::ops::Bound::Excluded(val) => val.saturating_add(1),
-            std::ops::Bound::Unbounded => 0,
+            core::ops::Bound::Included(val) => *val,
+            core::ops::Bound::Excluded(val) => val.saturating_add(1),
+            core::ops::Bound::Unbounded => 0,
         };
         let end = bound.end_bound().map(|val| usize::from(*val));
 
@@ -167,7 +186,7 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode256<K, V,
         // SAFETY: Due to niche optimization Option<NonNull> has the same
         // size as NonNull and NonNull has the same size as usize
         // so it's safe to transmute
-        let child_pointers: &[usize; 256] = unsafe { std::mem::transmute(&self.child_pointers) };
+        let child_pointers: &[usize; 256] = unsafe { core::mem::transmute(&self.child_pointers) };
         let empty = usizex64::splat(0);
         let r0 = usizex64::from_array(child_pointers[0..64].try_into().unwrap())
             .simd_eq(empty)
@@ -222,7 +241,7 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode256<K, V,
         // SAFETY: Due to niche optimization Option<NonNull> has the same
         // size as NonNull and NonNull has the same size as usize
         // so it's safe to transmute
-        let child_pointers: &[usize; 256] = unsafe { std::mem::transmute(&self.child_pointers) };
+        let child_pointers: &[usize; 256] = unsafe { core::mem::transmute(&self.child_pointers) };
         let empty = usizex64::splat(0);
         let r0 = usizex64::from_array(child_pointers[0..64].try_into().unwrap())
             .simd_eq(empty)
@@ -327,7 +346,8 @@ impl<'a, K, V, const PREFIX_LEN: usize> FusedIterator for Node256Iter<'a, K, V,
 
 #[cfg(test)]
 mod tests {
-    use std::ops::Bound;
+    use alloc::{boxed::Box, vec, vec::Vec};
+    use core::ops::Bound;
 
     use crate::{
         nodes::representation::tests::{
@@ -494,7 +514,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic = "slice index starts at 81 but ends at 80"]
+    #[should_panic]
     fn range_iterate_out_of_bounds_panic_both_excluded() {
         let (node, _, [_l1_ptr, _l2_ptr, _l3_ptr, _l4_ptr]) = fixture();
 
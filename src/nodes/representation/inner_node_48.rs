@@ -1,22 +1,24 @@
+#[cfg(not(feature = "nightly"))]
+use super::simd;
 use crate::{
     rust_nightly_apis::{
         assume, maybe_uninit_slice_assume_init_mut, maybe_uninit_slice_assume_init_ref,
         maybe_uninit_uninit_array,
     },
-    Header, InnerNode, InnerNode16, InnerNode256, InnerNodeCompressed, Node, NodePtr, NodeType,
+    Header, InnerNode, InnerNode256, InnerNode32, InnerNodeCompressed, Node, NodePtr, NodeType,
     OpaqueNodePtr,
 };
-use std::{
+use core::{
     cmp::Ordering,
-    error::Error,
     fmt,
     iter::{Enumerate, FusedIterator},
     mem::{self, MaybeUninit},
+    ptr::{self, NonNull},
     slice::Iter,
 };
 
 #[cfg(feature = "nightly")]
-use std::{
+use core::{
     iter::{FilterMap, Map},
     simd::{cmp::SimdPartialEq, u8x64},
 };
@@ -98,9 +100,10 @@ impl fmt::Display for TryFromByteError {
     }
 }
 
-impl Error for TryFromByteError {}
+#[cfg(feature = "std")]
+impl std::error::Error for TryFromByteError {}
 
-/// Node that references between 17 and 49 children
+/// Node that references between 33 and 48 children
 #[repr(C, align(8))]
 pub struct InnerNode48<K, V, const PREFIX_LEN: usize> {
     /// The common node fields.
@@ -147,6 +150,17 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode48<K, V, PREFIX_LEN> {
             maybe_uninit_slice_assume_init_ref(&self.child_pointers[..self.header.num_children()])
         }
     }
+
+    /// Return the child indices as raw bytes, where empty slots have the value
+    /// of [`RestrictedNodeIndex::EMPTY`].
+    #[cfg(not(feature = "nightly"))]
+    fn child_indices_bytes(&self) -> &[u8; 256] {
+        // SAFETY: `RestrictedNodeIndex` is `repr(transparent)` over a `u8`, so the
+        // arrays have the same layout
+        unsafe {
+            &*(&self.child_indices as *const [RestrictedNodeIndex<48>; 256]).cast::<[u8; 256]>()
+        }
+    }
 }
 
 impl<K, V, const PREFIX_LEN: usize> Node<PREFIX_LEN> for InnerNode48<K, V, PREFIX_LEN> {
@@ -168,7 +182,7 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode48<K, V,
         >,
         impl FnMut((u8, usize)) -> (u8, OpaqueNodePtr<K, V, PREFIX_LEN>),
     > where Self: 'a;
-    type ShrunkNode = InnerNode16<K, V, PREFIX_LEN>;
+    type ShrunkNode = InnerNode32<K, V, PREFIX_LEN>;
 
     fn header(&self) -> &Header<PREFIX_LEN> {
         &self.header
@@ -201,6 +215,27 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode48<K, V,
         }
     }
 
+    unsafe fn lookup_child_slot(
+        node: NonNull<Self>,
+        key_fragment: u8,
+    ) -> Option<NonNull<OpaqueNodePtr<K, V, PREFIX_LEN>>> {
+        // SAFETY: Covered by the safety requirements of the containing function. Only
+        // the `child_indices` array is borrowed, so there is no reference to the
+        // child slots.
+        let index = unsafe { (*node.as_ptr()).child_indices[usize::from(key_fragment)] };
+        if index.is_empty() {
+            return None;
+        }
+
+        // SAFETY: A non-empty index points into the initialized portion of the
+        // `child_pointers` array, and the pointer is derived from `node` so it is
+        // not null
+        unsafe {
+            let slot = ptr::addr_of_mut!((*node.as_ptr()).child_pointers[usize::from(index)]);
+            Some(NonNull::new_unchecked(slot.cast()))
+        }
+    }
+
     fn write_child(&mut self, key_fragment: u8, child_pointer: OpaqueNodePtr<K, V, PREFIX_LEN>) {
         let key_fragment_idx = usize::from(key_fragment);
         let child_index = if self.child_indices[key_fragment_idx] == RestrictedNodeIndex::EMPTY {
@@ -306,15 +341,15 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode48<K, V,
 
     fn shrink(&self) -> Self::ShrunkNode {
         debug_assert!(
-            self.header.num_children() <= 16,
-            "Cannot shrink a Node48 when it has more than 16 children. Currently has [{}] \
+            self.header.num_children() <= 32,
+            "Cannot shrink a Node48 when it has more than 32 children. Currently has [{}] \
              children.",
             self.header.num_children()
         );
 
         let header = self.header.clone();
 
-        let mut key_and_child_ptrs = maybe_uninit_uninit_array::<_, 16>();
+        let mut key_and_child_ptrs = maybe_uninit_uninit_array::<_, 32>();
 
         for (idx, value) in self.iter().enumerate() {
             key_and_child_ptrs[idx].write(value);
@@ -375,16 +410,16 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode48<K, V,
 
     fn range(
         &self,
-        bound: impl std::ops::RangeBounds<u8>,
+        bound: impl core::ops::RangeBounds<u8>,
     ) -> impl DoubleEndedIterator<Item = (u8, OpaqueNodePtr<Self::Key, Self::Value, PREFIX_LEN>)>
            + FusedIterator {
         let child_pointers = self.initialized_child_pointers();
 
         let start = bound.start_bound().map(|val| usize::from(*val));
         let key_offset = match bound.start_bound() {
-            std::ops::Bound::Included(val) => *val,
-            std::ops::Bound::Excluded(val) => val.saturating_add(1),
-            std::ops::Bound::Unbounded => 0,
+            core::ops::Bound::Included(val) => *val,
+            core::ops::Bound::Excluded(val) => val.saturating_add(1),
+            core::ops::Bound::Unbounded => 0,
         };
         let end = bound.end_bound().map(|val| usize::from(*val));
 
@@ -410,7 +445,7 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode48<K, V,
     fn min(&self) -> (u8, OpaqueNodePtr<K, V, PREFIX_LEN>) {
         // SAFETY: Since `RestrictedNodeIndex` is
         // repr(u8) is safe to transmute it
-        let child_indices: &[u8; 256] = unsafe { std::mem::transmute(&self.child_indices) };
+        let child_indices: &[u8; 256] = unsafe { core::mem::transmute(&self.child_indices) };
         let empty = u8x64::splat(48);
         let r0 = u8x64::from_array(child_indices[0..64].try_into().unwrap())
             .simd_eq(empty)
@@ -459,21 +494,23 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode48<K, V,
 
     #[cfg(not(feature = "nightly"))]
     fn min(&self) -> (u8, OpaqueNodePtr<K, V, PREFIX_LEN>) {
-        for (key, idx) in self.child_indices.iter().enumerate() {
-            if idx.is_empty() {
-                continue;
-            }
-            let child_pointers = self.initialized_child_pointers();
-            return (key as u8, child_pointers[usize::from(*idx)]);
-        }
-        unreachable!();
+        let key = simd::find_first_not_equal_256(
+            self.child_indices_bytes(),
+            RestrictedNodeIndex::<48>::EMPTY.0,
+        )
+        .expect("inner node should have at least one child");
+        let child_pointers = self.initialized_child_pointers();
+        (
+            key as u8,
+            child_pointers[usize::from(self.child_indices[key])],
+        )
     }
 
     #[cfg(feature = "nightly")]
     fn max(&self) -> (u8, OpaqueNodePtr<K, V, PREFIX_LEN>) {
         // SAFETY: Since `RestrictedNodeIndex` is
         // repr(u8) is safe to transmute it
-        let child_indices: &[u8; 256] = unsafe { std::mem::transmute(&self.child_indices) };
+        let child_indices: &[u8; 256] = unsafe { core::mem::transmute(&self.child_indices) };
         let empty = u8x64::splat(48);
         let r0 = u8x64::from_array(child_indices[0..64].try_into().unwrap())
             .simd_eq(empty)
@@ -522,14 +559,16 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode48<K, V,
 
     #[cfg(not(feature = "nightly"))]
     fn max(&self) -> (u8, OpaqueNodePtr<K, V, PREFIX_LEN>) {
-        for (key, idx) in self.child_indices.iter().enumerate().rev() {
-            if idx.is_empty() {
-                continue;
-            }
-            let child_pointers = self.initialized_child_pointers();
-            return (key as u8, child_pointers[usize::from(*idx)]);
-        }
-        unreachable!();
+        let key = simd::find_last_not_equal_256(
+            self.child_indices_bytes(),
+            RestrictedNodeIndex::<48>::EMPTY.0,
+        )
+        .expect("inner node should have at least one child");
+        let child_pointers = self.initialized_child_pointers();
+        (
+            key as u8,
+            child_pointers[usize::from(self.child_indices[key])],
+        )
     }
 
     #[inline(always)]
@@ -608,7 +647,8 @@ impl<'a, K, V, const PREFIX_LEN: usize> FusedIterator for Node48Iter<'a, K, V, P
 
 #[cfg(test)]
 mod tests {
-    use std::ops::Bound;
+    use alloc::{boxed::Box, vec, vec::Vec};
+    use core::ops::Bound;
 
     use crate::{
         nodes::representation::tests::{
@@ -688,14 +728,14 @@ mod tests {
 
     #[test]
     fn shrink() {
-        inner_node_shrink_test(InnerNode48::<_, _, 16>::empty(), 16);
+        inner_node_shrink_test(InnerNode48::<_, _, 16>::empty(), 32);
     }
 
     #[test]
-    #[should_panic = "Cannot shrink a Node48 when it has more than 16 children. Currently has [17] \
+    #[should_panic = "Cannot shrink a Node48 when it has more than 32 children. Currently has [33] \
                       children."]
     fn shrink_too_many_children_panic() {
-        inner_node_shrink_test(InnerNode48::<_, _, 16>::empty(), 17);
+        inner_node_shrink_test(InnerNode48::<_, _, 16>::empty(), 33);
     }
 
     fn fixture() -> FixtureReturn<InnerNode48<Box<[u8]>, (), 16>, 4> {
@@ -800,7 +840,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic = "slice index starts at 81 but ends at 80"]
+    #[should_panic]
     fn range_iterate_out_of_bounds_panic_both_excluded() {
         let (node, _, [_l1_ptr, _l2_ptr, _l3_ptr, _l4_ptr]) = fixture();
 
@@ -1,19 +1,22 @@
+#[cfg(not(feature = "nightly"))]
+use super::simd;
 use crate::{
     rust_nightly_apis::{assume, maybe_uninit_slice_assume_init_ref, maybe_uninit_uninit_array},
     Header, InnerNode, InnerNode48, Node, NodePtr, NodeType, OpaqueNodePtr, RestrictedNodeIndex,
 };
-use std::{
+use core::{
     fmt,
     iter::{Copied, Zip},
     mem::{self, MaybeUninit},
     ops::{Bound, RangeBounds},
+    ptr::{self, NonNull},
     slice::Iter,
 };
 
 #[cfg(feature = "nightly")]
-use std::simd::{
+use core::simd::{
     cmp::{SimdPartialEq, SimdPartialOrd},
-    u8x16,
+    u8x16, u8x32,
 };
 
 /// Where a write should happen inside the node
@@ -101,6 +104,18 @@ impl<K, V, const PREFIX_LEN: usize, const SIZE: usize> InnerNodeCompressed<K, V,
         }
     }
 
+    /// Return a copy of the key bytes, with the unused ones past the number of
+    /// children set to zero.
+    ///
+    /// The SIMD searches compare a whole vector of key bytes at a time, so
+    /// they work on this copy instead of the possibly uninitialized `keys`.
+    fn padded_keys(&self) -> [u8; SIZE] {
+        let (keys, _) = self.initialized_portion();
+        let mut padded_keys = [0; SIZE];
+        padded_keys[..keys.len()].copy_from_slice(keys);
+        padded_keys
+    }
+
     /// Generalized version of [`InnerNode::lookup_child`] for compressed nodes
     fn lookup_child_inner(&self, key_fragment: u8) -> Option<OpaqueNodePtr<K, V, PREFIX_LEN>>
     where
@@ -119,6 +134,30 @@ impl<K, V, const PREFIX_LEN: usize, const SIZE: usize> InnerNodeCompressed<K, V,
         }
     }
 
+    /// Generalized version of [`InnerNode::lookup_child_slot`] for compressed
+    /// nodes
+    ///
+    /// # Safety
+    ///  - `node` must point to a valid inner node, which is not mutated while
+    ///    the returned pointer is in use, other than through the pointer.
+    unsafe fn lookup_child_slot_inner(
+        node: NonNull<Self>,
+        key_fragment: u8,
+    ) -> Option<NonNull<OpaqueNodePtr<K, V, PREFIX_LEN>>>
+    where
+        Self: SearchInnerNodeCompressed,
+    {
+        // SAFETY: Covered by the safety requirements of the containing function
+        let idx = unsafe { node.as_ref() }.lookup_child_index(key_fragment)?;
+
+        // SAFETY: `idx` is in the initialized portion of the `child_pointers` array,
+        // and the pointer is derived from `node` so it is not null
+        unsafe {
+            let slot = ptr::addr_of_mut!((*node.as_ptr()).child_pointers[idx]);
+            Some(NonNull::new_unchecked(slot.cast()))
+        }
+    }
+
     /// Writes a child to the node by check the order of insertion
     ///
     /// # Panics
@@ -290,7 +329,7 @@ impl<K, V, const PREFIX_LEN: usize, const SIZE: usize> InnerNodeCompressed<K, V,
 
         for (index, key) in keys.iter().copied().enumerate() {
             // SAFETY: This `try_from` will not panic because index is guaranteed to
-            // be 15 or less because of the length of the `InnerNode16.keys` array.
+            // be 31 or less because of the length of the `InnerNode32.keys` array.
             child_indices[usize::from(key)] =
                 unsafe { RestrictedNodeIndex::try_from(index).unwrap_unchecked() };
         }
@@ -332,21 +371,34 @@ impl<K, V, const PREFIX_LEN: usize, const SIZE: usize> InnerNodeCompressed<K, V,
     where
         Self: SearchInnerNodeCompressed,
     {
-        fn fixup_bound_lookup(bound: Bound<WritePoint>) -> Bound<usize> {
-            match bound {
-                Bound::Included(WritePoint::Existing(idx))
-                | Bound::Included(WritePoint::Last(idx))
-                | Bound::Included(WritePoint::Shift(idx)) => Bound::Included(idx),
-                Bound::Excluded(WritePoint::Existing(idx))
-                | Bound::Excluded(WritePoint::Last(idx))
-                | Bound::Excluded(WritePoint::Shift(idx)) => Bound::Excluded(idx),
-                Bound::Unbounded => Bound::Unbounded,
+        /// Index of the first key fragment which is greater than or equal to
+        /// (or strictly greater than, if `inclusive` is false) the searched
+        /// value
+        fn partition_point(write_point: WritePoint, inclusive: bool) -> usize {
+            match write_point {
+                WritePoint::Existing(idx) if !inclusive => idx + 1,
+                WritePoint::Existing(idx) | WritePoint::Last(idx) | WritePoint::Shift(idx) => idx,
             }
         }
 
-        let start_idx =
-            fixup_bound_lookup(bound.start_bound().map(|val| self.find_write_point(*val)));
-        let end_idx = fixup_bound_lookup(bound.end_bound().map(|val| self.find_write_point(*val)));
+        if let (Bound::Excluded(start), Bound::Excluded(end)) =
+            (bound.start_bound(), bound.end_bound())
+        {
+            assert_ne!(start, end, "range start and end are equal and excluded");
+        }
+
+        let lower = |val: &u8| partition_point(self.find_write_point(*val), true);
+        let upper = |val: &u8| partition_point(self.find_write_point(*val), false);
+        let start_idx = match bound.start_bound() {
+            Bound::Included(val) => Bound::Included(lower(val)),
+            Bound::Excluded(val) => Bound::Included(upper(val)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let end_idx = match bound.end_bound() {
+            Bound::Included(val) => Bound::Excluded(upper(val)),
+            Bound::Excluded(val) => Bound::Excluded(lower(val)),
+            Bound::Unbounded => Bound::Unbounded,
+        };
 
         let slice_range = (start_idx, end_idx);
 
@@ -436,6 +488,14 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode4<K, V, P
         self.lookup_child_inner(key_fragment)
     }
 
+    unsafe fn lookup_child_slot(
+        node: NonNull<Self>,
+        key_fragment: u8,
+    ) -> Option<NonNull<OpaqueNodePtr<K, V, PREFIX_LEN>>> {
+        // SAFETY: Covered by the safety requirements of the containing function
+        unsafe { Self::lookup_child_slot_inner(node, key_fragment) }
+    }
+
     fn write_child(&mut self, key_fragment: u8, child_pointer: OpaqueNodePtr<K, V, PREFIX_LEN>) {
         self.write_child_inner(key_fragment, child_pointer)
     }
@@ -460,7 +520,7 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode4<K, V, P
         &self,
         bound: impl RangeBounds<u8>,
     ) -> impl DoubleEndedIterator<Item = (u8, OpaqueNodePtr<Self::Key, Self::Value, PREFIX_LEN>)>
-           + std::iter::FusedIterator {
+           + core::iter::FusedIterator {
         self.inner_range_iter(bound)
     }
 
@@ -502,10 +562,7 @@ pub type InnerNode16<K, V, const PREFIX_LEN: usize> = InnerNodeCompressed<K, V,
 impl<K, V, const PREFIX_LEN: usize> SearchInnerNodeCompressed for InnerNode16<K, V, PREFIX_LEN> {
     #[cfg(feature = "nightly")]
     fn lookup_child_index(&self, key_fragment: u8) -> Option<usize> {
-        // SAFETY: Even though the type is marked is uninit data, when
-        // crated this is filled with initialized data, we just use it to
-        // remind us that a portion might be uninitialized
-        let keys = unsafe { MaybeUninit::array_assume_init(self.keys) };
+        let keys = self.padded_keys();
         let cmp = u8x16::splat(key_fragment)
             .simd_eq(u8x16::from_array(keys))
             .to_bitmask() as u32;
@@ -520,14 +577,8 @@ impl<K, V, const PREFIX_LEN: usize> SearchInnerNodeCompressed for InnerNode16<K,
 
     #[cfg(not(feature = "nightly"))]
     fn lookup_child_index(&self, key_fragment: u8) -> Option<usize> {
-        let (keys, _) = self.initialized_portion();
-        for (child_index, key) in keys.iter().enumerate() {
-            if key_fragment == *key {
-                return Some(child_index);
-            }
-        }
-
-        None
+        let keys = self.padded_keys();
+        simd::find_equal_16(&keys, self.header.num_children(), key_fragment)
     }
 
     #[cfg(feature = "nightly")]
@@ -535,10 +586,7 @@ impl<K, V, const PREFIX_LEN: usize> SearchInnerNodeCompressed for InnerNode16<K,
         match self.lookup_child_index(key_fragment) {
             Some(child_index) => WritePoint::Existing(child_index),
             None => {
-                // SAFETY: Even though the type is marked is uninit data, when
-                // crated this is filled with initialized data, we just use it to
-                // remind us that a portion might be uninitialized
-                let keys = unsafe { MaybeUninit::array_assume_init(self.keys) };
+                let keys = self.padded_keys();
                 let cmp = u8x16::splat(key_fragment)
                     .simd_lt(u8x16::from_array(keys))
                     .to_bitmask() as u32;
@@ -555,19 +603,17 @@ impl<K, V, const PREFIX_LEN: usize> SearchInnerNodeCompressed for InnerNode16<K,
 
     #[cfg(not(feature = "nightly"))]
     fn find_write_point(&self, key_fragment: u8) -> WritePoint {
-        let (keys, _) = self.initialized_portion();
-
-        let mut child_index = 0;
-        for key in keys {
-            #[allow(clippy::comparison_chain)]
-            if key_fragment < *key {
-                return WritePoint::Shift(child_index);
-            } else if key_fragment == *key {
-                return WritePoint::Existing(child_index);
-            }
-            child_index += 1;
+        match self.lookup_child_index(key_fragment) {
+            Some(child_index) => WritePoint::Existing(child_index),
+            None => {
+                let num_children = self.header.num_children();
+                let keys = self.padded_keys();
+                match simd::find_greater_16(&keys, num_children, key_fragment) {
+                    Some(child_index) => WritePoint::Shift(child_index),
+                    None => WritePoint::Last(num_children),
+                }
+            },
         }
-        WritePoint::Last(child_index)
     }
 }
 
@@ -579,7 +625,7 @@ impl<K, V, const PREFIX_LEN: usize> Node<PREFIX_LEN> for InnerNode16<K, V, PREFI
 }
 
 impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode16<K, V, PREFIX_LEN> {
-    type GrownNode = InnerNode48<K, V, PREFIX_LEN>;
+    type GrownNode = InnerNode32<K, V, PREFIX_LEN>;
     type Iter<'a> = InnerNodeCompressedIter<'a, K, V, PREFIX_LEN> where Self: 'a;
     type ShrunkNode = InnerNode4<K, V, PREFIX_LEN>;
 
@@ -599,6 +645,171 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode16<K, V,
         self.lookup_child_inner(key_fragment)
     }
 
+    unsafe fn lookup_child_slot(
+        node: NonNull<Self>,
+        key_fragment: u8,
+    ) -> Option<NonNull<OpaqueNodePtr<K, V, PREFIX_LEN>>> {
+        // SAFETY: Covered by the safety requirements of the containing function
+        unsafe { Self::lookup_child_slot_inner(node, key_fragment) }
+    }
+
+    fn write_child(&mut self, key_fragment: u8, child_pointer: OpaqueNodePtr<K, V, PREFIX_LEN>) {
+        self.write_child_inner(key_fragment, child_pointer)
+    }
+
+    fn remove_child(&mut self, key_fragment: u8) -> Option<OpaqueNodePtr<K, V, PREFIX_LEN>> {
+        self.remove_child_inner(key_fragment)
+    }
+
+    fn grow(&self) -> Self::GrownNode {
+        self.change_block_size()
+    }
+
+    fn shrink(&self) -> Self::ShrunkNode {
+        self.change_block_size()
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.inner_iter()
+    }
+
+    fn range(
+        &self,
+        bound: impl RangeBounds<u8>,
+    ) -> impl DoubleEndedIterator<Item = (u8, OpaqueNodePtr<Self::Key, Self::Value, PREFIX_LEN>)>
+           + core::iter::FusedIterator {
+        self.inner_range_iter(bound)
+    }
+
+    fn min(&self) -> (u8, OpaqueNodePtr<K, V, PREFIX_LEN>) {
+        let (keys, children) = self.initialized_portion();
+        // SAFETY: Covered by the containing function
+        unsafe {
+            (
+                keys.first().copied().unwrap_unchecked(),
+                children.first().copied().unwrap_unchecked(),
+            )
+        }
+    }
+
+    fn max(&self) -> (u8, OpaqueNodePtr<K, V, PREFIX_LEN>) {
+        let (keys, children) = self.initialized_portion();
+        // SAFETY: Covered by the containing function
+        unsafe {
+            (
+                keys.last().copied().unwrap_unchecked(),
+                children.last().copied().unwrap_unchecked(),
+            )
+        }
+    }
+
+    #[inline(always)]
+    fn deep_clone(&self) -> NodePtr<PREFIX_LEN, Self>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.inner_deep_clone()
+    }
+}
+
+/// Node that references between 17 and 32 children
+pub type InnerNode32<K, V, const PREFIX_LEN: usize> = InnerNodeCompressed<K, V, PREFIX_LEN, 32>;
+
+impl<K, V, const PREFIX_LEN: usize> SearchInnerNodeCompressed for InnerNode32<K, V, PREFIX_LEN> {
+    #[cfg(feature = "nightly")]
+    fn lookup_child_index(&self, key_fragment: u8) -> Option<usize> {
+        let keys = self.padded_keys();
+        let cmp = u8x32::splat(key_fragment)
+            .simd_eq(u8x32::from_array(keys))
+            .to_bitmask();
+        let mask = (1u64 << self.header.num_children()) - 1;
+        let bitfield = cmp & mask;
+        if bitfield != 0 {
+            Some(bitfield.trailing_zeros() as usize)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    fn lookup_child_index(&self, key_fragment: u8) -> Option<usize> {
+        let keys = self.padded_keys();
+        simd::find_equal_32(&keys, self.header.num_children(), key_fragment)
+    }
+
+    #[cfg(feature = "nightly")]
+    fn find_write_point(&self, key_fragment: u8) -> WritePoint {
+        match self.lookup_child_index(key_fragment) {
+            Some(child_index) => WritePoint::Existing(child_index),
+            None => {
+                let keys = self.padded_keys();
+                let cmp = u8x32::splat(key_fragment)
+                    .simd_lt(u8x32::from_array(keys))
+                    .to_bitmask();
+                let mask = (1u64 << self.header.num_children()) - 1;
+                let bitfield = cmp & mask;
+                if bitfield != 0 {
+                    WritePoint::Shift(bitfield.trailing_zeros() as usize)
+                } else {
+                    WritePoint::Last(self.header.num_children())
+                }
+            },
+        }
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    fn find_write_point(&self, key_fragment: u8) -> WritePoint {
+        match self.lookup_child_index(key_fragment) {
+            Some(child_index) => WritePoint::Existing(child_index),
+            None => {
+                let num_children = self.header.num_children();
+                let keys = self.padded_keys();
+                match simd::find_greater_32(&keys, num_children, key_fragment) {
+                    Some(child_index) => WritePoint::Shift(child_index),
+                    None => WritePoint::Last(num_children),
+                }
+            },
+        }
+    }
+}
+
+impl<K, V, const PREFIX_LEN: usize> Node<PREFIX_LEN> for InnerNode32<K, V, PREFIX_LEN> {
+    type Key = K;
+    type Value = V;
+
+    const TYPE: NodeType = NodeType::Node32;
+}
+
+impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode32<K, V, PREFIX_LEN> {
+    type GrownNode = InnerNode48<K, V, PREFIX_LEN>;
+    type Iter<'a> = InnerNodeCompressedIter<'a, K, V, PREFIX_LEN> where Self: 'a;
+    type ShrunkNode = InnerNode16<K, V, PREFIX_LEN>;
+
+    fn header(&self) -> &Header<PREFIX_LEN> {
+        &self.header
+    }
+
+    fn from_header(header: Header<PREFIX_LEN>) -> Self {
+        Self {
+            header,
+            child_pointers: maybe_uninit_uninit_array(),
+            keys: [MaybeUninit::new(0); 32],
+        }
+    }
+
+    fn lookup_child(&self, key_fragment: u8) -> Option<OpaqueNodePtr<K, V, PREFIX_LEN>> {
+        self.lookup_child_inner(key_fragment)
+    }
+
+    unsafe fn lookup_child_slot(
+        node: NonNull<Self>,
+        key_fragment: u8,
+    ) -> Option<NonNull<OpaqueNodePtr<K, V, PREFIX_LEN>>> {
+        // SAFETY: Covered by the safety requirements of the containing function
+        unsafe { Self::lookup_child_slot_inner(node, key_fragment) }
+    }
+
     fn write_child(&mut self, key_fragment: u8, child_pointer: OpaqueNodePtr<K, V, PREFIX_LEN>) {
         self.write_child_inner(key_fragment, child_pointer)
     }
@@ -623,7 +834,7 @@ impl<K, V, const PREFIX_LEN: usize> InnerNode<PREFIX_LEN> for InnerNode16<K, V,
         &self,
         bound: impl RangeBounds<u8>,
     ) -> impl DoubleEndedIterator<Item = (u8, OpaqueNodePtr<Self::Key, Self::Value, PREFIX_LEN>)>
-           + std::iter::FusedIterator {
+           + core::iter::FusedIterator {
         self.inner_range_iter(bound)
     }
 
@@ -668,6 +879,7 @@ mod tests {
         },
         LeafNode,
     };
+    use alloc::{boxed::Box, vec, vec::Vec};
 
     use super::*;
 
@@ -772,6 +984,27 @@ mod tests {
         assert_eq!(n.lookup_child(123), Some(l2_ptr));
     }
 
+    #[test]
+    fn node16_search_with_uninit_unused_keys() {
+        let mut n = InnerNode16::<Box<[u8]>, (), 16>::empty();
+        n.keys = maybe_uninit_uninit_array();
+        let mut leaves = [
+            LeafNode::new(Box::from([]), ()),
+            LeafNode::new(Box::from([]), ()),
+            LeafNode::new(Box::from([]), ()),
+        ];
+        let [l1_ptr, l2_ptr, l3_ptr] = leaves.each_mut().map(|l| NodePtr::from(l).to_opaque());
+
+        n.write_child(123, l1_ptr);
+        n.write_child(3, l2_ptr);
+        n.write_child(200, l3_ptr);
+
+        assert_eq!(n.initialized_portion().0, &[3, 123, 200]);
+        assert_eq!(n.lookup_child(123), Some(l1_ptr));
+        assert_eq!(n.lookup_child(200), Some(l3_ptr));
+        assert!(n.lookup_child(0).is_none());
+    }
+
     #[test]
     fn node16_write_child() {
         inner_node_write_child_test(InnerNode16::<_, _, 16>::empty(), 16)
@@ -790,9 +1023,96 @@ mod tests {
     // }
 
     #[test]
-    #[should_panic = "Node must be full to grow to node 48"]
-    fn node16_grow_panic() {
+    fn node16_grow() {
         let mut n16 = InnerNode16::<Box<[u8]>, (), 16>::empty();
+        let mut v = Vec::new();
+        for i in 0..16 {
+            let mut l = LeafNode::new(vec![].into(), ());
+            let l_ptr = NodePtr::from(&mut l).to_opaque();
+            v.push(l_ptr);
+            n16.write_child(i * 2, l_ptr);
+        }
+
+        let n32 = n16.grow();
+
+        for i in 0..16 {
+            assert_eq!(n32.lookup_child(i * 2), Some(v[i as usize]));
+        }
+        assert_eq!(n32.lookup_child(1), None);
+    }
+
+    #[test]
+    fn node16_shrink() {
+        inner_node_shrink_test(InnerNode16::<_, _, 16>::empty(), 4);
+    }
+
+    #[test]
+    #[should_panic = "Cannot change InnerNodeCompressed<16> to size 4 when it has more than 4 \
+                      children. Currently has [5] children."]
+    fn node16_shrink_too_many_children_panic() {
+        inner_node_shrink_test(InnerNode16::<_, _, 16>::empty(), 5);
+    }
+
+    #[test]
+    fn node32_lookup() {
+        let mut n = InnerNode32::<Box<[u8]>, (), 16>::empty();
+        let mut l1 = LeafNode::new(Box::from([]), ());
+        let mut l2 = LeafNode::new(Box::from([]), ());
+        let mut l3 = LeafNode::new(Box::from([]), ());
+        let l1_ptr = NodePtr::from(&mut l1).to_opaque();
+        let l2_ptr = NodePtr::from(&mut l2).to_opaque();
+        let l3_ptr = NodePtr::from(&mut l3).to_opaque();
+
+        assert!(n.lookup_child(123).is_none());
+
+        n.header.inc_num_children();
+        n.header.inc_num_children();
+        n.header.inc_num_children();
+
+        n.keys[0].write(3);
+        n.keys[1].write(123);
+        n.keys[2].write(1);
+
+        n.child_pointers[0].write(l1_ptr);
+        n.child_pointers[1].write(l2_ptr);
+        n.child_pointers[2].write(l3_ptr);
+
+        assert_eq!(n.lookup_child(123), Some(l2_ptr));
+        assert_eq!(n.lookup_child(0), None);
+    }
+
+    #[test]
+    fn node32_search_with_uninit_unused_keys() {
+        let mut n = InnerNode32::<Box<[u8]>, (), 16>::empty();
+        n.keys = maybe_uninit_uninit_array();
+        let mut leaves: [_; 20] = core::array::from_fn(|_| LeafNode::new(Box::from([]), ()));
+        let leaf_ptrs = leaves.each_mut().map(|l| NodePtr::from(l).to_opaque());
+
+        // Write enough children that the search spans both halves of the keys
+        for (key_fragment, leaf_ptr) in (0..20u8).rev().zip(leaf_ptrs) {
+            n.write_child(key_fragment * 10, leaf_ptr);
+        }
+
+        assert_eq!(n.initialized_portion().0.len(), 20);
+        assert_eq!(n.lookup_child(190), Some(leaf_ptrs[0]));
+        assert_eq!(n.lookup_child(0), Some(leaf_ptrs[19]));
+        assert!(n.lookup_child(5).is_none());
+    }
+
+    #[test]
+    fn node32_write_child() {
+        inner_node_write_child_test(InnerNode32::<_, _, 16>::empty(), 32)
+    }
+
+    #[test]
+    fn node32_remove_child() {
+        inner_node_remove_child_test(InnerNode32::<_, _, 16>::empty(), 32)
+    }
+
+    #[test]
+    #[should_panic = "Node must be full to grow to node 48"]
+    fn node32_grow_panic() {
+        let mut n32 = InnerNode32::<Box<[u8]>, (), 16>::empty();
         let mut l1 = LeafNode::new(vec![].into(), ());
         let mut l2 = LeafNode::new(vec![].into(), ());
         let mut l3 = LeafNode::new(vec![].into(), ());
@@ -800,46 +1120,76 @@ mod tests {
         let l2_ptr = NodePtr::from(&mut l2).to_opaque();
         let l3_ptr = NodePtr::from(&mut l3).to_opaque();
 
-        n16.write_child(3, l1_ptr);
-        n16.write_child(123, l2_ptr);
-        n16.write_child(1, l3_ptr);
+        n32.write_child(3, l1_ptr);
+        n32.write_child(123, l2_ptr);
+        n32.write_child(1, l3_ptr);
 
-        let n48 = n16.grow();
-
-        assert_eq!(n48.lookup_child(3), Some(l1_ptr));
-        assert_eq!(n48.lookup_child(123), Some(l2_ptr));
-        assert_eq!(n48.lookup_child(1), Some(l3_ptr));
-        assert_eq!(n48.lookup_child(4), None);
+        let _n48 = n32.grow();
     }
 
     #[test]
-    fn node16_grow() {
-        let mut n16 = InnerNode16::<Box<[u8]>, (), 16>::empty();
+    fn node32_grow() {
+        let mut n32 = InnerNode32::<Box<[u8]>, (), 16>::empty();
         let mut v = Vec::new();
-        for i in 0..16 {
+        for i in 0..32 {
             let mut l = LeafNode::new(vec![].into(), ());
             let l_ptr = NodePtr::from(&mut l).to_opaque();
             v.push(l_ptr);
-            n16.write_child(i * 2, l_ptr);
+            n32.write_child(i * 2, l_ptr);
         }
 
-        let n48 = n16.grow();
+        let n48 = n32.grow();
 
-        for i in 0..16 {
+        for i in 0..32 {
             assert_eq!(n48.lookup_child(i * 2), Some(v[i as usize]));
         }
     }
 
     #[test]
-    fn node16_shrink() {
-        inner_node_shrink_test(InnerNode16::<_, _, 16>::empty(), 4);
+    fn node32_shrink() {
+        inner_node_shrink_test(InnerNode32::<_, _, 16>::empty(), 16);
     }
 
     #[test]
-    #[should_panic = "Cannot change InnerNodeCompressed<16> to size 4 when it has more than 4 \
-                      children. Currently has [5] children."]
-    fn node16_shrink_too_many_children_panic() {
-        inner_node_shrink_test(InnerNode16::<_, _, 16>::empty(), 5);
+    #[should_panic = "Cannot change InnerNodeCompressed<32> to size 16 when it has more than 16 \
+                      children. Currently has [17] children."]
+    fn node32_shrink_too_many_children_panic() {
+        inner_node_shrink_test(InnerNode32::<_, _, 16>::empty(), 17);
+    }
+
+    #[test]
+    fn node32_iterate_and_range() {
+        let mut n32 = InnerNode32::<Box<[u8]>, (), 16>::empty();
+        let mut leaves = Vec::new();
+        for _ in 0..32 {
+            leaves.push(LeafNode::new(vec![].into(), ()));
+        }
+        let ptrs = leaves
+            .iter_mut()
+            .map(|l| NodePtr::from(l).to_opaque())
+            .collect::<Vec<_>>();
+        // Write in reverse so every insert shifts the existing keys
+        for i in (0..32u8).rev() {
+            n32.write_child(i * 8, ptrs[usize::from(i)]);
+        }
+
+        let expected = (0..32u8)
+            .map(|i| (i * 8, ptrs[usize::from(i)]))
+            .collect::<Vec<_>>();
+        assert_eq!(n32.iter().collect::<Vec<_>>(), expected);
+        assert_eq!(n32.min(), (0, ptrs[0]));
+        assert_eq!(n32.max(), (248, ptrs[31]));
+
+        let pairs = n32.range(130..=200).collect::<Vec<_>>();
+        assert_eq!(pairs, &expected[17..26]);
+
+        let pairs = n32
+            .range((Bound::Excluded(136), Bound::Unbounded))
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, &expected[18..]);
+
+        assert_eq!(n32.lookup_child(129), None);
+        assert_eq!(n32.lookup_child(136), Some(ptrs[17]));
     }
 
     fn node4_fixture() -> FixtureReturn<InnerNode4<Box<[u8]>, (), 16>, 4> {
@@ -944,7 +1294,32 @@ mod tests {
     }
 
     #[test]
-    #[should_panic = "slice index starts at 3 but ends at 2"]
+    fn node4_range_iterate_absent_bounds() {
+        let (node, _, [l1_ptr, l2_ptr, l3_ptr, l4_ptr]) = node4_fixture();
+
+        let pairs = node.range(..=84).collect::<Vec<_>>();
+        assert_eq!(pairs, &[(0u8, l3_ptr), (3, l1_ptr)]);
+
+        let pairs = node.range(..84).collect::<Vec<_>>();
+        assert_eq!(pairs, &[(0u8, l3_ptr), (3, l1_ptr)]);
+
+        let pairs = node.range(4..).collect::<Vec<_>>();
+        assert_eq!(pairs, &[(85, l4_ptr), (255, l2_ptr)]);
+
+        let pairs = node
+            .range((Bound::Excluded(1), Bound::Included(254)))
+            .collect::<Vec<_>>();
+        assert_eq!(pairs, &[(3, l1_ptr), (85, l4_ptr)]);
+
+        let pairs = node.range(4..=84).collect::<Vec<_>>();
+        assert_eq!(pairs, &[]);
+
+        assert_eq!(node.range(..=2).next_back(), Some((0u8, l3_ptr)));
+        assert_eq!(node.range(86..).next(), Some((255, l2_ptr)));
+    }
+
+    #[test]
+    #[should_panic]
     fn node4_range_iterate_out_of_bounds_panic_both_excluded() {
         let (node, _, [_l1_ptr, _l2_ptr, _l3_ptr, _l4_ptr]) = node4_fixture();
 
@@ -1056,7 +1431,7 @@ mod tests {
     }
 
     #[test]
-    #[should_panic = "slice index starts at 3 but ends at 2"]
+    #[should_panic]
     fn node16_range_iterate_out_of_bounds_panic_both_excluded() {
         let (node, _, [_l1_ptr, _l2_ptr, _l3_ptr, _l4_ptr]) = node16_fixture();
 
@@ -0,0 +1,393 @@
+//! Searches over the key bytes of inner nodes that use the stable
+//! [`core::arch`] SIMD intrinsics.
+//!
+//! SSE2 and NEON are part of the baseline `x86_64` and `aarch64` targets, so
+//! they are always used on those architectures. The 32 and 256 byte searches
+//! use 32 byte AVX2 vectors when the `avx2` target feature is enabled at
+//! compile time (for example with `-C target-cpu=native`). All other
+//! architectures use scalar loops.
+//!
+//! Each architecture provides comparisons which return a bitmask with
+//! [`LANE_BITS`] bits set for each matching byte, the searches on top of them
+//! are shared.
+
+/// Return a bitmask covering the first `num_lanes` lanes of a comparison
+/// result.
+#[inline(always)]
+fn lanes_mask(num_lanes: usize) -> u64 {
+    let num_bits = num_lanes as u32 * LANE_BITS;
+    if num_bits >= u64::BITS {
+        u64::MAX
+    } else {
+        (1 << num_bits) - 1
+    }
+}
+
+/// Return the index of the first of the first `len` key bytes which is equal
+/// to `key_fragment`.
+#[inline(always)]
+pub fn find_equal_16(keys: &[u8; 16], len: usize, key_fragment: u8) -> Option<usize> {
+    let bitmask = eq_mask_16(keys, key_fragment) & lanes_mask(len);
+    (bitmask != 0).then(|| (bitmask.trailing_zeros() / LANE_BITS) as usize)
+}
+
+/// Return the index of the first of the first `len` key bytes which is
+/// greater than `key_fragment`.
+#[inline(always)]
+pub fn find_greater_16(keys: &[u8; 16], len: usize, key_fragment: u8) -> Option<usize> {
+    let bitmask = gt_mask_16(keys, key_fragment) & lanes_mask(len);
+    (bitmask != 0).then(|| (bitmask.trailing_zeros() / LANE_BITS) as usize)
+}
+
+/// Return the index of the first set lane in the bitmasks of the first `len`
+/// key bytes, comparing [`WIDE_LANES`] bytes at a time.
+#[inline(always)]
+fn find_in_32(
+    keys: &[u8; 32],
+    len: usize,
+    key_fragment: u8,
+    mask_fn: impl Fn(&[u8; WIDE_LANES], u8) -> u64,
+) -> Option<usize> {
+    for (chunk_idx, chunk) in keys.chunks_exact(WIDE_LANES).enumerate() {
+        let start = chunk_idx * WIDE_LANES;
+        if start >= len {
+            break;
+        }
+
+        // PANIC SAFETY: `chunks_exact` only returns chunks of `WIDE_LANES` bytes
+        let bitmask = mask_fn(chunk.try_into().unwrap(), key_fragment) & lanes_mask(len - start);
+        if bitmask != 0 {
+            return Some(start + (bitmask.trailing_zeros() / LANE_BITS) as usize);
+        }
+    }
+
+    None
+}
+
+/// Return the index of the first of the first `len` key bytes which is equal
+/// to `key_fragment`.
+#[inline(always)]
+pub fn find_equal_32(keys: &[u8; 32], len: usize, key_fragment: u8) -> Option<usize> {
+    find_in_32(keys, len, key_fragment, eq_mask_wide)
+}
+
+/// Return the index of the first of the first `len` key bytes which is
+/// greater than `key_fragment`.
+#[inline(always)]
+pub fn find_greater_32(keys: &[u8; 32], len: usize, key_fragment: u8) -> Option<usize> {
+    find_in_32(keys, len, key_fragment, gt_mask_wide)
+}
+
+/// Return the index of the first byte which is not equal to `value`.
+#[inline(always)]
+pub fn find_first_not_equal_256(bytes: &[u8; 256], value: u8) -> Option<usize> {
+    for (chunk_idx, chunk) in bytes.chunks_exact(WIDE_LANES).enumerate() {
+        // PANIC SAFETY: `chunks_exact` only returns chunks of `WIDE_LANES` bytes
+        let bitmask = !eq_mask_wide(chunk.try_into().unwrap(), value) & lanes_mask(WIDE_LANES);
+        if bitmask != 0 {
+            return Some(chunk_idx * WIDE_LANES + (bitmask.trailing_zeros() / LANE_BITS) as usize);
+        }
+    }
+
+    None
+}
+
+/// Return the index of the last byte which is not equal to `value`.
+#[inline(always)]
+pub fn find_last_not_equal_256(bytes: &[u8; 256], value: u8) -> Option<usize> {
+    for (chunk_idx, chunk) in bytes.chunks_exact(WIDE_LANES).enumerate().rev() {
+        // PANIC SAFETY: `chunks_exact` only returns chunks of `WIDE_LANES` bytes
+        let bitmask = !eq_mask_wide(chunk.try_into().unwrap(), value) & lanes_mask(WIDE_LANES);
+        if bitmask != 0 {
+            let last_bit = u64::BITS - 1 - bitmask.leading_zeros();
+            return Some(chunk_idx * WIDE_LANES + (last_bit / LANE_BITS) as usize);
+        }
+    }
+
+    None
+}
+
+#[cfg(target_arch = "x86_64")]
+use x86_64::*;
+
+#[cfg(target_arch = "x86_64")]
+mod x86_64 {
+    #[cfg(target_feature = "avx2")]
+    use core::arch::x86_64::{
+        _mm256_cmpeq_epi8, _mm256_cmpgt_epi8, _mm256_loadu_si256, _mm256_movemask_epi8,
+        _mm256_set1_epi8, _mm256_xor_si256,
+    };
+    use core::arch::x86_64::{
+        _mm_cmpeq_epi8, _mm_cmpgt_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_set1_epi8,
+        _mm_xor_si128,
+    };
+
+    /// The number of bits set in a bitmask for each matching byte
+    pub const LANE_BITS: u32 = 1;
+
+    /// The number of bytes compared by [`eq_mask_wide`]
+    #[cfg(target_feature = "avx2")]
+    pub const WIDE_LANES: usize = 32;
+
+    /// The number of bytes compared by [`eq_mask_wide`]
+    #[cfg(not(target_feature = "avx2"))]
+    pub const WIDE_LANES: usize = 16;
+
+    #[inline(always)]
+    pub fn eq_mask_16(keys: &[u8; 16], value: u8) -> u64 {
+        // SAFETY: SSE2 is always available on `x86_64`, and the unaligned load reads
+        // exactly the 16 bytes of `keys`
+        unsafe {
+            let keys = _mm_loadu_si128(keys.as_ptr().cast());
+            let cmp = _mm_cmpeq_epi8(keys, _mm_set1_epi8(value as i8));
+            u64::from(_mm_movemask_epi8(cmp) as u32)
+        }
+    }
+
+    #[inline(always)]
+    pub fn gt_mask_16(keys: &[u8; 16], value: u8) -> u64 {
+        // SAFETY: SSE2 is always available on `x86_64`, and the unaligned load reads
+        // exactly the 16 bytes of `keys`
+        unsafe {
+            // SSE2 only has a signed comparison, flipping the top bit of both sides maps
+            // the unsigned order onto the signed order
+            let bias = _mm_set1_epi8(i8::MIN);
+            let keys = _mm_xor_si128(_mm_loadu_si128(keys.as_ptr().cast()), bias);
+            let value = _mm_xor_si128(_mm_set1_epi8(value as i8), bias);
+            let cmp = _mm_cmpgt_epi8(keys, value);
+            u64::from(_mm_movemask_epi8(cmp) as u32)
+        }
+    }
+
+    #[cfg(target_feature = "avx2")]
+    #[inline(always)]
+    pub fn eq_mask_wide(bytes: &[u8; WIDE_LANES], value: u8) -> u64 {
+        // SAFETY: The `avx2` target feature is enabled for this compilation, and the
+        // unaligned load reads exactly the 32 bytes of `bytes`
+        unsafe {
+            let bytes = _mm256_loadu_si256(bytes.as_ptr().cast());
+            let cmp = _mm256_cmpeq_epi8(bytes, _mm256_set1_epi8(value as i8));
+            u64::from(_mm256_movemask_epi8(cmp) as u32)
+        }
+    }
+
+    #[cfg(target_feature = "avx2")]
+    #[inline(always)]
+    pub fn gt_mask_wide(bytes: &[u8; WIDE_LANES], value: u8) -> u64 {
+        // SAFETY: The `avx2` target feature is enabled for this compilation, and the
+        // unaligned load reads exactly the 32 bytes of `bytes`
+        unsafe {
+            // Same as `gt_mask_16`, map the unsigned order onto the signed order
+            let bias = _mm256_set1_epi8(i8::MIN);
+            let bytes = _mm256_xor_si256(_mm256_loadu_si256(bytes.as_ptr().cast()), bias);
+            let value = _mm256_xor_si256(_mm256_set1_epi8(value as i8), bias);
+            let cmp = _mm256_cmpgt_epi8(bytes, value);
+            u64::from(_mm256_movemask_epi8(cmp) as u32)
+        }
+    }
+
+    #[cfg(not(target_feature = "avx2"))]
+    #[inline(always)]
+    pub fn eq_mask_wide(bytes: &[u8; WIDE_LANES], value: u8) -> u64 {
+        eq_mask_16(bytes, value)
+    }
+
+    #[cfg(not(target_feature = "avx2"))]
+    #[inline(always)]
+    pub fn gt_mask_wide(bytes: &[u8; WIDE_LANES], value: u8) -> u64 {
+        gt_mask_16(bytes, value)
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+use aarch64::*;
+
+#[cfg(target_arch = "aarch64")]
+mod aarch64 {
+    use core::arch::aarch64::{
+        uint8x16_t, vceqq_u8, vcgtq_u8, vdupq_n_u8, vget_lane_u64, vld1q_u8, vreinterpret_u64_u8,
+        vreinterpretq_u16_u8, vshrn_n_u16,
+    };
+
+    /// The number of bits set in a bitmask for each matching byte
+    pub const LANE_BITS: u32 = 4;
+
+    /// The number of bytes compared by [`eq_mask_wide`]
+    pub const WIDE_LANES: usize = 16;
+
+    /// NEON has no byte movemask, instead narrow each 16 bit pair of lanes by 4
+    /// bits, which leaves 4 bits per byte lane in a 64 bit value.
+    #[inline(always)]
+    fn to_bitmask(cmp: uint8x16_t) -> u64 {
+        // SAFETY: NEON is always available on `aarch64`
+        unsafe {
+            vget_lane_u64::<0>(vreinterpret_u64_u8(vshrn_n_u16::<4>(vreinterpretq_u16_u8(
+                cmp,
+            ))))
+        }
+    }
+
+    #[inline(always)]
+    pub fn eq_mask_16(keys: &[u8; 16], value: u8) -> u64 {
+        // SAFETY: NEON is always available on `aarch64`, and the load reads exactly the
+        // 16 bytes of `keys`
+        to_bitmask(unsafe { vceqq_u8(vld1q_u8(keys.as_ptr()), vdupq_n_u8(value)) })
+    }
+
+    #[inline(always)]
+    pub fn gt_mask_16(keys: &[u8; 16], value: u8) -> u64 {
+        // SAFETY: NEON is always available on `aarch64`, and the load reads exactly the
+        // 16 bytes of `keys`
+        to_bitmask(unsafe { vcgtq_u8(vld1q_u8(keys.as_ptr()), vdupq_n_u8(value)) })
+    }
+
+    #[inline(always)]
+    pub fn eq_mask_wide(bytes: &[u8; WIDE_LANES], value: u8) -> u64 {
+        eq_mask_16(bytes, value)
+    }
+
+    #[inline(always)]
+    pub fn gt_mask_wide(bytes: &[u8; WIDE_LANES], value: u8) -> u64 {
+        gt_mask_16(bytes, value)
+    }
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+use scalar::*;
+
+#[cfg(any(test, not(any(target_arch = "x86_64", target_arch = "aarch64"))))]
+mod scalar {
+    /// The number of bits set in a bitmask for each matching byte
+    #[cfg_attr(test, allow(dead_code))]
+    pub const LANE_BITS: u32 = 1;
+
+    /// The number of bytes compared by [`eq_mask_wide`]
+    #[cfg_attr(test, allow(dead_code))]
+    pub const WIDE_LANES: usize = 16;
+
+    #[inline(always)]
+    pub fn eq_mask_16(keys: &[u8; 16], value: u8) -> u64 {
+        keys.iter()
+            .enumerate()
+            .filter(|(_, key)| **key == value)
+            .fold(0, |bitmask, (idx, _)| bitmask | (1 << idx))
+    }
+
+    #[inline(always)]
+    pub fn gt_mask_16(keys: &[u8; 16], value: u8) -> u64 {
+        keys.iter()
+            .enumerate()
+            .filter(|(_, key)| **key > value)
+            .fold(0, |bitmask, (idx, _)| bitmask | (1 << idx))
+    }
+
+    #[cfg_attr(test, allow(dead_code))]
+    #[inline(always)]
+    pub fn eq_mask_wide(bytes: &[u8; WIDE_LANES], value: u8) -> u64 {
+        eq_mask_16(bytes, value)
+    }
+
+    #[cfg_attr(test, allow(dead_code))]
+    #[inline(always)]
+    pub fn gt_mask_wide(bytes: &[u8; WIDE_LANES], value: u8) -> u64 {
+        gt_mask_16(bytes, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn eq_and_gt_masks_match_scalar() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for _ in 0..1000 {
+            // Use a small alphabet so that equal bytes are common
+            let keys: [u8; 16] = core::array::from_fn(|_| rng.gen_range(0..8) * 36);
+            let value = rng.gen_range(0..8) * 36 + rng.gen_range(0..2);
+
+            for len in 0..=16 {
+                let expected_eq =
+                    (0..len).find(|idx| scalar::eq_mask_16(&keys, value) & (1 << idx) != 0);
+                let expected_gt =
+                    (0..len).find(|idx| scalar::gt_mask_16(&keys, value) & (1 << idx) != 0);
+                assert_eq!(
+                    find_equal_16(&keys, len, value),
+                    expected_eq,
+                    "{keys:?} {len} {value}"
+                );
+                assert_eq!(
+                    find_greater_16(&keys, len, value),
+                    expected_gt,
+                    "{keys:?} {len} {value}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn eq_and_gt_32_match_scalar() {
+        let mut rng = StdRng::seed_from_u64(0x3232);
+        for _ in 0..500 {
+            let keys: [u8; 32] = core::array::from_fn(|_| rng.gen_range(0..8) * 36);
+            let value = rng.gen_range(0..8) * 36 + rng.gen_range(0..2);
+
+            for len in 0..=32 {
+                let expected_eq = keys[..len].iter().position(|key| *key == value);
+                let expected_gt = keys[..len].iter().position(|key| *key > value);
+                assert_eq!(
+                    find_equal_32(&keys, len, value),
+                    expected_eq,
+                    "{keys:?} {len} {value}"
+                );
+                assert_eq!(
+                    find_greater_32(&keys, len, value),
+                    expected_gt,
+                    "{keys:?} {len} {value}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn greater_uses_unsigned_order() {
+        let keys = [0, 1, 127, 128, 129, 254, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert_eq!(find_greater_16(&keys, 7, 0), Some(1));
+        assert_eq!(find_greater_16(&keys, 7, 127), Some(3));
+        assert_eq!(find_greater_16(&keys, 7, 128), Some(4));
+        assert_eq!(find_greater_16(&keys, 7, 254), Some(6));
+        assert_eq!(find_greater_16(&keys, 7, 255), None);
+        assert_eq!(find_equal_16(&keys, 7, 255), Some(6));
+        assert_eq!(find_equal_16(&keys, 16, 0), Some(0));
+        assert_eq!(find_equal_16(&keys, 0, 0), None);
+    }
+
+    #[test]
+    fn not_equal_256_matches_scalar() {
+        let mut rng = StdRng::seed_from_u64(0xa11);
+        let mut bytes = [48u8; 256];
+        assert_eq!(find_first_not_equal_256(&bytes, 48), None);
+        assert_eq!(find_last_not_equal_256(&bytes, 48), None);
+
+        for idx in 0..256 {
+            bytes[idx] = 0;
+            assert_eq!(find_first_not_equal_256(&bytes, 48), Some(idx));
+            assert_eq!(find_last_not_equal_256(&bytes, 48), Some(idx));
+            bytes[idx] = 48;
+        }
+
+        for _ in 0..200 {
+            let num_set = rng.gen_range(0..8);
+            bytes = [48; 256];
+            for _ in 0..num_set {
+                bytes[rng.gen_range(0..256)] = rng.gen_range(0..48);
+            }
+
+            let expected_first = bytes.iter().position(|byte| *byte != 48);
+            let expected_last = bytes.iter().rposition(|byte| *byte != 48);
+            assert_eq!(find_first_not_equal_256(&bytes, 48), expected_first);
+            assert_eq!(find_last_not_equal_256(&bytes, 48), expected_last);
+        }
+    }
+}
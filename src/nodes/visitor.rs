@@ -1,17 +1,31 @@
 //! Utilities for inspecting the trie structure.
 
+#[cfg(feature = "std")]
+mod exporters;
+mod memory_usage;
+mod post_order;
+#[cfg(feature = "std")]
 mod pretty_printer;
 mod tree_stats;
+mod walk;
 mod well_formed;
 
 use crate::{
-    ConcreteNodePtr, InnerNode, InnerNode16, InnerNode256, InnerNode4, InnerNode48, LeafNode, Node,
-    NodePtr, OpaqueNodePtr,
+    ConcreteNodePtr, InnerNode, InnerNode16, InnerNode256, InnerNode32, InnerNode4, InnerNode48,
+    LeafNode, Node, NodePtr, OpaqueNodePtr,
 };
+#[cfg(feature = "std")]
+pub use exporters::*;
+pub use memory_usage::*;
+pub use post_order::*;
+#[cfg(feature = "std")]
 pub use pretty_printer::*;
 pub use tree_stats::*;
+pub use walk::*;
 pub use well_formed::*;
 
+use alloc::{vec, vec::Vec};
+
 /// The `Visitable` trait allows [`Visitor`]s to traverse the structure of the
 /// implementing type and produce some output.
 pub trait Visitable<K, T, const PREFIX_LEN: usize> {
@@ -54,6 +68,7 @@ impl<K, T, const PREFIX_LEN: usize> Visitable<K, T, PREFIX_LEN>
         match self.to_node_ptr() {
             ConcreteNodePtr::Node4(inner) => inner.visit_with(visitor),
             ConcreteNodePtr::Node16(inner) => inner.visit_with(visitor),
+            ConcreteNodePtr::Node32(inner) => inner.visit_with(visitor),
             ConcreteNodePtr::Node48(inner) => inner.visit_with(visitor),
             ConcreteNodePtr::Node256(inner) => inner.visit_with(visitor),
             ConcreteNodePtr::LeafNode(inner) => inner.visit_with(visitor),
@@ -90,6 +105,16 @@ impl<K, T, const PREFIX_LEN: usize> Visitable<K, T, PREFIX_LEN> for InnerNode16<
     }
 }
 
+impl<K, T, const PREFIX_LEN: usize> Visitable<K, T, PREFIX_LEN> for InnerNode32<K, T, PREFIX_LEN> {
+    fn super_visit_with<V: Visitor<K, T, PREFIX_LEN>>(&self, visitor: &mut V) -> V::Output {
+        combine_inner_node_child_output(self.iter(), visitor)
+    }
+
+    fn visit_with<V: Visitor<K, T, PREFIX_LEN>>(&self, visitor: &mut V) -> V::Output {
+        visitor.visit_node32(self)
+    }
+}
+
 impl<K, T, const PREFIX_LEN: usize> Visitable<K, T, PREFIX_LEN> for InnerNode48<K, T, PREFIX_LEN> {
     fn super_visit_with<V: Visitor<K, T, PREFIX_LEN>>(&self, visitor: &mut V) -> V::Output {
         combine_inner_node_child_output(self.iter(), visitor)
@@ -122,6 +147,14 @@ impl<K, T, const PREFIX_LEN: usize> Visitable<K, T, PREFIX_LEN> for LeafNode<K,
 
 /// The `Visitor` trait allows creating new operations on the radix tree by
 /// overriding specific handling methods for each of the node types.
+///
+/// The children of the inner nodes are visited with an explicit stack, so the
+/// default traversal does not overflow the call stack on very deep trees (for
+/// example long keys with low fan-out). A hook which calls
+/// [`Visitable::super_visit_with`] itself still recurses once per level of the
+/// nodes it handles, and so do all the inner node hooks without the `std`
+/// feature. [`TreeVisitor`] with [`walk()`], and [`PostOrderVisitor`] with
+/// [`visit_post_order`], never recurse.
 pub trait Visitor<K, V, const PREFIX_LEN: usize>: Sized {
     /// The type of value that the visitor produces.
     type Output;
@@ -134,22 +167,27 @@ pub trait Visitor<K, V, const PREFIX_LEN: usize>: Sized {
 
     /// Visit a [`InnerNode4`].
     fn visit_node4(&mut self, t: &InnerNode4<K, V, PREFIX_LEN>) -> Self::Output {
-        t.super_visit_with(self)
+        visit_inner_node_children(t, self)
     }
 
     /// Visit a [`InnerNode16`].
     fn visit_node16(&mut self, t: &InnerNode16<K, V, PREFIX_LEN>) -> Self::Output {
-        t.super_visit_with(self)
+        visit_inner_node_children(t, self)
+    }
+
+    /// Visit a [`InnerNode32`].
+    fn visit_node32(&mut self, t: &InnerNode32<K, V, PREFIX_LEN>) -> Self::Output {
+        visit_inner_node_children(t, self)
     }
 
     /// Visit a [`InnerNode48`].
     fn visit_node48(&mut self, t: &InnerNode48<K, V, PREFIX_LEN>) -> Self::Output {
-        t.super_visit_with(self)
+        visit_inner_node_children(t, self)
     }
 
     /// Visit a [`InnerNode256`].
     fn visit_node256(&mut self, t: &InnerNode256<K, V, PREFIX_LEN>) -> Self::Output {
-        t.super_visit_with(self)
+        visit_inner_node_children(t, self)
     }
 
     /// Visit a [`LeafNode`].
@@ -158,19 +196,268 @@ pub trait Visitor<K, V, const PREFIX_LEN: usize>: Sized {
     }
 }
 
+/// The default handling of an inner node by the [`Visitor`] hooks, which visits
+/// the children of the node and combines their outputs.
+///
+/// If the node is being visited by [`combine_inner_node_child_output`], the
+/// children are pushed on its stack instead of being visited here, and the
+/// returned output is discarded.
+fn visit_inner_node_children<K, T, const PREFIX_LEN: usize, N, V>(
+    node: &N,
+    visitor: &mut V,
+) -> V::Output
+where
+    N: Visitable<K, T, PREFIX_LEN>,
+    V: Visitor<K, T, PREFIX_LEN>,
+{
+    if deferral::defer(node) {
+        visitor.default_output()
+    } else {
+        node.super_visit_with(visitor)
+    }
+}
+
+/// The outputs of the children of an inner node, while they are being visited
+/// by [`combine_inner_node_child_output`].
+struct ChildOutputs<O> {
+    /// The number of children which are still on the stack of pending nodes
+    remaining: usize,
+    /// The combined output of the children visited so far
+    accum: Option<O>,
+}
+
+impl<O> ChildOutputs<O> {
+    fn add<K, T, const PREFIX_LEN: usize, V>(&mut self, visitor: &V, output: O)
+    where
+        V: Visitor<K, T, PREFIX_LEN, Output = O>,
+    {
+        self.accum = Some(match self.accum.take() {
+            Some(accum) => visitor.combine_output(accum, output),
+            None => output,
+        });
+    }
+}
+
+/// Visit the children of an inner node and combine their outputs, in the same
+/// order as a recursive traversal.
+///
+/// This uses an explicit stack of partial outputs instead of recursion, so that
+/// deep trees cannot overflow the call stack. The descendants are still visited
+/// through the [`Visitor`] hooks, and only the hooks which call
+/// [`Visitable::super_visit_with`] themselves recurse.
 fn combine_inner_node_child_output<K, T, const PREFIX_LEN: usize, V: Visitor<K, T, PREFIX_LEN>>(
-    mut iter: impl Iterator<Item = (u8, OpaqueNodePtr<K, T, PREFIX_LEN>)>,
+    iter: impl DoubleEndedIterator<Item = (u8, OpaqueNodePtr<K, T, PREFIX_LEN>)>,
     visitor: &mut V,
 ) -> V::Output {
-    if let Some((_, first)) = iter.next() {
-        let mut accum = first.visit_with(visitor);
-        for (_, child) in iter {
-            let output = child.visit_with(visitor);
-            accum = visitor.combine_output(accum, output);
+    let mut pending = Vec::new();
+    let mut frames = vec![ChildOutputs {
+        remaining: push_children(&mut pending, iter),
+        accum: None,
+    }];
+
+    loop {
+        let frame = frames
+            .last_mut()
+            .expect("the stack should not be empty until the first frame is done");
+        if frame.remaining == 0 {
+            let output = frame
+                .accum
+                .take()
+                .unwrap_or_else(|| visitor.default_output());
+            frames.pop();
+            match frames.last_mut() {
+                Some(parent) => parent.add(visitor, output),
+                None => return output,
+            }
+            continue;
         }
 
-        accum
-    } else {
-        visitor.default_output()
+        frame.remaining -= 1;
+        let child = pending
+            .pop()
+            .expect("every remaining child should be on the pending stack");
+        let output = match child.to_node_ptr() {
+            ConcreteNodePtr::Node4(inner) => visit_child(&*inner.read(), visitor, &mut pending),
+            ConcreteNodePtr::Node16(inner) => visit_child(&*inner.read(), visitor, &mut pending),
+            ConcreteNodePtr::Node32(inner) => visit_child(&*inner.read(), visitor, &mut pending),
+            ConcreteNodePtr::Node48(inner) => visit_child(&*inner.read(), visitor, &mut pending),
+            ConcreteNodePtr::Node256(inner) => visit_child(&*inner.read(), visitor, &mut pending),
+            ConcreteNodePtr::LeafNode(leaf) => Ok(leaf.visit_with(visitor)),
+        };
+        match output {
+            Ok(output) => frames
+                .last_mut()
+                .expect("the frame of the child should still be on the stack")
+                .add(visitor, output),
+            Err(remaining) => frames.push(ChildOutputs {
+                remaining,
+                accum: None,
+            }),
+        }
+    }
+}
+
+/// Visit an inner node through its [`Visitor`] hook.
+///
+/// Returns the output of the hook, or `Err` with the number of children
+/// pushed on the pending stack if the hook left the children to the caller.
+fn visit_child<K, T, const PREFIX_LEN: usize, N, V>(
+    node: &N,
+    visitor: &mut V,
+    pending: &mut Vec<OpaqueNodePtr<K, T, PREFIX_LEN>>,
+) -> Result<V::Output, usize>
+where
+    N: InnerNode<PREFIX_LEN, Key = K, Value = T> + Visitable<K, T, PREFIX_LEN>,
+    V: Visitor<K, T, PREFIX_LEN>,
+{
+    match deferral::visit(node, || node.visit_with(visitor)) {
+        Some(output) => Ok(output),
+        None => Err(push_children(pending, node.iter())),
+    }
+}
+
+/// Push the children on the pending stack, so that the first child is on top,
+/// and return their number.
+fn push_children<K, T, const PREFIX_LEN: usize>(
+    pending: &mut Vec<OpaqueNodePtr<K, T, PREFIX_LEN>>,
+    iter: impl DoubleEndedIterator<Item = (u8, OpaqueNodePtr<K, T, PREFIX_LEN>)>,
+) -> usize {
+    let len = pending.len();
+    pending.extend(iter.rev().map(|(_, child)| child));
+    pending.len() - len
+}
+
+/// Lets the default [`Visitor`] hooks hand the children of an inner node back
+/// to [`combine_inner_node_child_output`], instead of visiting them with a
+/// nested call.
+///
+/// The hooks have no access to the traversal, so the node being visited is
+/// recorded in a thread local. Without `std` there is nowhere to record it, and
+/// the default hooks recurse once per level of the tree.
+mod deferral {
+    #[cfg(feature = "std")]
+    use core::cell::Cell;
+
+    #[cfg(feature = "std")]
+    std::thread_local! {
+        /// The address of the node whose hook is being called, and whether
+        /// the default hook left its children to the caller.
+        static VISITING: Cell<(*const (), bool)> = const { Cell::new((core::ptr::null(), false)) };
+    }
+
+    /// Call the hook of the node, returning its output or `None` if it was the
+    /// default hook and the children of the node still have to be visited.
+    #[cfg(feature = "std")]
+    pub(super) fn visit<N, O>(node: &N, hook: impl FnOnce() -> O) -> Option<O> {
+        /// Clears the recorded node, even if the hook panics.
+        struct Reset;
+
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                VISITING.with(|visiting| visiting.set((core::ptr::null(), false)));
+            }
+        }
+
+        VISITING.with(|visiting| visiting.set(((node as *const N).cast(), false)));
+        let reset = Reset;
+        let output = hook();
+        let (_, deferred) = VISITING.with(Cell::get);
+        drop(reset);
+
+        (!deferred).then_some(output)
+    }
+
+    /// Return true if the node is the one whose hook is being called by
+    /// [`visit`], in which case its children are left to the caller.
+    ///
+    /// Any hook called for another node, including the hooks of nested
+    /// traversals, clears the recorded node, so this only returns true if
+    /// nothing else was visited since the hook was called.
+    #[cfg(feature = "std")]
+    pub(super) fn defer<N>(node: &N) -> bool {
+        VISITING.with(|visiting| {
+            let (visiting_node, _) = visiting.get();
+            let defer = core::ptr::eq(visiting_node, (node as *const N).cast());
+            if defer {
+                visiting.set((core::ptr::null(), true));
+            }
+            defer
+        })
+    }
+
+    #[cfg(not(feature = "std"))]
+    pub(super) fn visit<N, O>(_node: &N, hook: impl FnOnce() -> O) -> Option<O> {
+        Some(hook())
+    }
+
+    #[cfg(not(feature = "std"))]
+    pub(super) fn defer<N>(_node: &N) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests_common::generate_key_fixed_length, visitor::TreeStatsCollector, TreeMap};
+    use alloc::boxed::Box;
+
+    /// Records the values of the leaves, in the order the outputs are
+    /// combined.
+    struct Values;
+
+    impl<const PREFIX_LEN: usize> Visitor<Box<[u8]>, usize, PREFIX_LEN> for Values {
+        type Output = Vec<usize>;
+
+        fn default_output(&self) -> Self::Output {
+            Vec::new()
+        }
+
+        fn combine_output(&self, mut o1: Self::Output, o2: Self::Output) -> Self::Output {
+            o1.extend(o2);
+            o1
+        }
+
+        fn visit_leaf(&mut self, t: &LeafNode<Box<[u8]>, usize>) -> Self::Output {
+            vec![*t.value_ref()]
+        }
+    }
+
+    /// Counts the [`InnerNode4`]s with an overridden hook which changes the
+    /// output of [`Visitable::super_visit_with`].
+    struct Node4Count;
+
+    impl<const PREFIX_LEN: usize> Visitor<Box<[u8]>, usize, PREFIX_LEN> for Node4Count {
+        type Output = usize;
+
+        fn default_output(&self) -> Self::Output {
+            0
+        }
+
+        fn combine_output(&self, o1: Self::Output, o2: Self::Output) -> Self::Output {
+            o1 + o2
+        }
+
+        fn visit_node4(&mut self, t: &InnerNode4<Box<[u8]>, usize, PREFIX_LEN>) -> Self::Output {
+            t.super_visit_with(self) + 1
+        }
+    }
+
+    #[test]
+    fn visit_combines_outputs_in_key_order() {
+        let mut tree = TreeMap::new();
+        for (value, key) in generate_key_fixed_length([15, 3, 2]).enumerate() {
+            tree.try_insert(key, value).unwrap();
+        }
+
+        let values = tree.root.unwrap().visit_with(&mut Values);
+        assert_eq!(values, tree.values().copied().collect::<Vec<_>>());
+
+        let stats = TreeStatsCollector::collect(&tree).unwrap();
+        assert!(stats.node4.count > 0);
+        assert_eq!(
+            tree.root.unwrap().visit_with(&mut Node4Count),
+            stats.node4.count
+        );
     }
 }
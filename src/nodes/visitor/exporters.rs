@@ -0,0 +1,177 @@
+//! Exporters which write the structure of a tree in formats other than the
+//! Graphviz output of [`DotPrinter`](crate::visitor::DotPrinter).
+
+use crate::{visitor::VisitContext, AsBytes};
+use core::ops::ControlFlow;
+use std::{
+    fmt::{self, Debug, Display, Formatter, Write as _},
+    io,
+};
+
+mod ascii;
+mod json;
+mod mermaid;
+
+pub use ascii::*;
+pub use json::*;
+pub use mermaid::*;
+
+/// Settings which customize the output of the [`JsonPrinter`],
+/// [`MermaidPrinter`], and [`AsciiPrinter`] exporters.
+///
+/// The keys and values are written using the `format_key` and `format_value`
+/// functions, which have the same signature as [`Display::fmt`]. The
+/// [`format_hex`], [`format_utf8_lossy`], [`format_debug`], and
+/// [`format_display`] functions cover the common cases, and any closure with
+/// the same signature can be used instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportSettings<FK, FV> {
+    /// Add node address to the output
+    pub display_node_address: bool,
+    /// Function used to write the keys
+    pub format_key: FK,
+    /// Function used to write the values
+    pub format_value: FV,
+}
+
+impl<FK, FV> ExportSettings<FK, FV> {
+    /// Create settings which write keys and values with the given functions,
+    /// without node addresses.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::visitor::{format_debug, format_hex, ExportSettings};
+    ///
+    /// let settings = ExportSettings::new(format_hex::<Box<[u8]>>, format_debug::<u32>);
+    /// assert!(!settings.display_node_address);
+    /// ```
+    pub fn new(format_key: FK, format_value: FV) -> Self {
+        ExportSettings {
+            display_node_address: false,
+            format_key,
+            format_value,
+        }
+    }
+}
+
+/// Write the bytes of a value as lowercase hexadecimal, with two digits per
+/// byte.
+pub fn format_hex<T: AsBytes + ?Sized>(value: &T, f: &mut Formatter<'_>) -> fmt::Result {
+    value
+        .as_bytes()
+        .iter()
+        .try_for_each(|byte| write!(f, "{byte:02x}"))
+}
+
+/// Write the bytes of a value as UTF-8 text, replacing invalid sequences with
+/// `U+FFFD REPLACEMENT CHARACTER`.
+pub fn format_utf8_lossy<T: AsBytes + ?Sized>(value: &T, f: &mut Formatter<'_>) -> fmt::Result {
+    Display::fmt(&String::from_utf8_lossy(value.as_bytes()), f)
+}
+
+/// Write a value with its [`Debug`] impl.
+pub fn format_debug<T: Debug + ?Sized>(value: &T, f: &mut Formatter<'_>) -> fmt::Result {
+    Debug::fmt(value, f)
+}
+
+/// Write a value with its [`Display`] impl.
+pub fn format_display<T: Display + ?Sized>(value: &T, f: &mut Formatter<'_>) -> fmt::Result {
+    Display::fmt(value, f)
+}
+
+/// Adapter to use a formatting function as a [`Display`] impl.
+struct Formatted<'a, T: ?Sized, F>(&'a T, &'a F);
+
+impl<T, F> Display for Formatted<'_, T, F>
+where
+    T: ?Sized,
+    F: Fn(&T, &mut Formatter<'_>) -> fmt::Result,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        (self.1)(self.0, f)
+    }
+}
+
+/// Adapter which escapes the output of another [`Display`] impl, by writing
+/// each character with the `escape` function.
+struct Escaped<D, E>(D, E);
+
+impl<D, E> Display for Escaped<D, E>
+where
+    D: Display,
+    E: Fn(char, &mut Formatter<'_>) -> fmt::Result,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        struct EscapeWriter<'a, 'b, E>(&'a mut Formatter<'b>, &'a E);
+
+        impl<E> fmt::Write for EscapeWriter<'_, '_, E>
+        where
+            E: Fn(char, &mut Formatter<'_>) -> fmt::Result,
+        {
+            fn write_str(&mut self, s: &str) -> fmt::Result {
+                s.chars().try_for_each(|c| (self.1)(c, self.0))
+            }
+        }
+
+        write!(EscapeWriter(f, &self.1), "{}", self.0)
+    }
+}
+
+/// Write a slice of bytes as a comma separated list of numbers.
+fn write_byte_list(output: &mut impl io::Write, bytes: &[u8]) -> io::Result<()> {
+    for (idx, byte) in bytes.iter().enumerate() {
+        if idx == 0 {
+            write!(output, "{byte}")?;
+        } else {
+            write!(output, ",{byte}")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert the result of a write to the return type of the visitor callbacks.
+fn break_on_error<T>(result: io::Result<T>) -> ControlFlow<io::Error, T> {
+    match result {
+        Ok(value) => ControlFlow::Continue(value),
+        Err(err) => ControlFlow::Break(err),
+    }
+}
+
+/// Return the key byte of the child pointer leading to a node, or `None` for
+/// the root.
+fn key_fragment(ctx: &VisitContext<'_>) -> Option<u8> {
+    if ctx.depth == 0 {
+        None
+    } else {
+        ctx.key_bytes.last().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatters() {
+        assert_eq!(
+            Formatted(&[0u8, 10, 255], &format_hex::<[u8; 3]>).to_string(),
+            "000aff"
+        );
+        assert_eq!(
+            Formatted(&b"ab\xffc"[..], &format_utf8_lossy::<[u8]>).to_string(),
+            "ab\u{FFFD}c"
+        );
+        assert_eq!(Formatted("ab", &format_debug::<str>).to_string(), "\"ab\"");
+        assert_eq!(Formatted(&5u8, &format_display::<u8>).to_string(), "5");
+        assert_eq!(
+            Escaped("a-b", |c, f: &mut Formatter<'_>| match c {
+                '-' => f.write_str("--"),
+                c => f.write_char(c),
+            })
+            .to_string(),
+            "a--b"
+        );
+    }
+}
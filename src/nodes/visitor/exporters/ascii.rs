@@ -0,0 +1,268 @@
+use super::{break_on_error, key_fragment, Escaped, ExportSettings, Formatted};
+use crate::{
+    visitor::{walk, TreeVisitor, VisitChildren, VisitContext},
+    AsBytes, InnerNode, LeafNode, NodeType, TreeMap,
+};
+use core::ops::ControlFlow;
+use std::{
+    fmt::{self, Display, Formatter, Write as _},
+    io::{self, Write},
+};
+
+/// A visitor of the radix trie that will write the tree as indented text,
+/// with one line per node, for debugging in a terminal.
+///
+/// Inner nodes are written with their type and full prefix, and leaves with
+/// their key and value, as written by the formatting functions of the
+/// settings. Each line of a child node starts with the key byte of the child
+/// pointer.
+///
+/// # Examples
+///
+/// ```rust
+/// use blart::{
+///     visitor::{format_debug, format_utf8_lossy, AsciiPrinter, ExportSettings},
+///     TreeMap,
+/// };
+///
+/// let mut tree = TreeMap::<Box<[u8]>, u32>::new();
+/// tree.try_insert(Box::from(&b"abc"[..]), 1).unwrap();
+/// tree.try_insert(Box::from(&b"abd"[..]), 2).unwrap();
+/// tree.try_insert(Box::from(&b"b"[..]), 3).unwrap();
+///
+/// let mut output = Vec::new();
+/// AsciiPrinter::print(
+///     &mut output,
+///     &tree,
+///     ExportSettings::new(format_utf8_lossy, format_debug),
+/// )
+/// .unwrap()
+/// .unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "\
+/// Node4 prefix []
+/// ├── 97: Node4 prefix [98]
+/// │   ├── 99: Leaf abc => 1
+/// │   └── 100: Leaf abd => 2
+/// └── 98: Leaf b => 3
+/// "
+/// );
+/// ```
+pub struct AsciiPrinter<O: Write, FK, FV> {
+    output: O,
+    /// The number of children left to write, for each inner node on the path
+    /// to the current node.
+    remaining_children: Vec<usize>,
+    settings: ExportSettings<FK, FV>,
+}
+
+impl<O: Write, FK, FV> AsciiPrinter<O, FK, FV> {
+    /// Write the text representation of the given tree to the given output.
+    ///
+    /// Returns `None` if the tree is empty.
+    pub fn print<K, V, const PREFIX_LEN: usize>(
+        output: O,
+        tree: &TreeMap<K, V, PREFIX_LEN>,
+        settings: ExportSettings<FK, FV>,
+    ) -> Option<io::Result<()>>
+    where
+        K: AsBytes,
+        FK: Fn(&K, &mut Formatter<'_>) -> fmt::Result,
+        FV: Fn(&V, &mut Formatter<'_>) -> fmt::Result,
+    {
+        tree.root?;
+
+        let mut visitor = AsciiPrinter {
+            output,
+            remaining_children: Vec::new(),
+            settings,
+        };
+
+        match walk(tree, &mut visitor) {
+            ControlFlow::Continue(()) => Some(Ok(())),
+            ControlFlow::Break(err) => Some(Err(err)),
+        }
+    }
+
+    /// Write the tree lines and key byte in front of a node.
+    fn start_node(&mut self, node_type: NodeType, ctx: &VisitContext<'_>) -> io::Result<()> {
+        if let Some((parent_remaining, ancestors)) = self.remaining_children.split_last_mut() {
+            for remaining in ancestors.iter() {
+                if *remaining > 0 {
+                    write!(self.output, "│   ")?;
+                } else {
+                    write!(self.output, "    ")?;
+                }
+            }
+
+            *parent_remaining = parent_remaining.saturating_sub(1);
+            if *parent_remaining > 0 {
+                write!(self.output, "├── ")?;
+            } else {
+                write!(self.output, "└── ")?;
+            }
+        }
+
+        if let Some(key_fragment) = key_fragment(ctx) {
+            write!(self.output, "{key_fragment}: ")?;
+        }
+        write!(self.output, "{node_type:?} ")
+    }
+
+    /// Write the end of the line of a node.
+    fn finish_node<T>(&mut self, node: &T) -> io::Result<()> {
+        if self.settings.display_node_address {
+            write!(self.output, " @ {:p}", node as *const T)?;
+        }
+        writeln!(self.output)
+    }
+
+    fn write_inner_node<N, const PREFIX_LEN: usize>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> io::Result<()>
+    where
+        N: InnerNode<PREFIX_LEN>,
+        N::Key: AsBytes,
+    {
+        let (prefix, _) = inner_node.read_full_prefix(ctx.key_bytes.len());
+        self.start_node(N::TYPE, ctx)?;
+        write!(self.output, "prefix {prefix:?}")?;
+        self.finish_node(inner_node)?;
+
+        self.remaining_children
+            .push(inner_node.header().num_children());
+        Ok(())
+    }
+
+    fn write_leaf<K, V>(&mut self, leaf: &LeafNode<K, V>, ctx: &VisitContext<'_>) -> io::Result<()>
+    where
+        FK: Fn(&K, &mut Formatter<'_>) -> fmt::Result,
+        FV: Fn(&V, &mut Formatter<'_>) -> fmt::Result,
+    {
+        self.start_node(NodeType::Leaf, ctx)?;
+        write!(
+            self.output,
+            "{} => {}",
+            single_line(Formatted(leaf.key_ref(), &self.settings.format_key)),
+            single_line(Formatted(leaf.value_ref(), &self.settings.format_value))
+        )?;
+        self.finish_node(leaf)
+    }
+}
+
+/// Escape the control characters in the output of the given [`Display`] impl,
+/// so that it does not break the lines of the tree.
+fn single_line(value: impl Display) -> impl Display {
+    Escaped(value, |c: char, f: &mut Formatter<'_>| {
+        if c.is_control() {
+            write!(f, "{}", c.escape_default())
+        } else {
+            f.write_char(c)
+        }
+    })
+}
+
+impl<K, V, O, FK, FV, const PREFIX_LEN: usize> TreeVisitor<K, V, PREFIX_LEN>
+    for AsciiPrinter<O, FK, FV>
+where
+    K: AsBytes,
+    O: Write,
+    FK: Fn(&K, &mut Formatter<'_>) -> fmt::Result,
+    FV: Fn(&V, &mut Formatter<'_>) -> fmt::Result,
+{
+    type Break = io::Error;
+
+    fn visit_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break, VisitChildren>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        break_on_error(self.write_inner_node(inner_node, ctx))?;
+        ControlFlow::Continue(VisitChildren::Visit)
+    }
+
+    fn leave_inner_node<N>(&mut self, _: &N, _: &VisitContext<'_>) -> ControlFlow<Self::Break>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        self.remaining_children.pop();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_leaf(
+        &mut self,
+        leaf: &LeafNode<K, V>,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break> {
+        break_on_error(self.write_leaf(leaf, ctx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tests_common::generate_key_fixed_length,
+        visitor::{format_debug, format_hex},
+    };
+
+    #[test]
+    fn ascii_output_fixed_length_tree() {
+        let mut tree = TreeMap::new();
+        for (value, key) in generate_key_fixed_length([1, 1]).enumerate() {
+            tree.try_insert(key, value).unwrap();
+        }
+
+        let mut output = Vec::new();
+        AsciiPrinter::print(
+            &mut output,
+            &tree,
+            ExportSettings::new(format_hex, format_debug),
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\
+Node4 prefix []
+├── 0: Node4 prefix []
+│   ├── 0: Leaf 0000 => 0
+│   └── 255: Leaf 00ff => 1
+└── 255: Node4 prefix []
+    ├── 0: Leaf ff00 => 2
+    └── 255: Leaf ffff => 3
+"
+        );
+    }
+
+    #[test]
+    fn ascii_output_escapes_control_characters() {
+        let mut tree = TreeMap::<Box<[u8]>, &str>::new();
+        tree.try_insert(Box::from(&b"a"[..]), "line\nbreak")
+            .unwrap();
+
+        let mut output = Vec::new();
+        AsciiPrinter::print(
+            &mut output,
+            &tree,
+            ExportSettings::new(format_hex, |value: &&str, f: &mut Formatter<'_>| {
+                f.write_str(value)
+            }),
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Leaf 61 => line\\nbreak\n"
+        );
+    }
+}
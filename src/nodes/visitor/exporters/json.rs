@@ -0,0 +1,287 @@
+use super::{break_on_error, key_fragment, write_byte_list, Escaped, ExportSettings, Formatted};
+use crate::{
+    visitor::{walk, TreeVisitor, VisitChildren, VisitContext},
+    AsBytes, InnerNode, LeafNode, NodeType, TreeMap,
+};
+use core::ops::ControlFlow;
+use std::{
+    fmt::{self, Display, Formatter, Write as _},
+    io::{self, Write},
+};
+
+/// A visitor of the radix trie that will write the tree as JSON.
+///
+/// Each node is written as an object with its `"type"`, `"depth"`, the
+/// `"key_fragment"` of the child pointer leading to it (`null` for the root),
+/// and its `"address"` if
+/// [`display_node_address`](ExportSettings::display_node_address) is set.
+///
+/// Inner nodes also have the `"prefix_len"`, the `"key_prefix"` bytes shared
+/// by all the keys below the node, including its own prefix, and the array of
+/// `"children"`. Leaf nodes have the `"key_bytes"`, and the `"key"` and
+/// `"value"` strings written by the formatting functions of the settings.
+///
+/// # Examples
+///
+/// ```rust
+/// use blart::{
+///     visitor::{format_debug, format_utf8_lossy, ExportSettings, JsonPrinter},
+///     TreeMap,
+/// };
+///
+/// let mut tree = TreeMap::<Box<[u8]>, u32>::new();
+/// tree.try_insert(Box::from(&b"ab"[..]), 1).unwrap();
+/// tree.try_insert(Box::from(&b"ac"[..]), 2).unwrap();
+///
+/// let mut output = Vec::new();
+/// JsonPrinter::print(
+///     &mut output,
+///     &tree,
+///     ExportSettings::new(format_utf8_lossy, format_debug),
+/// )
+/// .unwrap()
+/// .unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     concat!(
+///         r#"{"type":"Node4","depth":0,"key_fragment":null,"prefix_len":1,"key_prefix":[97],"children":["#,
+///         r#"{"type":"Leaf","depth":1,"key_fragment":98,"key_bytes":[97,98],"key":"ab","value":"1"},"#,
+///         r#"{"type":"Leaf","depth":1,"key_fragment":99,"key_bytes":[97,99],"key":"ac","value":"2"}"#,
+///         "]}\n",
+///     )
+/// );
+/// ```
+pub struct JsonPrinter<O: Write, FK, FV> {
+    output: O,
+    /// For each inner node on the path to the current node, whether any of
+    /// its children were written yet.
+    open_nodes: Vec<bool>,
+    settings: ExportSettings<FK, FV>,
+}
+
+impl<O: Write, FK, FV> JsonPrinter<O, FK, FV> {
+    /// Write the JSON representation of the given tree to the given output.
+    ///
+    /// Returns `None` if the tree is empty.
+    pub fn print<K, V, const PREFIX_LEN: usize>(
+        output: O,
+        tree: &TreeMap<K, V, PREFIX_LEN>,
+        settings: ExportSettings<FK, FV>,
+    ) -> Option<io::Result<()>>
+    where
+        K: AsBytes,
+        FK: Fn(&K, &mut Formatter<'_>) -> fmt::Result,
+        FV: Fn(&V, &mut Formatter<'_>) -> fmt::Result,
+    {
+        tree.root?;
+
+        let mut visitor = JsonPrinter {
+            output,
+            open_nodes: Vec::new(),
+            settings,
+        };
+
+        if let ControlFlow::Break(err) = walk(tree, &mut visitor) {
+            return Some(Err(err));
+        }
+        Some(writeln!(visitor.output))
+    }
+
+    /// Write the fields shared by inner nodes and leaves.
+    fn write_node_start<T>(
+        &mut self,
+        node_type: NodeType,
+        node: &T,
+        ctx: &VisitContext<'_>,
+    ) -> io::Result<()> {
+        if let Some(has_siblings) = self.open_nodes.last_mut() {
+            if *has_siblings {
+                write!(self.output, ",")?;
+            }
+            *has_siblings = true;
+        }
+
+        write!(
+            self.output,
+            "{{\"type\":\"{node_type:?}\",\"depth\":{},\"key_fragment\":",
+            ctx.depth
+        )?;
+        match key_fragment(ctx) {
+            Some(key_fragment) => write!(self.output, "{key_fragment}")?,
+            None => write!(self.output, "null")?,
+        }
+        if self.settings.display_node_address {
+            write!(self.output, ",\"address\":\"{:p}\"", node as *const T)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_inner_node<N, const PREFIX_LEN: usize>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> io::Result<()>
+    where
+        N: InnerNode<PREFIX_LEN>,
+        N::Key: AsBytes,
+    {
+        self.write_node_start(N::TYPE, inner_node, ctx)?;
+
+        let (prefix, _) = inner_node.read_full_prefix(ctx.key_bytes.len());
+        write!(
+            self.output,
+            ",\"prefix_len\":{},\"key_prefix\":[",
+            prefix.len()
+        )?;
+        write_byte_list(&mut self.output, ctx.key_bytes)?;
+        if !ctx.key_bytes.is_empty() && !prefix.is_empty() {
+            write!(self.output, ",")?;
+        }
+        write_byte_list(&mut self.output, prefix)?;
+        write!(self.output, "],\"children\":[")?;
+
+        self.open_nodes.push(false);
+        Ok(())
+    }
+
+    fn write_leaf<K, V>(&mut self, leaf: &LeafNode<K, V>, ctx: &VisitContext<'_>) -> io::Result<()>
+    where
+        K: AsBytes,
+        FK: Fn(&K, &mut Formatter<'_>) -> fmt::Result,
+        FV: Fn(&V, &mut Formatter<'_>) -> fmt::Result,
+    {
+        self.write_node_start(NodeType::Leaf, leaf, ctx)?;
+
+        write!(self.output, ",\"key_bytes\":[")?;
+        write_byte_list(&mut self.output, leaf.key_ref().as_bytes())?;
+        write!(
+            self.output,
+            "],\"key\":\"{}\",\"value\":\"{}\"}}",
+            json_string(Formatted(leaf.key_ref(), &self.settings.format_key)),
+            json_string(Formatted(leaf.value_ref(), &self.settings.format_value)),
+        )
+    }
+}
+
+/// Escape the output of the given [`Display`] impl to be used as the content
+/// of a JSON string.
+fn json_string(value: impl Display) -> impl Display {
+    Escaped(value, |c: char, f: &mut Formatter<'_>| match c {
+        '"' => f.write_str("\\\""),
+        '\\' => f.write_str("\\\\"),
+        '\n' => f.write_str("\\n"),
+        '\r' => f.write_str("\\r"),
+        '\t' => f.write_str("\\t"),
+        c if c.is_control() => write!(f, "\\u{:04x}", u32::from(c)),
+        c => f.write_char(c),
+    })
+}
+
+impl<K, V, O, FK, FV, const PREFIX_LEN: usize> TreeVisitor<K, V, PREFIX_LEN>
+    for JsonPrinter<O, FK, FV>
+where
+    K: AsBytes,
+    O: Write,
+    FK: Fn(&K, &mut Formatter<'_>) -> fmt::Result,
+    FV: Fn(&V, &mut Formatter<'_>) -> fmt::Result,
+{
+    type Break = io::Error;
+
+    fn visit_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break, VisitChildren>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        break_on_error(self.write_inner_node(inner_node, ctx))?;
+        ControlFlow::Continue(VisitChildren::Visit)
+    }
+
+    fn leave_inner_node<N>(&mut self, _: &N, _: &VisitContext<'_>) -> ControlFlow<Self::Break>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        self.open_nodes.pop();
+        break_on_error(write!(self.output, "]}}"))
+    }
+
+    fn visit_leaf(
+        &mut self,
+        leaf: &LeafNode<K, V>,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break> {
+        break_on_error(self.write_leaf(leaf, ctx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        tests_common::generate_key_fixed_length,
+        visitor::{format_debug, format_hex},
+    };
+
+    #[test]
+    fn json_output_escapes_strings() {
+        let mut tree = TreeMap::<Box<[u8]>, String>::new();
+        tree.try_insert(Box::from(&[0u8, 1][..]), String::from("say \"hi\"\n"))
+            .unwrap();
+
+        let mut output = Vec::new();
+        JsonPrinter::print(
+            &mut output,
+            &tree,
+            ExportSettings::new(format_hex, |value: &String, f: &mut Formatter<'_>| {
+                f.write_str(value)
+            }),
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"type\":\"Leaf\",\"depth\":0,\"key_fragment\":null,\"key_bytes\":[0,1],\"key\":\"\
+             0001\",\"value\":\"say \\\"hi\\\"\\n\"}\n"
+        );
+    }
+
+    #[test]
+    fn json_output_nested_nodes() {
+        let mut tree = TreeMap::new();
+        for (value, key) in generate_key_fixed_length([1, 1]).enumerate() {
+            tree.try_insert(key, value).unwrap();
+        }
+
+        let mut output = Vec::new();
+        JsonPrinter::print(
+            &mut output,
+            &tree,
+            ExportSettings::new(format_hex, format_debug),
+        )
+        .unwrap()
+        .unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.matches("\"type\":\"Node4\"").count(), 3);
+        assert_eq!(output.matches("\"type\":\"Leaf\"").count(), 4);
+        assert!(output.starts_with(
+            "{\"type\":\"Node4\",\"depth\":0,\"key_fragment\":null,\"prefix_len\":0,\"key_prefix\"\
+             :[],\"children\":[{\"type\":\"Node4\",\"depth\":1,\"key_fragment\":0,\"prefix_len\":\
+             0,\"key_prefix\":[0],\"children\":[{\"type\":\"Leaf\",\"depth\":2,\"key_fragment\":0,\
+             \"key_bytes\":[0,0],\"key\":\"0000\",\"value\":\"0\"},"
+        ));
+        assert!(output.ends_with("\"key\":\"ffff\",\"value\":\"3\"}]}]}\n"));
+
+        assert!(JsonPrinter::print(
+            Vec::new(),
+            &TreeMap::<u8, u8>::new(),
+            ExportSettings::new(format_hex, format_debug),
+        )
+        .is_none());
+    }
+}
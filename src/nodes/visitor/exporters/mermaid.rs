@@ -0,0 +1,236 @@
+use super::{break_on_error, key_fragment, Escaped, ExportSettings, Formatted};
+use crate::{
+    visitor::{walk, TreeVisitor, VisitChildren, VisitContext},
+    AsBytes, InnerNode, LeafNode, NodeType, TreeMap,
+};
+use core::ops::ControlFlow;
+use std::{
+    fmt::{self, Display, Formatter, Write as _},
+    io::{self, Write},
+};
+
+/// A visitor of the radix trie that will write the tree as a
+/// [Mermaid](https://mermaid.js.org/syntax/flowchart.html) flowchart.
+///
+/// Inner nodes are labelled with their type and full prefix, and leaves with
+/// their key and value, as written by the formatting functions of the
+/// settings. Each edge is labelled with the key byte of the child pointer.
+///
+/// # Examples
+///
+/// ```rust
+/// use blart::{
+///     visitor::{format_display, format_utf8_lossy, ExportSettings, MermaidPrinter},
+///     TreeMap,
+/// };
+///
+/// let mut tree = TreeMap::<Box<[u8]>, char>::new();
+/// tree.try_insert(Box::from(&b"ab"[..]), 'x').unwrap();
+/// tree.try_insert(Box::from(&b"ac"[..]), 'y').unwrap();
+///
+/// let mut output = Vec::new();
+/// MermaidPrinter::print(
+///     &mut output,
+///     &tree,
+///     ExportSettings::new(format_utf8_lossy, format_display),
+/// )
+/// .unwrap()
+/// .unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     r#"flowchart TD
+/// n0["Node4 | prefix [97]"]
+/// n1["Leaf | ab | x"]
+/// n0 -->|98| n1
+/// n2["Leaf | ac | y"]
+/// n0 -->|99| n2
+/// "#
+/// );
+/// ```
+pub struct MermaidPrinter<O: Write, FK, FV> {
+    output: O,
+    next_id: usize,
+    /// The ids of the inner nodes on the path to the current node.
+    open_nodes: Vec<usize>,
+    settings: ExportSettings<FK, FV>,
+}
+
+impl<O: Write, FK, FV> MermaidPrinter<O, FK, FV> {
+    /// Write the Mermaid flowchart of the given tree to the given output.
+    ///
+    /// Returns `None` if the tree is empty.
+    pub fn print<K, V, const PREFIX_LEN: usize>(
+        output: O,
+        tree: &TreeMap<K, V, PREFIX_LEN>,
+        settings: ExportSettings<FK, FV>,
+    ) -> Option<io::Result<()>>
+    where
+        K: AsBytes,
+        FK: Fn(&K, &mut Formatter<'_>) -> fmt::Result,
+        FV: Fn(&V, &mut Formatter<'_>) -> fmt::Result,
+    {
+        tree.root?;
+
+        let mut visitor = MermaidPrinter {
+            output,
+            next_id: 0,
+            open_nodes: Vec::new(),
+            settings,
+        };
+
+        if let Err(err) = writeln!(visitor.output, "flowchart TD") {
+            return Some(Err(err));
+        }
+        match walk(tree, &mut visitor) {
+            ControlFlow::Continue(()) => Some(Ok(())),
+            ControlFlow::Break(err) => Some(Err(err)),
+        }
+    }
+
+    /// Write the start of a node, up to its label.
+    fn start_node(&mut self, node_type: NodeType) -> io::Result<usize> {
+        let node_id = self.next_id;
+        self.next_id += 1;
+
+        write!(self.output, "n{node_id}[\"{node_type:?} | ")?;
+        Ok(node_id)
+    }
+
+    /// Write the end of a node after its label, and the edge from its parent.
+    fn finish_node<T>(
+        &mut self,
+        node: &T,
+        node_id: usize,
+        ctx: &VisitContext<'_>,
+    ) -> io::Result<()> {
+        if self.settings.display_node_address {
+            write!(self.output, " | {:p}", node as *const T)?;
+        }
+        writeln!(self.output, "\"]")?;
+
+        if let (Some(parent_id), Some(key_fragment)) = (self.open_nodes.last(), key_fragment(ctx)) {
+            writeln!(self.output, "n{parent_id} -->|{key_fragment}| n{node_id}")?;
+        }
+
+        Ok(())
+    }
+
+    fn write_inner_node<N, const PREFIX_LEN: usize>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> io::Result<usize>
+    where
+        N: InnerNode<PREFIX_LEN>,
+        N::Key: AsBytes,
+    {
+        let (prefix, _) = inner_node.read_full_prefix(ctx.key_bytes.len());
+        let node_id = self.start_node(N::TYPE)?;
+        write!(
+            self.output,
+            "{}",
+            mermaid_label(format_args!("prefix {prefix:?}"))
+        )?;
+        self.finish_node(inner_node, node_id, ctx)?;
+
+        Ok(node_id)
+    }
+
+    fn write_leaf<K, V>(&mut self, leaf: &LeafNode<K, V>, ctx: &VisitContext<'_>) -> io::Result<()>
+    where
+        FK: Fn(&K, &mut Formatter<'_>) -> fmt::Result,
+        FV: Fn(&V, &mut Formatter<'_>) -> fmt::Result,
+    {
+        let node_id = self.start_node(NodeType::Leaf)?;
+        write!(
+            self.output,
+            "{}",
+            mermaid_label(format_args!(
+                "{} | {}",
+                Formatted(leaf.key_ref(), &self.settings.format_key),
+                Formatted(leaf.value_ref(), &self.settings.format_value)
+            ))
+        )?;
+        self.finish_node(leaf, node_id, ctx)
+    }
+}
+
+/// Escape the output of the given [`Display`] impl to be used inside a quoted
+/// Mermaid label, using Mermaid entity codes.
+fn mermaid_label(value: impl Display) -> impl Display {
+    Escaped(value, |c: char, f: &mut Formatter<'_>| match c {
+        '"' => f.write_str("#quot;"),
+        '#' | '<' | '>' | '&' => write!(f, "#{};", u32::from(c)),
+        c if c.is_control() => write!(f, "#{};", u32::from(c)),
+        c => f.write_char(c),
+    })
+}
+
+impl<K, V, O, FK, FV, const PREFIX_LEN: usize> TreeVisitor<K, V, PREFIX_LEN>
+    for MermaidPrinter<O, FK, FV>
+where
+    K: AsBytes,
+    O: Write,
+    FK: Fn(&K, &mut Formatter<'_>) -> fmt::Result,
+    FV: Fn(&V, &mut Formatter<'_>) -> fmt::Result,
+{
+    type Break = io::Error;
+
+    fn visit_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break, VisitChildren>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        let node_id = break_on_error(self.write_inner_node(inner_node, ctx))?;
+        self.open_nodes.push(node_id);
+
+        ControlFlow::Continue(VisitChildren::Visit)
+    }
+
+    fn leave_inner_node<N>(&mut self, _: &N, _: &VisitContext<'_>) -> ControlFlow<Self::Break>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        self.open_nodes.pop();
+        ControlFlow::Continue(())
+    }
+
+    fn visit_leaf(
+        &mut self,
+        leaf: &LeafNode<K, V>,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break> {
+        break_on_error(self.write_leaf(leaf, ctx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::visitor::format_debug;
+
+    #[test]
+    fn mermaid_output_escapes_labels() {
+        let mut tree = TreeMap::<Box<[u8]>, String>::new();
+        tree.try_insert(Box::from(&b"a"[..]), String::from("<\"#\">"))
+            .unwrap();
+
+        let mut output = Vec::new();
+        MermaidPrinter::print(
+            &mut output,
+            &tree,
+            ExportSettings::new(format_debug, format_debug),
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "flowchart TD\nn0[\"Leaf | [97] | #quot;#60;\\#quot;#35;\\#quot;#62;#quot;\"]\n"
+        );
+    }
+}
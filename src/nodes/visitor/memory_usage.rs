@@ -0,0 +1,209 @@
+use core::{convert::Infallible, mem::size_of, ops::ControlFlow};
+
+use crate::{
+    visitor::{walk, TreeVisitor, VisitChildren, VisitContext},
+    AsBytes, HeapSize, InnerNode, LeafNode, TreeMap,
+};
+
+/// A breakdown of the heap memory used by a [`TreeMap`], as returned by
+/// [`TreeMap::memory_usage`].
+///
+/// Unlike [`TreeStats::total_memory_usage`](crate::visitor::TreeStats::total_memory_usage),
+/// which only counts the size of the nodes, this also includes the memory
+/// owned by the keys and values through [`HeapSize`], and an estimate of the
+/// memory lost to rounding up each allocation to the size classes of the
+/// allocator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MemoryUsage {
+    /// The size of all inner nodes
+    pub inner_nodes: usize,
+
+    /// The size of the prefixes copied to the heap by
+    /// [`PrefixStrategy::Pessimistic`](crate::PrefixStrategy::Pessimistic)
+    pub spilled_prefixes: usize,
+
+    /// The size of all leaf nodes, which includes the keys and values
+    /// themselves
+    pub leaves: usize,
+
+    /// The heap memory owned by the keys
+    pub key_heap: usize,
+
+    /// The heap memory owned by the values
+    pub value_heap: usize,
+
+    /// Estimated memory usage of the lookup index, see
+    /// [`TreeMap::enable_lookup_index`]
+    pub lookup_index: usize,
+
+    /// Estimated number of bytes lost to rounding up all of the above
+    /// allocations to the size classes of the allocator
+    pub allocator_slack: usize,
+}
+
+impl MemoryUsage {
+    /// Collect the memory usage of the given tree.
+    pub(crate) fn collect<K, V, const PREFIX_LEN: usize>(tree: &TreeMap<K, V, PREFIX_LEN>) -> Self
+    where
+        K: AsBytes + HeapSize,
+        V: HeapSize,
+    {
+        let mut usage = MemoryUsage::default();
+        let _ = walk(tree, &mut usage);
+        usage.lookup_index = tree.lookup_index_stats().mem_usage;
+        usage
+    }
+
+    /// The memory used by the nodes of the tree and the lookup index, without
+    /// the heap memory owned by the keys and values or the allocator slack.
+    pub fn node_overhead(&self) -> usize {
+        self.inner_nodes + self.spilled_prefixes + self.leaves + self.lookup_index
+    }
+
+    /// The total memory usage of the tree, including the heap memory owned by
+    /// the keys and values and the allocator slack.
+    pub fn total(&self) -> usize {
+        self.node_overhead() + self.key_heap + self.value_heap + self.allocator_slack
+    }
+
+    fn record_allocation(&mut self, size: usize) {
+        self.allocator_slack += allocation_size_class(size) - size;
+    }
+}
+
+impl<K, V, const PREFIX_LEN: usize> TreeVisitor<K, V, PREFIX_LEN> for MemoryUsage
+where
+    K: HeapSize,
+    V: HeapSize,
+{
+    type Break = Infallible;
+
+    fn visit_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        _: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break, VisitChildren>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        let node_size = size_of::<N>();
+        self.inner_nodes += node_size;
+        self.record_allocation(node_size);
+
+        let spilled = inner_node.header().heap_size();
+        if spilled > 0 {
+            self.spilled_prefixes += spilled;
+            self.record_allocation(spilled);
+        }
+
+        ControlFlow::Continue(VisitChildren::Visit)
+    }
+
+    fn visit_leaf(
+        &mut self,
+        leaf: &LeafNode<K, V>,
+        _: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break> {
+        let leaf_size = size_of::<LeafNode<K, V>>();
+        self.leaves += leaf_size;
+        self.record_allocation(leaf_size);
+
+        // The heap memory of a key or value could be split over many
+        // allocations, but the common cases of `Box<[u8]>`, `String` or `Vec`
+        // use a single one
+        let key_heap = leaf.key_ref().heap_size();
+        self.key_heap += key_heap;
+        self.record_allocation(key_heap);
+
+        let value_heap = leaf.value_ref().heap_size();
+        self.value_heap += value_heap;
+        self.record_allocation(value_heap);
+
+        ControlFlow::Continue(())
+    }
+}
+
+/// Estimate the number of bytes actually reserved by the allocator for an
+/// allocation of the given size.
+///
+/// This uses size classes like the ones of jemalloc and most other modern
+/// allocators: multiples of 16 bytes up to 128 bytes, then 4 classes between
+/// each power of two.
+fn allocation_size_class(size: usize) -> usize {
+    const MIN_SPACING: usize = 16;
+
+    match size {
+        0 => 0,
+        1..=8 => 8,
+        _ => {
+            // The number of bits needed for `size - 1`, so `size` is in
+            // `(2^(bits - 1), 2^bits]`
+            let bits = usize::BITS - (size - 1).leading_zeros();
+            let spacing = (1usize << bits.saturating_sub(3)).max(MIN_SPACING);
+            size.div_ceil(spacing) * spacing
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests_common::generate_key_fixed_length, PrefixStrategy};
+    use alloc::{string::String, vec::Vec};
+
+    #[test]
+    fn size_classes() {
+        let classes: Vec<_> = [0, 1, 8, 9, 16, 17, 33, 100, 129, 130, 161, 257, 1000, 4097]
+            .into_iter()
+            .map(allocation_size_class)
+            .collect();
+        assert_eq!(
+            classes,
+            [0, 8, 8, 16, 16, 32, 48, 112, 160, 160, 192, 320, 1024, 5120]
+        );
+    }
+
+    #[test]
+    fn memory_usage_includes_key_and_value_heap() {
+        let mut tree = TreeMap::new();
+        for (idx, key) in generate_key_fixed_length([3, 3]).enumerate() {
+            tree.try_insert(key, String::from("x").repeat(idx)).unwrap();
+        }
+
+        let usage = tree.memory_usage();
+        let stats = crate::visitor::TreeStatsCollector::collect(&tree).unwrap();
+
+        assert_eq!(usage.inner_nodes, stats.tree.mem_usage);
+        assert_eq!(usage.leaves, stats.leaf.mem_usage);
+        assert_eq!(usage.node_overhead(), stats.total_memory_usage());
+        assert_eq!(usage.spilled_prefixes, 0);
+        // Every key is a `Box<[u8]>` of 2 bytes
+        assert_eq!(usage.key_heap, 16 * 2);
+        assert_eq!(usage.value_heap, (0..16).sum::<usize>());
+        assert!(usage.allocator_slack > 0);
+        assert_eq!(
+            usage.total(),
+            usage.node_overhead() + 16 * 2 + 120 + usage.allocator_slack
+        );
+    }
+
+    #[test]
+    fn memory_usage_includes_spilled_prefixes() {
+        let mut tree: TreeMap<[u8; 20], (), 8> =
+            TreeMap::with_prefix_strategy(PrefixStrategy::Pessimistic);
+        let mut key = [7; 20];
+        tree.try_insert(key, ()).unwrap();
+        key[19] = 8;
+        tree.try_insert(key, ()).unwrap();
+
+        let usage = tree.memory_usage();
+        assert_eq!(usage.spilled_prefixes, 19);
+        assert_eq!(usage.key_heap, 0);
+        assert_eq!(usage.value_heap, 0);
+
+        assert_eq!(
+            TreeMap::<u8, ()>::new().memory_usage(),
+            MemoryUsage::default()
+        );
+    }
+}
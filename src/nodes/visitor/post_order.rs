@@ -0,0 +1,352 @@
+use crate::{
+    visitor::push_children, ConcreteNodePtr, InnerNode, InnerNode16, InnerNode256, InnerNode32,
+    InnerNode4, InnerNode48, LeafNode, OpaqueNodePtr, TreeMap,
+};
+use alloc::vec;
+
+/// A variant of the [`Visitor`](crate::visitor::Visitor) trait whose inner node
+/// hooks are called after the children of the node have been visited, with the
+/// combined output of the children.
+///
+/// Since no hook has to visit the children itself, [`visit_post_order`] drives
+/// the whole traversal with an explicit stack, so very deep trees (for example
+/// long keys with low fan-out) do not overflow the call stack, with or without
+/// the `std` feature.
+pub trait PostOrderVisitor<K, V, const PREFIX_LEN: usize> {
+    /// The type of value that the visitor produces.
+    type Output;
+
+    /// Produce the default value of the [`Self::Output`] type.
+    fn default_output(&self) -> Self::Output;
+
+    /// Combine two instances of the [`Self::Output`] type for this visitor.
+    fn combine_output(&self, o1: Self::Output, o2: Self::Output) -> Self::Output;
+
+    /// Visit a [`InnerNode4`], given the combined output of its children.
+    fn visit_node4(
+        &mut self,
+        t: &InnerNode4<K, V, PREFIX_LEN>,
+        children: Self::Output,
+    ) -> Self::Output {
+        let _ = t;
+        children
+    }
+
+    /// Visit a [`InnerNode16`], given the combined output of its children.
+    fn visit_node16(
+        &mut self,
+        t: &InnerNode16<K, V, PREFIX_LEN>,
+        children: Self::Output,
+    ) -> Self::Output {
+        let _ = t;
+        children
+    }
+
+    /// Visit a [`InnerNode32`], given the combined output of its children.
+    fn visit_node32(
+        &mut self,
+        t: &InnerNode32<K, V, PREFIX_LEN>,
+        children: Self::Output,
+    ) -> Self::Output {
+        let _ = t;
+        children
+    }
+
+    /// Visit a [`InnerNode48`], given the combined output of its children.
+    fn visit_node48(
+        &mut self,
+        t: &InnerNode48<K, V, PREFIX_LEN>,
+        children: Self::Output,
+    ) -> Self::Output {
+        let _ = t;
+        children
+    }
+
+    /// Visit a [`InnerNode256`], given the combined output of its children.
+    fn visit_node256(
+        &mut self,
+        t: &InnerNode256<K, V, PREFIX_LEN>,
+        children: Self::Output,
+    ) -> Self::Output {
+        let _ = t;
+        children
+    }
+
+    /// Visit a [`LeafNode`].
+    fn visit_leaf(&mut self, t: &LeafNode<K, V>) -> Self::Output {
+        let _ = t;
+        self.default_output()
+    }
+}
+
+/// Run the visitor on every node of the tree, calling the hook of each inner
+/// node once all of its children are visited, and return the output for the
+/// root.
+///
+/// Returns the [`PostOrderVisitor::default_output`] if the tree is empty.
+///
+/// # Examples
+///
+/// ```rust
+/// use blart::{
+///     visitor::{visit_post_order, PostOrderVisitor},
+///     InnerNode4, LeafNode, TreeMap,
+/// };
+///
+/// /// Compute the height of the tree.
+/// struct Height;
+///
+/// impl PostOrderVisitor<[u8; 2], u32, 16> for Height {
+///     type Output = usize;
+///
+///     fn default_output(&self) -> usize {
+///         0
+///     }
+///
+///     fn combine_output(&self, o1: usize, o2: usize) -> usize {
+///         o1.max(o2)
+///     }
+///
+///     fn visit_node4(&mut self, _: &InnerNode4<[u8; 2], u32, 16>, children: usize) -> usize {
+///         children + 1
+///     }
+///
+///     fn visit_leaf(&mut self, _: &LeafNode<[u8; 2], u32>) -> usize {
+///         1
+///     }
+/// }
+///
+/// let mut map = TreeMap::<_, u32>::new();
+/// map.insert([1, 2], 2);
+/// map.insert([1, 3], 3);
+/// map.insert([2, 0], 4);
+///
+/// // The root and the inner node for `1` are both `InnerNode4`s
+/// assert_eq!(visit_post_order(&map, &mut Height), 3);
+/// ```
+pub fn visit_post_order<K, V, T, const PREFIX_LEN: usize>(
+    tree: &TreeMap<K, V, PREFIX_LEN>,
+    visitor: &mut T,
+) -> T::Output
+where
+    T: PostOrderVisitor<K, V, PREFIX_LEN>,
+{
+    match tree.root {
+        // SAFETY: Since we get a reference to the `TreeMap`, we know the
+        // node and all descendants will not be mutated
+        Some(root) => unsafe { visit_tree(root, visitor) },
+        None => visitor.default_output(),
+    }
+}
+
+/// An inner node whose children are being visited by [`visit_tree`].
+struct Frame<K, V, O, const PREFIX_LEN: usize> {
+    /// The inner node, or `None` for the frame holding the root of the tree
+    node: Option<OpaqueNodePtr<K, V, PREFIX_LEN>>,
+    /// The number of children which are still on the stack of pending nodes
+    remaining: usize,
+    /// The combined output of the children visited so far
+    accum: Option<O>,
+}
+
+impl<K, V, O, const PREFIX_LEN: usize> Frame<K, V, O, PREFIX_LEN> {
+    fn add<T>(&mut self, visitor: &T, output: O)
+    where
+        T: PostOrderVisitor<K, V, PREFIX_LEN, Output = O>,
+    {
+        self.accum = Some(match self.accum.take() {
+            Some(accum) => visitor.combine_output(accum, output),
+            None => output,
+        });
+    }
+}
+
+/// Visit the tree under `root`, using an explicit stack of partial outputs
+/// instead of recursion.
+///
+/// # Safety
+///  - `root` and all its descendants must not be mutated for the duration of
+///    this function.
+unsafe fn visit_tree<K, V, T, const PREFIX_LEN: usize>(
+    root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    visitor: &mut T,
+) -> T::Output
+where
+    T: PostOrderVisitor<K, V, PREFIX_LEN>,
+{
+    let mut pending = vec![root];
+    let mut frames = vec![Frame {
+        node: None,
+        remaining: 1,
+        accum: None,
+    }];
+
+    loop {
+        let frame = frames
+            .last_mut()
+            .expect("the stack should not be empty until the root frame is done");
+        if frame.remaining == 0 {
+            let children = frame
+                .accum
+                .take()
+                .unwrap_or_else(|| visitor.default_output());
+            let node = frame.node;
+            frames.pop();
+            let Some(node) = node else {
+                return children;
+            };
+            let output = visit_inner_node(node, visitor, children);
+            frames
+                .last_mut()
+                .expect("the parent of a visited node should still be on the stack")
+                .add(visitor, output);
+            continue;
+        }
+
+        frame.remaining -= 1;
+        let child = pending
+            .pop()
+            .expect("every remaining child should be on the pending stack");
+        let remaining = match child.to_node_ptr() {
+            ConcreteNodePtr::Node4(inner) => push_children(&mut pending, inner.read().iter()),
+            ConcreteNodePtr::Node16(inner) => push_children(&mut pending, inner.read().iter()),
+            ConcreteNodePtr::Node32(inner) => push_children(&mut pending, inner.read().iter()),
+            ConcreteNodePtr::Node48(inner) => push_children(&mut pending, inner.read().iter()),
+            ConcreteNodePtr::Node256(inner) => push_children(&mut pending, inner.read().iter()),
+            ConcreteNodePtr::LeafNode(leaf) => {
+                let output = visitor.visit_leaf(&leaf.read());
+                frame.add(visitor, output);
+                continue;
+            },
+        };
+        frames.push(Frame {
+            node: Some(child),
+            remaining,
+            accum: None,
+        });
+    }
+}
+
+/// Call the [`PostOrderVisitor`] hook of an inner node, once its children are
+/// visited.
+fn visit_inner_node<K, V, T, const PREFIX_LEN: usize>(
+    node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    visitor: &mut T,
+    children: T::Output,
+) -> T::Output
+where
+    T: PostOrderVisitor<K, V, PREFIX_LEN>,
+{
+    match node.to_node_ptr() {
+        ConcreteNodePtr::Node4(inner) => visitor.visit_node4(&inner.read(), children),
+        ConcreteNodePtr::Node16(inner) => visitor.visit_node16(&inner.read(), children),
+        ConcreteNodePtr::Node32(inner) => visitor.visit_node32(&inner.read(), children),
+        ConcreteNodePtr::Node48(inner) => visitor.visit_node48(&inner.read(), children),
+        ConcreteNodePtr::Node256(inner) => visitor.visit_node256(&inner.read(), children),
+        ConcreteNodePtr::LeafNode(_) => unreachable!("only inner nodes have children to visit"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{tests_common::generate_key_fixed_length, visitor::TreeStatsCollector};
+    use alloc::{boxed::Box, string::String, vec::Vec};
+
+    /// Records the values of the leaves, in the order the outputs are
+    /// combined.
+    struct Values;
+
+    impl<K, const PREFIX_LEN: usize> PostOrderVisitor<K, usize, PREFIX_LEN> for Values {
+        type Output = Vec<usize>;
+
+        fn default_output(&self) -> Self::Output {
+            Vec::new()
+        }
+
+        fn combine_output(&self, mut o1: Self::Output, o2: Self::Output) -> Self::Output {
+            o1.extend(o2);
+            o1
+        }
+
+        fn visit_leaf(&mut self, t: &LeafNode<K, usize>) -> Self::Output {
+            vec![*t.value_ref()]
+        }
+    }
+
+    /// Counts the [`InnerNode4`]s, by changing the combined output of their
+    /// children.
+    struct Node4Count;
+
+    impl<K, const PREFIX_LEN: usize> PostOrderVisitor<K, usize, PREFIX_LEN> for Node4Count {
+        type Output = usize;
+
+        fn default_output(&self) -> Self::Output {
+            0
+        }
+
+        fn combine_output(&self, o1: Self::Output, o2: Self::Output) -> Self::Output {
+            o1 + o2
+        }
+
+        fn visit_node4(
+            &mut self,
+            _t: &InnerNode4<K, usize, PREFIX_LEN>,
+            children: Self::Output,
+        ) -> Self::Output {
+            children + 1
+        }
+    }
+
+    #[test]
+    fn visit_post_order_combines_outputs_in_key_order() {
+        let mut tree: TreeMap<Box<[u8]>, usize> = TreeMap::new();
+        for (value, key) in generate_key_fixed_length([15, 3, 2]).enumerate() {
+            tree.try_insert(key, value).unwrap();
+        }
+
+        let values = visit_post_order(&tree, &mut Values);
+        assert_eq!(values, tree.values().copied().collect::<Vec<_>>());
+
+        let stats = TreeStatsCollector::collect(&tree).unwrap();
+        assert!(stats.node4.count > 0);
+        assert_eq!(visit_post_order(&tree, &mut Node4Count), stats.node4.count);
+    }
+
+    #[test]
+    fn visit_post_order_empty_tree() {
+        let tree: TreeMap<Box<[u8]>, usize> = TreeMap::new();
+
+        assert_eq!(visit_post_order(&tree, &mut Values), Vec::<usize>::new());
+    }
+
+    #[test]
+    #[cfg(not(miri))]
+    fn visit_post_order_deep_tree_small_stack() {
+        // Each key differs from the previous ones at a single byte, so there is one
+        // inner node per byte of the longest key.
+        const MAX_KEY_LEN: usize = 4096;
+
+        let mut tree: TreeMap<String, usize> = TreeMap::new();
+        for len in 1..=MAX_KEY_LEN {
+            let key = "a".repeat(len - 1) + "b";
+            tree.try_insert(key, len).unwrap();
+        }
+
+        // A recursive traversal needs far more than this for a tree this deep
+        let handle = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(move || {
+                assert_eq!(visit_post_order(&tree, &mut Node4Count), MAX_KEY_LEN - 1);
+                assert_eq!(
+                    visit_post_order(&tree, &mut Values),
+                    (1..=MAX_KEY_LEN).rev().collect::<Vec<_>>()
+                );
+
+                tree
+            })
+            .unwrap();
+
+        drop(handle.join().unwrap());
+    }
+}
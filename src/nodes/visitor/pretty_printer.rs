@@ -1,11 +1,12 @@
 use crate::{
-    visitor::{Visitable, Visitor},
-    InnerNode, NodeType, OpaqueNodePtr, TreeMap,
+    visitor::{walk_tree, TreeVisitor, VisitChildren, VisitContext},
+    AsBytes, InnerNode, LeafNode, NodeType, OpaqueNodePtr, TreeMap,
 };
-use std::{
+use core::{
     fmt::{Debug, Display},
-    io::{self, Write},
+    ops::ControlFlow,
 };
+use std::io::{self, Write};
 
 /// Settings which customize the output of the [`DotPrinter`] visitor.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -21,6 +22,9 @@ pub struct DotPrinterSettings {
 pub struct DotPrinter<O: Write> {
     output: O,
     next_id: usize,
+    /// The ids of the inner nodes on the path to the current node, along with
+    /// the index of the next child edge to write.
+    open_nodes: Vec<(usize, usize)>,
     settings: DotPrinterSettings,
 }
 
@@ -32,13 +36,13 @@ impl<O: Write> DotPrinter<O> {
         settings: DotPrinterSettings,
     ) -> Option<io::Result<()>>
     where
-        K: Display,
+        K: AsBytes + Display,
         V: Display,
     {
         tree.root.map(|root| {
             // SAFETY: Since we get a reference to the `TreeMap`, we know the
             // node and all descendants will not be mutated
-            unsafe { Self::print_tree(output, &root, settings) }
+            unsafe { Self::print_tree(output, root, settings) }
         })
     }
 
@@ -49,21 +53,25 @@ impl<O: Write> DotPrinter<O> {
     ///    children nodes must not get mutated.
     unsafe fn print_tree<K, V, const PREFIX_LEN: usize>(
         output: O,
-        tree: &OpaqueNodePtr<K, V, PREFIX_LEN>,
+        tree: OpaqueNodePtr<K, V, PREFIX_LEN>,
         settings: DotPrinterSettings,
     ) -> io::Result<()>
     where
-        K: Display,
+        K: AsBytes + Display,
         V: Display,
     {
         let mut visitor = DotPrinter {
             output,
             next_id: 0,
+            open_nodes: Vec::new(),
             settings,
         };
 
         visitor.output_prelude()?;
-        let _ = tree.visit_with(&mut visitor)?;
+        // SAFETY: Covered by the safety requirements of this function
+        if let ControlFlow::Break(err) = unsafe { walk_tree(tree, &mut visitor) } {
+            return Err(err);
+        }
         visitor.output_epilogue()
     }
 
@@ -82,6 +90,17 @@ impl<O: Write> DotPrinter<O> {
         new_id
     }
 
+    /// Write the edge from the parent node to the node which was just
+    /// finished, after its whole subtree has been written.
+    fn write_parent_edge(&mut self, node_id: usize) -> io::Result<()> {
+        if let Some((parent_id, key_frag_id)) = self.open_nodes.last_mut() {
+            writeln!(self.output, "n{parent_id}:c{key_frag_id} -> n{node_id}:h0")?;
+            *key_frag_id += 1;
+        }
+
+        Ok(())
+    }
+
     fn write_inner_node<K, T, N, const PREFIX_LEN: usize>(
         &mut self,
         inner_node: &N,
@@ -115,11 +134,7 @@ impl<O: Write> DotPrinter<O> {
             )?;
         }
 
-        // SAFETY: The `child_it` does not live beyond the following loop and will not
-        // overlap with any mutating access or operation, which is guaranteed by the
-        // `print_tree` caller requirements.
-        let child_it = inner_node.iter();
-        for (idx, (key_fragment, _)) in child_it.enumerate() {
+        for (idx, (key_fragment, _)) in inner_node.iter().enumerate() {
             if idx == 0 {
                 write!(self.output, "<c{idx}> {key_fragment}")?;
             } else {
@@ -128,53 +143,14 @@ impl<O: Write> DotPrinter<O> {
         }
         writeln!(self.output, "}}}}\"]")?;
 
-        // SAFETY: The `child_it` does not live beyond the following loop and will not
-        // overlap with any mutating access or operation, which is guaranteed by the
-        // `print_tree` caller requirements.
-        let child_it = inner_node.iter();
-        for (key_frag_id, (_, child)) in child_it.enumerate() {
-            let child_id = child.visit_with(self)?;
-
-            writeln!(self.output, "n{node_id}:c{key_frag_id} -> n{child_id}:h0")?;
-        }
-
         Ok(node_id)
     }
-}
-
-impl<K, T, O, const PREFIX_LEN: usize> Visitor<K, T, PREFIX_LEN> for DotPrinter<O>
-where
-    K: Display,
-    T: Display,
-    O: Write,
-{
-    type Output = io::Result<usize>;
-
-    fn default_output(&self) -> Self::Output {
-        unimplemented!("this visitor should never use the default output")
-    }
-
-    fn combine_output(&self, _: Self::Output, _: Self::Output) -> Self::Output {
-        unimplemented!("this visitor should never combine outputs")
-    }
-
-    fn visit_node4(&mut self, t: &crate::InnerNode4<K, T, PREFIX_LEN>) -> Self::Output {
-        self.write_inner_node(t)
-    }
 
-    fn visit_node16(&mut self, t: &crate::InnerNode16<K, T, PREFIX_LEN>) -> Self::Output {
-        self.write_inner_node(t)
-    }
-
-    fn visit_node48(&mut self, t: &crate::InnerNode48<K, T, PREFIX_LEN>) -> Self::Output {
-        self.write_inner_node(t)
-    }
-
-    fn visit_node256(&mut self, t: &crate::InnerNode256<K, T, PREFIX_LEN>) -> Self::Output {
-        self.write_inner_node(t)
-    }
-
-    fn visit_leaf(&mut self, t: &crate::LeafNode<K, T>) -> Self::Output {
+    fn write_leaf<K, T>(&mut self, t: &LeafNode<K, T>) -> io::Result<usize>
+    where
+        K: Display,
+        T: Display,
+    {
         let node_id = self.get_id();
         write!(self.output, "n{node_id} ")?;
         write!(self.output, "[label=\"{{")?;
@@ -202,9 +178,60 @@ where
     }
 }
 
+impl<K, T, O, const PREFIX_LEN: usize> TreeVisitor<K, T, PREFIX_LEN> for DotPrinter<O>
+where
+    K: Display,
+    T: Display,
+    O: Write,
+{
+    type Break = io::Error;
+
+    fn visit_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        _: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break, VisitChildren>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = T>,
+    {
+        match self.write_inner_node(inner_node) {
+            Ok(node_id) => {
+                self.open_nodes.push((node_id, 0));
+                ControlFlow::Continue(VisitChildren::Visit)
+            },
+            Err(err) => ControlFlow::Break(err),
+        }
+    }
+
+    fn leave_inner_node<N>(&mut self, _: &N, _: &VisitContext<'_>) -> ControlFlow<Self::Break>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = T>,
+    {
+        let (node_id, _) = self
+            .open_nodes
+            .pop()
+            .expect("should match push of inner node");
+
+        match self.write_parent_edge(node_id) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(err) => ControlFlow::Break(err),
+        }
+    }
+
+    fn visit_leaf(&mut self, t: &LeafNode<K, T>, _: &VisitContext<'_>) -> ControlFlow<Self::Break> {
+        match self
+            .write_leaf(t)
+            .and_then(|node_id| self.write_parent_edge(node_id))
+        {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(err) => ControlFlow::Break(err),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{deallocate_tree, AsBytes};
+    use crate::deallocate_tree;
 
     use super::*;
 
@@ -216,7 +243,7 @@ mod tests {
         where
             T: Debug,
         {
-            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
                 <T as Debug>::fmt(&self.0, f)
             }
         }
@@ -242,7 +269,7 @@ mod tests {
         unsafe {
             DotPrinter::print_tree(
                 &mut buffer,
-                &root,
+                root,
                 DotPrinterSettings {
                     display_node_address: false,
                 },
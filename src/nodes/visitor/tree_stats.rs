@@ -1,9 +1,15 @@
-use std::ops::Add;
+use core::{
+    convert::Infallible,
+    fmt::Write as _,
+    ops::{Add, ControlFlow},
+};
 
 use crate::{
-    visitor::{Visitable, Visitor},
-    AsBytes, InnerNode, InnerNode16, InnerNode256, InnerNode4, InnerNode48, LeafNode, TreeMap,
+    visitor::{walk, TreeVisitor, VisitChildren, VisitContext},
+    AsBytes, ConcreteNodePtr, InnerNode, KeylessTreeMap, LeafNode, NodePtr, NodeType,
+    OpaqueNodePtr, PrefixStrategy, TreeMap,
 };
+use alloc::vec::Vec;
 
 /// A visitor of the radix tree which collects statistics about the tree, like
 /// how many inner nodes of each type, how many leaves
@@ -18,12 +24,14 @@ impl TreeStatsCollector {
     pub fn collect<K: AsBytes, V, const PREFIX_LEN: usize>(
         tree: &TreeMap<K, V, PREFIX_LEN>,
     ) -> Option<TreeStats> {
-        if let Some(root) = tree.root {
+        if tree.root.is_some() {
             let mut collector = TreeStatsCollector {
                 current: TreeStats::default(),
             };
 
-            root.visit_with(&mut collector);
+            let _ = walk(tree, &mut collector);
+            collector.current.lookup_index = tree.lookup_index_stats();
+            collector.current.resizes = tree.resize_stats();
 
             Some(collector.current)
         } else {
@@ -31,33 +39,139 @@ impl TreeStatsCollector {
         }
     }
 
+    /// Run the tree stats collection on the given [`KeylessTreeMap`], then
+    /// return the accumulated stats.
+    ///
+    /// The values which the map stores in the child slots of their parent are
+    /// counted in [`LeafStats::inline_count`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::{visitor::TreeStatsCollector, KeylessTreeMap};
+    ///
+    /// let mut map = KeylessTreeMap::<[u8; 2], u8>::with_inline_values();
+    /// for key in 0..=255 {
+    ///     map.insert([1, key], key);
+    /// }
+    ///
+    /// let stats = TreeStatsCollector::collect_keyless(&map).unwrap();
+    /// assert_eq!(stats.leaf.count, 256);
+    /// assert_eq!(stats.leaf.inline_count, 256);
+    /// assert_eq!(stats.leaf.mem_usage, 0);
+    /// assert!(stats.leaf.inline_saved_bytes > 0);
+    /// ```
+    pub fn collect_keyless<K, V, const PREFIX_LEN: usize>(
+        map: &KeylessTreeMap<K, V, PREFIX_LEN>,
+    ) -> Option<TreeStats> {
+        if map.is_empty() {
+            return None;
+        }
+
+        let mut collector = TreeStatsCollector {
+            current: TreeStats::default(),
+        };
+        map.collect_stats(&mut collector);
+
+        Some(collector.current)
+    }
+
+    /// Record the stats of an inner node at the given depth.
+    pub(crate) fn record_inner_node<N, const PREFIX_LEN: usize>(
+        &mut self,
+        inner_node: &N,
+        depth: usize,
+    ) where
+        N: InnerNode<PREFIX_LEN>,
+    {
+        let node_stats = match N::TYPE {
+            NodeType::Node4 => &mut self.current.node4,
+            NodeType::Node16 => &mut self.current.node16,
+            NodeType::Node32 => &mut self.current.node32,
+            NodeType::Node48 => &mut self.current.node48,
+            NodeType::Node256 => &mut self.current.node256,
+            NodeType::Leaf => unreachable!("inner nodes are never leaves"),
+        };
+        node_stats.aggregate_data(inner_node);
+        self.current.tree.aggregate_data(inner_node);
+
+        let header = inner_node.header();
+        self.current.fan_out.record(header.num_children());
+        self.current.prefix_len.record(header.prefix_len());
+
+        let level = self.level_mut(depth);
+        match N::TYPE {
+            NodeType::Node4 => level.node4 += 1,
+            NodeType::Node16 => level.node16 += 1,
+            NodeType::Node32 => level.node32 += 1,
+            NodeType::Node48 => level.node48 += 1,
+            NodeType::Node256 => level.node256 += 1,
+            NodeType::Leaf => unreachable!("inner nodes are never leaves"),
+        }
+        level.sum_children += header.num_children();
+        level.sum_prefix_len_bytes += header.prefix_len();
+    }
+
+    /// Record the stats of a leaf at the given depth, which uses `mem_usage`
+    /// bytes.
+    pub(crate) fn record_leaf(&mut self, key_len: usize, mem_usage: usize, depth: usize) {
+        self.current.leaf.count += 1;
+        self.current.leaf.sum_key_bytes += key_len;
+        self.current.leaf.mem_usage += mem_usage;
+
+        self.current.leaf_depth.record(depth);
+        self.current.key_len.record(key_len);
+        self.level_mut(depth).leaf += 1;
+    }
+
+    /// Record the stats of a value which is stored in the child slot of its
+    /// parent, instead of a leaf which would have used `saved_bytes` bytes.
+    pub(crate) fn record_inline_leaf(&mut self, key_len: usize, saved_bytes: usize, depth: usize) {
+        self.record_leaf(key_len, 0, depth);
+        self.current.leaf.inline_count += 1;
+        self.current.leaf.inline_saved_bytes += saved_bytes;
+    }
+
+    fn level_mut(&mut self, depth: usize) -> &mut LevelStats {
+        let levels = &mut self.current.levels;
+        if depth >= levels.len() {
+            levels.resize(depth + 1, LevelStats::default());
+        }
+        &mut levels[depth]
+    }
+
     /// Iterate through the given tree and return the number of leaf nodes.
     pub fn count_leaf_nodes<K, V, const PREFIX_LEN: usize>(
         tree: &TreeMap<K, V, PREFIX_LEN>,
     ) -> usize {
-        struct LeafNodeCounter;
-
-        impl<K, V, const PREFIX_LEN: usize> Visitor<K, V, PREFIX_LEN> for LeafNodeCounter {
-            type Output = usize;
-
-            fn default_output(&self) -> Self::Output {
-                0
-            }
-
-            fn combine_output(&self, o1: Self::Output, o2: Self::Output) -> Self::Output {
-                o1 + o2
-            }
+        fn push_children<K, V, N, const PREFIX_LEN: usize>(
+            stack: &mut Vec<OpaqueNodePtr<K, V, PREFIX_LEN>>,
+            inner_ptr: NodePtr<PREFIX_LEN, N>,
+        ) where
+            N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+        {
+            // SAFETY: The reference does not outlive this function, and since we get
+            // a reference to the `TreeMap`, no mutation can happen to any of the nodes.
+            let inner_node = unsafe { inner_ptr.as_ref() };
+            stack.extend(inner_node.iter().map(|(_, child)| child));
+        }
 
-            fn visit_leaf(&mut self, _t: &crate::LeafNode<K, V>) -> Self::Output {
-                1
+        let mut num_leaves = 0;
+        let mut stack = Vec::new();
+        stack.extend(tree.root);
+
+        while let Some(node) = stack.pop() {
+            match node.to_node_ptr() {
+                ConcreteNodePtr::Node4(inner_ptr) => push_children(&mut stack, inner_ptr),
+                ConcreteNodePtr::Node16(inner_ptr) => push_children(&mut stack, inner_ptr),
+                ConcreteNodePtr::Node32(inner_ptr) => push_children(&mut stack, inner_ptr),
+                ConcreteNodePtr::Node48(inner_ptr) => push_children(&mut stack, inner_ptr),
+                ConcreteNodePtr::Node256(inner_ptr) => push_children(&mut stack, inner_ptr),
+                ConcreteNodePtr::LeafNode(_) => num_leaves += 1,
             }
         }
 
-        let mut counter = LeafNodeCounter;
-
-        tree.root
-            .map(|root| root.visit_with(&mut counter))
-            .unwrap_or(0)
+        num_leaves
     }
 }
 
@@ -104,7 +218,7 @@ impl InnerNodeStats {
         self.sum_capped_prefix_len_bytes += t.header().capped_prefix_len();
         self.max_prefix_len_bytes = self.max_prefix_len_bytes.max(t.header().prefix_len());
 
-        self.mem_usage += std::mem::size_of_val(t);
+        self.mem_usage += core::mem::size_of_val(t);
     }
 
     /// How many free slots
@@ -173,6 +287,15 @@ pub struct LeafStats {
 
     /// Total memory usage
     pub mem_usage: usize,
+
+    /// The number of values which are stored in the child slot of their
+    /// parent instead of a leaf node, see
+    /// [`KeylessTreeMap::with_inline_values`]. These are included in
+    /// `count`, but not in `mem_usage`.
+    pub inline_count: usize,
+
+    /// The memory which the leaf nodes of the inline values would have used
+    pub inline_saved_bytes: usize,
 }
 
 impl Add for LeafStats {
@@ -183,23 +306,247 @@ impl Add for LeafStats {
             count: self.count + rhs.count,
             sum_key_bytes: self.sum_key_bytes + rhs.sum_key_bytes,
             mem_usage: self.mem_usage + rhs.mem_usage,
+            inline_count: self.inline_count + rhs.inline_count,
+            inline_saved_bytes: self.inline_saved_bytes + rhs.inline_saved_bytes,
         }
     }
 }
 
-/// Collection of stats about the number of nodes types present in a tree
+/// Statistics for the lookup index of a [`TreeMap`], see
+/// [`TreeMap::enable_lookup_index`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct LookupIndexStats {
+    /// The number of key bytes used as the index key, 0 if the index is not
+    /// enabled
+    pub depth: usize,
+
+    /// The number of entries in the index
+    pub count: usize,
+
+    /// Estimated memory usage
+    pub mem_usage: usize,
+}
+
+/// Counters for the number of times inner nodes of a [`TreeMap`] changed
+/// their node type, see [`ShrinkPolicy`](crate::ShrinkPolicy).
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ResizeStats {
+    /// The number of times an inner node was grown into a larger node type
+    /// by an insert
+    pub grow_count: usize,
+
+    /// The number of times an inner node was shrunk into a smaller node type
+    /// by a remove
+    pub shrink_count: usize,
+}
+
+/// A histogram of `usize` values, which counts the number of occurrences of
+/// each value.
+///
+/// # Examples
+///
+/// ```rust
+/// use blart::visitor::Histogram;
+///
+/// let histogram: Histogram = [2, 3, 3, 7].into_iter().collect();
+/// assert_eq!(histogram.count(3), 2);
+/// assert_eq!(histogram.total(), 4);
+/// assert_eq!(histogram.min(), Some(2));
+/// assert_eq!(histogram.max(), Some(7));
+/// assert_eq!(histogram.mean(), 3.75);
+/// assert_eq!(histogram.percentile(0.5), Some(3));
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, Default)]
+pub struct Histogram {
+    /// The number of occurrences of each value, indexed by value. The last
+    /// element is never 0.
+    counts: Vec<usize>,
+}
+
+impl Histogram {
+    /// Count one occurrence of the value.
+    pub fn record(&mut self, value: usize) {
+        if value >= self.counts.len() {
+            self.counts.resize(value + 1, 0);
+        }
+        self.counts[value] += 1;
+    }
+
+    /// The number of occurrences of the value.
+    pub fn count(&self, value: usize) -> usize {
+        self.counts.get(value).copied().unwrap_or(0)
+    }
+
+    /// The total number of occurrences of all values.
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Returns `true` if no values were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// The smallest value recorded, if any.
+    pub fn min(&self) -> Option<usize> {
+        self.counts.iter().position(|&count| count > 0)
+    }
+
+    /// The largest value recorded, if any.
+    pub fn max(&self) -> Option<usize> {
+        self.counts.len().checked_sub(1)
+    }
+
+    /// The average of the values recorded.
+    pub fn mean(&self) -> f64 {
+        let sum: usize = self.iter().map(|(value, count)| value * count).sum();
+        sum as f64 / self.total() as f64
+    }
+
+    /// The smallest value such that at least the given fraction (between 0
+    /// and 1) of the recorded values are less than or equal to it.
+    pub fn percentile(&self, fraction: f64) -> Option<usize> {
+        let total = self.total();
+        if total == 0 {
+            return None;
+        }
+
+        // `f64::ceil` is not available without `std`
+        let scaled = fraction * total as f64;
+        let target = if scaled > (scaled as usize) as f64 {
+            scaled as usize + 1
+        } else {
+            scaled as usize
+        };
+        let target = target.clamp(1, total);
+        let mut running = 0;
+        self.iter().find_map(|(value, count)| {
+            running += count;
+            (running >= target).then_some(value)
+        })
+    }
+
+    /// Iterate over the values which were recorded at least once, in
+    /// increasing order, along with their number of occurrences.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(|(value, &count)| (value, count))
+    }
+}
+
+impl Extend<usize> for Histogram {
+    fn extend<T: IntoIterator<Item = usize>>(&mut self, iter: T) {
+        for value in iter {
+            self.record(value);
+        }
+    }
+}
+
+impl FromIterator<usize> for Histogram {
+    fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
+        let mut histogram = Histogram::default();
+        histogram.extend(iter);
+        histogram
+    }
+}
+
+impl core::fmt::Debug for Histogram {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl core::fmt::Display for Histogram {
+    /// Render one line per recorded value, with a bar proportional to its
+    /// number of occurrences.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        const BAR_WIDTH: usize = 40;
+
+        let Some(max_value) = self.max() else {
+            return f.write_str("  (empty)\n");
+        };
+        let value_width = max_value.checked_ilog10().unwrap_or(0) as usize + 1;
+        let max_count = self.iter().map(|(_, count)| count).max().unwrap_or(0);
+        let total = self.total();
+
+        for (value, count) in self.iter() {
+            let bar_len = (count * BAR_WIDTH).div_ceil(max_count);
+            write!(f, "  {value:>value_width$} | ")?;
+            for idx in 0..BAR_WIDTH {
+                f.write_char(if idx < bar_len { '#' } else { ' ' })?;
+            }
+            writeln!(f, " {count} ({:.1}%)", 100.0 * count as f64 / total as f64)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Statistics for all the nodes at the same depth of the tree, where the root
+/// is at depth 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct LevelStats {
+    /// The number of [`InnerNode4`](crate::InnerNode4)s
+    pub node4: usize,
+
+    /// The number of [`InnerNode16`](crate::InnerNode16)s
+    pub node16: usize,
+
+    /// The number of [`InnerNode32`](crate::InnerNode32)s
+    pub node32: usize,
+
+    /// The number of [`InnerNode48`](crate::InnerNode48)s
+    pub node48: usize,
+
+    /// The number of [`InnerNode256`](crate::InnerNode256)s
+    pub node256: usize,
+
+    /// The number of [`LeafNode`]s
+    pub leaf: usize,
+
+    /// The sum of the number of children of the inner nodes
+    pub sum_children: usize,
+
+    /// The sum of the prefix lengths of the inner nodes
+    pub sum_prefix_len_bytes: usize,
+}
+
+impl LevelStats {
+    /// The number of inner nodes of all types
+    pub fn inner_node_count(&self) -> usize {
+        self.node4 + self.node16 + self.node32 + self.node48 + self.node256
+    }
+
+    /// The average number of children of the inner nodes
+    pub fn avg_fan_out(&self) -> f64 {
+        self.sum_children as f64 / self.inner_node_count() as f64
+    }
+
+    /// The average prefix length of the inner nodes
+    pub fn avg_prefix_len(&self) -> f64 {
+        self.sum_prefix_len_bytes as f64 / self.inner_node_count() as f64
+    }
+}
+
+/// Collection of stats about the number of nodes types present in a tree
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub struct TreeStats {
-    /// Stats for [`InnerNode4`]s
+    /// Stats for [`InnerNode4`](crate::InnerNode4)s
     pub node4: InnerNodeStats,
 
-    /// Stats for [`InnerNode16`]s
+    /// Stats for [`InnerNode16`](crate::InnerNode16)s
     pub node16: InnerNodeStats,
 
-    /// Stats for [`InnerNode48`]s
+    /// Stats for [`InnerNode32`](crate::InnerNode32)s
+    pub node32: InnerNodeStats,
+
+    /// Stats for [`InnerNode48`](crate::InnerNode48)s
     pub node48: InnerNodeStats,
 
-    /// Stats for [`InnerNode256`]s
+    /// Stats for [`InnerNode256`](crate::InnerNode256)s
     pub node256: InnerNodeStats,
 
     /// Stats for the whole tree
@@ -208,12 +555,35 @@ pub struct TreeStats {
     /// Number of [`LeafNode`]s present in the
     /// tree.
     pub leaf: LeafStats,
+
+    /// Stats for the lookup index of the map, if enabled
+    pub lookup_index: LookupIndexStats,
+
+    /// The number of grow and shrink events over the lifetime of the map
+    pub resizes: ResizeStats,
+
+    /// The depth of each leaf, which is the number of inner nodes on the
+    /// path from the root to the leaf
+    pub leaf_depth: Histogram,
+
+    /// The number of children of each inner node
+    pub fan_out: Histogram,
+
+    /// The prefix length of each inner node, including the part of the
+    /// prefix which does not fit in the node header
+    pub prefix_len: Histogram,
+
+    /// The length in bytes of each key
+    pub key_len: Histogram,
+
+    /// Stats for the nodes at each depth of the tree, starting from the root
+    pub levels: Vec<LevelStats>,
 }
 
 impl TreeStats {
-    /// Total memory usage of the tree (inner nodes + leaf)
+    /// Total memory usage of the tree (inner nodes + leaf + lookup index)
     pub fn total_memory_usage(&self) -> usize {
-        self.tree.mem_usage + self.leaf.mem_usage
+        self.tree.mem_usage + self.leaf.mem_usage + self.lookup_index.mem_usage
     }
 
     /// Bytes used per entry in the tree (only inner node memory usage)
@@ -225,64 +595,120 @@ impl TreeStats {
     pub fn bytes_per_entry_with_leaf(&self) -> f64 {
         self.total_memory_usage() as f64 / self.leaf.count as f64
     }
-}
-
-impl<K, V, const PREFIX_LEN: usize> Visitor<K, V, PREFIX_LEN> for TreeStatsCollector
-where
-    K: AsBytes,
-{
-    type Output = ();
 
-    fn default_output(&self) -> Self::Output {}
+    /// Recommend a `PREFIX_LEN` for trees with keys like the ones in this
+    /// sample, based on the average and maximum prefix length of the inner
+    /// nodes.
+    ///
+    /// If the longest prefix is at most twice the average, it is used so that
+    /// every prefix fits in the header. Otherwise the recommendation is twice
+    /// the average, and the longer prefixes are left to the
+    /// [`PrefixStrategy`]. The result is rounded up to a multiple of 8,
+    /// since the header is padded to that anyway.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use blart::{visitor::TreeStatsCollector, TreeMap};
+    ///
+    /// let mut map = TreeMap::<Box<[u8]>, usize>::new();
+    /// let paths = ["/usr/local/bin", "/usr/local/lib", "/usr/share/doc"];
+    /// for (idx, path) in paths.iter().enumerate() {
+    ///     map.try_insert(path.as_bytes().into(), idx).unwrap();
+    /// }
+    ///
+    /// let stats = TreeStatsCollector::collect(&map).unwrap();
+    /// assert_eq!(stats.tree.max_prefix_len_bytes, 5);
+    /// assert_eq!(stats.recommended_prefix_len(), 8);
+    /// ```
+    pub fn recommended_prefix_len(&self) -> usize {
+        if self.tree.count == 0 {
+            return 0;
+        }
 
-    fn combine_output(&self, _: Self::Output, _: Self::Output) -> Self::Output {}
+        let avg_prefix_len = self.tree.sum_prefix_len_bytes.div_ceil(self.tree.count);
+        let max_prefix_len = self.tree.max_prefix_len_bytes;
+        let prefix_len = if max_prefix_len <= 2 * avg_prefix_len {
+            max_prefix_len
+        } else {
+            2 * avg_prefix_len
+        };
 
-    fn visit_node4(&mut self, t: &InnerNode4<K, V, PREFIX_LEN>) -> Self::Output {
-        t.super_visit_with(self);
-        self.current.node4.aggregate_data(t);
-        self.current.tree.aggregate_data(t);
+        prefix_len.next_multiple_of(8)
     }
 
-    fn visit_node16(&mut self, t: &InnerNode16<K, V, PREFIX_LEN>) -> Self::Output {
-        t.super_visit_with(self);
-        self.current.node16.aggregate_data(t);
-        self.current.tree.aggregate_data(t);
+    /// Recommend a [`PrefixStrategy`] for trees with keys like the ones in
+    /// this sample, when using the given `PREFIX_LEN`.
+    ///
+    /// If some prefixes do not fit in `prefix_len` bytes, the
+    /// [`PrefixStrategy::Pessimistic`] strategy avoids reading a leaf every
+    /// time they are compared against a search key. It is only recommended if
+    /// `prefix_len` supports it, see [`PrefixStrategy::is_supported`].
+    pub fn recommended_prefix_strategy(&self, prefix_len: usize) -> PrefixStrategy {
+        if self.tree.max_prefix_len_bytes > prefix_len
+            && PrefixStrategy::Pessimistic.is_supported(prefix_len)
+        {
+            PrefixStrategy::Pessimistic
+        } else {
+            PrefixStrategy::Optimistic
+        }
     }
+}
 
-    fn visit_node48(&mut self, t: &InnerNode48<K, V, PREFIX_LEN>) -> Self::Output {
-        t.super_visit_with(self);
-        self.current.node48.aggregate_data(t);
-        self.current.tree.aggregate_data(t);
-    }
+impl<K, V, const PREFIX_LEN: usize> TreeVisitor<K, V, PREFIX_LEN> for TreeStatsCollector
+where
+    K: AsBytes,
+{
+    type Break = Infallible;
+
+    fn visit_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break, VisitChildren>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        self.record_inner_node(inner_node, ctx.depth);
 
-    fn visit_node256(&mut self, t: &InnerNode256<K, V, PREFIX_LEN>) -> Self::Output {
-        t.super_visit_with(self);
-        self.current.node256.aggregate_data(t);
-        self.current.tree.aggregate_data(t);
+        ControlFlow::Continue(VisitChildren::Visit)
     }
 
-    fn visit_leaf(&mut self, t: &LeafNode<K, V>) -> Self::Output {
-        self.current.leaf.count += 1;
-        self.current.leaf.sum_key_bytes += t.key_ref().as_bytes().len();
-        self.current.leaf.mem_usage += std::mem::size_of_val(t);
+    fn visit_leaf(
+        &mut self,
+        t: &LeafNode<K, V>,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break> {
+        let key_len = t.key_ref().as_bytes().len();
+        self.record_leaf(key_len, core::mem::size_of_val(t), ctx.depth);
+
+        ControlFlow::Continue(())
     }
 }
 
-impl std::fmt::Display for TreeStats {
+impl core::fmt::Display for TreeStats {
     #[rustfmt::skip]
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         let TreeStats {
             node4,
             node16,
+            node32,
             node48,
             node256,
             tree,
+            leaf,
+            lookup_index,
+            resizes,
             ..
         } = self;
-        write!(f, "{:#?}", self)?;
+        f.write_fmt(format_args!("node4: {node4:#?}\nnode16: {node16:#?}\nnode32: {node32:#?}\n"))?;
+        f.write_fmt(format_args!("node48: {node48:#?}\nnode256: {node256:#?}\ntree: {tree:#?}\n"))?;
+        f.write_fmt(format_args!("leaf: {leaf:#?}\nlookup_index: {lookup_index:#?}\nresizes: {resizes:#?}\n"))?;
         f.write_str("\n")?;
         f.write_fmt(format_args!("memory usage (inner nodes):        {} bytes\n", tree.mem_usage))?;
-        f.write_fmt(format_args!("memory usage (inner nodes + leaf): {} bytes\n", self.total_memory_usage()))?;
+        f.write_fmt(format_args!("memory usage (lookup index):       {} bytes\n", lookup_index.mem_usage))?;
+        f.write_fmt(format_args!("memory usage (total):              {} bytes\n", self.total_memory_usage()))?;
+        f.write_fmt(format_args!("memory saved (inline values):      {} bytes\n", leaf.inline_saved_bytes))?;
         f.write_fmt(format_args!("bytes/entry:                       {:.5}\n", self.bytes_per_entry()))?;
         f.write_fmt(format_args!("bytes/entry (with leaf):           {:.5}\n", self.bytes_per_entry_with_leaf()))?;
         f.write_fmt(format_args!("avg prefix length:                 {:.5} bytes\n", tree.avg_prefix_len()))?;
@@ -291,9 +717,25 @@ impl std::fmt::Display for TreeStats {
         f.write_fmt(format_args!("% used slots (0-1):                {:.5}\n", tree.percentage_slots()))?;
         f.write_fmt(format_args!("n4 size:                           {:?} bytes\n", node4.node_size()))?;
         f.write_fmt(format_args!("n16 size:                          {:?} bytes\n", node16.node_size()))?;
+        f.write_fmt(format_args!("n32 size:                          {:?} bytes\n", node32.node_size()))?;
         f.write_fmt(format_args!("n48 size:                          {:?} bytes\n", node48.node_size()))?;
         f.write_fmt(format_args!("n256 size:                         {:?} bytes\n", node256.node_size()))?;
-        f.write_fmt(format_args!("max prefix length:                 {} bytes", tree.max_prefix_len_bytes))?;
+        f.write_fmt(format_args!("max prefix length:                 {} bytes\n", tree.max_prefix_len_bytes))?;
+        f.write_fmt(format_args!("grow/shrink count:                 {}/{}\n", resizes.grow_count, resizes.shrink_count))?;
+        f.write_str("\n")?;
+        f.write_fmt(format_args!("leaf depth:\n{}", self.leaf_depth))?;
+        f.write_fmt(format_args!("inner node fan-out:\n{}", self.fan_out))?;
+        f.write_fmt(format_args!("inner node prefix length (bytes):\n{}", self.prefix_len))?;
+        f.write_fmt(format_args!("key length (bytes):\n{}", self.key_len))?;
+        f.write_str("\n")?;
+        f.write_fmt(format_args!("{:>5} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>11} {:>11}\n", "level", "n4", "n16", "n32", "n48", "n256", "leaves", "avg fan-out", "avg prefix"))?;
+        for (depth, level) in self.levels.iter().enumerate() {
+            if level.inner_node_count() == 0 {
+                f.write_fmt(format_args!("{:>5} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>11} {:>11}\n", depth, 0, 0, 0, 0, 0, level.leaf, "-", "-"))?;
+            } else {
+                f.write_fmt(format_args!("{:>5} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>11.2} {:>11.2}\n", depth, level.node4, level.node16, level.node32, level.node48, level.node256, level.leaf, level.avg_fan_out(), level.avg_prefix_len()))?;
+            }
+        }
         Ok(())
     }
 }
@@ -302,6 +744,7 @@ impl std::fmt::Display for TreeStats {
 mod tests {
     use super::*;
     use crate::{tests_common::generate_key_fixed_length, TreeMap};
+    use alloc::string::ToString;
 
     #[test]
     fn mostly_empty_tree_stats_fixed_length_tree() {
@@ -331,7 +774,26 @@ mod tests {
                 count: 16,
                 sum_key_bytes: 64,
                 mem_usage: 384,
+                inline_count: 0,
+                inline_saved_bytes: 0,
             },
+            leaf_depth: [4; 16].into_iter().collect(),
+            fan_out: [2; 15].into_iter().collect(),
+            prefix_len: [0; 15].into_iter().collect(),
+            key_len: [4; 16].into_iter().collect(),
+            // Each level doubles the number of nodes
+            levels: [1, 2, 4, 8]
+                .into_iter()
+                .map(|node4| LevelStats {
+                    node4,
+                    sum_children: 2 * node4,
+                    ..Default::default()
+                })
+                .chain([LevelStats {
+                    leaf: 16,
+                    ..Default::default()
+                }])
+                .collect(),
             ..Default::default()
         };
 
@@ -377,10 +839,121 @@ mod tests {
                 count: 64,
                 sum_key_bytes: 128,
                 mem_usage: 1536,
+                inline_count: 0,
+                inline_saved_bytes: 0,
+            },
+            // The root grew from an `InnerNode4` to an `InnerNode16`
+            resizes: ResizeStats {
+                grow_count: 1,
+                shrink_count: 0,
             },
+            leaf_depth: [2; 64].into_iter().collect(),
+            fan_out: [16].into_iter().chain([4; 16]).collect(),
+            prefix_len: [0; 17].into_iter().collect(),
+            key_len: [2; 64].into_iter().collect(),
+            levels: Vec::from([
+                LevelStats {
+                    node16: 1,
+                    sum_children: 16,
+                    ..Default::default()
+                },
+                LevelStats {
+                    node4: 16,
+                    sum_children: 64,
+                    ..Default::default()
+                },
+                LevelStats {
+                    leaf: 64,
+                    ..Default::default()
+                },
+            ]),
             ..Default::default()
         };
 
         assert_eq!(stats, expected);
+        assert_eq!(stats.recommended_prefix_len(), 0);
+        assert_eq!(
+            stats.recommended_prefix_strategy(0),
+            PrefixStrategy::Optimistic
+        );
+    }
+
+    #[test]
+    fn recommended_prefix_len_and_strategy() {
+        fn stats_with_prefixes(count: usize, sum: usize, max: usize) -> TreeStats {
+            TreeStats {
+                tree: InnerNodeStats {
+                    count,
+                    sum_prefix_len_bytes: sum,
+                    max_prefix_len_bytes: max,
+                    ..Default::default()
+                },
+                ..Default::default()
+            }
+        }
+
+        assert_eq!(TreeStats::default().recommended_prefix_len(), 0);
+
+        // All the prefixes are close to the average, cover the longest one
+        let stats = stats_with_prefixes(10, 100, 19);
+        assert_eq!(stats.recommended_prefix_len(), 24);
+        assert_eq!(
+            stats.recommended_prefix_strategy(24),
+            PrefixStrategy::Optimistic
+        );
+
+        // A few long outliers, size for the average and spill the rest
+        let stats = stats_with_prefixes(10, 45, 120);
+        assert_eq!(stats.recommended_prefix_len(), 16);
+        assert_eq!(
+            stats.recommended_prefix_strategy(16),
+            PrefixStrategy::Pessimistic
+        );
+        // Too small to hold the pointer to a spilled prefix
+        assert_eq!(
+            stats.recommended_prefix_strategy(4),
+            PrefixStrategy::Optimistic
+        );
+    }
+
+    #[test]
+    fn histogram_percentiles_and_display() {
+        let histogram: Histogram = [1, 1, 1, 2, 10].into_iter().collect();
+        assert_eq!(histogram.total(), 5);
+        assert_eq!(histogram.percentile(0.0), Some(1));
+        assert_eq!(histogram.percentile(0.6), Some(1));
+        assert_eq!(histogram.percentile(0.8), Some(2));
+        assert_eq!(histogram.percentile(1.0), Some(10));
+        assert_eq!(Histogram::default().percentile(0.5), None);
+        assert!(Histogram::default().mean().is_nan());
+
+        assert_eq!(
+            histogram.to_string(),
+            "   1 | ######################################## 3 (60.0%)
+   2 | ##############                           1 (20.0%)
+  10 | ##############                           1 (20.0%)
+"
+        );
+        assert_eq!(Histogram::default().to_string(), "  (empty)\n");
+    }
+
+    #[test]
+    fn tree_stats_report_includes_levels() {
+        let mut tree = TreeMap::new();
+        for (k, v) in generate_key_fixed_length([2, 1])
+            .enumerate()
+            .map(|(a, b)| (b, a))
+        {
+            tree.try_insert(k, v).unwrap();
+        }
+        let report = TreeStatsCollector::collect(&tree).unwrap().to_string();
+
+        assert!(report.contains("leaf depth:\n  2 | ########################################"));
+        assert!(report.contains(
+            "    0        1        0        0        0        0        0        3.00        0.00\n"
+        ));
+        assert!(report.contains(
+            "    2        0        0        0        0        0        6           -           -\n"
+        ));
     }
 }
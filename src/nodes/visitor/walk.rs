@@ -0,0 +1,857 @@
+use crate::{AsBytes, ConcreteNodePtr, InnerNode, LeafNode, NodePtr, OpaqueNodePtr, TreeMap};
+use alloc::vec::Vec;
+use core::ops::ControlFlow;
+
+/// The position of a node in the tree, passed to every [`TreeVisitor`] and
+/// [`TreeVisitorMut`] callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VisitContext<'a> {
+    /// The key bytes on the path from the root to the node.
+    ///
+    /// For an inner node this is every byte before the node prefix: the
+    /// prefixes of the ancestor nodes and the key bytes of the child pointers
+    /// leading to it. For a leaf node this also includes the key byte of the
+    /// child pointer of the parent node, so it is a prefix of the leaf key.
+    pub key_bytes: &'a [u8],
+    /// The number of inner nodes between the root and the node, so the root
+    /// is at depth 0.
+    pub depth: usize,
+}
+
+/// Whether the traversal should descend into the children of an inner node,
+/// returned from [`TreeVisitor::visit_inner_node`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum VisitChildren {
+    /// Visit the children of the inner node.
+    #[default]
+    Visit,
+    /// Skip all the children of the inner node, and continue with its next
+    /// sibling.
+    Skip,
+}
+
+/// A visitor of the tree which is given the key bytes and depth of each node,
+/// and can stop the traversal early.
+///
+/// The tree is traversed depth-first, with the children of each inner node in
+/// key order. Every callback returns a [`ControlFlow`], and the traversal stops
+/// as soon as one of them returns [`ControlFlow::Break`].
+///
+/// See [`walk`] to run a visitor on a [`TreeMap`], and [`TreeVisitorMut`] for a
+/// visitor which can modify the values in the tree.
+pub trait TreeVisitor<K, V, const PREFIX_LEN: usize> {
+    /// The value the traversal stops with.
+    type Break;
+
+    /// Called for every inner node, before its children are visited.
+    ///
+    /// Returning [`VisitChildren::Skip`] skips the children of the node, but
+    /// [`TreeVisitor::leave_inner_node`] is still called for it.
+    fn visit_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break, VisitChildren>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        let _ = (inner_node, ctx);
+        ControlFlow::Continue(VisitChildren::Visit)
+    }
+
+    /// Called for every inner node, after its children are visited or
+    /// skipped.
+    fn leave_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        let _ = (inner_node, ctx);
+        ControlFlow::Continue(())
+    }
+
+    /// Called for every leaf node.
+    fn visit_leaf(
+        &mut self,
+        leaf: &LeafNode<K, V>,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break> {
+        let _ = (leaf, ctx);
+        ControlFlow::Continue(())
+    }
+}
+
+/// A visitor of the tree like [`TreeVisitor`], which can modify the values of
+/// the leaves in place.
+///
+/// See [`walk_mut`] to run a visitor on a [`TreeMap`].
+pub trait TreeVisitorMut<K, V, const PREFIX_LEN: usize> {
+    /// The value the traversal stops with.
+    type Break;
+
+    /// Called for every inner node, before its children are visited.
+    ///
+    /// Returning [`VisitChildren::Skip`] skips the children of the node, but
+    /// [`TreeVisitorMut::leave_inner_node`] is still called for it.
+    fn visit_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break, VisitChildren>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        let _ = (inner_node, ctx);
+        ControlFlow::Continue(VisitChildren::Visit)
+    }
+
+    /// Called for every inner node, after its children are visited or
+    /// skipped.
+    fn leave_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        let _ = (inner_node, ctx);
+        ControlFlow::Continue(())
+    }
+
+    /// Called for every leaf node, with a mutable reference to its value.
+    fn visit_leaf(
+        &mut self,
+        key: &K,
+        value: &mut V,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break> {
+        let _ = (key, value, ctx);
+        ControlFlow::Continue(())
+    }
+}
+
+/// Run the visitor on every node of the tree, in depth-first order.
+///
+/// Returns the [`ControlFlow::Break`] value of the callback which stopped the
+/// traversal, or [`ControlFlow::Continue`] if the whole tree was visited.
+///
+/// # Examples
+///
+/// ```rust
+/// use blart::{visitor::{walk, TreeVisitor, VisitContext}, LeafNode, TreeMap};
+/// use std::ops::ControlFlow;
+///
+/// /// Find the depth of the first leaf with an odd value.
+/// struct FindOdd;
+///
+/// impl TreeVisitor<[u8; 2], u32, 16> for FindOdd {
+///     type Break = usize;
+///
+///     fn visit_leaf(
+///         &mut self,
+///         leaf: &LeafNode<[u8; 2], u32>,
+///         ctx: &VisitContext<'_>,
+///     ) -> ControlFlow<usize> {
+///         if leaf.value_ref() % 2 == 1 {
+///             ControlFlow::Break(ctx.depth)
+///         } else {
+///             ControlFlow::Continue(())
+///         }
+///     }
+/// }
+///
+/// let mut map = TreeMap::<_, u32>::new();
+/// map.insert([1, 2], 2);
+/// map.insert([1, 3], 3);
+/// map.insert([2, 0], 4);
+///
+/// // The root has children for `1` and `2`, and `[1, 3]` is in the inner node
+/// // for `1`
+/// assert_eq!(walk(&map, &mut FindOdd), ControlFlow::Break(2));
+/// ```
+pub fn walk<K, V, T, const PREFIX_LEN: usize>(
+    tree: &TreeMap<K, V, PREFIX_LEN>,
+    visitor: &mut T,
+) -> ControlFlow<T::Break>
+where
+    K: AsBytes,
+    T: TreeVisitor<K, V, PREFIX_LEN>,
+{
+    match tree.root {
+        // SAFETY: Since we get a reference to the `TreeMap`, we know the
+        // node and all descendants will not be mutated
+        Some(root) => unsafe { walk_tree(root, visitor) },
+        None => ControlFlow::Continue(()),
+    }
+}
+
+/// Run the visitor on every node of the tree, in depth-first order, allowing
+/// the visitor to modify the values of the leaves.
+///
+/// Returns the [`ControlFlow::Break`] value of the callback which stopped the
+/// traversal, or [`ControlFlow::Continue`] if the whole tree was visited.
+///
+/// # Examples
+///
+/// ```rust
+/// use blart::{visitor::{walk_mut, TreeVisitorMut, VisitContext}, TreeMap};
+/// use std::ops::ControlFlow;
+///
+/// /// Add the depth of each leaf to its value.
+/// struct AddDepth;
+///
+/// impl TreeVisitorMut<[u8; 2], usize, 16> for AddDepth {
+///     type Break = ();
+///
+///     fn visit_leaf(
+///         &mut self,
+///         _: &[u8; 2],
+///         value: &mut usize,
+///         ctx: &VisitContext<'_>,
+///     ) -> ControlFlow<()> {
+///         *value += ctx.depth;
+///         ControlFlow::Continue(())
+///     }
+/// }
+///
+/// let mut map = TreeMap::<_, usize>::new();
+/// map.insert([1, 2], 10);
+/// map.insert([1, 3], 20);
+/// map.insert([2, 0], 30);
+///
+/// assert_eq!(walk_mut(&mut map, &mut AddDepth), ControlFlow::Continue(()));
+/// assert_eq!(map.values().copied().collect::<Vec<_>>(), [12, 22, 31]);
+/// ```
+pub fn walk_mut<K, V, T, const PREFIX_LEN: usize>(
+    tree: &mut TreeMap<K, V, PREFIX_LEN>,
+    visitor: &mut T,
+) -> ControlFlow<T::Break>
+where
+    K: AsBytes,
+    T: TreeVisitorMut<K, V, PREFIX_LEN>,
+{
+    match tree.root {
+        // SAFETY: Since we get a mutable reference to the `TreeMap`, we know
+        // there are no other references to the nodes of the tree
+        Some(root) => unsafe { walk_tree_mut(root, visitor) },
+        None => ControlFlow::Continue(()),
+    }
+}
+
+/// Run the visitor on every node of the tree rooted at `root`.
+///
+/// # Safety
+///  - For the duration of this function, the given node and all its children
+///    nodes must not get mutated.
+pub(crate) unsafe fn walk_tree<K, V, T, const PREFIX_LEN: usize>(
+    root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    visitor: &mut T,
+) -> ControlFlow<T::Break>
+where
+    K: AsBytes,
+    T: TreeVisitor<K, V, PREFIX_LEN>,
+{
+    // SAFETY: Covered by the safety requirements of this function
+    unsafe { walk_node(root, &mut Shared(visitor)) }
+}
+
+/// Run the visitor on every node of the tree rooted at `root`, allowing the
+/// visitor to modify the values of the leaves.
+///
+/// # Safety
+///  - For the duration of this function, the given node and all its children
+///    nodes must not get accessed through any other pointer.
+pub(crate) unsafe fn walk_tree_mut<K, V, T, const PREFIX_LEN: usize>(
+    root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    visitor: &mut T,
+) -> ControlFlow<T::Break>
+where
+    K: AsBytes,
+    T: TreeVisitorMut<K, V, PREFIX_LEN>,
+{
+    // SAFETY: Covered by the safety requirements of this function
+    unsafe { walk_node(root, &mut Exclusive(visitor)) }
+}
+
+/// The callbacks used by [`walk_node`], so that the same traversal can drive
+/// both [`TreeVisitor`] and [`TreeVisitorMut`].
+trait Callbacks<K, V, const PREFIX_LEN: usize> {
+    type Break;
+
+    fn visit_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break, VisitChildren>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>;
+
+    fn leave_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>;
+
+    /// # Safety
+    ///  - The leaf must be valid to access as required by the visitor, see
+    ///    [`walk_tree`] and [`walk_tree_mut`].
+    unsafe fn visit_leaf(
+        &mut self,
+        leaf_ptr: NodePtr<PREFIX_LEN, LeafNode<K, V>>,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break>;
+}
+
+struct Shared<'v, T>(&'v mut T);
+
+impl<K, V, T, const PREFIX_LEN: usize> Callbacks<K, V, PREFIX_LEN> for Shared<'_, T>
+where
+    T: TreeVisitor<K, V, PREFIX_LEN>,
+{
+    type Break = T::Break;
+
+    fn visit_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break, VisitChildren>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        self.0.visit_inner_node(inner_node, ctx)
+    }
+
+    fn leave_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        self.0.leave_inner_node(inner_node, ctx)
+    }
+
+    unsafe fn visit_leaf(
+        &mut self,
+        leaf_ptr: NodePtr<PREFIX_LEN, LeafNode<K, V>>,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break> {
+        // SAFETY: The lifetime of the reference is bounded to this call, and the
+        // caller guarantees that the leaf is not mutated during it.
+        let leaf = unsafe { leaf_ptr.as_ref() };
+        self.0.visit_leaf(leaf, ctx)
+    }
+}
+
+struct Exclusive<'v, T>(&'v mut T);
+
+impl<K, V, T, const PREFIX_LEN: usize> Callbacks<K, V, PREFIX_LEN> for Exclusive<'_, T>
+where
+    T: TreeVisitorMut<K, V, PREFIX_LEN>,
+{
+    type Break = T::Break;
+
+    fn visit_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break, VisitChildren>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        self.0.visit_inner_node(inner_node, ctx)
+    }
+
+    fn leave_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        self.0.leave_inner_node(inner_node, ctx)
+    }
+
+    unsafe fn visit_leaf(
+        &mut self,
+        leaf_ptr: NodePtr<PREFIX_LEN, LeafNode<K, V>>,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break> {
+        // SAFETY: The lifetime of the references is bounded to this call, and the
+        // caller guarantees that the leaf is not accessed through any other pointer
+        // during it.
+        let (key, value) = unsafe { leaf_ptr.as_key_ref_value_mut() };
+        self.0.visit_leaf(key, value, ctx)
+    }
+}
+
+/// A pending step of the traversal in [`walk_node`].
+enum Step<K, V, const PREFIX_LEN: usize> {
+    /// Visit the node, after restoring the key bytes to the first `parent_len`
+    /// bytes and appending the key fragment of the child pointer.
+    Enter {
+        node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        parent_len: usize,
+        key_fragment: Option<u8>,
+        depth: usize,
+    },
+    /// Leave the inner node, after restoring the key bytes to the first
+    /// `key_len` bytes.
+    Leave {
+        node: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        key_len: usize,
+        depth: usize,
+    },
+}
+
+/// Visit the given node and all of its descendants.
+///
+/// This uses an explicit stack instead of recursion, so that deep trees (for
+/// example long keys with one inner node per byte) cannot overflow the call
+/// stack.
+///
+/// # Safety
+///  - The nodes must be valid to access as required by the callbacks, see
+///    [`walk_tree`] and [`walk_tree_mut`].
+unsafe fn walk_node<K, V, C, const PREFIX_LEN: usize>(
+    root: OpaqueNodePtr<K, V, PREFIX_LEN>,
+    callbacks: &mut C,
+) -> ControlFlow<C::Break>
+where
+    K: AsBytes,
+    C: Callbacks<K, V, PREFIX_LEN>,
+{
+    /// # Safety
+    ///  - Same as the containing function.
+    unsafe fn enter_inner_node<K, V, C, N, const PREFIX_LEN: usize>(
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+        callbacks: &mut C,
+        stack: &mut Vec<Step<K, V, PREFIX_LEN>>,
+        key_bytes: &mut Vec<u8>,
+        depth: usize,
+    ) -> ControlFlow<C::Break>
+    where
+        K: AsBytes,
+        C: Callbacks<K, V, PREFIX_LEN>,
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        // SAFETY: The reference does not outlive this function, and the inner node
+        // itself is never mutated by the traversal. Only the values of the leaves are
+        // handed out mutably, which are separate allocations.
+        let inner_node = unsafe { inner_ptr.as_ref() };
+
+        let ctx = VisitContext { key_bytes, depth };
+        let children = callbacks.visit_inner_node(inner_node, &ctx)?;
+
+        stack.push(Step::Leave {
+            node: inner_ptr.to_opaque(),
+            key_len: key_bytes.len(),
+            depth,
+        });
+
+        if children == VisitChildren::Visit {
+            key_bytes.extend(inner_node.read_full_prefix(key_bytes.len()).0);
+            let parent_len = key_bytes.len();
+
+            // Push the children in reverse order, so that they are popped and
+            // visited in key order.
+            let start = stack.len();
+            stack.extend(inner_node.iter().map(|(key_fragment, child)| Step::Enter {
+                node: child,
+                parent_len,
+                key_fragment: Some(key_fragment),
+                depth: depth + 1,
+            }));
+            stack[start..].reverse();
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// # Safety
+    ///  - Same as the containing function.
+    unsafe fn leave_inner_node<K, V, C, N, const PREFIX_LEN: usize>(
+        inner_ptr: NodePtr<PREFIX_LEN, N>,
+        callbacks: &mut C,
+        key_bytes: &[u8],
+        depth: usize,
+    ) -> ControlFlow<C::Break>
+    where
+        C: Callbacks<K, V, PREFIX_LEN>,
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        // SAFETY: Same as in `enter_inner_node`
+        let inner_node = unsafe { inner_ptr.as_ref() };
+
+        let ctx = VisitContext { key_bytes, depth };
+        callbacks.leave_inner_node(inner_node, &ctx)
+    }
+
+    let mut key_bytes = Vec::new();
+    let mut stack = Vec::new();
+    stack.push(Step::Enter {
+        node: root,
+        parent_len: 0,
+        key_fragment: None,
+        depth: 0,
+    });
+
+    while let Some(step) = stack.pop() {
+        match step {
+            Step::Enter {
+                node,
+                parent_len,
+                key_fragment,
+                depth,
+            } => {
+                key_bytes.truncate(parent_len);
+                key_bytes.extend(key_fragment);
+
+                // SAFETY: Covered by the safety requirements of this function
+                unsafe {
+                    match node.to_node_ptr() {
+                        ConcreteNodePtr::Node4(inner_ptr) => enter_inner_node(
+                            inner_ptr,
+                            callbacks,
+                            &mut stack,
+                            &mut key_bytes,
+                            depth,
+                        ),
+                        ConcreteNodePtr::Node16(inner_ptr) => enter_inner_node(
+                            inner_ptr,
+                            callbacks,
+                            &mut stack,
+                            &mut key_bytes,
+                            depth,
+                        ),
+                        ConcreteNodePtr::Node32(inner_ptr) => enter_inner_node(
+                            inner_ptr,
+                            callbacks,
+                            &mut stack,
+                            &mut key_bytes,
+                            depth,
+                        ),
+                        ConcreteNodePtr::Node48(inner_ptr) => enter_inner_node(
+                            inner_ptr,
+                            callbacks,
+                            &mut stack,
+                            &mut key_bytes,
+                            depth,
+                        ),
+                        ConcreteNodePtr::Node256(inner_ptr) => enter_inner_node(
+                            inner_ptr,
+                            callbacks,
+                            &mut stack,
+                            &mut key_bytes,
+                            depth,
+                        ),
+                        ConcreteNodePtr::LeafNode(leaf_ptr) => {
+                            let ctx = VisitContext {
+                                key_bytes: &key_bytes,
+                                depth,
+                            };
+                            callbacks.visit_leaf(leaf_ptr, &ctx)
+                        },
+                    }
+                }?;
+            },
+            Step::Leave {
+                node,
+                key_len,
+                depth,
+            } => {
+                key_bytes.truncate(key_len);
+
+                // SAFETY: Covered by the safety requirements of this function
+                unsafe {
+                    match node.to_node_ptr() {
+                        ConcreteNodePtr::Node4(inner_ptr) => {
+                            leave_inner_node(inner_ptr, callbacks, &key_bytes, depth)
+                        },
+                        ConcreteNodePtr::Node16(inner_ptr) => {
+                            leave_inner_node(inner_ptr, callbacks, &key_bytes, depth)
+                        },
+                        ConcreteNodePtr::Node32(inner_ptr) => {
+                            leave_inner_node(inner_ptr, callbacks, &key_bytes, depth)
+                        },
+                        ConcreteNodePtr::Node48(inner_ptr) => {
+                            leave_inner_node(inner_ptr, callbacks, &key_bytes, depth)
+                        },
+                        ConcreteNodePtr::Node256(inner_ptr) => {
+                            leave_inner_node(inner_ptr, callbacks, &key_bytes, depth)
+                        },
+                        ConcreteNodePtr::LeafNode(_) => {
+                            unreachable!("only inner nodes are left after their children")
+                        },
+                    }
+                }?;
+            },
+        }
+    }
+
+    ControlFlow::Continue(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests_common::generate_key_fixed_length;
+    use alloc::{boxed::Box, vec};
+
+    fn fixed_length_tree() -> TreeMap<Box<[u8]>, usize> {
+        let mut tree = TreeMap::new();
+        for (value, key) in generate_key_fixed_length([3, 3]).enumerate() {
+            tree.try_insert(key, value).unwrap();
+        }
+        tree
+    }
+
+    /// Records the key bytes and depth of every callback.
+    #[derive(Default)]
+    struct Recorder {
+        events: Vec<(&'static str, Vec<u8>, usize)>,
+        skip_at_depth: Option<usize>,
+        stop_after_leaves: Option<usize>,
+        num_leaves: usize,
+    }
+
+    impl<K, V, const PREFIX_LEN: usize> TreeVisitor<K, V, PREFIX_LEN> for Recorder {
+        type Break = Vec<u8>;
+
+        fn visit_inner_node<N>(
+            &mut self,
+            _: &N,
+            ctx: &VisitContext<'_>,
+        ) -> ControlFlow<Self::Break, VisitChildren>
+        where
+            N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+        {
+            self.events
+                .push(("enter", ctx.key_bytes.to_vec(), ctx.depth));
+            if self.skip_at_depth == Some(ctx.depth) {
+                ControlFlow::Continue(VisitChildren::Skip)
+            } else {
+                ControlFlow::Continue(VisitChildren::Visit)
+            }
+        }
+
+        fn leave_inner_node<N>(&mut self, _: &N, ctx: &VisitContext<'_>) -> ControlFlow<Self::Break>
+        where
+            N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+        {
+            self.events
+                .push(("leave", ctx.key_bytes.to_vec(), ctx.depth));
+            ControlFlow::Continue(())
+        }
+
+        fn visit_leaf(
+            &mut self,
+            _: &LeafNode<K, V>,
+            ctx: &VisitContext<'_>,
+        ) -> ControlFlow<Self::Break> {
+            self.events
+                .push(("leaf", ctx.key_bytes.to_vec(), ctx.depth));
+            self.num_leaves += 1;
+            if self.stop_after_leaves == Some(self.num_leaves) {
+                ControlFlow::Break(ctx.key_bytes.to_vec())
+            } else {
+                ControlFlow::Continue(())
+            }
+        }
+    }
+
+    #[test]
+    fn walk_passes_key_bytes_and_depth() {
+        let tree = fixed_length_tree();
+        let mut recorder = Recorder::default();
+
+        assert_eq!(walk(&tree, &mut recorder), ControlFlow::Continue(()));
+        // 1 root + 4 inner nodes, each with an enter and a leave, and 16 leaves
+        assert_eq!(recorder.events.len(), 2 * 5 + 16);
+        assert_eq!(recorder.events[0], ("enter", vec![], 0));
+        assert_eq!(recorder.events[1], ("enter", vec![0], 1));
+        assert_eq!(recorder.events[2], ("leaf", vec![0, 0], 2));
+        assert_eq!(recorder.events[5], ("leaf", vec![0, 255], 2));
+        assert_eq!(recorder.events[6], ("leave", vec![0], 1));
+        assert_eq!(recorder.events[7], ("enter", vec![85], 1));
+        assert_eq!(recorder.events.last().unwrap(), &("leave", vec![], 0));
+
+        let leaf_keys: Vec<_> = recorder
+            .events
+            .iter()
+            .filter(|(kind, ..)| *kind == "leaf")
+            .map(|(_, key_bytes, _)| key_bytes.clone().into_boxed_slice())
+            .collect();
+        let tree_keys: Vec<_> = tree.keys().cloned().collect();
+        assert_eq!(leaf_keys, tree_keys);
+    }
+
+    #[test]
+    fn walk_skip_children() {
+        let tree = fixed_length_tree();
+        let mut recorder = Recorder {
+            skip_at_depth: Some(1),
+            ..Default::default()
+        };
+
+        assert_eq!(walk(&tree, &mut recorder), ControlFlow::Continue(()));
+        assert_eq!(recorder.num_leaves, 0);
+        // root enter/leave and 4 child enter/leave
+        assert_eq!(recorder.events.len(), 10);
+        assert_eq!(recorder.events[1], ("enter", vec![0], 1));
+        assert_eq!(recorder.events[2], ("leave", vec![0], 1));
+    }
+
+    #[test]
+    fn walk_stops_early() {
+        let tree = fixed_length_tree();
+        let mut recorder = Recorder {
+            stop_after_leaves: Some(6),
+            ..Default::default()
+        };
+
+        assert_eq!(walk(&tree, &mut recorder), ControlFlow::Break(vec![85, 85]));
+        assert_eq!(recorder.num_leaves, 6);
+        assert_eq!(recorder.events.last().unwrap(), &("leaf", vec![85, 85], 2));
+    }
+
+    #[test]
+    fn walk_empty_tree() {
+        let mut tree: TreeMap<Box<[u8]>, usize> = TreeMap::new();
+        assert_eq!(
+            walk(&tree, &mut Recorder::default()),
+            ControlFlow::Continue(())
+        );
+
+        struct Never;
+
+        impl TreeVisitorMut<Box<[u8]>, usize, 16> for Never {
+            type Break = ();
+
+            fn visit_leaf(
+                &mut self,
+                _: &Box<[u8]>,
+                _: &mut usize,
+                _: &VisitContext<'_>,
+            ) -> ControlFlow<()> {
+                ControlFlow::Break(())
+            }
+        }
+
+        assert_eq!(walk_mut(&mut tree, &mut Never), ControlFlow::Continue(()));
+    }
+
+    #[test]
+    fn walk_mut_modifies_values() {
+        struct Double {
+            stop_at: u8,
+        }
+
+        impl TreeVisitorMut<Box<[u8]>, usize, 16> for Double {
+            type Break = ();
+
+            fn visit_leaf(
+                &mut self,
+                key: &Box<[u8]>,
+                value: &mut usize,
+                ctx: &VisitContext<'_>,
+            ) -> ControlFlow<()> {
+                assert_eq!(key.as_ref(), ctx.key_bytes);
+                if key[0] == self.stop_at {
+                    return ControlFlow::Break(());
+                }
+                *value *= 2;
+                ControlFlow::Continue(())
+            }
+        }
+
+        let mut tree = fixed_length_tree();
+        assert_eq!(
+            walk_mut(&mut tree, &mut Double { stop_at: 170 }),
+            ControlFlow::Break(())
+        );
+
+        let values: Vec<_> = tree.values().copied().collect();
+        assert_eq!(
+            values,
+            [0, 2, 4, 6, 8, 10, 12, 14, 8, 9, 10, 11, 12, 13, 14, 15]
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "std", not(miri)))]
+    fn walk_deep_tree_small_stack() {
+        use crate::visitor::{
+            DotPrinter, TreeStatsCollector, Visitable, Visitor, WellFormedChecker,
+        };
+
+        /// A [`Visitor`] which only overrides the leaf hook, so that the whole
+        /// traversal uses the default inner node hooks.
+        struct KeyBytes;
+
+        impl<const PREFIX_LEN: usize> Visitor<alloc::string::String, usize, PREFIX_LEN> for KeyBytes {
+            type Output = usize;
+
+            fn default_output(&self) -> Self::Output {
+                0
+            }
+
+            fn combine_output(&self, o1: Self::Output, o2: Self::Output) -> Self::Output {
+                o1 + o2
+            }
+
+            fn visit_leaf(&mut self, t: &LeafNode<alloc::string::String, usize>) -> Self::Output {
+                t.key_ref().len()
+            }
+        }
+
+        // Each key differs from the previous ones at a single byte, so there is one
+        // inner node per byte of the longest key.
+        const MAX_KEY_LEN: usize = 4096;
+
+        let mut tree: TreeMap<alloc::string::String, usize> = TreeMap::new();
+        for len in 1..=MAX_KEY_LEN {
+            let key = "a".repeat(len - 1) + "b";
+            tree.try_insert(key, len).unwrap();
+        }
+
+        // A recursive traversal needs far more than this for a tree this deep
+        let handle = std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(move || {
+                assert_eq!(WellFormedChecker::check(&tree), Ok(2 * MAX_KEY_LEN - 1));
+
+                let stats = TreeStatsCollector::collect(&tree).unwrap();
+                assert_eq!(stats.node4.count, MAX_KEY_LEN - 1);
+                assert_eq!(stats.leaf.count, MAX_KEY_LEN);
+                assert_eq!(TreeStatsCollector::count_leaf_nodes(&tree), MAX_KEY_LEN);
+
+                let mut buffer = Vec::new();
+                DotPrinter::print(&mut buffer, &tree, Default::default())
+                    .unwrap()
+                    .unwrap();
+                assert!(buffer.len() > MAX_KEY_LEN);
+
+                let key_bytes = tree.root.unwrap().visit_with(&mut KeyBytes);
+                assert_eq!(key_bytes, MAX_KEY_LEN * (MAX_KEY_LEN + 1) / 2);
+
+                let mut recorder = Recorder::default();
+                assert_eq!(walk(&tree, &mut recorder), ControlFlow::Continue(()));
+                let max_depth = recorder.events.iter().map(|(_, _, depth)| *depth).max();
+                assert_eq!(max_depth, Some(MAX_KEY_LEN - 1));
+
+                tree
+            })
+            .unwrap();
+
+        drop(handle.join().unwrap());
+    }
+}
@@ -1,11 +1,16 @@
 use crate::{
-    nodes::visitor::{Visitable, Visitor},
-    AsBytes, InnerNode, NodeType, OpaqueNodePtr, TreeMap,
+    nodes::visitor::{walk_tree, TreeVisitor, VisitChildren, VisitContext},
+    AsBytes, InnerNode, InnerNode16, InnerNode32, InnerNode4, InnerNode48, LeafNode, NodeType,
+    OpaqueNodePtr, ShrinkPolicy, TreeMap,
 };
-use std::{
-    collections::{hash_map::Entry, HashMap},
-    error::Error,
+use alloc::{
+    boxed::Box,
+    collections::{btree_map::Entry, BTreeMap},
+    vec::Vec,
+};
+use core::{
     fmt,
+    ops::{ControlFlow, Range},
 };
 
 /// A portion of an entire key that should uniquely identify each node in
@@ -51,6 +56,47 @@ pub enum MalformedTreeError<K, V, const PREFIX_LEN: usize> {
         inner_node_type: NodeType,
         /// The number of children found at the inner node
         num_children: usize,
+        /// The range of number of children allowed for the inner node type
+        /// under the [`ShrinkPolicy`] of the tree
+        expected_range: Range<usize>,
+    },
+    /// The number of children in the header of an inner node did not match
+    /// the number of child pointers in the node
+    ChildrenCountMismatch {
+        /// The key prefix identifying the inner node
+        key_prefix: KeyPrefix,
+        /// The type of the inner node (InnerNode4, InnerNode16, etc)
+        ///
+        /// This field is guaranteed not to be [`NodeType::Leaf`]
+        inner_node_type: NodeType,
+        /// The number of children in the header of the inner node
+        header_num_children: usize,
+        /// The number of child pointers found in the inner node
+        num_children: usize,
+    },
+    /// The key bytes of an InnerNode4, InnerNode16, or InnerNode32 were not
+    /// sorted in strictly increasing order
+    UnsortedKeys {
+        /// The key prefix identifying the inner node
+        key_prefix: KeyPrefix,
+        /// The type of the inner node (InnerNode4, InnerNode16, or
+        /// InnerNode32)
+        inner_node_type: NodeType,
+        /// The key bytes of the inner node, in the order they are stored
+        keys: Box<[u8]>,
+    },
+    /// A key byte of an InnerNode48 mapped to an index outside of the
+    /// initialized child pointers, or to an index which is also used by
+    /// another key byte
+    InvalidChildIndex {
+        /// The key prefix identifying the inner node
+        key_prefix: KeyPrefix,
+        /// The key byte with the invalid index
+        key_fragment: u8,
+        /// The index in the child pointers array
+        child_index: usize,
+        /// The number of children in the header of the inner node
+        num_children: usize,
     },
     /// The expected key prefix did not match the actual prefix that was present
     /// in the leaf
@@ -60,9 +106,24 @@ pub enum MalformedTreeError<K, V, const PREFIX_LEN: usize> {
         /// The entire key
         entire_key: K,
     },
+    /// The key of a leaf was a prefix of (or equal to) the key of the next
+    /// leaf in the tree
+    KeyIsPrefix {
+        /// The key of the earlier leaf
+        prefix: KeyPrefix,
+        /// The entire key of the later leaf
+        entire_key: K,
+    },
     /// The length of the tree is not 0, even though the root is
     /// [`Option::None`]
     EmptyTreeWithLen,
+    /// The length of the tree did not match the number of leaves in the tree
+    LenMismatch {
+        /// The length of the tree
+        len: usize,
+        /// The number of leaves found in the tree
+        num_leaves: usize,
+    },
 }
 
 impl<K, V, const PREFIX_LEN: usize> fmt::Debug for MalformedTreeError<K, V, PREFIX_LEN>
@@ -85,11 +146,47 @@ where
                 key_prefix,
                 inner_node_type,
                 num_children,
+                expected_range,
             } => f
                 .debug_struct("WrongChildrenCount")
                 .field("key_prefix", key_prefix)
                 .field("inner_node_type", inner_node_type)
                 .field("num_children", num_children)
+                .field("expected_range", expected_range)
+                .finish(),
+            Self::ChildrenCountMismatch {
+                key_prefix,
+                inner_node_type,
+                header_num_children,
+                num_children,
+            } => f
+                .debug_struct("ChildrenCountMismatch")
+                .field("key_prefix", key_prefix)
+                .field("inner_node_type", inner_node_type)
+                .field("header_num_children", header_num_children)
+                .field("num_children", num_children)
+                .finish(),
+            Self::UnsortedKeys {
+                key_prefix,
+                inner_node_type,
+                keys,
+            } => f
+                .debug_struct("UnsortedKeys")
+                .field("key_prefix", key_prefix)
+                .field("inner_node_type", inner_node_type)
+                .field("keys", keys)
+                .finish(),
+            Self::InvalidChildIndex {
+                key_prefix,
+                key_fragment,
+                child_index,
+                num_children,
+            } => f
+                .debug_struct("InvalidChildIndex")
+                .field("key_prefix", key_prefix)
+                .field("key_fragment", key_fragment)
+                .field("child_index", child_index)
+                .field("num_children", num_children)
                 .finish(),
             Self::PrefixMismatch {
                 expected_prefix,
@@ -99,7 +196,17 @@ where
                 .field("expected_prefix", expected_prefix)
                 .field("entire_key", &entire_key.as_bytes() as &dyn fmt::Debug)
                 .finish(),
+            Self::KeyIsPrefix { prefix, entire_key } => f
+                .debug_struct("KeyIsPrefix")
+                .field("prefix", prefix)
+                .field("entire_key", &entire_key.as_bytes() as &dyn fmt::Debug)
+                .finish(),
             Self::EmptyTreeWithLen => f.debug_struct("EmptyTreeWithLen").finish(),
+            Self::LenMismatch { len, num_leaves } => f
+                .debug_struct("LenMismatch")
+                .field("len", len)
+                .field("num_leaves", num_leaves)
+                .finish(),
         }
     }
 }
@@ -126,13 +233,51 @@ where
                 key_prefix,
                 inner_node_type,
                 num_children,
+                expected_range,
             } => {
                 write!(
                     f,
                     "Found an inner node of type [{inner_node_type:?}] at location \
                      [{key_prefix:?}] that had the wrong number of children! Expected children in \
-                     range [{:?}], but found [{num_children}] children",
-                    inner_node_type.capacity_range(),
+                     range [{expected_range:?}], but found [{num_children}] children",
+                )
+            },
+            MalformedTreeError::ChildrenCountMismatch {
+                key_prefix,
+                inner_node_type,
+                header_num_children,
+                num_children,
+            } => {
+                write!(
+                    f,
+                    "Found an inner node of type [{inner_node_type:?}] at location \
+                     [{key_prefix:?}] whose header had [{header_num_children}] children, but \
+                     found [{num_children}] child pointers in the node",
+                )
+            },
+            MalformedTreeError::UnsortedKeys {
+                key_prefix,
+                inner_node_type,
+                keys,
+            } => {
+                write!(
+                    f,
+                    "Found an inner node of type [{inner_node_type:?}] at location \
+                     [{key_prefix:?}] whose key bytes were not sorted! Found keys [{keys:?}]",
+                )
+            },
+            MalformedTreeError::InvalidChildIndex {
+                key_prefix,
+                key_fragment,
+                child_index,
+                num_children,
+            } => {
+                write!(
+                    f,
+                    "Found an InnerNode48 at location [{key_prefix:?}] where the key byte \
+                     [{key_fragment}] mapped to the child index [{child_index}], which is either \
+                     not less than the number of children [{num_children}] or is used by another \
+                     key byte",
                 )
             },
             MalformedTreeError::PrefixMismatch {
@@ -146,12 +291,26 @@ where
                     entire_key.as_bytes()
                 )
             },
+            MalformedTreeError::KeyIsPrefix { prefix, entire_key } => {
+                write!(
+                    f,
+                    "Found a leaf key [{prefix:?}] that was a prefix of the next leaf key [{:?}]",
+                    entire_key.as_bytes()
+                )
+            },
             MalformedTreeError::EmptyTreeWithLen => {
                 write!(
                     f,
                     "The length of the tree is not 0, even though the root is None",
                 )
             },
+            MalformedTreeError::LenMismatch { len, num_leaves } => {
+                write!(
+                    f,
+                    "The length of the tree is [{len}], but found [{num_leaves}] leaves in the \
+                     tree",
+                )
+            },
         }
     }
 }
@@ -172,10 +331,43 @@ impl<K: Clone, V, const PREFIX_LEN: usize> Clone for MalformedTreeError<K, V, PR
                 key_prefix,
                 inner_node_type,
                 num_children,
+                expected_range,
             } => Self::WrongChildrenCount {
                 key_prefix: key_prefix.clone(),
                 inner_node_type: *inner_node_type,
                 num_children: *num_children,
+                expected_range: expected_range.clone(),
+            },
+            Self::ChildrenCountMismatch {
+                key_prefix,
+                inner_node_type,
+                header_num_children,
+                num_children,
+            } => Self::ChildrenCountMismatch {
+                key_prefix: key_prefix.clone(),
+                inner_node_type: *inner_node_type,
+                header_num_children: *header_num_children,
+                num_children: *num_children,
+            },
+            Self::UnsortedKeys {
+                key_prefix,
+                inner_node_type,
+                keys,
+            } => Self::UnsortedKeys {
+                key_prefix: key_prefix.clone(),
+                inner_node_type: *inner_node_type,
+                keys: keys.clone(),
+            },
+            Self::InvalidChildIndex {
+                key_prefix,
+                key_fragment,
+                child_index,
+                num_children,
+            } => Self::InvalidChildIndex {
+                key_prefix: key_prefix.clone(),
+                key_fragment: *key_fragment,
+                child_index: *child_index,
+                num_children: *num_children,
             },
             Self::PrefixMismatch {
                 expected_prefix,
@@ -184,33 +376,82 @@ impl<K: Clone, V, const PREFIX_LEN: usize> Clone for MalformedTreeError<K, V, PR
                 expected_prefix: expected_prefix.clone(),
                 entire_key: entire_key.clone(),
             },
+            Self::KeyIsPrefix { prefix, entire_key } => Self::KeyIsPrefix {
+                prefix: prefix.clone(),
+                entire_key: entire_key.clone(),
+            },
             Self::EmptyTreeWithLen => Self::EmptyTreeWithLen,
+            Self::LenMismatch { len, num_leaves } => Self::LenMismatch {
+                len: *len,
+                num_leaves: *num_leaves,
+            },
         }
     }
 }
 
-impl<K: AsBytes, V, const PREFIX_LEN: usize> Error for MalformedTreeError<K, V, PREFIX_LEN> {}
+#[cfg(feature = "std")]
+impl<K: AsBytes, V, const PREFIX_LEN: usize> std::error::Error
+    for MalformedTreeError<K, V, PREFIX_LEN>
+{
+}
 
 /// A visitor of the radix tree which checks that the tree is well-formed.
 ///
 /// In this context, well-formed means that in the tree:
 ///  1. there are no loops between nodes
 ///  2. every inner node has a number of children that is in range for the inner
-///     node type. For example, InnerNode16 has between 5 and 16 children.
-///  3. the elements of the key (as part of inner node prefixes and child
+///     node type and the [`ShrinkPolicy`] of the tree. For example, with the
+///     default policy InnerNode16 has between 5 and 16 children, so every node
+///     is the smallest node type that can hold its children.
+///  3. the number of children in the header of every inner node matches the
+///     child pointers in the node, the key bytes of InnerNode4, InnerNode16,
+///     and InnerNode32 are sorted, and every key byte of an InnerNode48 maps to
+///     a distinct initialized child pointer
+///  4. the elements of the key (as part of inner node prefixes and child
 ///     pointers) combine to match the leaf node key prefix
+///  5. no leaf key is a prefix of another leaf key
+///  6. the length of the tree matches the number of leaves
 ///
-/// #1 and #3 are unlikely, but #2 is a possibility if specific tree operations
-/// are not implemented correctly. This visitor can be used to sanity check the
-/// tree in unit tests or other test cases.
+/// #1, #3, #4, and #5 are unlikely, but #2 and #6 are a possibility if specific
+/// tree operations are not implemented correctly. This visitor can be used to
+/// sanity check the tree in unit tests or other test cases.
 ///
-/// This checker will only return a single issue at a time. A tree is only
-/// "well-formed" (by the definition given above) if the checker returns
-/// `Ok(())`.
-#[derive(Debug)]
+/// [`WellFormedChecker::check`] only returns the first issue it finds. A tree
+/// is only "well-formed" (by the definition given above) if the checker
+/// returns `Ok(_)`. [`WellFormedChecker::check_all`] keeps going after an
+/// issue and returns all of them, except that it does not descend into the
+/// children of a node when they cannot be read safely (the node has too many
+/// children, an invalid InnerNode48 index, or a child which was already
+/// visited).
 pub struct WellFormedChecker<K, V, const PREFIX_LEN: usize> {
-    current_key_prefix: Vec<u8>,
-    seen_nodes: HashMap<OpaqueNodePtr<K, V, PREFIX_LEN>, KeyPrefix>,
+    seen_nodes: BTreeMap<OpaqueNodePtr<K, V, PREFIX_LEN>, KeyPrefix>,
+    shrink_policy: ShrinkPolicy,
+    num_nodes: usize,
+    num_leaves: usize,
+    /// The key of the last leaf visited, in order to check that it is not a
+    /// prefix of the next leaf key
+    previous_key: Option<Vec<u8>>,
+    collect_all: bool,
+    skipped_children: bool,
+    errors: Vec<MalformedTreeError<K, V, PREFIX_LEN>>,
+}
+
+impl<K, V, const PREFIX_LEN: usize> fmt::Debug for WellFormedChecker<K, V, PREFIX_LEN>
+where
+    K: AsBytes,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WellFormedChecker")
+            .field("seen_nodes", &self.seen_nodes)
+            .field("shrink_policy", &self.shrink_policy)
+            .field("num_nodes", &self.num_nodes)
+            .field("num_leaves", &self.num_leaves)
+            .field("previous_key", &self.previous_key)
+            .field("collect_all", &self.collect_all)
+            .field("skipped_children", &self.skipped_children)
+            .field("errors", &self.errors)
+            .finish()
+    }
 }
 
 impl<K, V, const PREFIX_LEN: usize> WellFormedChecker<K, V, PREFIX_LEN>
@@ -221,27 +462,61 @@ where
     /// number of nodes in the tree.
     ///
     /// # Errors
-    ///  - Returns an error if the given tree is not well-formed.
+    ///  - Returns the first issue found if the given tree is not well-formed.
     pub fn check(
         tree: &TreeMap<K, V, PREFIX_LEN>,
     ) -> Result<usize, MalformedTreeError<K, V, PREFIX_LEN>> {
-        tree.root
-            .map(|root| {
-                // SAFETY: Since we get a reference to the TreeMap, we know no
-                // mutation can happen to any of the nodes
-                unsafe { Self::check_tree(root) }
-            })
-            .unwrap_or_else(|| {
-                if tree.is_empty() {
-                    Ok(0)
-                } else {
-                    Err(MalformedTreeError::EmptyTreeWithLen)
-                }
-            })
+        Self::check_map(tree, false).map_err(|errors| {
+            errors
+                .into_iter()
+                .next()
+                .expect("should have at least one error")
+        })
     }
 
-    /// Traverse the given tree and check that it is well-formed. Returns the
-    /// number of nodes in the tree.
+    /// Traverse the given tree and check that it is well-formed, without
+    /// stopping at the first issue. Returns the number of nodes in the tree.
+    ///
+    /// # Errors
+    ///  - Returns all the issues found if the given tree is not well-formed.
+    pub fn check_all(
+        tree: &TreeMap<K, V, PREFIX_LEN>,
+    ) -> Result<usize, Vec<MalformedTreeError<K, V, PREFIX_LEN>>> {
+        Self::check_map(tree, true)
+    }
+
+    fn check_map(
+        tree: &TreeMap<K, V, PREFIX_LEN>,
+        collect_all: bool,
+    ) -> Result<usize, Vec<MalformedTreeError<K, V, PREFIX_LEN>>> {
+        let Some(root) = tree.root else {
+            return if tree.is_empty() {
+                Ok(0)
+            } else {
+                Err(Vec::from([MalformedTreeError::EmptyTreeWithLen]))
+            };
+        };
+
+        // SAFETY: Since we get a reference to the TreeMap, we know no
+        // mutation can happen to any of the nodes
+        let mut checker = unsafe { Self::walk(root, tree.shrink_policy(), collect_all) };
+
+        // The leaf count is only complete if the whole tree was traversed
+        if (checker.errors.is_empty() || !checker.skipped_children)
+            && checker.num_leaves != tree.len()
+        {
+            let _ = checker.report(MalformedTreeError::LenMismatch {
+                len: tree.len(),
+                num_leaves: checker.num_leaves,
+            });
+        }
+
+        checker.into_result()
+    }
+
+    /// Traverse the given tree and check that it is well-formed, for a tree
+    /// using the given [`ShrinkPolicy`]. Returns the number of nodes in the
+    /// tree.
     ///
     /// # Safety
     ///  - For the duration of this function, the given node and all its
@@ -249,146 +524,305 @@ where
     ///
     /// # Errors
     ///  - Returns an error if the given tree is not well-formed.
-    unsafe fn check_tree(
+    #[cfg(test)]
+    pub(crate) unsafe fn check_tree(
         tree: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        shrink_policy: ShrinkPolicy,
     ) -> Result<usize, MalformedTreeError<K, V, PREFIX_LEN>> {
+        // SAFETY: Covered by the safety requirements of this function
+        unsafe { Self::walk(tree, shrink_policy, false) }
+            .into_result()
+            .map_err(|errors| {
+                errors
+                    .into_iter()
+                    .next()
+                    .expect("should have at least one error")
+            })
+    }
+
+    /// Run the checker over the given tree.
+    ///
+    /// # Safety
+    ///  - For the duration of this function, the given node and all its
+    ///    children nodes must not get mutated.
+    unsafe fn walk(
+        tree: OpaqueNodePtr<K, V, PREFIX_LEN>,
+        shrink_policy: ShrinkPolicy,
+        collect_all: bool,
+    ) -> Self {
         let mut visitor = WellFormedChecker {
-            current_key_prefix: vec![],
-            seen_nodes: HashMap::new(),
+            seen_nodes: BTreeMap::new(),
+            shrink_policy,
+            num_nodes: 0,
+            num_leaves: 0,
+            previous_key: None,
+            collect_all,
+            skipped_children: false,
+            errors: Vec::new(),
         };
 
         // We see the root node at the empty prefix
         visitor.seen_nodes.insert(tree, KeyPrefix::default());
 
-        tree.visit_with(&mut visitor)
+        // SAFETY: Covered by the safety requirements of this function
+        let _ = unsafe { walk_tree(tree, &mut visitor) };
+
+        visitor
     }
 
+    fn into_result(self) -> Result<usize, Vec<MalformedTreeError<K, V, PREFIX_LEN>>> {
+        if self.errors.is_empty() {
+            Ok(self.num_nodes)
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    /// Record the issue, and stop the traversal unless all issues are being
+    /// collected.
+    fn report(&mut self, error: MalformedTreeError<K, V, PREFIX_LEN>) -> ControlFlow<()> {
+        self.errors.push(error);
+        if self.collect_all {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    }
+
+    /// Check the layout specific to the type of the inner node. Returns
+    /// whether the children of the node can be safely read.
+    fn check_node_layout<N>(&mut self, inner_node: &N, key_prefix: &[u8]) -> ControlFlow<(), bool>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        if let Some(node) = downcast_ref::<N, InnerNode4<K, V, PREFIX_LEN>, PREFIX_LEN>(inner_node)
+        {
+            self.check_sorted_keys(node.initialized_portion().0, N::TYPE, key_prefix)?;
+        } else if let Some(node) =
+            downcast_ref::<N, InnerNode16<K, V, PREFIX_LEN>, PREFIX_LEN>(inner_node)
+        {
+            self.check_sorted_keys(node.initialized_portion().0, N::TYPE, key_prefix)?;
+        } else if let Some(node) =
+            downcast_ref::<N, InnerNode32<K, V, PREFIX_LEN>, PREFIX_LEN>(inner_node)
+        {
+            self.check_sorted_keys(node.initialized_portion().0, N::TYPE, key_prefix)?;
+        } else if let Some(node) =
+            downcast_ref::<N, InnerNode48<K, V, PREFIX_LEN>, PREFIX_LEN>(inner_node)
+        {
+            return self.check_child_indices(node, key_prefix);
+        }
+
+        ControlFlow::Continue(true)
+    }
+
+    fn check_sorted_keys(
+        &mut self,
+        keys: &[u8],
+        inner_node_type: NodeType,
+        key_prefix: &[u8],
+    ) -> ControlFlow<()> {
+        if keys.windows(2).any(|pair| pair[0] >= pair[1]) {
+            self.report(MalformedTreeError::UnsortedKeys {
+                key_prefix: key_prefix.into(),
+                inner_node_type,
+                keys: keys.into(),
+            })?;
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Returns whether all the child indices are valid.
+    fn check_child_indices(
+        &mut self,
+        inner_node: &InnerNode48<K, V, PREFIX_LEN>,
+        key_prefix: &[u8],
+    ) -> ControlFlow<(), bool> {
+        let num_children = inner_node.header.num_children();
+        let mut used = [false; 48];
+        let mut all_valid = true;
+        for (key_fragment, child_index) in (0..=u8::MAX).zip(inner_node.child_indices) {
+            if child_index.is_empty() {
+                continue;
+            }
+
+            let child_index = usize::from(child_index);
+            if child_index >= num_children || used[child_index] {
+                all_valid = false;
+                self.report(MalformedTreeError::InvalidChildIndex {
+                    key_prefix: key_prefix.into(),
+                    key_fragment,
+                    child_index,
+                    num_children,
+                })?;
+            } else {
+                used[child_index] = true;
+            }
+        }
+
+        ControlFlow::Continue(all_valid)
+    }
+}
+
+/// Reinterpret the inner node as the concrete node type `M`, if they are the
+/// same node type.
+fn downcast_ref<N, M, const PREFIX_LEN: usize>(inner_node: &N) -> Option<&M>
+where
+    N: InnerNode<PREFIX_LEN>,
+    M: InnerNode<PREFIX_LEN, Key = N::Key, Value = N::Value>,
+{
+    if N::TYPE == M::TYPE {
+        // SAFETY: Each node type is implemented by a single concrete type for a
+        // given key, value, and prefix length, the same property that
+        // `OpaqueNodePtr` relies on to cast to the concrete node pointers.
+        Some(unsafe { &*(inner_node as *const N).cast::<M>() })
+    } else {
+        None
+    }
+}
+
+impl<K, V, const PREFIX_LEN: usize> TreeVisitor<K, V, PREFIX_LEN>
+    for WellFormedChecker<K, V, PREFIX_LEN>
+where
+    K: Clone + AsBytes,
+{
+    type Break = ();
+
     fn visit_inner_node<N>(
         &mut self,
         inner_node: &N,
-    ) -> Result<usize, MalformedTreeError<K, V, PREFIX_LEN>>
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break, VisitChildren>
     where
         N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
     {
-        let original_key_prefix_len = self.current_key_prefix.len();
-
-        // update running key prefix with inner node partial prefix
-        // TODO: Fix this, here the we should return the full reconstructed prefix if
-        // prefix len > PREFIX_LEN
-        self.current_key_prefix
-            .extend(inner_node.read_full_prefix(original_key_prefix_len).0);
+        self.num_nodes += 1;
+
+        // The number of children is checked against the node type once the children
+        // have been visited, but the children cannot be read if there are too many
+        let header_num_children = inner_node.header().num_children();
+        let can_read_children = header_num_children <= N::TYPE.upper_capacity()
+            && self.check_node_layout(inner_node, ctx.key_bytes)?;
+        if !can_read_children {
+            self.skipped_children = true;
+            return ControlFlow::Continue(VisitChildren::Skip);
+        }
 
-        // SAFETY: The `child_it` does not live beyond the following loop and will not
-        // overlap with any mutating access or operation, which is guaranteed by the
-        // `check_tree` caller requirements.
-        let child_it = inner_node.iter();
+        // Check the children for loops before the traversal descends into them
+        let mut child_key_prefix = ctx.key_bytes.to_vec();
+        child_key_prefix.extend(inner_node.read_full_prefix(ctx.key_bytes.len()).0);
 
-        let mut running_node_count = 0;
+        let mut found_loop = false;
         let mut num_children: usize = 0;
-        for (key_byte, child_pointer) in child_it {
-            // update running key prefix with child pointer key fragment
-            self.current_key_prefix.push(key_byte);
+        for (key_byte, child_pointer) in inner_node.iter() {
+            child_key_prefix.push(key_byte);
 
-            let current_key_prefix: KeyPrefix = self.current_key_prefix.as_slice().into();
+            let current_key_prefix: KeyPrefix = child_key_prefix.as_slice().into();
 
             match self.seen_nodes.entry(child_pointer) {
                 Entry::Occupied(entry) => {
-                    return Err(MalformedTreeError::LoopFound {
+                    found_loop = true;
+                    let first_observed = entry.get().clone();
+                    self.report(MalformedTreeError::LoopFound {
                         node_ptr: child_pointer,
-                        first_observed: entry.get().clone(),
+                        first_observed,
                         later_observed: current_key_prefix,
-                    });
+                    })?;
                 },
                 Entry::Vacant(entry) => {
                     entry.insert(current_key_prefix);
                 },
             }
 
-            running_node_count += child_pointer.visit_with(self)?;
-
-            // remove child pointer key fragment
-            assert_eq!(
-                self.current_key_prefix
-                    .pop()
-                    .expect("should match push of key byte"),
-                key_byte
-            );
-
+            child_key_prefix.pop();
             num_children += 1;
         }
 
-        // remove inner node partial key prefix
-        self.current_key_prefix.truncate(original_key_prefix_len);
-
-        if !(N::TYPE.capacity_range().contains(&num_children)) {
-            let current_key_prefix: KeyPrefix = self.current_key_prefix.as_slice().into();
-            return Err(MalformedTreeError::WrongChildrenCount {
-                key_prefix: current_key_prefix,
+        if num_children != header_num_children {
+            self.report(MalformedTreeError::ChildrenCountMismatch {
+                key_prefix: ctx.key_bytes.into(),
                 inner_node_type: N::TYPE,
+                header_num_children,
                 num_children,
-            });
+            })?;
         }
 
-        Ok(running_node_count + 1)
-    }
-}
-
-impl<K, V, const PREFIX_LEN: usize> Visitor<K, V, PREFIX_LEN>
-    for WellFormedChecker<K, V, PREFIX_LEN>
-where
-    K: Clone + AsBytes,
-{
-    type Output = Result<usize, MalformedTreeError<K, V, PREFIX_LEN>>;
-
-    fn default_output(&self) -> Self::Output {
-        // Chose zero so that any places that call `default_output` don't influence the
-        // overall count
-        Ok(0)
-    }
-
-    fn combine_output(&self, o1: Self::Output, o2: Self::Output) -> Self::Output {
-        Ok(o1? + o2?)
-    }
-
-    fn visit_node4(&mut self, t: &crate::InnerNode4<K, V, PREFIX_LEN>) -> Self::Output {
-        self.visit_inner_node(t)
+        if found_loop {
+            self.skipped_children = true;
+            ControlFlow::Continue(VisitChildren::Skip)
+        } else {
+            ControlFlow::Continue(VisitChildren::Visit)
+        }
     }
 
-    fn visit_node16(&mut self, t: &crate::InnerNode16<K, V, PREFIX_LEN>) -> Self::Output {
-        self.visit_inner_node(t)
-    }
+    fn leave_inner_node<N>(
+        &mut self,
+        inner_node: &N,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break>
+    where
+        N: InnerNode<PREFIX_LEN, Key = K, Value = V>,
+    {
+        let num_children = inner_node.header().num_children();
+        let expected_range = self.shrink_policy.capacity_range(N::TYPE);
+        if !(expected_range.contains(&num_children)) {
+            self.report(MalformedTreeError::WrongChildrenCount {
+                key_prefix: ctx.key_bytes.into(),
+                inner_node_type: N::TYPE,
+                num_children,
+                expected_range,
+            })?;
+        }
 
-    fn visit_node48(&mut self, t: &crate::InnerNode48<K, V, PREFIX_LEN>) -> Self::Output {
-        self.visit_inner_node(t)
+        ControlFlow::Continue(())
     }
 
-    fn visit_node256(&mut self, t: &crate::InnerNode256<K, V, PREFIX_LEN>) -> Self::Output {
-        self.visit_inner_node(t)
-    }
+    fn visit_leaf(
+        &mut self,
+        t: &LeafNode<K, V>,
+        ctx: &VisitContext<'_>,
+    ) -> ControlFlow<Self::Break> {
+        self.num_nodes += 1;
+        self.num_leaves += 1;
+
+        let key = t.key_ref().as_bytes();
+        if !key.starts_with(ctx.key_bytes) {
+            self.report(MalformedTreeError::PrefixMismatch {
+                expected_prefix: ctx.key_bytes.into(),
+                entire_key: t.key_ref().clone(),
+            })?;
+        }
 
-    fn visit_leaf(&mut self, t: &crate::LeafNode<K, V>) -> Self::Output {
-        if !t.key_ref().as_bytes().starts_with(&self.current_key_prefix) {
-            let current_key_prefix: KeyPrefix = self.current_key_prefix.as_slice().into();
-            return Err(MalformedTreeError::PrefixMismatch {
-                expected_prefix: current_key_prefix,
+        // The leaves are visited in key order, so if any earlier key is a prefix of
+        // this one then so is the previous key.
+        let previous_key = self.previous_key.get_or_insert_with(Vec::new);
+        if !previous_key.is_empty() && key.starts_with(previous_key) {
+            let prefix = previous_key.as_slice().into();
+            self.report(MalformedTreeError::KeyIsPrefix {
+                prefix,
                 entire_key: t.key_ref().clone(),
-            });
+            })?;
         }
 
-        Ok(1)
+        let previous_key = self.previous_key.get_or_insert_with(Vec::new);
+        previous_key.clear();
+        previous_key.extend_from_slice(key);
+
+        ControlFlow::Continue(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::ffi::CString;
+    use alloc::ffi::CString;
 
     use super::*;
     use crate::{
         deallocate_tree,
         tests_common::{generate_key_fixed_length, setup_tree_from_entries},
-        InnerNode16, InnerNode4, LeafNode, NodePtr, TreeMap,
+        InnerNode16, InnerNode4, LeafNode, NodePtr, RestrictedNodeIndex, TreeMap,
     };
+    use core::mem::MaybeUninit;
 
     #[test]
     fn check_well_formed_tree() {
@@ -404,11 +838,25 @@ mod tests {
         // 4  * 3 * 2
         assert_eq!(num_leaves, 24);
 
-        assert_eq!(unsafe { WellFormedChecker::check_tree(root) }, Ok(41));
+        assert_eq!(
+            unsafe { WellFormedChecker::check_tree(root, ShrinkPolicy::Eager) },
+            Ok(41)
+        );
 
         unsafe { deallocate_tree(root) };
     }
 
+    #[test]
+    fn check_well_formed_full_node256() {
+        let mut tree: TreeMap<[u8; 1], u8> = TreeMap::new();
+        for byte in 0..=255u8 {
+            tree.insert([byte], byte);
+        }
+
+        // The root node and one leaf for each of the 256 children
+        assert_eq!(WellFormedChecker::check(&tree), Ok(257));
+    }
+
     #[test]
     fn check_well_formed_tree_long_prefix() {
         let mut tree: TreeMap<CString, i32> = TreeMap::new();
@@ -464,8 +912,9 @@ mod tests {
             n16.write_child(4, n4_right_ptr.to_opaque());
         }
 
-        let check_result = unsafe { WellFormedChecker::check_tree(root.to_opaque()) }
-            .expect_err("should have failed for loop");
+        let check_result =
+            unsafe { WellFormedChecker::check_tree(root.to_opaque(), ShrinkPolicy::Eager) }
+                .expect_err("should have failed for loop");
         match check_result {
             MalformedTreeError::LoopFound {
                 node_ptr,
@@ -535,22 +984,30 @@ mod tests {
 
         let root = NodePtr::from(&mut n16).to_opaque();
 
-        let check_result = unsafe { WellFormedChecker::check_tree(root) }
+        let check_result = unsafe { WellFormedChecker::check_tree(root, ShrinkPolicy::Eager) }
             .expect_err("should have failed for loop");
         match check_result {
             MalformedTreeError::WrongChildrenCount {
                 key_prefix,
                 inner_node_type,
                 num_children,
+                expected_range,
             } => {
                 assert_eq!(key_prefix, []);
                 assert_eq!(inner_node_type, NodeType::Node16);
                 assert_eq!(num_children, 2);
+                assert_eq!(expected_range, 5..17);
             },
             _ => {
                 panic!("expected a WrongChildrenCount error")
             },
         }
+
+        // An `InnerNode16` with 2 children is fine if nodes are not shrunk
+        assert_eq!(
+            unsafe { WellFormedChecker::check_tree(root, ShrinkPolicy::Lazy) },
+            Ok(7)
+        );
     }
 
     #[test]
@@ -584,7 +1041,7 @@ mod tests {
 
         let root = NodePtr::from(&mut n16).to_opaque();
 
-        let check_result = unsafe { WellFormedChecker::check_tree(root) }
+        let check_result = unsafe { WellFormedChecker::check_tree(root, ShrinkPolicy::Eager) }
             .expect_err("should have failed for loop");
         match check_result {
             MalformedTreeError::PrefixMismatch {
@@ -599,4 +1056,142 @@ mod tests {
             },
         }
     }
+
+    #[test]
+    fn check_all_collects_every_issue() {
+        let l1_ptr = NodePtr::allocate_node_ptr(LeafNode::new(Box::<[u8]>::from([1, 2]), 12));
+        let l2_ptr = NodePtr::allocate_node_ptr(LeafNode::new(Box::<[u8]>::from([1, 2, 3]), 123));
+
+        let mut n4: InnerNode4<Box<[u8]>, i32, 16> = InnerNode4::from_prefix(&[1], 1);
+        n4.write_child(2, l1_ptr.to_opaque());
+        n4.write_child(3, l2_ptr.to_opaque());
+        // Corrupt the second key byte, so that both leaves are found under the same
+        // key byte
+        n4.keys[1] = MaybeUninit::new(2);
+
+        // The tree length is left at 0
+        let mut tree = TreeMap::new();
+        tree.root = Some(NodePtr::allocate_node_ptr(n4).to_opaque());
+
+        let unsorted_keys = MalformedTreeError::UnsortedKeys {
+            key_prefix: KeyPrefix::default(),
+            inner_node_type: NodeType::Node4,
+            keys: Box::from([2, 2]),
+        };
+        assert_eq!(WellFormedChecker::check(&tree), Err(unsorted_keys.clone()));
+        assert_eq!(
+            WellFormedChecker::check_all(&tree),
+            Err(Vec::from([
+                unsorted_keys,
+                MalformedTreeError::KeyIsPrefix {
+                    prefix: [1, 2].as_slice().into(),
+                    entire_key: Box::from([1, 2, 3]),
+                },
+                MalformedTreeError::LenMismatch {
+                    len: 0,
+                    num_leaves: 2,
+                },
+            ]))
+        );
+    }
+
+    #[test]
+    fn check_tree_with_invalid_node48_index() {
+        let mut l1 = LeafNode::new(Box::new([1, 1]), 11);
+        let mut l2 = LeafNode::new(Box::new([1, 2]), 12);
+
+        let l1_ptr: OpaqueNodePtr<Box<[u8; 2]>, i32, 16> = NodePtr::from(&mut l1).to_opaque();
+        let l2_ptr = NodePtr::from(&mut l2).to_opaque();
+
+        let mut n48 = InnerNode48::from_prefix(&[1], 1);
+        n48.write_child(1, l1_ptr);
+        n48.write_child(2, l2_ptr);
+
+        let root = NodePtr::from(&mut n48).to_opaque();
+        assert_eq!(
+            unsafe { WellFormedChecker::check_tree(root, ShrinkPolicy::Lazy) },
+            Ok(3)
+        );
+
+        // An index past the initialized child pointers
+        n48.child_indices[3] = RestrictedNodeIndex::try_from(5u8).unwrap();
+        let root = NodePtr::from(&mut n48).to_opaque();
+        assert_eq!(
+            unsafe { WellFormedChecker::check_tree(root, ShrinkPolicy::Lazy) },
+            Err(MalformedTreeError::InvalidChildIndex {
+                key_prefix: KeyPrefix::default(),
+                key_fragment: 3,
+                child_index: 5,
+                num_children: 2,
+            })
+        );
+
+        // An index shared with another key byte
+        n48.child_indices[3] = n48.child_indices[1];
+        let root = NodePtr::from(&mut n48).to_opaque();
+        assert_eq!(
+            unsafe { WellFormedChecker::check_tree(root, ShrinkPolicy::Lazy) },
+            Err(MalformedTreeError::InvalidChildIndex {
+                key_prefix: KeyPrefix::default(),
+                key_fragment: 3,
+                child_index: 0,
+                num_children: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn check_tree_with_wrong_header_count() {
+        let mut l1 = LeafNode::new(Box::new([1, 1]), 11);
+        let mut l2 = LeafNode::new(Box::new([1, 2]), 12);
+
+        let l1_ptr: OpaqueNodePtr<Box<[u8; 2]>, i32, 16> = NodePtr::from(&mut l1).to_opaque();
+        let l2_ptr = NodePtr::from(&mut l2).to_opaque();
+
+        let mut n48 = InnerNode48::from_prefix(&[1], 1);
+        n48.write_child(1, l1_ptr);
+        n48.write_child(2, l2_ptr);
+        // Unmap the second child, while keeping it counted in the header
+        n48.child_indices[2] = RestrictedNodeIndex::EMPTY;
+
+        let root = NodePtr::from(&mut n48).to_opaque();
+        assert_eq!(
+            unsafe { WellFormedChecker::check_tree(root, ShrinkPolicy::Lazy) },
+            Err(MalformedTreeError::ChildrenCountMismatch {
+                key_prefix: KeyPrefix::default(),
+                inner_node_type: NodeType::Node48,
+                header_num_children: 2,
+                num_children: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn check_tree_len_mismatch() {
+        let mut tree: TreeMap<[u8; 2], i32> = TreeMap::new();
+        tree.insert([1, 1], 11);
+        tree.insert([1, 2], 12);
+        tree.insert([2, 1], 21);
+        assert_eq!(WellFormedChecker::check_all(&tree), Ok(5));
+
+        let mut other = TreeMap::new();
+        other.insert([3, 1], 31);
+        // Swap the roots, so the lengths no longer match the leaves
+        core::mem::swap(&mut tree.root, &mut other.root);
+
+        assert_eq!(
+            WellFormedChecker::check(&tree),
+            Err(MalformedTreeError::LenMismatch {
+                len: 3,
+                num_leaves: 1,
+            })
+        );
+        assert_eq!(
+            WellFormedChecker::check(&other),
+            Err(MalformedTreeError::LenMismatch {
+                len: 1,
+                num_leaves: 3,
+            })
+        );
+    }
 }
@@ -1,6 +1,10 @@
 //! Module containing copies of Rust standard library unstable functions for use
 //! outside of the nightly distribution.
 
+use alloc::boxed::Box;
+#[cfg(not(feature = "nightly"))]
+use alloc::vec::Vec;
+
 /// Assuming all the elements are initialized, get a slice to them.
 ///
 /// # Safety
@@ -10,7 +14,7 @@
 /// Calling this when the content is not yet fully initialized causes
 /// undefined behavior.
 ///
-/// See [`assume_init_ref`][std::mem::MaybeUninit::assume_init_ref] for more
+/// See [`assume_init_ref`][core::mem::MaybeUninit::assume_init_ref] for more
 /// details and examples.
 ///
 /// **This is a unstable API copied from the Rust standard library, tracking
@@ -19,12 +23,12 @@
 /// [issue-63569]: https://github.com/rust-lang/rust/issues/63569
 #[inline(always)]
 pub const unsafe fn maybe_uninit_slice_assume_init_ref<T>(
-    slice: &[std::mem::MaybeUninit<T>],
+    slice: &[core::mem::MaybeUninit<T>],
 ) -> &[T] {
     #[cfg(feature = "nightly")]
     {
         // SAFETY: Covered by condition of containing function
-        unsafe { std::mem::MaybeUninit::slice_assume_init_ref(slice) }
+        unsafe { core::mem::MaybeUninit::slice_assume_init_ref(slice) }
     }
 
     #[cfg(not(feature = "nightly"))]
@@ -34,7 +38,7 @@ pub const unsafe fn maybe_uninit_slice_assume_init_ref<T>(
         // the same layout as `T`. The pointer obtained is valid since it refers
         // to memory owned by `slice` which is a reference and thus guaranteed
         // to be valid for reads.
-        unsafe { &*(slice as *const [std::mem::MaybeUninit<T>] as *const [T]) }
+        unsafe { &*(slice as *const [core::mem::MaybeUninit<T>] as *const [T]) }
     }
 }
 
@@ -47,7 +51,7 @@ pub const unsafe fn maybe_uninit_slice_assume_init_ref<T>(
 /// Calling this when the content is not yet fully initialized causes
 /// undefined behavior.
 ///
-/// See [`assume_init_mut`][std::mem::MaybeUninit::assume_init_mut] for more
+/// See [`assume_init_mut`][core::mem::MaybeUninit::assume_init_mut] for more
 /// details and examples.
 ///
 /// **This is a unstable API copied from the Rust standard library, tracking
@@ -56,19 +60,19 @@ pub const unsafe fn maybe_uninit_slice_assume_init_ref<T>(
 /// [issue-63569]: https://github.com/rust-lang/rust/issues/63569
 #[inline(always)]
 pub unsafe fn maybe_uninit_slice_assume_init_mut<T>(
-    slice: &mut [std::mem::MaybeUninit<T>],
+    slice: &mut [core::mem::MaybeUninit<T>],
 ) -> &mut [T] {
     #[cfg(feature = "nightly")]
     {
         // SAFETY: Covered by condition of containing function
-        unsafe { std::mem::MaybeUninit::slice_assume_init_mut(slice) }
+        unsafe { core::mem::MaybeUninit::slice_assume_init_mut(slice) }
     }
 
     #[cfg(not(feature = "nightly"))]
     {
         // SAFETY: similar to safety notes for `slice_get_ref`, but we have a
         // mutable reference which is also guaranteed to be valid for writes.
-        unsafe { &mut *(slice as *mut [std::mem::MaybeUninit<T>] as *mut [T]) }
+        unsafe { &mut *(slice as *mut [core::mem::MaybeUninit<T>] as *mut [T]) }
     }
 }
 
@@ -86,7 +90,7 @@ pub unsafe fn maybe_uninit_slice_assume_init_mut<T>(
 ///
 /// This method is only for providing domain separation.  If you want to
 /// hash a `usize` that represents part of the *data*, then it's important
-/// that you pass it to [`Hasher::write_usize`][std::hash::Hasher::write_usize]
+/// that you pass it to [`Hasher::write_usize`][core::hash::Hasher::write_usize]
 /// instead of to this method.
 ///
 /// # Note to Implementers
@@ -100,10 +104,10 @@ pub unsafe fn maybe_uninit_slice_assume_init_mut<T>(
 ///
 /// [issue-96762]: https://github.com/rust-lang/rust/issues/96762
 #[inline(always)]
-pub fn hasher_write_length_prefix<H: std::hash::Hasher>(state: &mut H, num_entries: usize) {
+pub fn hasher_write_length_prefix<H: core::hash::Hasher>(state: &mut H, num_entries: usize) {
     #[cfg(feature = "nightly")]
     {
-        <H as std::hash::Hasher>::write_length_prefix(state, num_entries);
+        <H as core::hash::Hasher>::write_length_prefix(state, num_entries);
     }
 
     #[cfg(not(feature = "nightly"))]
@@ -112,6 +116,47 @@ pub fn hasher_write_length_prefix<H: std::hash::Hasher>(state: &mut H, num_entri
     }
 }
 
+/// Gets the "address" portion of the pointer.
+///
+/// **This is a strict provenance API, stable since Rust 1.84. On older
+/// toolchains it is provided by the `sptr` crate, tracking issue is
+/// [#95228][issue-95228]**
+///
+/// [issue-95228]: https://github.com/rust-lang/rust/issues/95228
+#[inline(always)]
+pub fn ptr_addr<T>(ptr: *const T) -> usize {
+    #[cfg(feature = "nightly")]
+    {
+        ptr.addr()
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    {
+        sptr::Strict::addr(ptr)
+    }
+}
+
+/// Creates a new pointer by mapping `ptr`'s address to a new one, preserving
+/// the original pointer's provenance.
+///
+/// **This is a strict provenance API, stable since Rust 1.84. On older
+/// toolchains it is provided by the `sptr` crate, tracking issue is
+/// [#95228][issue-95228]**
+///
+/// [issue-95228]: https://github.com/rust-lang/rust/issues/95228
+#[inline(always)]
+pub fn ptr_map_addr<T>(ptr: *mut T, f: impl FnOnce(usize) -> usize) -> *mut T {
+    #[cfg(feature = "nightly")]
+    {
+        ptr.map_addr(f)
+    }
+
+    #[cfg(not(feature = "nightly"))]
+    {
+        sptr::Strict::map_addr(ptr, f)
+    }
+}
+
 /// Create a new array of `MaybeUninit<T>` items, in an uninitialized state.
 ///
 /// Note: in a future Rust version this method may become unnecessary
@@ -126,8 +171,8 @@ pub fn hasher_write_length_prefix<H: std::hash::Hasher>(state: &mut H, num_entri
 /// [issue-96097]: https://github.com/rust-lang/rust/issues/96097
 #[cfg(feature = "nightly")]
 #[inline(always)]
-pub const fn maybe_uninit_uninit_array<T, const N: usize>() -> [std::mem::MaybeUninit<T>; N] {
-    std::mem::MaybeUninit::uninit_array()
+pub const fn maybe_uninit_uninit_array<T, const N: usize>() -> [core::mem::MaybeUninit<T>; N] {
+    core::mem::MaybeUninit::uninit_array()
 }
 
 /// Create a new array of `MaybeUninit<T>` items, in an uninitialized state.
@@ -144,8 +189,8 @@ pub const fn maybe_uninit_uninit_array<T, const N: usize>() -> [std::mem::MaybeU
 /// [issue-96097]: https://github.com/rust-lang/rust/issues/96097
 #[cfg(not(feature = "nightly"))]
 #[inline(always)]
-pub fn maybe_uninit_uninit_array<T, const N: usize>() -> [std::mem::MaybeUninit<T>; N] {
-    std::array::from_fn(|_| std::mem::MaybeUninit::uninit())
+pub fn maybe_uninit_uninit_array<T, const N: usize>() -> [core::mem::MaybeUninit<T>; N] {
+    core::array::from_fn(|_| core::mem::MaybeUninit::uninit())
 }
 
 /// Constructs a new boxed slice with uninitialized contents.
@@ -155,7 +200,7 @@ pub fn maybe_uninit_uninit_array<T, const N: usize>() -> [std::mem::MaybeUninit<
 ///
 /// [issue-63291]: https://github.com/rust-lang/rust/issues/63291
 #[inline(always)]
-pub fn box_new_uninit_slice<T>(len: usize) -> Box<[std::mem::MaybeUninit<T>]> {
+pub fn box_new_uninit_slice<T>(len: usize) -> Box<[core::mem::MaybeUninit<T>]> {
     #[cfg(feature = "nightly")]
     {
         Box::new_uninit_slice(len)
@@ -163,7 +208,7 @@ pub fn box_new_uninit_slice<T>(len: usize) -> Box<[std::mem::MaybeUninit<T>]> {
 
     #[cfg(not(feature = "nightly"))]
     {
-        Vec::from_iter((0..len).map(|_| std::mem::MaybeUninit::uninit())).into_boxed_slice()
+        Vec::from_iter((0..len).map(|_| core::mem::MaybeUninit::uninit())).into_boxed_slice()
     }
 }
 
@@ -182,7 +227,7 @@ pub fn box_new_uninit_slice<T>(len: usize) -> Box<[std::mem::MaybeUninit<T>]> {
 macro_rules! assume {
     ($b:expr) => {
         #[cfg(feature = "nightly")]
-        std::intrinsics::assume($b)
+        core::intrinsics::assume($b)
     };
 }
 
@@ -204,7 +249,7 @@ pub(crate) use assume;
 #[cfg(feature = "nightly")]
 macro_rules! likely {
     ($b:expr) => {
-        std::intrinsics::likely($b)
+        core::intrinsics::likely($b)
     };
 }
 
@@ -246,7 +291,7 @@ pub(crate) use likely;
 #[cfg(feature = "nightly")]
 macro_rules! unlikely {
     ($b:expr) => {
-        std::intrinsics::unlikely($b)
+        core::intrinsics::unlikely($b)
     };
 }
 
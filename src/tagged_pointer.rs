@@ -8,10 +8,8 @@
 //! pointed-to type, so that it can store several bits of information. For a
 //! type with alignment `A`, the number of available bits is `log_2(A)`.
 
-use std::{fmt, mem::align_of, ptr::NonNull};
-
-#[cfg(not(feature = "nightly"))]
-use sptr::Strict;
+use crate::rust_nightly_apis::{ptr_addr, ptr_map_addr};
+use core::{fmt, mem::align_of, ptr::NonNull};
 
 /// A non-null pointer type which carries several bits of metadata.
 ///
@@ -85,7 +83,7 @@ impl<P, const MIN_BITS: u32> TaggedPointer<P, MIN_BITS> {
         // assumes that null is always a zero value.
         let unchecked_ptr = unsafe { NonNull::new_unchecked(pointer) };
 
-        let ptr_addr = unchecked_ptr.as_ptr().addr();
+        let ptr_addr = ptr_addr(unchecked_ptr.as_ptr());
 
         // Double-check that there are no existing bits stored in the data-carrying
         // positions
@@ -118,14 +116,12 @@ impl<P, const MIN_BITS: u32> TaggedPointer<P, MIN_BITS> {
     ///
     /// This pointer is guaranteed to be non-null.
     pub fn to_ptr(self) -> *mut P {
-        self.0
-            .as_ptr()
-            .map_addr(|ptr_addr| ptr_addr & Self::POINTER_MASK)
+        ptr_map_addr(self.0.as_ptr(), |ptr_addr| ptr_addr & Self::POINTER_MASK)
     }
 
     /// Consume this tagged pointer and produce the data it carries.
     pub fn to_data(self) -> usize {
-        let ptr_addr = self.0.as_ptr().addr();
+        let ptr_addr = ptr_addr(self.0.as_ptr());
         ptr_addr & Self::DATA_MASK
     }
 
@@ -142,10 +138,9 @@ impl<P, const MIN_BITS: u32> TaggedPointer<P, MIN_BITS> {
         );
         let data = data & Self::DATA_MASK;
 
-        let ptr_with_new_data = self
-            .0
-            .as_ptr()
-            .map_addr(|ptr_addr| (ptr_addr & Self::POINTER_MASK) | data);
+        let ptr_with_new_data = ptr_map_addr(self.0.as_ptr(), |ptr_addr| {
+            (ptr_addr & Self::POINTER_MASK) | data
+        });
 
         // The `ptr_with_new_data` is guaranteed to be non-null because it's pointer
         // address was derived from a non-null pointer using operations that would not
@@ -208,21 +203,21 @@ impl<P, const MIN_BITS: u32> From<&mut P> for TaggedPointer<P, MIN_BITS> {
     }
 }
 
-impl<P, const MIN_BITS: u32> std::hash::Hash for TaggedPointer<P, MIN_BITS> {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+impl<P, const MIN_BITS: u32> core::hash::Hash for TaggedPointer<P, MIN_BITS> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
         self.0.hash(state);
     }
 }
 
 impl<P, const MIN_BITS: u32> Ord for TaggedPointer<P, MIN_BITS> {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         self.0.cmp(&other.0)
     }
 }
 
 impl<P, const MIN_BITS: u32> PartialOrd for TaggedPointer<P, MIN_BITS> {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.0.cmp(&other.0))
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
     }
 }
 
@@ -260,6 +255,7 @@ impl<P, const MIN_BITS: u32> fmt::Pointer for TaggedPointer<P, MIN_BITS> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::boxed::Box;
 
     #[test]
     fn successful_tag() {
@@ -1,5 +1,6 @@
 //! Helper function for writing tests
 
+use alloc::{boxed::Box, vec};
 use std::{collections::HashSet, iter};
 
 use crate::{AsBytes, InsertPrefixError, InsertResult, OpaqueNodePtr};
@@ -303,7 +304,7 @@ pub fn generate_key_with_prefix<const KEY_LENGTH: usize>(
     }
 
     let mut sorted_expansions = expansions.to_vec();
-    sorted_expansions.sort_by(|a, b| a.base_index.cmp(&b.base_index));
+    sorted_expansions.sort_by_key(|a| a.base_index);
 
     let full_key_len = expansions
         .iter()
@@ -328,7 +329,7 @@ where
     use crate::search_for_insert_point;
 
     let insert_point = unsafe { search_for_insert_point(root, key.as_bytes())? };
-    Ok(insert_point.apply(key, value))
+    Ok(insert_point.apply(key, value, crate::PrefixStrategy::default()))
 }
 
 #[allow(dead_code)]
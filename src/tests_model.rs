@@ -0,0 +1,1259 @@
+//! Model-based testing of [`TreeMap`], shared by the unit tests and the
+//! fuzzers.
+//!
+//! [`check_operations`] applies a sequence of [`Operation`]s to a [`TreeMap`]
+//! configured by a [`ModelConfig`], and to a [`Model`] backed by a
+//! [`BTreeMap`] in lockstep. After each operation it compares their results,
+//! checks that the tree is well-formed, and compares the entries of the tree
+//! and the model.
+//!
+//! The configuration and the operations are decoded from bytes by
+//! [`decode_input`], so the same cases can be generated from random bytes in
+//! `cargo test`, or taken from the fuzzer input. [`assert_operations`] shrinks
+//! a failing case down to a minimal one before panicking with it, while the
+//! fuzzers call [`check_operations`] and minimize failing inputs themselves.
+
+use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    ops::Bound,
+    panic::{self, AssertUnwindSafe},
+};
+
+use crate::{
+    map::{Entry, EntryRef},
+    visitor::WellFormedChecker,
+    PrefixStrategy, ShrinkPolicy, TreeMap,
+};
+
+/// The tree which the operations are applied to.
+pub type ModelTree<const PREFIX_LEN: usize = 16> = TreeMap<Box<[u8]>, u32, PREFIX_LEN>;
+
+/// The maximum number of bytes decoded by [`ByteSource::key`] after the
+/// optional [`KEY_STEM`].
+pub const MAX_KEY_LEN: usize = 4;
+
+/// The stem shared by about a quarter of the decoded keys.
+///
+/// It is longer than the inline prefix of every [`ModelConfig`], so that the
+/// prefixes of the inner nodes below it do not fit in their headers.
+pub const KEY_STEM: &[u8] = &[
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19,
+];
+
+/// The maximum edit distance of the decoded [`Operation::Fuzzy`] searches.
+pub const MAX_EDIT_DIST: usize = 2;
+
+/// A cursor over the bytes which the operations are decoded from.
+///
+/// Once the bytes run out it returns zeros, so that any byte string can be
+/// decoded.
+#[derive(Debug, Clone)]
+pub struct ByteSource<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteSource<'a> {
+    /// Create a source which reads the given bytes.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        ByteSource { bytes }
+    }
+
+    /// Return true if all the bytes have been read.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Read the next byte, or zero if there are none left.
+    pub fn byte(&mut self) -> u8 {
+        match self.bytes.split_first() {
+            Some((first, rest)) => {
+                self.bytes = rest;
+                *first
+            },
+            None => 0,
+        }
+    }
+
+    /// Read a value.
+    pub fn value(&mut self) -> u32 {
+        u32::from(self.byte())
+    }
+
+    /// Read a key of up to [`MAX_KEY_LEN`] bytes, which starts with the
+    /// [`KEY_STEM`] if the length byte is 192 or more.
+    ///
+    /// About half of the key bytes are taken from a small alphabet, so that
+    /// the keys often repeat and share prefixes. The other half are spread
+    /// over a wide range, so that the inner nodes grow to the larger node
+    /// types.
+    pub fn key(&mut self) -> Box<[u8]> {
+        let len_byte = self.byte();
+        let stem = if len_byte >= 192 { KEY_STEM } else { &[] };
+        let len = usize::from(len_byte) % (MAX_KEY_LEN + 1);
+        stem.iter()
+            .copied()
+            .chain((0..len).map(|_| match self.byte() {
+                byte @ 0..=127 => byte % 4,
+                byte => byte,
+            }))
+            .collect()
+    }
+
+    /// Read a list of up to 7 entries.
+    fn entries(&mut self) -> Vec<(Box<[u8]>, u32)> {
+        let len = self.byte() % 8;
+        (0..len).map(|_| (self.key(), self.value())).collect()
+    }
+}
+
+/// The settings of the tree which the operations are applied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelConfig {
+    /// The number of prefix bytes stored in the node headers, one of
+    /// [`ModelConfig::PREFIX_LENS`]
+    pub prefix_len: usize,
+    /// See [`TreeMap::with_prefix_strategy`]
+    pub prefix_strategy: PrefixStrategy,
+    /// See [`TreeMap::set_shrink_policy`]
+    pub shrink_policy: ShrinkPolicy,
+    /// The depth of the lookup index if it is enabled, see
+    /// `TreeMap::enable_lookup_index`. It is ignored without the `std`
+    /// feature.
+    pub lookup_index_depth: Option<usize>,
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        ModelConfig {
+            prefix_len: 16,
+            prefix_strategy: PrefixStrategy::default(),
+            shrink_policy: ShrinkPolicy::default(),
+            lookup_index_depth: None,
+        }
+    }
+}
+
+impl ModelConfig {
+    /// The supported values of [`ModelConfig::prefix_len`].
+    pub const PREFIX_LENS: [usize; 4] = [16, 8, 2, 1];
+
+    /// Decode a configuration from a single byte, where zero decodes to the
+    /// default configuration.
+    ///
+    /// The pessimistic prefix strategy is replaced by the optimistic one if
+    /// it is not supported with the decoded prefix length.
+    pub fn decode(byte: u8) -> ModelConfig {
+        let prefix_len = Self::PREFIX_LENS[usize::from(byte & 0b11)];
+        let prefix_strategy =
+            if byte & 0b100 != 0 && PrefixStrategy::Pessimistic.is_supported(prefix_len) {
+                PrefixStrategy::Pessimistic
+            } else {
+                PrefixStrategy::Optimistic
+            };
+        let shrink_policy = match (byte >> 3) & 0b11 {
+            1 => ShrinkPolicy::Hysteresis,
+            2 => ShrinkPolicy::Lazy,
+            _ => ShrinkPolicy::Eager,
+        };
+        let lookup_index_depth = match usize::from(byte >> 5) {
+            depth @ 4..=7 if cfg!(feature = "std") => Some(depth - 3),
+            _ => None,
+        };
+
+        ModelConfig {
+            prefix_len,
+            prefix_strategy,
+            shrink_policy,
+            lookup_index_depth,
+        }
+    }
+
+    /// Create an empty tree with these settings.
+    ///
+    /// # Panics
+    ///  - If `PREFIX_LEN` is not equal to [`ModelConfig::prefix_len`].
+    pub fn new_tree<const PREFIX_LEN: usize>(&self) -> ModelTree<PREFIX_LEN> {
+        assert_eq!(PREFIX_LEN, self.prefix_len);
+
+        let mut tree = TreeMap::with_prefix_strategy(self.prefix_strategy);
+        tree.set_shrink_policy(self.shrink_policy);
+        #[cfg(feature = "std")]
+        if let Some(depth) = self.lookup_index_depth {
+            tree.enable_lookup_index(depth);
+        }
+        tree
+    }
+
+    /// Return a description of the first setting of the tree which differs
+    /// from this configuration, if there is one.
+    fn mismatch<const PREFIX_LEN: usize>(&self, tree: &ModelTree<PREFIX_LEN>) -> Option<String> {
+        if tree.prefix_strategy() != self.prefix_strategy {
+            return Some(format!(
+                "the tree has the prefix strategy {:?}, but the configuration has {:?}",
+                tree.prefix_strategy(),
+                self.prefix_strategy
+            ));
+        }
+        if tree.shrink_policy() != self.shrink_policy {
+            return Some(format!(
+                "the tree has the shrink policy {:?}, but the configuration has {:?}",
+                tree.shrink_policy(),
+                self.shrink_policy
+            ));
+        }
+        #[cfg(feature = "std")]
+        if tree.lookup_index_depth() != self.lookup_index_depth {
+            return Some(format!(
+                "the tree has the lookup index depth {:?}, but the configuration has {:?}",
+                tree.lookup_index_depth(),
+                self.lookup_index_depth
+            ));
+        }
+        None
+    }
+
+    /// Return the configurations which are one step closer to the default
+    /// one, used to shrink a failing case.
+    fn simplifications(&self) -> Vec<ModelConfig> {
+        let default = ModelConfig::default();
+        let mut simpler = Vec::new();
+        if self.prefix_len != default.prefix_len
+            && self.prefix_strategy.is_supported(default.prefix_len)
+        {
+            simpler.push(ModelConfig {
+                prefix_len: default.prefix_len,
+                ..*self
+            });
+        }
+        if self.prefix_strategy != default.prefix_strategy {
+            simpler.push(ModelConfig {
+                prefix_strategy: default.prefix_strategy,
+                ..*self
+            });
+        }
+        if self.shrink_policy != default.shrink_policy {
+            simpler.push(ModelConfig {
+                shrink_policy: default.shrink_policy,
+                ..*self
+            });
+        }
+        if self.lookup_index_depth != default.lookup_index_depth {
+            simpler.push(ModelConfig {
+                lookup_index_depth: default.lookup_index_depth,
+                ..*self
+            });
+        }
+        simpler
+    }
+}
+
+/// Decode the configuration of the tree from the first byte, and the
+/// operations from the remaining bytes.
+pub fn decode_input(bytes: &[u8]) -> (ModelConfig, Vec<Operation>) {
+    match bytes.split_first() {
+        Some((first, rest)) => (ModelConfig::decode(*first), Operation::decode_all(rest)),
+        None => (ModelConfig::default(), Vec::new()),
+    }
+}
+
+/// An operation which is applied to both the tree and the model.
+///
+/// New operations, like range queries once the [`TreeMap`] supports them, are
+/// added here along with their decoding in [`Operation::decode`], their
+/// application in [`Model::apply`] and [`apply_to_tree`], and their
+/// simplifications in [`Operation::simplifications`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    /// [`TreeMap::try_insert`]
+    TryInsert(Box<[u8]>, u32),
+    /// [`TreeMap::remove`]
+    Remove(Box<[u8]>),
+    /// [`TreeMap::get`]
+    Get(Box<[u8]>),
+    /// [`TreeMap::get_many`]
+    GetMany(Vec<Box<[u8]>>),
+    /// [`TreeMap::try_entry`], followed by an operation on the entry
+    Entry(Box<[u8]>, EntryOperation),
+    /// [`TreeMap::try_entry_ref`], followed by an operation on the entry
+    EntryRef(Box<[u8]>, EntryOperation),
+    /// [`TreeMap::pop_first`]
+    PopFirst,
+    /// [`TreeMap::pop_last`]
+    PopLast,
+    /// [`TreeMap::prefix`]
+    Prefix(Box<[u8]>),
+    /// [`TreeMap::remove_prefix`]
+    RemovePrefix(Box<[u8]>),
+    /// [`TreeMap::fuzzy`], with a maximum edit distance
+    Fuzzy(Box<[u8]>, usize),
+    /// A search for the entry nearest to a key
+    Nearest(NearestSearch, Box<[u8]>),
+    /// A set operation with a tree built from the entries, skipping the ones
+    /// which are rejected by [`TreeMap::try_insert`]
+    SetOperation(SetOperation, Vec<(Box<[u8]>, u32)>),
+    /// Replace the tree with its [`Clone`]
+    Clone,
+    /// [`TreeMap::compact`]
+    Compact,
+    /// [`TreeMap::clear`]
+    Clear,
+}
+
+/// An operation on the entry returned by [`TreeMap::try_entry`] or
+/// [`TreeMap::try_entry_ref`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryOperation {
+    /// `and_modify`, which increments the value
+    AndModify,
+    /// `insert_entry`
+    InsertEntry(u32),
+    /// `or_default`
+    OrDefault,
+    /// `or_insert`
+    OrInsert(u32),
+    /// `remove_entry`, if the entry is occupied
+    RemoveEntry,
+}
+
+/// The searches for the entry nearest to a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NearestSearch {
+    /// [`TreeMap::floor`]
+    Floor,
+    /// [`TreeMap::ceiling`]
+    Ceiling,
+    /// [`TreeMap::predecessor`]
+    Predecessor,
+    /// [`TreeMap::successor`]
+    Successor,
+}
+
+/// The set operations with another tree.
+///
+/// The values of the keys present in both trees are added, and
+/// [`SetOperation::Difference`] and [`SetOperation::SymmetricDifference`] only
+/// keep the sums which are odd.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    /// [`TreeMap::union_with_conflicts`]
+    Union,
+    /// [`TreeMap::intersection_with`]
+    Intersection,
+    /// [`TreeMap::difference`]
+    Difference,
+    /// [`TreeMap::symmetric_difference_with_conflicts`]
+    SymmetricDifference,
+}
+
+impl Operation {
+    /// Decode all the bytes into a sequence of operations.
+    pub fn decode_all(bytes: &[u8]) -> Vec<Operation> {
+        let mut source = ByteSource::new(bytes);
+        let mut operations = Vec::new();
+        while !source.is_empty() {
+            operations.push(Operation::decode(&mut source));
+        }
+        operations
+    }
+
+    /// Decode a single operation, weighted towards insertions so that the
+    /// tree grows.
+    pub fn decode(source: &mut ByteSource<'_>) -> Operation {
+        match source.byte() % 40 {
+            0..=11 => Operation::TryInsert(source.key(), source.value()),
+            12..=14 => Operation::Remove(source.key()),
+            15..=16 => Operation::Get(source.key()),
+            17..=18 => Operation::Entry(source.key(), EntryOperation::decode(source)),
+            19..=20 => Operation::EntryRef(source.key(), EntryOperation::decode(source)),
+            21 => Operation::PopFirst,
+            22 => Operation::PopLast,
+            23..=24 => Operation::Prefix(source.key()),
+            25 => Operation::RemovePrefix(source.key()),
+            26..=27 => Operation::Fuzzy(
+                source.key(),
+                usize::from(source.byte()) % (MAX_EDIT_DIST + 1),
+            ),
+            28..=29 => Operation::Nearest(NearestSearch::decode(source), source.key()),
+            30 => Operation::Clone,
+            31 => Operation::Clear,
+            32..=34 => Operation::GetMany((0..source.byte() % 8).map(|_| source.key()).collect()),
+            35..=38 => Operation::SetOperation(SetOperation::decode(source), source.entries()),
+            _ => Operation::Compact,
+        }
+    }
+
+    /// Return the operations which are one step simpler than this one, used
+    /// to shrink a failing sequence.
+    ///
+    /// Each simplification removes a key byte, or sets a key byte, a value or
+    /// a distance closer to zero, so that shrinking always terminates.
+    pub fn simplifications(&self) -> Vec<Operation> {
+        fn keys(key: &[u8]) -> Vec<Box<[u8]>> {
+            let removed = (0..key.len()).map(|idx| [&key[..idx], &key[(idx + 1)..]].concat());
+            let zeroed = (0..key.len()).filter(|idx| key[*idx] != 0).map(|idx| {
+                let mut key = key.to_vec();
+                key[idx] = 0;
+                key
+            });
+            removed.chain(zeroed).map(Vec::into_boxed_slice).collect()
+        }
+
+        fn values(value: u32) -> Vec<u32> {
+            if value == 0 {
+                Vec::new()
+            } else {
+                vec![0]
+            }
+        }
+
+        // Remove an item, or replace an item by one of its simplifications
+        fn lists<T: Clone>(items: &[T], simplify: impl Fn(&T) -> Vec<T>) -> Vec<Vec<T>> {
+            let removed = (0..items.len()).map(|idx| [&items[..idx], &items[(idx + 1)..]].concat());
+            let simplified = items.iter().enumerate().flat_map(|(idx, item)| {
+                simplify(item).into_iter().map(move |simpler| {
+                    let mut items = items.to_vec();
+                    items[idx] = simpler;
+                    items
+                })
+            });
+            removed.chain(simplified).collect()
+        }
+
+        let with_key = |key: &[u8], f: &dyn Fn(Box<[u8]>) -> Operation| {
+            keys(key).into_iter().map(f).collect::<Vec<_>>()
+        };
+
+        match self {
+            Operation::TryInsert(key, value) => {
+                let mut simpler = with_key(key, &|key| Operation::TryInsert(key, *value));
+                simpler.extend(
+                    values(*value)
+                        .into_iter()
+                        .map(|value| Operation::TryInsert(key.clone(), value)),
+                );
+                simpler
+            },
+            Operation::Remove(key) => with_key(key, &Operation::Remove),
+            Operation::Get(key) => with_key(key, &Operation::Get),
+            Operation::GetMany(keys_list) => lists(keys_list, |key| keys(key))
+                .into_iter()
+                .map(Operation::GetMany)
+                .collect(),
+            Operation::Entry(key, entry_op) => {
+                let mut simpler = with_key(key, &|key| Operation::Entry(key, *entry_op));
+                simpler.extend(
+                    entry_op
+                        .simplifications()
+                        .map(|entry_op| Operation::Entry(key.clone(), entry_op)),
+                );
+                simpler
+            },
+            Operation::EntryRef(key, entry_op) => {
+                let mut simpler = with_key(key, &|key| Operation::EntryRef(key, *entry_op));
+                simpler.extend(
+                    entry_op
+                        .simplifications()
+                        .map(|entry_op| Operation::EntryRef(key.clone(), entry_op)),
+                );
+                simpler
+            },
+            Operation::Prefix(key) => with_key(key, &Operation::Prefix),
+            Operation::RemovePrefix(key) => with_key(key, &Operation::RemovePrefix),
+            Operation::Fuzzy(key, max_edit_dist) => {
+                let mut simpler = with_key(key, &|key| Operation::Fuzzy(key, *max_edit_dist));
+                if let Some(max_edit_dist) = max_edit_dist.checked_sub(1) {
+                    simpler.push(Operation::Fuzzy(key.clone(), max_edit_dist));
+                }
+                simpler
+            },
+            Operation::Nearest(search, key) => {
+                with_key(key, &|key| Operation::Nearest(*search, key))
+            },
+            Operation::SetOperation(set_op, entries) => lists(entries, |(key, value)| {
+                let mut simpler: Vec<_> = keys(key).into_iter().map(|key| (key, *value)).collect();
+                simpler.extend(values(*value).into_iter().map(|value| (key.clone(), value)));
+                simpler
+            })
+            .into_iter()
+            .map(|entries| Operation::SetOperation(*set_op, entries))
+            .collect(),
+            Operation::PopFirst
+            | Operation::PopLast
+            | Operation::Clone
+            | Operation::Compact
+            | Operation::Clear => Vec::new(),
+        }
+    }
+}
+
+impl EntryOperation {
+    fn decode(source: &mut ByteSource<'_>) -> EntryOperation {
+        match source.byte() % 5 {
+            0 => EntryOperation::AndModify,
+            1 => EntryOperation::InsertEntry(source.value()),
+            2 => EntryOperation::OrDefault,
+            3 => EntryOperation::OrInsert(source.value()),
+            _ => EntryOperation::RemoveEntry,
+        }
+    }
+
+    fn simplifications(self) -> impl Iterator<Item = EntryOperation> {
+        let simpler = match self {
+            EntryOperation::InsertEntry(value) if value != 0 => {
+                Some(EntryOperation::InsertEntry(0))
+            },
+            EntryOperation::OrInsert(value) if value != 0 => Some(EntryOperation::OrInsert(0)),
+            _ => None,
+        };
+        simpler.into_iter()
+    }
+}
+
+impl SetOperation {
+    fn decode(source: &mut ByteSource<'_>) -> SetOperation {
+        match source.byte() % 4 {
+            0 => SetOperation::Union,
+            1 => SetOperation::Intersection,
+            2 => SetOperation::Difference,
+            _ => SetOperation::SymmetricDifference,
+        }
+    }
+
+    /// Combine the values of a key present in both trees.
+    fn combine(self, left: u32, right: u32) -> Option<u32> {
+        let sum = left.wrapping_add(right);
+        match self {
+            SetOperation::Union | SetOperation::Intersection => Some(sum),
+            SetOperation::Difference | SetOperation::SymmetricDifference => {
+                Some(sum).filter(|sum| sum % 2 == 1)
+            },
+        }
+    }
+}
+
+impl NearestSearch {
+    fn decode(source: &mut ByteSource<'_>) -> NearestSearch {
+        match source.byte() % 4 {
+            0 => NearestSearch::Floor,
+            1 => NearestSearch::Ceiling,
+            2 => NearestSearch::Predecessor,
+            _ => NearestSearch::Successor,
+        }
+    }
+}
+
+/// The result of an operation, which must be the same for the tree and the
+/// model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The operation has no result
+    Unit,
+    /// The key was rejected, because it is a prefix of an existing key or an
+    /// existing key is a prefix of it
+    PrefixError,
+    /// A value, if there was one
+    Value(Option<u32>),
+    /// A list of values, if there were any
+    Values(Vec<Option<u32>>),
+    /// An entry, if there was one
+    Entry(Option<(Box<[u8]>, u32)>),
+    /// A list of entries
+    Entries(Vec<(Box<[u8]>, u32)>),
+}
+
+/// The reference implementation of the operations, on a [`BTreeMap`].
+#[derive(Debug, Clone, Default)]
+pub struct Model {
+    entries: BTreeMap<Box<[u8]>, u32>,
+}
+
+impl Model {
+    /// Return the entries of the model, in order.
+    pub fn entries(&self) -> &BTreeMap<Box<[u8]>, u32> {
+        &self.entries
+    }
+
+    /// Return true if the key is a prefix of a different existing key, or a
+    /// different existing key is a prefix of it.
+    fn has_prefix_conflict(&self, key: &[u8]) -> bool {
+        has_prefix_conflict(&self.entries, key)
+    }
+
+    /// Apply the operation to the model, and return its result.
+    pub fn apply(&mut self, operation: &Operation) -> Outcome {
+        match operation {
+            Operation::TryInsert(key, value) => {
+                if self.has_prefix_conflict(key) {
+                    Outcome::PrefixError
+                } else {
+                    Outcome::Value(self.entries.insert(key.clone(), *value))
+                }
+            },
+            Operation::Remove(key) => Outcome::Value(self.entries.remove(key)),
+            Operation::Get(key) => Outcome::Value(self.entries.get(key).copied()),
+            Operation::GetMany(keys) => Outcome::Values(
+                keys.iter()
+                    .map(|key| self.entries.get(key).copied())
+                    .collect(),
+            ),
+            Operation::Entry(key, entry_op) | Operation::EntryRef(key, entry_op) => {
+                self.apply_entry(key, *entry_op)
+            },
+            Operation::PopFirst => Outcome::Entry(self.entries.pop_first()),
+            Operation::PopLast => Outcome::Entry(self.entries.pop_last()),
+            Operation::Prefix(prefix) => Outcome::Entries(
+                self.entries
+                    .iter()
+                    .filter(|(key, _)| key.starts_with(prefix))
+                    .map(|(key, value)| (key.clone(), *value))
+                    .collect(),
+            ),
+            Operation::RemovePrefix(prefix) => {
+                let (removed, kept) = std::mem::take(&mut self.entries)
+                    .into_iter()
+                    .partition::<BTreeMap<_, _>, _>(|(key, _)| key.starts_with(prefix));
+                self.entries = kept;
+                Outcome::Entries(removed.into_iter().collect())
+            },
+            Operation::Fuzzy(key, max_edit_dist) => Outcome::Entries(
+                self.entries
+                    .iter()
+                    .filter(|(existing, _)| edit_dist(existing, key) <= *max_edit_dist)
+                    .map(|(key, value)| (key.clone(), *value))
+                    .collect(),
+            ),
+            Operation::Nearest(search, key) => {
+                let key: &[u8] = key;
+                let entry = match search {
+                    NearestSearch::Floor => self
+                        .entries
+                        .range::<[u8], _>((Bound::Unbounded, Bound::Included(key)))
+                        .next_back(),
+                    NearestSearch::Ceiling => self
+                        .entries
+                        .range::<[u8], _>((Bound::Included(key), Bound::Unbounded))
+                        .next(),
+                    NearestSearch::Predecessor => self
+                        .entries
+                        .range::<[u8], _>((Bound::Unbounded, Bound::Excluded(key)))
+                        .next_back(),
+                    NearestSearch::Successor => self
+                        .entries
+                        .range::<[u8], _>((Bound::Excluded(key), Bound::Unbounded))
+                        .next(),
+                };
+                Outcome::Entry(entry.map(|(key, value)| (key.clone(), *value)))
+            },
+            Operation::SetOperation(set_op, entries) => self.apply_set_operation(*set_op, entries),
+            Operation::Clone | Operation::Compact => Outcome::Unit,
+            Operation::Clear => {
+                self.entries.clear();
+                Outcome::Unit
+            },
+        }
+    }
+
+    fn apply_set_operation(
+        &mut self,
+        set_op: SetOperation,
+        entries: &[(Box<[u8]>, u32)],
+    ) -> Outcome {
+        let mut other = BTreeMap::new();
+        for (key, value) in entries {
+            if !has_prefix_conflict(&other, key) {
+                other.insert(key.clone(), *value);
+            }
+        }
+
+        // The union and symmetric difference reject the keys of the other tree
+        // which conflict with the keys of this one
+        let mut rejected = Vec::new();
+        if matches!(
+            set_op,
+            SetOperation::Union | SetOperation::SymmetricDifference
+        ) {
+            other.retain(|key, value| {
+                let conflicts = self.has_prefix_conflict(key);
+                if conflicts {
+                    rejected.push((key.clone(), *value));
+                }
+                !conflicts
+            });
+        }
+
+        let mut merged = BTreeMap::new();
+        for (key, left) in std::mem::take(&mut self.entries) {
+            let value = match other.remove(&key) {
+                Some(right) => set_op.combine(left, right),
+                None if set_op == SetOperation::Intersection => None,
+                None => Some(left),
+            };
+            merged.extend(value.map(|value| (key, value)));
+        }
+        if matches!(
+            set_op,
+            SetOperation::Union | SetOperation::SymmetricDifference
+        ) {
+            merged.extend(other);
+        }
+        self.entries = merged;
+
+        match set_op {
+            SetOperation::Union | SetOperation::SymmetricDifference => Outcome::Entries(rejected),
+            SetOperation::Intersection | SetOperation::Difference => Outcome::Unit,
+        }
+    }
+
+    fn apply_entry(&mut self, key: &[u8], entry_op: EntryOperation) -> Outcome {
+        let occupied = self.entries.contains_key(key);
+        if !occupied && self.has_prefix_conflict(key) {
+            return Outcome::PrefixError;
+        }
+
+        match entry_op {
+            EntryOperation::AndModify => {
+                if let Some(value) = self.entries.get_mut(key) {
+                    *value = value.wrapping_add(1);
+                }
+                Outcome::Unit
+            },
+            EntryOperation::InsertEntry(value) => {
+                self.entries.insert(key.into(), value);
+                Outcome::Unit
+            },
+            EntryOperation::OrDefault => {
+                Outcome::Value(Some(*self.entries.entry(key.into()).or_default()))
+            },
+            EntryOperation::OrInsert(value) => {
+                Outcome::Value(Some(*self.entries.entry(key.into()).or_insert(value)))
+            },
+            EntryOperation::RemoveEntry => Outcome::Entry(self.entries.remove_entry(key)),
+        }
+    }
+}
+
+/// Return true if the key is a prefix of a different key of the entries, or a
+/// different key of the entries is a prefix of it.
+fn has_prefix_conflict(entries: &BTreeMap<Box<[u8]>, u32>, key: &[u8]) -> bool {
+    entries.keys().any(|existing| {
+        **existing != *key && (existing.starts_with(key) || key.starts_with(existing))
+    })
+}
+
+/// Return the Levenshtein distance between the two byte strings.
+fn edit_dist(a: &[u8], b: &[u8]) -> usize {
+    let mut prev_row: Vec<usize> = (0..=b.len()).collect();
+    let mut row = vec![0; b.len() + 1];
+    for (i, a_byte) in a.iter().enumerate() {
+        row[0] = i + 1;
+        for (j, b_byte) in b.iter().enumerate() {
+            let substitution = prev_row[j] + usize::from(a_byte != b_byte);
+            row[j + 1] = substitution.min(prev_row[j + 1] + 1).min(row[j] + 1);
+        }
+        std::mem::swap(&mut prev_row, &mut row);
+    }
+    prev_row[b.len()]
+}
+
+/// Apply the operation to the tree, and return its result.
+///
+/// The other trees of [`Operation::SetOperation`] are created with the same
+/// configuration as `tree`.
+pub fn apply_to_tree<const PREFIX_LEN: usize>(
+    config: &ModelConfig,
+    tree: &mut ModelTree<PREFIX_LEN>,
+    operation: &Operation,
+) -> Outcome {
+    fn entries<'a>(entries: impl Iterator<Item = (&'a Box<[u8]>, &'a u32)>) -> Outcome {
+        Outcome::Entries(entries.map(|(key, value)| (key.clone(), *value)).collect())
+    }
+
+    // `Entry` and `EntryRef` have the same methods, but no common trait
+    macro_rules! apply_entry_operation {
+        ($entry:expr, $entry_op:expr, $entry_type:ident) => {
+            match $entry_op {
+                EntryOperation::AndModify => {
+                    $entry.and_modify(|value| *value = value.wrapping_add(1));
+                    Outcome::Unit
+                },
+                EntryOperation::InsertEntry(value) => {
+                    $entry.insert_entry(value);
+                    Outcome::Unit
+                },
+                EntryOperation::OrDefault => Outcome::Value(Some(*$entry.or_default())),
+                EntryOperation::OrInsert(value) => Outcome::Value(Some(*$entry.or_insert(value))),
+                EntryOperation::RemoveEntry => match $entry {
+                    $entry_type::Occupied(entry) => Outcome::Entry(Some(entry.remove_entry())),
+                    $entry_type::Vacant(_) => Outcome::Entry(None),
+                },
+            }
+        };
+    }
+
+    match operation {
+        Operation::TryInsert(key, value) => match tree.try_insert(key.clone(), *value) {
+            Ok(previous) => Outcome::Value(previous),
+            Err(_) => Outcome::PrefixError,
+        },
+        Operation::Remove(key) => Outcome::Value(tree.remove(key.as_ref())),
+        Operation::Get(key) => Outcome::Value(tree.get(key.as_ref()).copied()),
+        Operation::GetMany(keys) => {
+            let keys: Vec<&[u8]> = keys.iter().map(AsRef::as_ref).collect();
+            Outcome::Values(
+                tree.get_many(&keys)
+                    .into_iter()
+                    .map(|value| value.copied())
+                    .collect(),
+            )
+        },
+        Operation::Entry(key, entry_op) => match tree.try_entry(key.clone()) {
+            Ok(entry) => apply_entry_operation!(entry, *entry_op, Entry),
+            Err(_) => Outcome::PrefixError,
+        },
+        Operation::EntryRef(key, entry_op) => match tree.try_entry_ref(key.as_ref()) {
+            Ok(entry) => apply_entry_operation!(entry, *entry_op, EntryRef),
+            Err(_) => Outcome::PrefixError,
+        },
+        Operation::PopFirst => Outcome::Entry(tree.pop_first()),
+        Operation::PopLast => Outcome::Entry(tree.pop_last()),
+        Operation::Prefix(prefix) => entries(tree.prefix(prefix)),
+        Operation::RemovePrefix(prefix) => entries(tree.remove_prefix(prefix).iter()),
+        Operation::Fuzzy(key, max_edit_dist) => {
+            // The fuzzy search does not return the entries in order
+            let Outcome::Entries(mut found) = entries(tree.fuzzy(key.as_ref(), *max_edit_dist))
+            else {
+                unreachable!()
+            };
+            found.sort();
+            Outcome::Entries(found)
+        },
+        Operation::Nearest(search, key) => {
+            let key: &[u8] = key;
+            let entry = match search {
+                NearestSearch::Floor => tree.floor(key),
+                NearestSearch::Ceiling => tree.ceiling(key),
+                NearestSearch::Predecessor => tree.predecessor(key),
+                NearestSearch::Successor => tree.successor(key),
+            };
+            Outcome::Entry(entry.map(|(key, value)| (key.clone(), *value)))
+        },
+        Operation::SetOperation(set_op, other_entries) => {
+            let mut other = config.new_tree();
+            for (key, value) in other_entries {
+                let _ = other.try_insert(key.clone(), *value);
+            }
+            let combine = |left, right| set_op.combine(left, right);
+            match set_op {
+                SetOperation::Union => entries(
+                    tree.union_with_conflicts(other, |_, left, right| {
+                        combine(left, right).unwrap()
+                    })
+                    .iter(),
+                ),
+                SetOperation::Intersection => {
+                    tree.intersection_with(other, |_, left, right| combine(left, right).unwrap());
+                    Outcome::Unit
+                },
+                SetOperation::Difference => {
+                    tree.difference(other, |_, left, right| combine(left, right));
+                    Outcome::Unit
+                },
+                SetOperation::SymmetricDifference => entries(
+                    tree.symmetric_difference_with_conflicts(other, |_, left, right| {
+                        combine(left, right)
+                    })
+                    .iter(),
+                ),
+            }
+        },
+        Operation::Clone => {
+            *tree = tree.clone();
+            Outcome::Unit
+        },
+        Operation::Compact => {
+            tree.compact();
+            Outcome::Unit
+        },
+        Operation::Clear => {
+            tree.clear();
+            Outcome::Unit
+        },
+    }
+}
+
+/// A difference between the tree and the model, found by
+/// [`check_operations`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelError {
+    /// The index of the operation after which the difference was found
+    pub step: usize,
+    /// The operation after which the difference was found
+    pub operation: Operation,
+    /// A description of the difference
+    pub message: String,
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "after operation {} ({:?}): {}",
+            self.step, self.operation, self.message
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ModelError {}
+
+/// Apply the operations to a new tree with the given configuration and a new
+/// model in lockstep, and return the first difference between them.
+///
+/// After each operation the results are compared, the tree is checked with
+/// the [`WellFormedChecker`], the entries of the tree are compared with the
+/// entries of the model, in both directions, and the settings of the tree are
+/// compared with the configuration.
+///
+/// # Panics
+///  - If [`ModelConfig::prefix_len`] is not one of
+///    [`ModelConfig::PREFIX_LENS`].
+pub fn check_operations(config: &ModelConfig, operations: &[Operation]) -> Result<(), ModelError> {
+    match config.prefix_len {
+        16 => check_operations_with_prefix_len::<16>(config, operations),
+        8 => check_operations_with_prefix_len::<8>(config, operations),
+        2 => check_operations_with_prefix_len::<2>(config, operations),
+        1 => check_operations_with_prefix_len::<1>(config, operations),
+        prefix_len => panic!("unsupported prefix length {prefix_len}"),
+    }
+}
+
+fn check_operations_with_prefix_len<const PREFIX_LEN: usize>(
+    config: &ModelConfig,
+    operations: &[Operation],
+) -> Result<(), ModelError> {
+    let mut tree = config.new_tree::<PREFIX_LEN>();
+    let mut model = Model::default();
+
+    for (step, operation) in operations.iter().enumerate() {
+        let error = |message: String| ModelError {
+            step,
+            operation: operation.clone(),
+            message,
+        };
+
+        let expected = model.apply(operation);
+        let actual = apply_to_tree(config, &mut tree, operation);
+        if actual != expected {
+            return Err(error(format!(
+                "the tree returned {actual:?}, but the model returned {expected:?}"
+            )));
+        }
+
+        if let Err(err) = WellFormedChecker::check(&tree) {
+            return Err(error(format!("the tree is not well-formed: {err}")));
+        }
+
+        let model_entries = model.entries();
+        if tree.len() != model_entries.len() {
+            return Err(error(format!(
+                "the tree has {} entries, but the model has {}",
+                tree.len(),
+                model_entries.len()
+            )));
+        }
+        if !tree.iter().eq(model_entries.iter())
+            || !tree.iter().rev().eq(model_entries.iter().rev())
+        {
+            return Err(error(format!(
+                "the tree has the entries {:?}, but the model has {model_entries:?}",
+                tree.iter().collect::<Vec<_>>()
+            )));
+        }
+
+        if let Some(mismatch) = config.mismatch(&tree) {
+            return Err(error(mismatch));
+        }
+    }
+
+    Ok(())
+}
+
+/// Run [`check_operations`], catching any panic, and return the failure if
+/// there is one.
+fn find_failure(config: &ModelConfig, operations: &[Operation]) -> Option<String> {
+    match panic::catch_unwind(AssertUnwindSafe(|| check_operations(config, operations))) {
+        Ok(Ok(())) => None,
+        Ok(Err(err)) => Some(err.to_string()),
+        Err(payload) => Some(
+            payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .map_or_else(
+                    || "panicked".into(),
+                    |message| format!("panicked: {message}"),
+                ),
+        ),
+    }
+}
+
+/// Check the operations with [`check_operations`], and panic with a minimal
+/// failing configuration and sequence of operations if they fail or panic.
+///
+/// The configuration is shrunk towards the default one first, then the
+/// operations are shrunk with [`shrink`].
+///
+/// The panics of the shrinking attempts are caught without replacing the
+/// panic hook, so their messages are still printed by it. Fuzzers, whose
+/// panic hook aborts the process, should call [`check_operations`] instead
+/// and minimize the failing inputs with their own tools.
+pub fn assert_operations(config: &ModelConfig, operations: &[Operation]) {
+    if find_failure(config, operations).is_none() {
+        return;
+    }
+
+    let mut config = *config;
+    while let Some(simpler) = config
+        .simplifications()
+        .into_iter()
+        .find(|simpler| find_failure(simpler, operations).is_some())
+    {
+        config = simpler;
+    }
+
+    let minimal = shrink(operations.to_vec(), |operations| {
+        find_failure(&config, operations).is_some()
+    });
+
+    let failure = find_failure(&config, &minimal).expect("the shrunk operations should still fail");
+    panic!(
+        "{failure}\nminimal failing configuration: {config:?}\nminimal failing operations ({} of \
+         {}): {minimal:#?}",
+        minimal.len(),
+        operations.len()
+    );
+}
+
+/// Shrink a sequence of operations for which `fails` returns true, to a
+/// sequence for which it still returns true but which cannot be made any
+/// simpler.
+///
+/// First chunks of operations are removed, from half of the sequence down to
+/// single operations, then each operation is replaced by its
+/// [`simplifications`](Operation::simplifications), until neither makes
+/// progress.
+pub fn shrink(
+    mut operations: Vec<Operation>,
+    mut fails: impl FnMut(&[Operation]) -> bool,
+) -> Vec<Operation> {
+    loop {
+        let mut progress = false;
+
+        let mut chunk_len = (operations.len() / 2).max(1);
+        while chunk_len > 0 {
+            let mut start = 0;
+            while start < operations.len() {
+                let end = (start + chunk_len).min(operations.len());
+                let mut candidate = operations.clone();
+                candidate.drain(start..end);
+                if fails(&candidate) {
+                    operations = candidate;
+                    progress = true;
+                } else {
+                    start += chunk_len;
+                }
+            }
+            chunk_len /= 2;
+        }
+
+        for idx in 0..operations.len() {
+            for simpler in operations[idx].simplifications() {
+                let mut candidate = operations.clone();
+                candidate[idx] = simpler;
+                if fails(&candidate) {
+                    operations = candidate;
+                    progress = true;
+                    break;
+                }
+            }
+        }
+
+        if !progress {
+            return operations;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    #[test]
+    fn decode_is_total() {
+        assert_eq!(Operation::decode_all(&[]), []);
+        assert_eq!(
+            Operation::decode_all(&[0, 2, 5, 200, 7, 21, 31]),
+            [
+                Operation::TryInsert([1, 200].into(), 7),
+                Operation::PopFirst,
+                Operation::Clear
+            ]
+        );
+        // The missing key and value bytes are read as zeros
+        assert_eq!(
+            Operation::decode_all(&[17]),
+            [Operation::Entry([].into(), EntryOperation::AndModify)]
+        );
+    }
+
+    #[test]
+    fn decode_config() {
+        assert_eq!(decode_input(&[]), (ModelConfig::default(), Vec::new()));
+        assert_eq!(ModelConfig::decode(0), ModelConfig::default());
+        assert_eq!(
+            ModelConfig::decode(0b1010_1101),
+            ModelConfig {
+                prefix_len: 8,
+                prefix_strategy: PrefixStrategy::Pessimistic,
+                shrink_policy: ShrinkPolicy::Hysteresis,
+                lookup_index_depth: cfg!(feature = "std").then_some(2),
+            }
+        );
+        // There is no room for the pointer to a spilled prefix
+        assert_eq!(
+            ModelConfig::decode(0b1_11).prefix_strategy,
+            PrefixStrategy::Optimistic
+        );
+        // The length byte selects the stem
+        assert_eq!(
+            Operation::decode_all(&[15, 196, 3]),
+            [Operation::Get([KEY_STEM, &[3]].concat().into())]
+        );
+    }
+
+    #[test]
+    fn model_set_operations() {
+        let mut model = Model::default();
+        for key in [[1, 2], [2, 1], [3, 3]] {
+            model.apply(&Operation::TryInsert(key.into(), 1));
+        }
+        let other = vec![
+            (Box::from([1]), 10),
+            (Box::from([2, 1]), 2),
+            (Box::from([3, 3, 3]), 30),
+            (Box::from([4]), 4),
+            (Box::from([4, 4]), 40),
+        ];
+
+        let mut union = model.clone();
+        assert_eq!(
+            union.apply(&Operation::SetOperation(SetOperation::Union, other.clone())),
+            Outcome::Entries(vec![(Box::from([1]), 10), (Box::from([3, 3, 3]), 30)])
+        );
+        assert_eq!(
+            union.entries().iter().collect::<Vec<_>>(),
+            [
+                (&Box::from([1, 2]), &1),
+                (&Box::from([2, 1]), &3),
+                (&Box::from([3, 3]), &1),
+                (&Box::from([4]), &4)
+            ]
+        );
+
+        let mut difference = model.clone();
+        assert_eq!(
+            difference.apply(&Operation::SetOperation(SetOperation::Difference, other)),
+            Outcome::Unit
+        );
+        assert_eq!(
+            difference.entries().iter().collect::<Vec<_>>(),
+            [
+                (&Box::from([1, 2]), &1),
+                (&Box::from([2, 1]), &3),
+                (&Box::from([3, 3]), &1)
+            ]
+        );
+    }
+
+    #[test]
+    fn edit_dist_matches_definition() {
+        assert_eq!(edit_dist(b"", b""), 0);
+        assert_eq!(edit_dist(b"abc", b""), 3);
+        assert_eq!(edit_dist(b"kitten", b"sitting"), 3);
+        assert_eq!(edit_dist(b"ab", b"ba"), 2);
+    }
+
+    #[test]
+    fn model_rejects_prefix_keys() {
+        let mut model = Model::default();
+        assert_eq!(
+            model.apply(&Operation::TryInsert([1, 2].into(), 0)),
+            Outcome::Value(None)
+        );
+        assert_eq!(
+            model.apply(&Operation::TryInsert([1].into(), 0)),
+            Outcome::PrefixError
+        );
+        assert_eq!(
+            model.apply(&Operation::TryInsert([1, 2, 3].into(), 0)),
+            Outcome::PrefixError
+        );
+        assert_eq!(
+            model.apply(&Operation::TryInsert([1, 2].into(), 1)),
+            Outcome::Value(Some(0))
+        );
+        assert_eq!(
+            model.apply(&Operation::Entry([1].into(), EntryOperation::OrDefault)),
+            Outcome::PrefixError
+        );
+    }
+
+    #[test]
+    fn shrink_to_minimal_operations() {
+        // Fails if any key containing the byte 7 is inserted
+        let operations = vec![
+            Operation::Clear,
+            Operation::TryInsert([1, 2].into(), 10),
+            Operation::TryInsert([3, 7, 9].into(), 20),
+            Operation::PopFirst,
+            Operation::Get([7].into()),
+        ];
+        let minimal = shrink(operations, |operations| {
+            operations.iter().any(
+                |operation| matches!(operation, Operation::TryInsert(key, _) if key.contains(&7)),
+            )
+        });
+
+        assert_eq!(minimal, [Operation::TryInsert([7].into(), 0)]);
+    }
+
+    #[test]
+    fn random_operations() {
+        #[cfg(not(miri))]
+        const NUM_CASES: usize = 256;
+        #[cfg(miri)]
+        const NUM_CASES: usize = 4;
+
+        #[cfg(not(miri))]
+        const MAX_INPUT_LEN: usize = 2048;
+        #[cfg(miri)]
+        const MAX_INPUT_LEN: usize = 128;
+
+        let mut rng = StdRng::seed_from_u64(0x30de1);
+        for _ in 0..NUM_CASES {
+            let len = rng.gen_range(0..=MAX_INPUT_LEN);
+            let bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+            let (config, operations) = decode_input(&bytes);
+            assert_operations(&config, &operations);
+        }
+    }
+}